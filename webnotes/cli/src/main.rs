@@ -9,8 +9,9 @@ mod syntax;
 mod ui;
 mod utils;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use app::App;
+use notes::note::Note;
 
 fn main() -> Result<()> {
     let args: Vec<String> = std::env::args().collect();
@@ -19,6 +20,18 @@ fn main() -> Result<()> {
         Some("init") => {
             storage::workspace::init_workspace()?;
         }
+        Some("list") => {
+            let conn = storage::db::open()?;
+            print_notes(&webnotes_core::notes::list_notes(&conn)?);
+        }
+        Some("search") => {
+            let query = args[2..].join(" ");
+            if query.trim().is_empty() {
+                bail!("Usage: webnotes search <query>");
+            }
+            let conn = storage::db::open()?;
+            print_notes(&search::index::search(&conn, &query)?);
+        }
         Some("--help") | Some("-h") => {
             print_help();
         }
@@ -41,6 +54,22 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+fn print_notes(notes: &[Note]) {
+    for note in notes {
+        let title = if note.title.trim().is_empty() {
+            "Untitled"
+        } else {
+            note.title.trim()
+        };
+        let updated = note
+            .updated()
+            .map(utils::time::format_relative)
+            .unwrap_or_default();
+        let pin = if note.is_pinned { "*" } else { " " };
+        println!("{} {:<50} {}", pin, title, updated);
+    }
+}
+
 fn print_help() {
     println!(
         r#"
@@ -55,6 +84,8 @@ OPTIONS:
 
 COMMANDS:
     init             Initialize a new webnotes workspace
+    list             List notes, pinned first
+    search <query>   Full-text search notes
     <file>           Open a specific file
     (none)           Open note picker
 
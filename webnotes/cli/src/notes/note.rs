@@ -1,14 +1,5 @@
 // src/notes/note.rs
 
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use uuid::Uuid;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Note {
-    pub id: Uuid,
-    pub title: String,
-    pub created: DateTime<Utc>,
-    pub updated: DateTime<Utc>,
-    pub tags: Vec<String>,
-}
+// The note type the desktop app stores. The CLI keeps its own database
+// (see storage::db) but reads and writes it with the same core code.
+pub use webnotes_core::Note;
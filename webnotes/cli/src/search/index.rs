@@ -1,3 +1,11 @@
 // src/search/index.rs
 
-// TODO: Full-text search
+use anyhow::Result;
+use webnotes_core::rusqlite::Connection;
+
+use crate::notes::note::Note;
+
+/// Full-text search over the workspace database, best match first
+pub fn search(conn: &Connection, query: &str) -> Result<Vec<Note>> {
+    Ok(webnotes_core::search::run_search(conn, query)?)
+}
@@ -1,3 +1,14 @@
 // src/storage/db.rs
 
-// TODO: SQLite metadata store
+use anyhow::{Context, Result};
+use std::path::Path;
+use webnotes_core::rusqlite::Connection;
+
+/// Notes database inside the workspace config directory
+pub const DB_PATH: &str = ".webnotes/notes.db";
+
+/// Open the workspace database, creating and migrating it if needed
+pub fn open() -> Result<Connection> {
+    let path = Path::new(DB_PATH);
+    webnotes_core::open(path).with_context(|| format!("Failed to open database at {:?}", path))
+}
@@ -4,6 +4,8 @@ use anyhow::Result;
 use std::fs;
 use std::path::Path;
 
+use super::db;
+
 pub fn init_workspace() -> Result<()> {
     let notes_dir = Path::new("notes");
     let config_dir = Path::new(".webnotes");
@@ -20,10 +22,11 @@ pub fn init_workspace() -> Result<()> {
         config_dir.join("config.json"),
         serde_json::to_string_pretty(&config)?,
     )?;
+    db::open()?;
 
     println!("✓ Initialized webnotes workspace");
     println!("  ./notes/           Your notes");
-    println!("  ./.webnotes/       Config");
+    println!("  ./.webnotes/       Config and notes database");
 
     Ok(())
 }
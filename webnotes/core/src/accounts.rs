@@ -0,0 +1,291 @@
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::model::Account;
+use crate::settings::{read_setting, write_setting};
+
+// =============================================================================
+// ACCOUNTS
+// =============================================================================
+//
+// Notes and folders carry the ID of the account that owns them in
+// `owner_id`. At most one account is active at a time. Inserts that leave
+// `owner_id` unset are given to the active account by trigger (see
+// `schema::migrate`), and reads and writes are limited to its rows with
+// `OWNED_BY_ACTIVE`. While nobody is signed in the active ID is NULL, so
+// only rows that no account owns are visible.
+//
+// The first account to sign in is given the rows that predate accounts.
+
+/// SQL condition matching rows owned by the active account. Prefix it with
+/// a table alias where one is needed (`n.{}`).
+pub const OWNED_BY_ACTIVE: &str = "owner_id IS (SELECT id FROM accounts WHERE is_active = 1)";
+
+/// SQL condition matching rows whose `note_id` is one of the active
+/// account's notes, for tables kept per note
+pub const NOTE_OWNED_BY_ACTIVE: &str =
+    "note_id IN (SELECT id FROM notes WHERE owner_id IS (SELECT id FROM accounts WHERE is_active = 1))";
+
+/// Tables whose rows belong to an account
+pub const OWNED_TABLES: [&str; 6] = [
+    "notes",
+    "folders",
+    "smart_folders",
+    "drafts",
+    "operation_journal",
+    "daily_notes",
+];
+
+/// Tables keyed by `note_id`, whose rows belong to the note's owner. They
+/// are cleared along with their note.
+pub const NOTE_TABLES: [&str; 9] = [
+    "note_versions",
+    "reminders",
+    "drafts",
+    "tasks",
+    "note_vectors",
+    "note_opens",
+    "note_frecency",
+    "note_properties",
+    "daily_notes",
+];
+
+/// ID of the account that was given the rows that predate accounts
+const UNOWNED_CLAIMED_KEY: &str = "accounts.unowned_claimed_by";
+
+const ACCOUNT_COLUMNS: &str = "id, email, name, image, signed_in_at";
+
+fn account_from_row(row: &Row) -> SqliteResult<Account> {
+    Ok(Account {
+        id: row.get(0)?,
+        email: row.get(1)?,
+        name: row.get(2)?,
+        image: row.get(3)?,
+        signed_in_at: row.get(4)?,
+    })
+}
+
+/// Every account that has signed in here, most recent first
+pub fn load_accounts(conn: &Connection) -> SqliteResult<Vec<Account>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM accounts ORDER BY signed_in_at DESC",
+        ACCOUNT_COLUMNS
+    ))?;
+    let accounts = stmt.query_map([], account_from_row)?;
+    accounts.collect()
+}
+
+pub fn active_account(conn: &Connection) -> SqliteResult<Option<Account>> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM accounts WHERE is_active = 1",
+            ACCOUNT_COLUMNS
+        ),
+        [],
+        account_from_row,
+    )
+    .optional()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SignIn {
+    /// The account as stored, now active
+    pub account: Option<Account>,
+    /// Whether this sign-in was given the rows that predate accounts
+    pub claimed_unowned: bool,
+    pub claimed_notes: usize,
+    pub claimed_folders: usize,
+}
+
+/// Make `account` the active one, recording it if it is new. The first
+/// account ever to sign in is given every row nobody owns. Callers should
+/// wrap this in a transaction.
+pub fn sign_in(conn: &Connection, account: &Account) -> SqliteResult<SignIn> {
+    conn.execute("UPDATE accounts SET is_active = 0 WHERE is_active = 1", [])?;
+    conn.execute(
+        "INSERT INTO accounts (id, email, name, image, signed_in_at, is_active)
+         VALUES (?1, ?2, ?3, ?4, ?5, 1)
+         ON CONFLICT(id) DO UPDATE SET
+            email = excluded.email,
+            name = excluded.name,
+            image = excluded.image,
+            signed_in_at = excluded.signed_in_at,
+            is_active = 1",
+        params![
+            account.id,
+            account.email,
+            account.name,
+            account.image,
+            Utc::now().to_rfc3339()
+        ],
+    )?;
+
+    let mut report = SignIn {
+        account: active_account(conn)?,
+        ..SignIn::default()
+    };
+    if read_setting::<String>(conn, UNOWNED_CLAIMED_KEY)?.is_none() {
+        write_setting(conn, UNOWNED_CLAIMED_KEY, &account.id)?;
+        report.claimed_unowned = true;
+        report.claimed_notes = claim_unowned(conn, "notes", &account.id)?;
+        report.claimed_folders = claim_unowned(conn, "folders", &account.id)?;
+        for table in &OWNED_TABLES[2..] {
+            claim_unowned(conn, table, &account.id)?;
+        }
+    }
+    Ok(report)
+}
+
+/// Leave no account active. Nothing is deleted.
+pub fn sign_out(conn: &Connection) -> SqliteResult<()> {
+    conn.execute("UPDATE accounts SET is_active = 0 WHERE is_active = 1", [])?;
+    Ok(())
+}
+
+/// Give every row of `table` that nobody owns to `account_id`. `table` must
+/// have an `owner_id` column.
+fn claim_unowned(conn: &Connection, table: &str, account_id: &str) -> SqliteResult<usize> {
+    conn.execute(
+        &format!("UPDATE {} SET owner_id = ?1 WHERE owner_id IS NULL", table),
+        params![account_id],
+    )
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountWipe {
+    pub notes: usize,
+    pub folders: usize,
+    pub note_versions: usize,
+}
+
+/// Delete an account's notes with everything kept per note, its other
+/// rows and the account itself. Rows of other accounts, and rows nobody
+/// owns, are left alone.
+pub fn wipe_account(conn: &Connection, account_id: &str) -> SqliteResult<AccountWipe> {
+    let owned_notes = "SELECT id FROM notes WHERE owner_id = ?1";
+    let mut note_versions = 0;
+    for table in NOTE_TABLES {
+        let deleted = conn.execute(
+            &format!("DELETE FROM {} WHERE note_id IN ({})", table, owned_notes),
+            params![account_id],
+        )?;
+        if table == "note_versions" {
+            note_versions = deleted;
+        }
+    }
+    conn.execute(
+        &format!("DELETE FROM notes_fts WHERE id IN ({})", owned_notes),
+        params![account_id],
+    )?;
+    let notes = conn.execute("DELETE FROM notes WHERE owner_id = ?1", params![account_id])?;
+    let folders = conn.execute(
+        "DELETE FROM folders WHERE owner_id = ?1",
+        params![account_id],
+    )?;
+    for table in &OWNED_TABLES[2..] {
+        conn.execute(
+            &format!("DELETE FROM {} WHERE owner_id = ?1", table),
+            params![account_id],
+        )?;
+    }
+    conn.execute("DELETE FROM accounts WHERE id = ?1", params![account_id])?;
+
+    Ok(AccountWipe {
+        notes,
+        folders,
+        note_versions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folders::{load_folders, save_folder};
+    use crate::notes::{list_notes, load_note, save_note};
+    use crate::search::run_search;
+    use crate::{open_in_memory, Folder, Note};
+
+    fn account(id: &str) -> Account {
+        Account {
+            id: id.to_string(),
+            email: Some(format!("{}@example.com", id)),
+            name: None,
+            image: None,
+            signed_in_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn first_account_claims_unowned_rows() {
+        let conn = open_in_memory().unwrap();
+        let before = Note::new("Before accounts", "");
+        save_note(&conn, &before).unwrap();
+        save_folder(&conn, &Folder::new("Old")).unwrap();
+
+        let first = sign_in(&conn, &account("alice")).unwrap();
+        assert!(first.claimed_unowned);
+        assert_eq!((first.claimed_notes, first.claimed_folders), (1, 1));
+        assert_eq!(list_notes(&conn).unwrap(), std::slice::from_ref(&before));
+
+        // Later accounts start empty, even once the first has signed out
+        sign_out(&conn).unwrap();
+        save_note(&conn, &Note::new("Signed out", "")).unwrap();
+        let second = sign_in(&conn, &account("bob")).unwrap();
+        assert!(!second.claimed_unowned);
+        assert!(list_notes(&conn).unwrap().is_empty());
+        assert!(load_folders(&conn).unwrap().is_empty());
+        assert_eq!(load_note(&conn, &before.id).unwrap(), None);
+    }
+
+    #[test]
+    fn accounts_only_see_their_own_rows() {
+        let conn = open_in_memory().unwrap();
+        sign_in(&conn, &account("alice")).unwrap();
+        let alices = Note::new("Shared word", "");
+        save_note(&conn, &alices).unwrap();
+
+        sign_in(&conn, &account("bob")).unwrap();
+        let bobs = Note::new("Shared word", "");
+        save_note(&conn, &bobs).unwrap();
+        assert_eq!(list_notes(&conn).unwrap(), std::slice::from_ref(&bobs));
+        assert_eq!(
+            run_search(&conn, "shared").unwrap(),
+            std::slice::from_ref(&bobs)
+        );
+
+        // Another account's note cannot be written through its ID
+        let mut taken = alices.clone();
+        taken.title = "Taken".to_string();
+        assert!(save_note(&conn, &taken).is_err());
+
+        assert_eq!(active_account(&conn).unwrap().unwrap().id, "bob");
+        sign_in(&conn, &account("alice")).unwrap();
+        assert_eq!(list_notes(&conn).unwrap(), [alices]);
+        assert_eq!(load_accounts(&conn).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn wipe_leaves_other_accounts_alone() {
+        let conn = open_in_memory().unwrap();
+        sign_in(&conn, &account("alice")).unwrap();
+        let alices = Note::new("Alice", "");
+        save_note(&conn, &alices).unwrap();
+
+        sign_in(&conn, &account("bob")).unwrap();
+        save_note(&conn, &Note::new("Bob", "")).unwrap();
+        save_folder(&conn, &Folder::new("Bob's")).unwrap();
+
+        let wiped = wipe_account(&conn, "bob").unwrap();
+        assert_eq!((wiped.notes, wiped.folders), (1, 1));
+        // Wiping the active account signs it out
+        assert_eq!(active_account(&conn).unwrap(), None);
+        assert_eq!(load_accounts(&conn).unwrap().len(), 1);
+
+        sign_in(&conn, &account("alice")).unwrap();
+        assert_eq!(list_notes(&conn).unwrap(), [alices]);
+        assert_eq!(run_search(&conn, "alice").unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,809 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::accounts::{NOTE_OWNED_BY_ACTIVE, OWNED_BY_ACTIVE};
+use crate::model::{Folder, Note, NoteVersion};
+use crate::notes::{note_from_row, restore_note, NOTE_COLUMNS};
+use crate::versions::{insert_note_version, note_version_from_row, NOTE_VERSION_COLUMNS};
+
+// =============================================================================
+// ARCHIVES
+// =============================================================================
+//
+// A complete copy of the signed-in account's data and the attachments its
+// notes use as one JSON document, for backups and for moving data between the desktop
+// SQLite store and the web app's Postgres database. Records use the Prisma
+// model field names, so they map one-to-one onto `Folder`, `Note` and
+// `NoteVersion` rows (the owning `userId` is set by whoever imports into
+// Postgres):
+//
+//   {
+//     "format": "webnotes-archive",
+//     "version": 1,
+//     "exportedAt": "2025-01-31T09:00:00+00:00",
+//     "folders":      [{ "id", "name", "createdAt" }],
+//     "notes":        [{ "id", "title", "content", "folderId", "isPinned",
+//                        "pinnedAt", "font", "updatedAt", "createdAt",
+//                        "version" }],
+//     "noteVersions": [{ "id", "noteId", "title", "content", "createdAt",
+//                        "changeType" }],
+//     "attachments":  [{ "path", "data" }]
+//   }
+//
+// Timestamps are RFC3339 strings, copied as stored. `content` is the editor
+// HTML, uncompressed. Attachment paths are relative to the attachments
+// directory with `/` separators, and `data` is standard base64.
+//
+// `version` is bumped for incompatible changes only; readers accept any
+// version up to their own and ignore fields they do not know.
+
+pub const ARCHIVE_FORMAT: &str = "webnotes-archive";
+pub const ARCHIVE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Archive {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: String,
+    #[serde(default)]
+    pub folders: Vec<Folder>,
+    #[serde(default)]
+    pub notes: Vec<Note>,
+    #[serde(default)]
+    pub note_versions: Vec<NoteVersion>,
+    #[serde(default)]
+    pub attachments: Vec<ArchivedAttachment>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedAttachment {
+    /// Relative to the attachments directory, `/`-separated
+    pub path: String,
+    /// File contents, base64
+    pub data: String,
+}
+
+/// What to do with an archived record whose ID is already taken
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictMode {
+    /// Keep the stored record
+    #[default]
+    Skip,
+    /// Replace the stored record with the archived one
+    Overwrite,
+    /// Import the archived record under a new ID
+    Duplicate,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    /// Records written under their archived IDs
+    pub imported: usize,
+    /// Stored records replaced (`Overwrite`)
+    pub overwritten: usize,
+    /// Records written under new IDs (`Duplicate`)
+    pub duplicated: usize,
+    /// Records left out because their ID was taken (`Skip`)
+    pub skipped: usize,
+    /// Notes that were written
+    pub note_ids: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    Sqlite(rusqlite::Error),
+    Io(PathBuf, std::io::Error),
+    /// The archive is not one this version can read
+    Invalid(String),
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sqlite(e) => write!(f, "{}", e),
+            Self::Io(path, e) => write!(f, "{:?}: {}", path, e),
+            Self::Invalid(reason) => write!(f, "Invalid archive: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {}
+
+impl From<rusqlite::Error> for ArchiveError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Sqlite(e)
+    }
+}
+
+// =============================================================================
+// EXPORT
+// =============================================================================
+
+/// The active account's notes, folders and versions, and the files in the
+/// attachments directory they refer to. The directory is shared by every
+/// account, so other files are left out. Records are sorted by creation,
+/// so exporting the same data twice gives the same archive apart from
+/// `exportedAt`.
+pub fn export_archive(conn: &Connection, attachments_dir: &Path) -> Result<Archive, ArchiveError> {
+    let folders = {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, name, created_at FROM folders WHERE {} ORDER BY created_at, id",
+            OWNED_BY_ACTIVE
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            Ok(Folder {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                created_at: row.get(2)?,
+            })
+        })?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()?
+    };
+
+    let notes = {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM notes WHERE {} ORDER BY created_at, id",
+            NOTE_COLUMNS, OWNED_BY_ACTIVE
+        ))?;
+        let rows = stmt.query_map([], note_from_row)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()?
+    };
+
+    let note_versions = {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM note_versions WHERE {} ORDER BY note_id, created_at, id",
+            NOTE_VERSION_COLUMNS, NOTE_OWNED_BY_ACTIVE
+        ))?;
+        let rows = stmt.query_map([], note_version_from_row)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()?
+    };
+
+    let mut files = Vec::new();
+    collect_attachments(attachments_dir, "", &mut files)?;
+    let contents: Vec<&str> = notes
+        .iter()
+        .map(|note| note.content.as_str())
+        .chain(note_versions.iter().map(|version| version.content.as_str()))
+        .collect();
+
+    let mut attachments = Vec::new();
+    for (relative, path) in files {
+        if !contents.iter().any(|content| refers_to(content, &relative)) {
+            continue;
+        }
+        let data = fs::read(&path).map_err(|e| ArchiveError::Io(path.clone(), e))?;
+        attachments.push(ArchivedAttachment {
+            path: relative,
+            data: BASE64.encode(data),
+        });
+    }
+
+    Ok(Archive {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        exported_at: Utc::now().to_rfc3339(),
+        folders,
+        notes,
+        note_versions,
+        attachments,
+    })
+}
+
+/// Every file under `dir` as (archive path, file path), sorted
+fn collect_attachments(
+    dir: &Path,
+    prefix: &str,
+    out: &mut Vec<(String, PathBuf)>,
+) -> Result<(), ArchiveError> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(ArchiveError::Io(dir.to_path_buf(), e)),
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<_, _>>()
+        .map_err(|e| ArchiveError::Io(dir.to_path_buf(), e))?;
+    paths.sort();
+
+    for path in paths {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let relative = format!("{}{}", prefix, name);
+        if path.is_dir() {
+            collect_attachments(&path, &format!("{}/", relative), out)?;
+        } else {
+            out.push((relative, path));
+        }
+    }
+    Ok(())
+}
+
+/// Whether note content refers to the attachment at `path`, written as is
+/// (`images/a.png`) or percent-encoded the way the app builds asset URLs,
+/// with or without its `/`s encoded. `images/a.png` does not match
+/// `images/data.png`.
+fn refers_to(content: &str, path: &str) -> bool {
+    let encoded = percent_encode(path, false);
+    let fully_encoded = percent_encode(path, true);
+    [path, encoded.as_str(), fully_encoded.as_str()]
+        .iter()
+        .any(|needle| {
+            content.match_indices(needle).any(|(at, _)| {
+                let before = &content[..at];
+                let after = content[at + needle.len()..].chars().next();
+                let starts = before.is_empty()
+                    || before.ends_with(['/', '"', '\'', '(', '=', ' '])
+                    || before.to_ascii_uppercase().ends_with("%2F");
+                let ends = !after.is_some_and(|c| {
+                    c.is_alphanumeric() || matches!(c, '.' | '-' | '_' | '/' | '%')
+                });
+                starts && ends
+            })
+        })
+}
+
+fn percent_encode(path: &str, encode_slashes: bool) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if !encode_slashes => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+// =============================================================================
+// IMPORT
+// =============================================================================
+
+/// Write an archive into the database and attachments directory, resolving
+/// taken IDs (and attachment paths) with `mode`. Nothing is deleted. Run it
+/// in a transaction; attachments are written last, so a failure before
+/// then leaves no files behind.
+pub fn import_archive(
+    conn: &Connection,
+    archive: &Archive,
+    attachments_dir: &Path,
+    mode: ConflictMode,
+) -> Result<ImportReport, ArchiveError> {
+    check_archive(archive)?;
+    let mut report = ImportReport::default();
+
+    // Decode everything up front so a bad attachment fails the import
+    // before anything is written
+    let mut files = Vec::with_capacity(archive.attachments.len());
+    for attachment in &archive.attachments {
+        let relative = attachment_path(&attachment.path)?;
+        let data = BASE64
+            .decode(attachment.data.trim())
+            .map_err(|e| ArchiveError::Invalid(format!("attachment {}: {}", attachment.path, e)))?;
+        files.push((attachment.path.as_str(), relative, data));
+    }
+
+    // Attachments that had to be renamed, so note content can follow them
+    let mut renamed: Vec<(String, String)> = Vec::new();
+    let mut writes: Vec<(PathBuf, Vec<u8>)> = Vec::new();
+    for (path, relative, data) in files {
+        let target = attachments_dir.join(&relative);
+        match fs::read(&target) {
+            Err(_) => {
+                report.imported += 1;
+                writes.push((target, data));
+            }
+            Ok(existing) if existing == data => {}
+            Ok(_) => match mode {
+                ConflictMode::Skip => report.skipped += 1,
+                ConflictMode::Overwrite => {
+                    report.overwritten += 1;
+                    writes.push((target, data));
+                }
+                ConflictMode::Duplicate => {
+                    let (new_path, new_target) = free_attachment_path(attachments_dir, path)?;
+                    report.duplicated += 1;
+                    renamed.push((path.to_string(), new_path));
+                    writes.push((new_target, data));
+                }
+            },
+        }
+    }
+
+    let mut folder_ids: HashMap<&str, String> = HashMap::new();
+    for folder in &archive.folders {
+        let id = match (stored(conn, "folders", &folder.id)?, mode) {
+            (Stored::Free, _) => {
+                report.imported += 1;
+                folder.id.clone()
+            }
+            (Stored::Ours, ConflictMode::Skip) => {
+                report.skipped += 1;
+                continue;
+            }
+            (Stored::Ours, ConflictMode::Overwrite) => {
+                report.overwritten += 1;
+                folder.id.clone()
+            }
+            (Stored::Ours, ConflictMode::Duplicate) | (Stored::Theirs, _) => {
+                report.duplicated += 1;
+                uuid::Uuid::new_v4().to_string()
+            }
+        };
+        conn.execute(
+            "INSERT INTO folders (id, name, created_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(id) DO UPDATE SET name = excluded.name, created_at = excluded.created_at",
+            params![id, folder.name, folder.created_at],
+        )?;
+        folder_ids.insert(&folder.id, id);
+    }
+
+    // Archived note ID → ID written, or None if the note was skipped
+    let mut note_ids: HashMap<&str, Option<String>> = HashMap::new();
+    for note in &archive.notes {
+        let id = match (stored(conn, "notes", &note.id)?, mode) {
+            (Stored::Free, _) => {
+                report.imported += 1;
+                note.id.clone()
+            }
+            (Stored::Ours, ConflictMode::Skip) => {
+                report.skipped += 1;
+                note_ids.insert(&note.id, None);
+                continue;
+            }
+            (Stored::Ours, ConflictMode::Overwrite) => {
+                report.overwritten += 1;
+                note.id.clone()
+            }
+            (Stored::Ours, ConflictMode::Duplicate) | (Stored::Theirs, _) => {
+                report.duplicated += 1;
+                uuid::Uuid::new_v4().to_string()
+            }
+        };
+
+        let mut content = note.content.clone();
+        for (from, to) in &renamed {
+            content = content.replace(from.as_str(), to);
+        }
+        let folder_id = note
+            .folder_id
+            .as_deref()
+            .map(|folder_id| {
+                folder_ids
+                    .get(folder_id)
+                    .map_or(folder_id, |id| id.as_str())
+            })
+            .map(str::to_string);
+
+        restore_note(
+            conn,
+            &Note {
+                id: id.clone(),
+                content,
+                folder_id,
+                ..note.clone()
+            },
+        )?;
+        note_ids.insert(&note.id, Some(id.clone()));
+        report.note_ids.push(id);
+    }
+
+    for version in &archive.note_versions {
+        let note_id = match note_ids.get(version.note_id.as_str()) {
+            Some(Some(id)) => id.clone(),
+            // Belongs to a note that was skipped
+            Some(None) => {
+                report.skipped += 1;
+                continue;
+            }
+            // Belongs to a note outside the archive, which has to be ours
+            None if stored(conn, "notes", &version.note_id)? == Stored::Ours => {
+                version.note_id.clone()
+            }
+            None => {
+                report.skipped += 1;
+                continue;
+            }
+        };
+        // A duplicated note gets its own copies of its versions
+        let id = if note_id != version.note_id {
+            report.duplicated += 1;
+            uuid::Uuid::new_v4().to_string()
+        } else {
+            match (stored(conn, "note_versions", &version.id)?, mode) {
+                (Stored::Free, _) => {
+                    report.imported += 1;
+                    version.id.clone()
+                }
+                (Stored::Ours, ConflictMode::Skip) => {
+                    report.skipped += 1;
+                    continue;
+                }
+                (Stored::Ours, ConflictMode::Overwrite) => {
+                    report.overwritten += 1;
+                    conn.execute(
+                        "DELETE FROM note_versions WHERE id = ?1",
+                        params![version.id],
+                    )?;
+                    version.id.clone()
+                }
+                (Stored::Ours, ConflictMode::Duplicate) | (Stored::Theirs, _) => {
+                    report.duplicated += 1;
+                    uuid::Uuid::new_v4().to_string()
+                }
+            }
+        };
+
+        insert_note_version(
+            conn,
+            &NoteVersion {
+                id,
+                note_id,
+                ..version.clone()
+            },
+        )?;
+    }
+
+    for (target, data) in writes {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| ArchiveError::Io(parent.to_path_buf(), e))?;
+        }
+        fs::write(&target, data).map_err(|e| ArchiveError::Io(target.clone(), e))?;
+    }
+
+    Ok(report)
+}
+
+fn check_archive(archive: &Archive) -> Result<(), ArchiveError> {
+    if archive.format != ARCHIVE_FORMAT {
+        return Err(ArchiveError::Invalid(format!(
+            "unknown format '{}'",
+            archive.format
+        )));
+    }
+    if archive.version == 0 || archive.version > ARCHIVE_VERSION {
+        return Err(ArchiveError::Invalid(format!(
+            "version {} is not supported (newest supported is {})",
+            archive.version, ARCHIVE_VERSION
+        )));
+    }
+    Ok(())
+}
+
+/// Whether an archived ID is free, and if not, whose row has it
+#[derive(Debug, PartialEq)]
+enum Stored {
+    Free,
+    /// The active account's; the conflict mode decides
+    Ours,
+    /// Another account's, which an import never touches
+    Theirs,
+}
+
+fn stored(conn: &Connection, table: &str, id: &str) -> rusqlite::Result<Stored> {
+    // Versions belong to whoever owns their note
+    let owned = match table {
+        "note_versions" => NOTE_OWNED_BY_ACTIVE,
+        _ => OWNED_BY_ACTIVE,
+    };
+    let ours: Option<bool> = conn
+        .query_row(
+            &format!("SELECT {} FROM {} WHERE id = ?1", owned, table),
+            params![id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(match ours {
+        None => Stored::Free,
+        Some(true) => Stored::Ours,
+        Some(false) => Stored::Theirs,
+    })
+}
+
+/// An archived attachment path as a relative path that cannot leave the
+/// attachments directory
+fn attachment_path(path: &str) -> Result<PathBuf, ArchiveError> {
+    let relative = PathBuf::from(path);
+    let safe = !path.is_empty()
+        && relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+    if safe {
+        Ok(relative)
+    } else {
+        Err(ArchiveError::Invalid(format!(
+            "attachment path '{}' is not relative",
+            path
+        )))
+    }
+}
+
+/// `dir/name-2.ext`, `dir/name-3.ext`, ... whichever is free first
+fn free_attachment_path(
+    attachments_dir: &Path,
+    path: &str,
+) -> Result<(String, PathBuf), ArchiveError> {
+    let (parent, file) = path.rsplit_once('/').unwrap_or(("", path));
+    let (stem, extension) = match file.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+        _ => (file, String::new()),
+    };
+
+    for n in 2.. {
+        let name = format!("{}-{}{}", stem, n, extension);
+        let candidate = if parent.is_empty() {
+            name
+        } else {
+            format!("{}/{}", parent, name)
+        };
+        let target = attachments_dir.join(attachment_path(&candidate)?);
+        if !target.exists() {
+            return Ok((candidate, target));
+        }
+    }
+    unreachable!("some attachment name is free")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compression::COMPRESSION_THRESHOLD;
+    use crate::folders::save_folder;
+    use crate::notes::{load_note, save_note, toggle_pin};
+    use crate::open_in_memory;
+    use crate::versions::load_note_versions;
+
+    /// A fresh directory under the system temp dir, removed on drop
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("webnotes-{}", uuid::Uuid::new_v4()));
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn version_of(note: &Note, id: &str, created_at: &str) -> NoteVersion {
+        NoteVersion {
+            id: id.to_string(),
+            note_id: note.id.clone(),
+            title: note.title.clone(),
+            content: note.content.clone(),
+            created_at: created_at.to_string(),
+            change_type: "manual".to_string(),
+        }
+    }
+
+    /// A database with a bit of everything, and its attachments directory
+    fn sample() -> (Connection, TempDir, Note) {
+        let conn = open_in_memory().unwrap();
+        let attachments = TempDir::new();
+
+        let mut folder = Folder::new("Work");
+        folder.created_at = "2024-01-01T00:00:00+00:00".to_string();
+        save_folder(&conn, &folder).unwrap();
+
+        let mut note = Note::new("Plan", "<p><img src=\"images/plan.png\"></p>");
+        note.folder_id = Some(folder.id.clone());
+        note.font = Some("serif".to_string());
+        save_note(&conn, &note).unwrap();
+        toggle_pin(&conn, &note.id).unwrap();
+
+        let big = Note::new(
+            "Big",
+            format!("<p>{}</p>", "x".repeat(COMPRESSION_THRESHOLD)),
+        );
+        save_note(&conn, &big).unwrap();
+
+        insert_note_version(&conn, &version_of(&note, "v1", "2024-01-02T00:00:00+00:00")).unwrap();
+        insert_note_version(&conn, &version_of(&big, "v2", "2024-01-03T00:00:00+00:00")).unwrap();
+
+        fs::create_dir_all(attachments.0.join("images")).unwrap();
+        fs::write(
+            attachments.0.join("images/plan.png"),
+            [0x89, b'P', b'N', b'G', 0],
+        )
+        .unwrap();
+        fs::write(attachments.0.join("notes.txt"), "hello").unwrap();
+
+        let note = load_note(&conn, &note.id).unwrap().unwrap();
+        (conn, attachments, note)
+    }
+
+    #[test]
+    fn round_trips_without_loss() {
+        let (conn, attachments, _) = sample();
+        let archive = export_archive(&conn, &attachments.0).unwrap();
+        assert_eq!((archive.folders.len(), archive.notes.len()), (1, 2));
+        // notes.txt is not used by any note, so it may be another account's
+        assert_eq!(
+            (archive.note_versions.len(), archive.attachments.len()),
+            (2, 1)
+        );
+        assert_eq!(archive.attachments[0].path, "images/plan.png");
+
+        let json = serde_json::to_string(&archive).unwrap();
+        let parsed: Archive = serde_json::from_str(&json).unwrap();
+
+        let other = open_in_memory().unwrap();
+        let other_attachments = TempDir::new();
+        let report =
+            import_archive(&other, &parsed, &other_attachments.0, ConflictMode::Skip).unwrap();
+        assert_eq!(report.imported, 1 + 2 + 2 + 1);
+        assert_eq!(report.note_ids.len(), 2);
+
+        let mut copy = export_archive(&other, &other_attachments.0).unwrap();
+        copy.exported_at = archive.exported_at.clone();
+        assert_eq!(copy, archive);
+    }
+
+    #[test]
+    fn finds_attachments_the_way_notes_refer_to_them() {
+        let path = "images/my plan.png";
+        for content in [
+            "<img src=\"images/my plan.png\">",
+            "<img src=\"images/my%20plan.png\">",
+            "<img src=\"asset://localhost/%2Fhome%2Fu%2Fattachments%2Fimages%2Fmy%20plan.png\">",
+            "<img src=\"http://asset.localhost/C:/attachments/images/my%20plan.png?v=1\">",
+        ] {
+            assert!(refers_to(content, path), "{}", content);
+        }
+        for content in [
+            "<img src=\"images/my plan.png.bak\">",
+            "<img src=\"old-images/my plan.png\">",
+            "<img src=\"images/my%20plan.png2\">",
+        ] {
+            assert!(!refers_to(content, path), "{}", content);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_formats_and_unsafe_paths() {
+        let conn = open_in_memory().unwrap();
+        let dir = TempDir::new();
+        let mut archive = export_archive(&conn, &dir.0).unwrap();
+
+        archive.version = ARCHIVE_VERSION + 1;
+        let result = import_archive(&conn, &archive, &dir.0, ConflictMode::Skip);
+        assert!(matches!(result, Err(ArchiveError::Invalid(_))));
+
+        archive.version = ARCHIVE_VERSION;
+        for path in ["../escape.txt", "/etc/passwd", ""] {
+            archive.attachments = vec![ArchivedAttachment {
+                path: path.to_string(),
+                data: String::new(),
+            }];
+            let result = import_archive(&conn, &archive, &dir.0, ConflictMode::Skip);
+            assert!(matches!(result, Err(ArchiveError::Invalid(_))), "{}", path);
+        }
+    }
+
+    /// The sample archive, with the note edited so it differs from the
+    /// stored one
+    fn edited_archive(conn: &Connection, attachments: &Path) -> Archive {
+        let mut archive = export_archive(conn, attachments).unwrap();
+        let plan = archive
+            .notes
+            .iter_mut()
+            .find(|n| n.title == "Plan")
+            .unwrap();
+        plan.title = "Plan (archived)".to_string();
+        archive.attachments[0].data = BASE64.encode("different");
+        archive
+    }
+
+    #[test]
+    fn skip_keeps_stored_records() {
+        let (conn, attachments, note) = sample();
+        let archive = edited_archive(&conn, &attachments.0);
+
+        let report = import_archive(&conn, &archive, &attachments.0, ConflictMode::Skip).unwrap();
+        assert_eq!(report.imported, 0);
+        assert!(report.note_ids.is_empty());
+        // 1 folder, 2 notes, 2 versions of skipped notes, 1 changed attachment
+        // (the unchanged one is already there)
+        assert_eq!(report.skipped, 6);
+
+        assert_eq!(load_note(&conn, &note.id).unwrap().unwrap(), note);
+        let png = fs::read(attachments.0.join("images/plan.png")).unwrap();
+        assert_eq!(png, [0x89, b'P', b'N', b'G', 0]);
+    }
+
+    #[test]
+    fn overwrite_replaces_stored_records() {
+        let (conn, attachments, note) = sample();
+        let archive = edited_archive(&conn, &attachments.0);
+
+        let report =
+            import_archive(&conn, &archive, &attachments.0, ConflictMode::Overwrite).unwrap();
+        assert_eq!(report.overwritten, 6);
+        assert_eq!(report.note_ids.len(), 2);
+
+        let stored = load_note(&conn, &note.id).unwrap().unwrap();
+        assert_eq!(stored.title, "Plan (archived)");
+        assert_eq!(
+            (stored.version, stored.created_at),
+            (note.version, note.created_at)
+        );
+        assert_eq!(load_note_versions(&conn, &note.id).unwrap().len(), 1);
+        let png = fs::read(attachments.0.join("images/plan.png")).unwrap();
+        assert_eq!(png, b"different");
+    }
+
+    #[test]
+    fn duplicate_imports_copies_under_new_ids() {
+        let (conn, attachments, note) = sample();
+        let archive = edited_archive(&conn, &attachments.0);
+
+        let report =
+            import_archive(&conn, &archive, &attachments.0, ConflictMode::Duplicate).unwrap();
+        assert_eq!(report.duplicated, 6);
+        assert_eq!(load_note(&conn, &note.id).unwrap().unwrap(), note);
+
+        let copy_id = report
+            .note_ids
+            .iter()
+            .find(|id| load_note(&conn, id).unwrap().unwrap().title == "Plan (archived)")
+            .unwrap();
+        let copy = load_note(&conn, copy_id).unwrap().unwrap();
+        assert_ne!(copy.id, note.id);
+        assert_ne!(copy.folder_id, note.folder_id);
+        assert!(copy.is_pinned);
+        // The changed attachment was saved alongside and the copy points at it
+        assert_eq!(copy.content, "<p><img src=\"images/plan-2.png\"></p>");
+        assert_eq!(
+            fs::read(attachments.0.join("images/plan-2.png")).unwrap(),
+            b"different"
+        );
+
+        let versions = load_note_versions(&conn, copy_id).unwrap();
+        assert_eq!(versions.len(), 1);
+        assert_ne!(versions[0].id, "v1");
+        assert_eq!(load_note_versions(&conn, &note.id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn never_touches_other_accounts_records() {
+        let (conn, attachments, note) = sample();
+        let archive = edited_archive(&conn, &attachments.0);
+        let account = |id: &str| crate::Account {
+            id: id.to_string(),
+            email: None,
+            name: None,
+            image: None,
+            signed_in_at: String::new(),
+        };
+        // The sample's rows predate accounts, so they become alice's
+        crate::accounts::sign_in(&conn, &account("alice")).unwrap();
+        crate::accounts::sign_in(&conn, &account("bob")).unwrap();
+
+        let report =
+            import_archive(&conn, &archive, &attachments.0, ConflictMode::Overwrite).unwrap();
+        assert_eq!((report.overwritten, report.duplicated), (1, 5));
+        assert_eq!(report.note_ids.len(), 2);
+        assert!(!report.note_ids.contains(&note.id));
+
+        crate::accounts::sign_in(&conn, &account("alice")).unwrap();
+        assert_eq!(load_note(&conn, &note.id).unwrap().unwrap(), note);
+    }
+}
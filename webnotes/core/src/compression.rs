@@ -0,0 +1,227 @@
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, Value, ValueRef};
+use rusqlite::{params, Connection, Result as SqliteResult};
+
+use crate::settings::{read_setting, write_setting};
+
+// =============================================================================
+// CONTENT COMPRESSION
+// =============================================================================
+//
+// Content above `COMPRESSION_THRESHOLD` bytes is stored as a zstd frame in a
+// BLOB; everything else stays TEXT. SQLite columns are dynamically typed, so
+// the storage class alone says which one a row holds and no extra column is
+// needed. Write content with `encode_content` and read it as
+// `StoredContent` (which `note_from_row` does); SQL that looks inside
+// `notes.content` or `note_versions.content` must expect a BLOB for large
+// notes.
+//
+// `notes_fts` always gets the plain content.
+
+/// Content smaller than this is not worth compressing
+pub const COMPRESSION_THRESHOLD: usize = 64 * 1024;
+
+/// Trades a little speed for size; saves happen on every edit
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Mark databases whose existing large notes, and large note versions,
+/// have been compressed. Versions got their own key after notes, so
+/// databases compressed before then still compress their versions once.
+const COMPRESSED_KEY: &str = "content_compressed";
+const VERSIONS_COMPRESSED_KEY: &str = "version_content_compressed";
+
+/// Value to store in `notes.content`
+pub fn encode_content(content: &str) -> Value {
+    if content.len() < COMPRESSION_THRESHOLD {
+        return Value::Text(content.to_string());
+    }
+
+    match zstd::bulk::compress(content.as_bytes(), COMPRESSION_LEVEL) {
+        Ok(compressed) if compressed.len() < content.len() => Value::Blob(compressed),
+        Ok(_) => Value::Text(content.to_string()),
+        Err(e) => {
+            log::warn!("Storing note uncompressed: {}", e);
+            Value::Text(content.to_string())
+        }
+    }
+}
+
+/// Note content read from `notes.content`, decompressed if needed
+pub struct StoredContent(pub String);
+
+impl FromSql for StoredContent {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Blob(compressed) => decode_content(compressed)
+                .map(StoredContent)
+                .map_err(|e| FromSqlError::Other(e.into())),
+            other => String::column_result(other).map(StoredContent),
+        }
+    }
+}
+
+fn decode_content(compressed: &[u8]) -> Result<String, String> {
+    let bytes = zstd::decode_all(compressed)
+        .map_err(|e| format!("Failed to decompress note content: {}", e))?;
+    String::from_utf8(bytes).map_err(|e| format!("Compressed note content is not UTF-8: {}", e))
+}
+
+/// Size of the content a compressed value holds, read from the frame header
+pub fn uncompressed_size(compressed: &[u8]) -> Option<u64> {
+    zstd::zstd_safe::get_frame_content_size(compressed)
+        .ok()
+        .flatten()
+}
+
+/// Compress large notes and note versions stored before compression
+/// existed. Only does any work the first time it runs on a database.
+/// Returns the number of rows compressed.
+pub fn compress_existing_notes(conn: &Connection) -> SqliteResult<usize> {
+    let notes = compress_existing(conn, "notes", COMPRESSED_KEY)?;
+    let versions = compress_existing(conn, "note_versions", VERSIONS_COMPRESSED_KEY)?;
+    if notes + versions > 0 {
+        log::info!(
+            "Compressed {} large notes and {} large note versions",
+            notes,
+            versions
+        );
+    }
+    Ok(notes + versions)
+}
+
+/// Compress the large TEXT `content` of `table`, unless `key` says it was
+/// done before
+fn compress_existing(conn: &Connection, table: &str, key: &str) -> SqliteResult<usize> {
+    if read_setting::<bool>(conn, key)? == Some(true) {
+        return Ok(0);
+    }
+
+    let large: Vec<(String, String)> = {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, content FROM {}
+             WHERE typeof(content) = 'text' AND length(CAST(content AS BLOB)) >= ?1",
+            table
+        ))?;
+        let rows = stmt.query_map(params![COMPRESSION_THRESHOLD as i64], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+        rows.collect::<SqliteResult<_>>()?
+    };
+
+    let mut compressed = 0;
+    for (id, content) in &large {
+        let value = encode_content(content);
+        if matches!(value, Value::Blob(_)) {
+            conn.execute(
+                &format!("UPDATE {} SET content = ?1 WHERE id = ?2", table),
+                params![value, id],
+            )?;
+            compressed += 1;
+        }
+    }
+
+    write_setting(conn, key, &true)?;
+    Ok(compressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::{insert_note, load_note};
+    use crate::search::run_search;
+    use crate::{open_in_memory, Note};
+
+    fn large_content() -> String {
+        "<p>2024-01-01 12:00:00 INFO request served in 12ms</p>".repeat(4000)
+    }
+
+    fn storage_class(conn: &Connection, id: &str) -> String {
+        conn.query_row(
+            "SELECT typeof(content) FROM notes WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn compresses_only_large_content() {
+        let conn = open_in_memory().unwrap();
+        let small = Note::new("Small", "<p>hello</p>");
+        let large = Note::new("Large", large_content());
+        insert_note(&conn, &small).unwrap();
+        insert_note(&conn, &large).unwrap();
+
+        assert_eq!(storage_class(&conn, &small.id), "text");
+        assert_eq!(storage_class(&conn, &large.id), "blob");
+        assert_eq!(
+            load_note(&conn, &large.id).unwrap().unwrap().content,
+            large.content
+        );
+    }
+
+    #[test]
+    fn searches_compressed_content() {
+        let conn = open_in_memory().unwrap();
+        let large = Note::new("Log", format!("{}<p>needle</p>", large_content()));
+        insert_note(&conn, &large).unwrap();
+
+        let found = run_search(&conn, "needle").unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].content, large.content);
+    }
+
+    #[test]
+    fn compresses_existing_notes_once() {
+        let conn = open_in_memory().unwrap();
+        let large = Note::new("Large", large_content());
+        insert_note(&conn, &large).unwrap();
+        conn.execute(
+            "UPDATE notes SET content = ?1 WHERE id = ?2",
+            params![large.content, large.id],
+        )
+        .unwrap();
+        conn.execute("DELETE FROM app_settings", []).unwrap();
+
+        assert_eq!(compress_existing_notes(&conn).unwrap(), 1);
+        assert_eq!(storage_class(&conn, &large.id), "blob");
+        assert_eq!(compress_existing_notes(&conn).unwrap(), 0);
+        assert_eq!(
+            load_note(&conn, &large.id).unwrap().unwrap().content,
+            large.content
+        );
+    }
+
+    #[test]
+    fn compresses_existing_versions() {
+        let conn = open_in_memory().unwrap();
+        let large = Note::new("Large", large_content());
+        insert_note(&conn, &large).unwrap();
+        conn.execute(
+            "INSERT INTO note_versions (id, note_id, title, content, created_at, change_type)
+             VALUES ('v1', ?1, ?2, ?3, ?4, 'edit')",
+            params![large.id, large.title, large.content, large.created_at],
+        )
+        .unwrap();
+        write_setting(&conn, VERSIONS_COMPRESSED_KEY, &false).unwrap();
+
+        assert_eq!(compress_existing_notes(&conn).unwrap(), 1);
+        let (class, content): (String, StoredContent) = conn
+            .query_row(
+                "SELECT typeof(content), content FROM note_versions WHERE id = 'v1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(class, "blob");
+        assert_eq!(content.0, large.content);
+    }
+
+    #[test]
+    fn reports_uncompressed_size() {
+        let content = large_content();
+        let Value::Blob(compressed) = encode_content(&content) else {
+            panic!("expected compressed content");
+        };
+        assert_eq!(uncompressed_size(&compressed), Some(content.len() as u64));
+    }
+}
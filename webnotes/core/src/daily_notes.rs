@@ -0,0 +1,319 @@
+use chrono::{NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+
+use crate::accounts::OWNED_BY_ACTIVE;
+use crate::folders::{ensure_folder_named, NOT_IN_TRASH};
+use crate::model::Note;
+use crate::notes::{insert_note, load_note, note_from_row, NOTE_COLUMNS};
+use crate::settings::{read_setting, write_setting};
+
+// =============================================================================
+// DAILY NOTES
+// =============================================================================
+//
+// One note per calendar day and account, filed in a journal folder and
+// titled from the day's date. `daily_notes` maps days to notes.
+
+const DAILY_NOTE_CONFIG_KEY: &str = "daily_notes";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DailyNoteConfig {
+    pub folder_name: String,
+    pub title_format: String, // chrono strftime format
+    pub template: Option<String>,
+}
+
+impl Default for DailyNoteConfig {
+    fn default() -> Self {
+        Self {
+            folder_name: "Journal".to_string(),
+            title_format: "%Y-%m-%d".to_string(),
+            template: None,
+        }
+    }
+}
+
+impl DailyNoteConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.folder_name.trim().is_empty() {
+            return Err("Journal folder name cannot be empty".to_string());
+        }
+        if self.title_format.trim().is_empty() {
+            return Err("Daily note title format cannot be empty".to_string());
+        }
+        // Formatting a date panics on invalid specifiers and on ones a date
+        // cannot fill (%H, %S, ...), so try it once here where it only errors
+        let mut title = String::new();
+        if write!(title, "{}", NaiveDate::MIN.format(&self.title_format)).is_err() {
+            return Err(format!("Invalid title format '{}'", self.title_format));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyNote {
+    pub date: String,
+    pub note: Note,
+}
+
+/// The stored config, or the default if none is stored
+pub fn load_config(conn: &Connection) -> SqliteResult<DailyNoteConfig> {
+    Ok(read_setting(conn, DAILY_NOTE_CONFIG_KEY)?.unwrap_or_default())
+}
+
+/// Store a config that passed `DailyNoteConfig::validate`
+pub fn save_config(conn: &Connection, config: &DailyNoteConfig) -> SqliteResult<()> {
+    write_setting(conn, DAILY_NOTE_CONFIG_KEY, config)
+}
+
+/// Fill `{{date}}`, `{{title}}` and `{{weekday}}` placeholders in a template
+fn render_template(template: &str, date: NaiveDate, title: &str) -> String {
+    template
+        .replace("{{date}}", &date.format("%Y-%m-%d").to_string())
+        .replace("{{title}}", title)
+        .replace("{{weekday}}", &date.format("%A").to_string())
+}
+
+/// Return the active account's note for `day`, creating it if needed. A
+/// day whose note is in the trash gets a new one.
+/// Lookup and creation run in one transaction so concurrent callers can
+/// never produce two notes for the same day.
+pub fn get_or_create(conn: &Connection, day: NaiveDate) -> SqliteResult<Note> {
+    let day_key = day.format("%Y-%m-%d").to_string();
+
+    let config = load_config(conn)?;
+    // A bad stored format falls back to the default instead of panicking
+    let config = match config.validate() {
+        Ok(()) => config,
+        Err(e) => {
+            log::warn!("Invalid daily note config, using defaults: {}", e);
+            DailyNoteConfig::default()
+        }
+    };
+
+    let tx = conn.unchecked_transaction()?;
+
+    let mapped: Option<String> = tx
+        .query_row(
+            &format!(
+                "SELECT note_id FROM daily_notes
+                 WHERE date = ?1 AND {} AND note_id IN (SELECT id FROM notes WHERE {})",
+                OWNED_BY_ACTIVE, NOT_IN_TRASH
+            ),
+            params![day_key],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    if let Some(note_id) = mapped {
+        if let Some(note) = load_note(&tx, &note_id)? {
+            tx.commit()?;
+            return Ok(note);
+        }
+    }
+    // Drop the mapping if its note was deleted or trashed; the day's note is
+    // recreated below
+    tx.execute(
+        &format!(
+            "DELETE FROM daily_notes WHERE date = ?1 AND {}",
+            OWNED_BY_ACTIVE
+        ),
+        params![day_key],
+    )?;
+
+    let folder_id = ensure_folder_named(&tx, config.folder_name.trim())?;
+    let title = day.format(&config.title_format).to_string();
+
+    // Adopt a note the user created by hand before daily notes existed
+    let adopted = tx
+        .query_row(
+            &format!(
+                "SELECT {} FROM notes WHERE folder_id = ?1 AND title = ?2 AND {}
+                 ORDER BY created_at ASC LIMIT 1",
+                NOTE_COLUMNS, OWNED_BY_ACTIVE
+            ),
+            params![folder_id, title],
+            note_from_row,
+        )
+        .optional()?;
+
+    let note = match adopted {
+        Some(note) => note,
+        None => {
+            let now = Utc::now().to_rfc3339();
+            let note = Note {
+                id: uuid::Uuid::new_v4().to_string(),
+                title: title.clone(),
+                content: config
+                    .template
+                    .as_deref()
+                    .map(|t| render_template(t, day, &title))
+                    .unwrap_or_default(),
+                folder_id: Some(folder_id),
+                is_pinned: false,
+                pinned_at: None,
+                font: None,
+                updated_at: now.clone(),
+                created_at: now,
+                version: 0,
+            };
+            insert_note(&tx, &note)?;
+            note
+        }
+    };
+
+    tx.execute(
+        "INSERT INTO daily_notes (date, note_id, owner_id)
+         SELECT ?1, id, owner_id FROM notes WHERE id = ?2",
+        params![day_key, note.id],
+    )?;
+    tx.commit()?;
+
+    Ok(note)
+}
+
+/// The active account's daily notes from `start` to `end` inclusive,
+/// oldest first
+pub fn list_daily_notes(
+    conn: &Connection,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> SqliteResult<Vec<DailyNote>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, d.date
+         FROM daily_notes d
+         JOIN notes n ON n.id = d.note_id
+         WHERE d.date BETWEEN ?1 AND ?2 AND n.{} AND {}
+         ORDER BY d.date ASC",
+        NOTE_COLUMNS, OWNED_BY_ACTIVE, NOT_IN_TRASH
+    ))?;
+
+    let daily = stmt.query_map(
+        params![
+            start.format("%Y-%m-%d").to_string(),
+            end.format("%Y-%m-%d").to_string()
+        ],
+        |row| {
+            Ok(DailyNote {
+                note: note_from_row(row)?,
+                date: row.get(10)?,
+            })
+        },
+    )?;
+
+    daily.collect::<SqliteResult<Vec<_>>>()
+}
+
+/// Make `to` the daily note for the days `from` was. Both notes belong to
+/// one account and so did `from`'s days, which keeps each day's note unique.
+pub fn move_daily_notes(conn: &Connection, from: &str, to: &str) -> SqliteResult<()> {
+    conn.execute(
+        "UPDATE daily_notes SET note_id = ?1 WHERE note_id = ?2",
+        params![to, from],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounts::sign_in;
+    use crate::{open_in_memory, Account};
+
+    fn account(id: &str) -> Account {
+        Account {
+            id: id.to_string(),
+            email: None,
+            name: None,
+            image: None,
+            signed_in_at: String::new(),
+        }
+    }
+
+    fn day() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, 14).unwrap()
+    }
+
+    #[test]
+    fn each_account_has_its_own_note_per_day() {
+        let conn = open_in_memory().unwrap();
+        sign_in(&conn, &account("alice")).unwrap();
+        let alice = get_or_create(&conn, day()).unwrap();
+        assert_eq!(get_or_create(&conn, day()).unwrap().id, alice.id);
+
+        sign_in(&conn, &account("bob")).unwrap();
+        let bob = get_or_create(&conn, day()).unwrap();
+        assert_ne!(bob.id, alice.id);
+        assert_eq!(get_or_create(&conn, day()).unwrap().id, bob.id);
+
+        sign_in(&conn, &account("alice")).unwrap();
+        assert_eq!(get_or_create(&conn, day()).unwrap().id, alice.id);
+
+        let rows: i64 = conn
+            .query_row("SELECT COUNT(*) FROM daily_notes", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(rows, 2);
+    }
+
+    #[test]
+    fn a_day_cannot_map_to_two_notes_of_one_account() {
+        let conn = open_in_memory().unwrap();
+        sign_in(&conn, &account("alice")).unwrap();
+        let note = get_or_create(&conn, day()).unwrap();
+        let other = Note::new("Other", "");
+        crate::notes::save_note(&conn, &other).unwrap();
+
+        let duplicate = conn.execute(
+            "INSERT INTO daily_notes (date, note_id, owner_id) VALUES ('2025-03-14', ?1, 'alice')",
+            params![other.id],
+        );
+        assert!(duplicate.is_err());
+
+        move_daily_notes(&conn, &note.id, &other.id).unwrap();
+        assert_eq!(get_or_create(&conn, day()).unwrap().id, other.id);
+    }
+
+    #[test]
+    fn a_trashed_daily_note_is_replaced() {
+        let conn = open_in_memory().unwrap();
+        let trashed = get_or_create(&conn, day()).unwrap();
+        let trash_id = crate::folders::ensure_trash_folder(&conn).unwrap();
+        assert!(crate::notes::trash_note_if_version(
+            &conn,
+            &trashed.id,
+            &trash_id,
+            trashed.version
+        )
+        .unwrap());
+        assert!(list_daily_notes(&conn, day(), day()).unwrap().is_empty());
+
+        let note = get_or_create(&conn, day()).unwrap();
+        assert_ne!(note.id, trashed.id);
+        assert_eq!(note.folder_id, trashed.folder_id);
+        let listed = list_daily_notes(&conn, day(), day()).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].note.id, note.id);
+    }
+
+    #[test]
+    fn title_formats_must_fit_a_date() {
+        let with_format = |title_format: &str| DailyNoteConfig {
+            title_format: title_format.to_string(),
+            ..DailyNoteConfig::default()
+        };
+
+        assert!(with_format("%A, %B %-d %Y").validate().is_ok());
+        for invalid in ["%Y-%m-%d %H:%M", "%Q", "%s", "Week %"] {
+            assert!(
+                with_format(invalid).validate().is_err(),
+                "{} should be rejected",
+                invalid
+            );
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::accounts::OWNED_BY_ACTIVE;
+use crate::model::Note;
+use crate::notes::load_note;
+
+// =============================================================================
+// DRAFTS
+// =============================================================================
+//
+// Editors write their state here on every change, much more often than they
+// save the note. Saving the note clears the draft, so a draft that is still
+// newer than its note at launch holds edits a crash would have lost.
+
+/// Unsaved editor state for a note, written far more often than the note
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Draft {
+    pub note_id: String,
+    pub title: String,
+    pub content: String,
+    pub saved_at: String,
+}
+
+fn draft_from_row(row: &Row) -> SqliteResult<Draft> {
+    Ok(Draft {
+        note_id: row.get(0)?,
+        title: row.get(1)?,
+        content: row.get(2)?,
+        saved_at: row.get(3)?,
+    })
+}
+
+/// Whether `note` already holds everything in `draft`
+pub fn draft_is_saved(draft: &Draft, note: &Note) -> bool {
+    if draft.title == note.title && draft.content == note.content {
+        return true;
+    }
+    let saved_at = DateTime::parse_from_rfc3339(&draft.saved_at).map(|at| at.with_timezone(&Utc));
+    match (saved_at, note.updated()) {
+        (Ok(saved_at), Some(updated)) => saved_at <= updated,
+        _ => false,
+    }
+}
+
+/// Store the editor state of one of the active account's notes
+pub fn save_draft(
+    conn: &Connection,
+    note_id: &str,
+    title: &str,
+    content: &str,
+) -> SqliteResult<()> {
+    conn.execute(
+        &format!(
+            "INSERT INTO drafts (note_id, title, content, saved_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(note_id) DO UPDATE SET
+                title = excluded.title,
+                content = excluded.content,
+                saved_at = excluded.saved_at
+             WHERE drafts.{}",
+            OWNED_BY_ACTIVE
+        ),
+        params![
+            note_id,
+            title,
+            content,
+            Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn discard_draft(conn: &Connection, note_id: &str) -> SqliteResult<()> {
+    conn.execute(
+        &format!(
+            "DELETE FROM drafts WHERE note_id = ?1 AND {}",
+            OWNED_BY_ACTIVE
+        ),
+        params![note_id],
+    )?;
+    Ok(())
+}
+
+/// Drop the draft of a note just saved, unless it holds newer edits
+pub fn clear_saved_draft(conn: &Connection, note: &Note) -> SqliteResult<()> {
+    let draft = conn
+        .query_row(
+            "SELECT note_id, title, content, saved_at FROM drafts WHERE note_id = ?1",
+            params![note.id],
+            draft_from_row,
+        )
+        .optional()?;
+
+    if let Some(draft) = draft {
+        // save_note stamps an empty updated_at itself; compare with what was stored
+        let stored = load_note(conn, &note.id)?;
+        if stored.is_some_and(|stored| draft_is_saved(&draft, &stored)) {
+            conn.execute("DELETE FROM drafts WHERE note_id = ?1", params![note.id])?;
+        }
+    }
+    Ok(())
+}
+
+/// Drop the active account's drafts that match their note as stored
+pub fn prune_saved_drafts(conn: &Connection) -> SqliteResult<()> {
+    for (draft, note) in load_drafts(conn)? {
+        if note.is_some_and(|note| draft_is_saved(&draft, &note)) {
+            conn.execute(
+                "DELETE FROM drafts WHERE note_id = ?1",
+                params![draft.note_id],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Every draft of the active account with its stored note, newest first
+pub fn load_drafts(conn: &Connection) -> SqliteResult<Vec<(Draft, Option<Note>)>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT note_id, title, content, saved_at FROM drafts WHERE {} ORDER BY saved_at DESC",
+        OWNED_BY_ACTIVE
+    ))?;
+    let drafts = stmt
+        .query_map([], draft_from_row)?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    drafts
+        .into_iter()
+        .map(|draft| {
+            let note = load_note(conn, &draft.note_id)?;
+            Ok((draft, note))
+        })
+        .collect()
+}
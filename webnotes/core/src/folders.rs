@@ -0,0 +1,188 @@
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+
+use crate::accounts::OWNED_BY_ACTIVE;
+use crate::model::Folder;
+
+// =============================================================================
+// FOLDER OPERATIONS
+// =============================================================================
+
+// Like notes, only the active account's folders are read or written.
+
+/// Name given to the trash folder when it is created
+pub const TRASH_FOLDER_NAME: &str = "Trash";
+
+/// SQL condition on `notes`: the note is not in the account's trash folder
+pub const NOT_IN_TRASH: &str =
+    "(folder_id IS NULL OR folder_id NOT IN (SELECT id FROM folders WHERE is_trash = 1))";
+
+/// Every folder, newest first
+pub fn load_folders(conn: &Connection) -> SqliteResult<Vec<Folder>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, name, created_at FROM folders WHERE {} ORDER BY created_at DESC",
+        OWNED_BY_ACTIVE
+    ))?;
+
+    let folders = stmt.query_map([], |row| {
+        Ok(Folder {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            created_at: row.get(2)?,
+        })
+    })?;
+
+    folders.collect::<SqliteResult<Vec<_>>>()
+}
+
+/// Insert a folder, or rename it if it exists. Fails with
+/// `QueryReturnedNoRows` if the ID belongs to another account.
+pub fn save_folder(conn: &Connection, folder: &Folder) -> SqliteResult<()> {
+    let written = conn.execute(
+        &format!(
+            "INSERT INTO folders (id, name, created_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(id) DO UPDATE SET name = excluded.name WHERE folders.{}",
+            OWNED_BY_ACTIVE
+        ),
+        params![folder.id, folder.name, folder.created_at],
+    )?;
+    if written == 0 {
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+/// Delete a folder; its notes become unfiled rather than deleted
+pub fn delete_folder(conn: &Connection, id: &str) -> SqliteResult<()> {
+    // Unfile notes first, then delete folder
+    conn.execute(
+        &format!(
+            "UPDATE notes SET folder_id = NULL, version = version + 1
+             WHERE folder_id = ?1 AND {}",
+            OWNED_BY_ACTIVE
+        ),
+        params![id],
+    )?;
+    conn.execute(
+        &format!("DELETE FROM folders WHERE id = ?1 AND {}", OWNED_BY_ACTIVE),
+        params![id],
+    )?;
+    Ok(())
+}
+
+/// Find the oldest folder called `name`, other than the trash, creating it
+/// on first use
+pub fn ensure_folder_named(conn: &Connection, name: &str) -> SqliteResult<String> {
+    let existing: Option<String> = conn
+        .query_row(
+            &format!(
+                "SELECT id FROM folders WHERE name = ?1 AND is_trash = 0 AND {}
+                 ORDER BY created_at ASC LIMIT 1",
+                OWNED_BY_ACTIVE
+            ),
+            params![name],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    if let Some(id) = existing {
+        return Ok(id);
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO folders (id, name, created_at) VALUES (?1, ?2, ?3)",
+        params![id, name, Utc::now().to_rfc3339()],
+    )?;
+    Ok(id)
+}
+
+/// The folder notes are moved to instead of being deleted, creating it on
+/// first use. It is marked by `is_trash`, not its name, so a folder the user
+/// named "Trash" is left alone.
+pub fn ensure_trash_folder(conn: &Connection) -> SqliteResult<String> {
+    let existing: Option<String> = conn
+        .query_row(
+            &format!(
+                "SELECT id FROM folders WHERE is_trash = 1 AND {} ORDER BY created_at ASC LIMIT 1",
+                OWNED_BY_ACTIVE
+            ),
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    if let Some(id) = existing {
+        return Ok(id);
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO folders (id, name, created_at, is_trash) VALUES (?1, ?2, ?3, 1)",
+        params![id, TRASH_FOLDER_NAME, Utc::now().to_rfc3339()],
+    )?;
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::{load_note, save_note};
+    use crate::{open_in_memory, Note};
+
+    #[test]
+    fn saves_and_renames() {
+        let conn = open_in_memory().unwrap();
+        let mut folder = Folder::new("Work");
+        save_folder(&conn, &folder).unwrap();
+
+        folder.name = "Projects".to_string();
+        save_folder(&conn, &folder).unwrap();
+        assert_eq!(load_folders(&conn).unwrap(), [folder]);
+    }
+
+    #[test]
+    fn delete_unfiles_notes() {
+        let conn = open_in_memory().unwrap();
+        let folder = Folder::new("Old");
+        save_folder(&conn, &folder).unwrap();
+        let mut note = Note::new("Filed", "");
+        note.folder_id = Some(folder.id.clone());
+        save_note(&conn, &note).unwrap();
+
+        delete_folder(&conn, &folder.id).unwrap();
+        assert!(load_folders(&conn).unwrap().is_empty());
+        assert_eq!(load_note(&conn, &note.id).unwrap().unwrap().folder_id, None);
+    }
+
+    #[test]
+    fn ensure_folder_named_reuses_the_oldest() {
+        let conn = open_in_memory().unwrap();
+        let id = ensure_folder_named(&conn, "Journal").unwrap();
+        assert_eq!(ensure_folder_named(&conn, "Journal").unwrap(), id);
+        assert_ne!(ensure_folder_named(&conn, "Trash").unwrap(), id);
+        assert_eq!(load_folders(&conn).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn trash_is_not_the_users_own_trash_folder() {
+        let conn = open_in_memory().unwrap();
+        let own = ensure_folder_named(&conn, TRASH_FOLDER_NAME).unwrap();
+        let mut note = Note::new("Kept", "");
+        note.folder_id = Some(own.clone());
+        save_note(&conn, &note).unwrap();
+
+        let trash = ensure_trash_folder(&conn).unwrap();
+        assert_ne!(trash, own);
+        assert_eq!(ensure_trash_folder(&conn).unwrap(), trash);
+
+        let outside: i64 = conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM notes WHERE {}", NOT_IN_TRASH),
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(outside, 1);
+    }
+}
@@ -0,0 +1,165 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::accounts::{NOTE_OWNED_BY_ACTIVE, OWNED_BY_ACTIVE};
+use crate::model::Note;
+use crate::notes::note_from_row;
+
+// =============================================================================
+// RECENT NOTES & FRECENCY
+// =============================================================================
+//
+// `note_opens` logs every time a note is opened; `note_frecency` keeps a
+// running score per note.
+//
+// Frecency is the number of opens with each open's weight halving every
+// `FRECENCY_HALF_LIFE_DAYS`. Rather than decaying every score over time, a
+// note stores `rank = log2(score) + now / half_life` as of its last open.
+// Every note's score decays at the same rate, so ranks order notes the same
+// way current scores do and can be sorted in plain SQL.
+
+const FRECENCY_HALF_LIFE_DAYS: f64 = 7.0;
+/// Re-opening the same note within this window is not another open
+const NOTE_OPEN_DEBOUNCE_SECS: i64 = 60;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentNote {
+    pub note: Note,
+    pub last_opened_at: String,
+    pub open_count: u32,
+    /// Opens weighted by how recent they are; only meaningful relative to
+    /// other notes' scores
+    pub frecency: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum RecentOrder {
+    /// Most recently opened first
+    #[default]
+    Recent,
+    /// Highest frecency first
+    Frecency,
+}
+
+/// Time in half-lives since the Unix epoch
+fn frecency_epoch(at: DateTime<Utc>) -> f64 {
+    at.timestamp() as f64 / 86_400.0 / FRECENCY_HALF_LIFE_DAYS
+}
+
+/// Score at `now` for a stored rank
+fn frecency_score(rank: f64, now: DateTime<Utc>) -> f64 {
+    (rank - frecency_epoch(now)).exp2()
+}
+
+/// Count an open of note `id` at `now`. Opens of another account's notes,
+/// and repeated opens within a minute, are not recorded.
+pub fn record_open(conn: &Connection, id: &str, now: DateTime<Utc>) -> SqliteResult<()> {
+    let foreign = conn
+        .query_row(
+            &format!(
+                "SELECT 1 FROM notes WHERE id = ?1 AND NOT {}",
+                OWNED_BY_ACTIVE
+            ),
+            params![id],
+            |_| Ok(()),
+        )
+        .optional()?;
+    if foreign.is_some() {
+        return Ok(());
+    }
+
+    let previous: Option<(String, f64)> = conn
+        .query_row(
+            "SELECT last_opened_at, rank FROM note_frecency WHERE note_id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+
+    let last_opened = previous
+        .as_ref()
+        .and_then(|(at, _)| DateTime::parse_from_rfc3339(at).ok());
+    if last_opened
+        .is_some_and(|at| (now - at.with_timezone(&Utc)).num_seconds() < NOTE_OPEN_DEBOUNCE_SECS)
+    {
+        return Ok(());
+    }
+
+    let score = previous.map_or(0.0, |(_, rank)| frecency_score(rank, now)) + 1.0;
+    let rank = score.log2() + frecency_epoch(now);
+    let opened_at = now.to_rfc3339();
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO note_opens (note_id, opened_at) VALUES (?1, ?2)",
+        params![id, opened_at],
+    )?;
+    tx.execute(
+        "INSERT INTO note_frecency (note_id, open_count, last_opened_at, rank)
+         VALUES (?1, 1, ?2, ?3)
+         ON CONFLICT(note_id) DO UPDATE SET
+            open_count = open_count + 1,
+            last_opened_at = excluded.last_opened_at,
+            rank = excluded.rank",
+        params![id, opened_at, rank],
+    )?;
+    tx.commit()
+}
+
+/// The active account's opened notes, most recent or most frecent first
+pub fn recent_notes(
+    conn: &Connection,
+    limit: usize,
+    order: RecentOrder,
+) -> SqliteResult<Vec<RecentNote>> {
+    let order_by = match order {
+        RecentOrder::Recent => "f.last_opened_at DESC",
+        RecentOrder::Frecency => "f.rank DESC",
+    };
+
+    let now = Utc::now();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT n.id, n.title, n.content, n.folder_id, n.is_pinned, n.pinned_at, n.font,
+                n.updated_at, n.created_at, n.version, f.last_opened_at, f.open_count, f.rank
+         FROM note_frecency f
+         JOIN notes n ON n.id = f.note_id
+         WHERE n.{}
+         ORDER BY {}
+         LIMIT ?1",
+        OWNED_BY_ACTIVE, order_by
+    ))?;
+    let notes = stmt.query_map(params![limit as i64], |row| {
+        Ok(RecentNote {
+            note: note_from_row(row)?,
+            last_opened_at: row.get(10)?,
+            open_count: row.get(11)?,
+            frecency: frecency_score(row.get(12)?, now),
+        })
+    })?;
+    notes.collect::<SqliteResult<Vec<_>>>()
+}
+
+/// Current frecency of every opened note of the active account, by note ID
+pub fn frecency_scores(conn: &Connection) -> SqliteResult<HashMap<String, f64>> {
+    let now = Utc::now();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT note_id, rank FROM note_frecency WHERE {}",
+        NOTE_OWNED_BY_ACTIVE
+    ))?;
+    let scores = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, frecency_score(row.get(1)?, now)))
+    })?;
+    scores.collect::<SqliteResult<HashMap<_, _>>>()
+}
+
+/// Drop opens logged before `cutoff`; scores are kept in `note_frecency`
+pub fn prune_opens(conn: &Connection, cutoff: DateTime<Utc>) -> SqliteResult<usize> {
+    conn.execute(
+        "DELETE FROM note_opens WHERE opened_at < ?1",
+        params![cutoff.to_rfc3339()],
+    )
+}
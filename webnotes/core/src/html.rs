@@ -0,0 +1,296 @@
+use std::ops::Range;
+
+// =============================================================================
+// EDITOR HTML HELPERS
+// =============================================================================
+//
+// Note content is the HTML the Tiptap editor serializes. It is well-formed
+// and predictable, so a small tag scanner is enough; this is not a general
+// HTML parser.
+
+/// An opening or closing tag found by `scan_tags`
+#[derive(Debug, Clone)]
+pub struct Tag {
+    /// Byte range of the whole tag, `<` through `>`
+    pub span: Range<usize>,
+    /// Lowercased element name
+    pub name: String,
+    pub closing: bool,
+    /// Attribute names (lowercased) and decoded values
+    pub attrs: Vec<(String, String)>,
+}
+
+impl Tag {
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Elements that never have a closing tag
+pub const VOID_ELEMENTS: &[&str] = &[
+    "area", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "wbr",
+];
+
+/// Find every tag in document order. Comments and doctypes are skipped.
+pub fn scan_tags(html: &str) -> Vec<Tag> {
+    let bytes = html.as_bytes();
+    let mut tags = Vec::new();
+    let mut i = 0;
+
+    while let Some(found) = html[i..].find('<') {
+        let start = i + found;
+        let next = bytes.get(start + 1).copied();
+
+        if html[start..].starts_with("<!--") {
+            i = html[start..]
+                .find("-->")
+                .map_or(html.len(), |end| start + end + 3);
+            continue;
+        }
+
+        let closing = next == Some(b'/');
+        let name_start = if closing { start + 2 } else { start + 1 };
+        if !bytes.get(name_start).is_some_and(u8::is_ascii_alphabetic) {
+            i = start + 1;
+            continue;
+        }
+
+        let Some(end) = find_tag_end(html, name_start) else {
+            break;
+        };
+
+        let inner = html[name_start..end].trim_end_matches('/');
+        let name_len = inner
+            .find(|c: char| c.is_whitespace() || c == '/')
+            .unwrap_or(inner.len());
+
+        tags.push(Tag {
+            span: start..end + 1,
+            name: inner[..name_len].to_ascii_lowercase(),
+            closing,
+            attrs: parse_attrs(&inner[name_len..]),
+        });
+        i = end + 1;
+    }
+
+    tags
+}
+
+/// Split HTML into its top-level elements (paragraphs, lists, tables, ...)
+/// and any loose text between them, in document order
+pub fn top_level_blocks(html: &str) -> Vec<&str> {
+    let mut blocks = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+
+    for tag in scan_tags(html) {
+        if depth == 0 {
+            let text = html[start..tag.span.start].trim();
+            if !text.is_empty() {
+                blocks.push(text);
+            }
+            start = tag.span.start;
+        }
+
+        let self_closing =
+            VOID_ELEMENTS.contains(&tag.name.as_str()) || html[tag.span.clone()].ends_with("/>");
+        if tag.closing {
+            depth = depth.saturating_sub(1);
+        } else if !self_closing {
+            depth += 1;
+        }
+
+        if depth == 0 {
+            blocks.push(&html[start..tag.span.end]);
+            start = tag.span.end;
+        }
+    }
+
+    let rest = html[start..].trim();
+    if !rest.is_empty() {
+        blocks.push(rest);
+    }
+    blocks
+}
+
+/// Index of the `>` closing a tag, ignoring any inside quoted values
+pub(crate) fn find_tag_end(html: &str, from: usize) -> Option<usize> {
+    let mut quote = None;
+    for (offset, c) in html[from..].char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '>') => return Some(from + offset),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_attrs(source: &str) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+    let mut rest = source.trim_start();
+
+    while !rest.is_empty() {
+        let name_len = rest
+            .find(|c: char| c.is_whitespace() || c == '=')
+            .unwrap_or(rest.len());
+        let name = rest[..name_len].to_ascii_lowercase();
+        rest = rest[name_len..].trim_start();
+
+        let mut value = String::new();
+        if let Some(after_eq) = rest.strip_prefix('=') {
+            let after_eq = after_eq.trim_start();
+            let (raw, remaining) = match after_eq.chars().next() {
+                Some(q @ ('"' | '\'')) => {
+                    let body = &after_eq[1..];
+                    let close = body.find(q).unwrap_or(body.len());
+                    (&body[..close], body.get(close + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let close = after_eq.find(char::is_whitespace).unwrap_or(after_eq.len());
+                    (&after_eq[..close], &after_eq[close..])
+                }
+            };
+            value = decode_entities(raw);
+            rest = remaining.trim_start();
+        }
+
+        if !name.is_empty() {
+            attrs.push((name, value));
+        }
+    }
+
+    attrs
+}
+
+/// Render a tag back to HTML from its parts
+pub fn render_tag(name: &str, attrs: &[(String, String)]) -> String {
+    let mut out = format!("<{}", name);
+    for (key, value) in attrs {
+        out.push_str(&format!(" {}=\"{}\"", key, escape_html(value)));
+    }
+    out.push('>');
+    out
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Decode the entities Tiptap emits
+pub fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Strip tags, decode entities and collapse whitespace
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut last = 0;
+
+    for tag in scan_tags(html) {
+        text.push_str(&html[last..tag.span.start]);
+        text.push(' ');
+        last = tag.span.end;
+    }
+    text.push_str(&html[last..]);
+
+    decode_entities(&text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Elements that start a new line in `html_to_lines`
+const LINE_ELEMENTS: &[&str] = &[
+    "blockquote",
+    "br",
+    "div",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "table",
+    "tr",
+    "ul",
+];
+
+/// Visible text with one line per paragraph, heading, list item or table
+/// row. Code blocks keep their own line breaks.
+pub fn html_to_lines(html: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut preformatted = false;
+    let mut last = 0;
+
+    for tag in scan_tags(html) {
+        push_line_text(
+            &html[last..tag.span.start],
+            preformatted,
+            &mut line,
+            &mut lines,
+        );
+        last = tag.span.end;
+
+        match tag.name.as_str() {
+            "pre" => {
+                end_line(&mut line, &mut lines);
+                preformatted = !tag.closing;
+            }
+            "td" | "th" if !tag.closing => push_line_text(" ", false, &mut line, &mut lines),
+            name if LINE_ELEMENTS.contains(&name) => end_line(&mut line, &mut lines),
+            _ => {}
+        }
+    }
+    push_line_text(&html[last..], preformatted, &mut line, &mut lines);
+    end_line(&mut line, &mut lines);
+
+    lines
+}
+
+fn push_line_text(text: &str, preformatted: bool, line: &mut String, lines: &mut Vec<String>) {
+    let text = decode_entities(text);
+    if preformatted {
+        for (i, part) in text.split('\n').enumerate() {
+            if i > 0 {
+                end_line(line, lines);
+            }
+            line.push_str(part);
+        }
+        return;
+    }
+    for c in text.chars() {
+        if !c.is_whitespace() {
+            line.push(c);
+        } else if !line.is_empty() && !line.ends_with(' ') {
+            line.push(' ');
+        }
+    }
+}
+
+fn end_line(line: &mut String, lines: &mut Vec<String>) {
+    // Only trailing space: code keeps its indentation
+    let text = line.trim_end();
+    if !text.is_empty() {
+        lines.push(text.to_string());
+    }
+    line.clear();
+}
@@ -0,0 +1,627 @@
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::accounts::OWNED_BY_ACTIVE;
+use crate::model::{Folder, Note, NoteVersion};
+use crate::properties::{load_properties, PropertyValue};
+use crate::reminders::{reminder_from_row, Reminder, RepeatRule, REMINDER_COLUMNS};
+use crate::versions::{insert_note_version, load_note_versions};
+use crate::{folders, notes};
+
+// =============================================================================
+// OPERATION JOURNAL
+// =============================================================================
+//
+// Destructive and bulk changes are recorded with enough data to invert them.
+// Undo walks back from the newest entry and redo forward from the oldest
+// undone one; recording a new change drops whatever was left to redo, as in
+// an editor. Inverting never overwrites later edits: a note is only put back
+// the way it was if it still looks the way the operation left it.
+
+/// Entries kept in the journal; older ones can no longer be undone
+const JOURNAL_LIMIT: i64 = 200;
+
+/// Everything removed with a note
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedNote {
+    pub note: Note,
+    pub properties: BTreeMap<String, PropertyValue>,
+    pub reminders: Vec<Reminder>,
+    /// Day this note was the daily note for
+    pub daily_date: Option<String>,
+    #[serde(default)]
+    pub versions: Vec<NoteVersion>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteFolder {
+    pub note_id: String,
+    pub folder_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NotePin {
+    pub note_id: String,
+    pub is_pinned: bool,
+    pub pinned_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentChange {
+    pub note_id: String,
+    pub before: String,
+    pub after: String,
+}
+
+/// A recorded change and the state it replaced
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Operation {
+    #[serde(rename_all = "camelCase")]
+    DeleteNote { deleted: Box<DeletedNote> },
+    #[serde(rename_all = "camelCase")]
+    DeleteFolder {
+        folder: Folder,
+        /// Notes that were unfiled by the delete
+        note_ids: Vec<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    MoveNotes {
+        folder_id: Option<String>,
+        folder_name: Option<String>,
+        /// Where each note was before the move
+        from: Vec<NoteFolder>,
+    },
+    #[serde(rename_all = "camelCase")]
+    PinNotes {
+        pinned: bool,
+        /// Pin state of each note before the change
+        before: Vec<NotePin>,
+    },
+    #[serde(rename_all = "camelCase")]
+    RenameTag {
+        from: String,
+        to: String,
+        changes: Vec<ContentChange>,
+    },
+    #[serde(rename_all = "camelCase")]
+    MergeNotes {
+        /// The note the others were merged into
+        into: String,
+        into_title: String,
+        /// The merged notes as they were, with what moved to `into`
+        merged: Vec<DeletedNote>,
+        /// Properties `into` was given by the merged notes
+        copied_properties: Vec<String>,
+        /// The survivor's content, then notes whose links were retargeted,
+        /// in the order they were written
+        changes: Vec<ContentChange>,
+        /// Trash folder the merged notes went to; `None` if deleted
+        trash_folder_id: Option<String>,
+    },
+}
+
+/// A journal entry as shown in the UI
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    pub id: i64,
+    pub kind: String,
+    /// e.g. "delete folder 'Work'", to follow "Undo" or "Redo"
+    pub description: String,
+    pub created_at: String,
+    pub undone: bool,
+}
+
+impl Operation {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Operation::DeleteNote { .. } => "deleteNote",
+            Operation::DeleteFolder { .. } => "deleteFolder",
+            Operation::MoveNotes { .. } => "moveNotes",
+            Operation::PinNotes { .. } => "pinNotes",
+            Operation::RenameTag { .. } => "renameTag",
+            Operation::MergeNotes { .. } => "mergeNotes",
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Operation::DeleteNote { deleted } => {
+                format!("delete note '{}'", display_title(&deleted.note.title))
+            }
+            Operation::DeleteFolder { folder, .. } => format!("delete folder '{}'", folder.name),
+            Operation::MoveNotes {
+                folder_name, from, ..
+            } => match folder_name {
+                Some(name) => format!("move {} to '{}'", count_notes(from.len()), name),
+                None => format!("remove {} from folders", count_notes(from.len())),
+            },
+            Operation::PinNotes { pinned, before } => {
+                let verb = if *pinned { "pin" } else { "unpin" };
+                format!("{} {}", verb, count_notes(before.len()))
+            }
+            Operation::RenameTag { from, to, .. } => format!("rename tag #{} to #{}", from, to),
+            Operation::MergeNotes {
+                into_title, merged, ..
+            } => format!(
+                "merge {} into '{}'",
+                count_notes(merged.len()),
+                display_title(into_title)
+            ),
+        }
+    }
+}
+
+fn display_title(title: &str) -> &str {
+    if title.trim().is_empty() {
+        "Untitled"
+    } else {
+        title
+    }
+}
+
+fn count_notes(count: usize) -> String {
+    if count == 1 {
+        "1 note".to_string()
+    } else {
+        format!("{} notes", count)
+    }
+}
+
+fn to_sql_error(e: serde_json::Error) -> rusqlite::Error {
+    rusqlite::Error::ToSqlConversionFailure(Box::new(e))
+}
+
+/// Add an operation to the journal. Call inside the transaction that made
+/// the change.
+pub fn record(conn: &Connection, operation: &Operation) -> SqliteResult<()> {
+    let data = serde_json::to_string(operation).map_err(to_sql_error)?;
+
+    conn.execute(
+        &format!(
+            "DELETE FROM operation_journal WHERE undone = 1 AND {}",
+            OWNED_BY_ACTIVE
+        ),
+        [],
+    )?;
+    conn.execute(
+        "INSERT INTO operation_journal (kind, description, data, created_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![
+            operation.kind(),
+            operation.describe(),
+            data,
+            Utc::now().to_rfc3339()
+        ],
+    )?;
+    conn.execute(
+        &format!(
+            "DELETE FROM operation_journal
+             WHERE {0} AND id NOT IN (
+                SELECT id FROM operation_journal WHERE {0} ORDER BY id DESC LIMIT ?1
+             )",
+            OWNED_BY_ACTIVE
+        ),
+        params![JOURNAL_LIMIT],
+    )?;
+    Ok(())
+}
+
+const ENTRY_COLUMNS: &str = "id, kind, description, created_at, undone, data";
+
+fn entry_from_row(row: &rusqlite::Row) -> SqliteResult<(JournalEntry, String)> {
+    Ok((
+        JournalEntry {
+            id: row.get(0)?,
+            kind: row.get(1)?,
+            description: row.get(2)?,
+            created_at: row.get(3)?,
+            undone: row.get(4)?,
+        },
+        row.get(5)?,
+    ))
+}
+
+fn parse_operation(data: &str) -> SqliteResult<Operation> {
+    serde_json::from_str(data).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, e.into())
+    })
+}
+
+/// The active account's entries, newest first
+pub fn recent(conn: &Connection, limit: usize) -> SqliteResult<Vec<JournalEntry>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM operation_journal WHERE {} ORDER BY id DESC LIMIT ?1",
+        ENTRY_COLUMNS, OWNED_BY_ACTIVE
+    ))?;
+    let entries = stmt
+        .query_map(params![limit as i64], entry_from_row)?
+        .map(|entry| entry.map(|(entry, _)| entry))
+        .collect();
+    entries
+}
+
+/// Invert the newest operation that is not undone yet
+pub fn undo_last(conn: &Connection) -> SqliteResult<Option<JournalEntry>> {
+    let Some((mut entry, data)) = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM operation_journal WHERE undone = 0 AND {}
+                 ORDER BY id DESC LIMIT 1",
+                ENTRY_COLUMNS, OWNED_BY_ACTIVE
+            ),
+            [],
+            entry_from_row,
+        )
+        .optional()?
+    else {
+        return Ok(None);
+    };
+
+    revert(conn, &parse_operation(&data)?)?;
+    conn.execute(
+        "UPDATE operation_journal SET undone = 1 WHERE id = ?1",
+        params![entry.id],
+    )?;
+    entry.undone = true;
+    Ok(Some(entry))
+}
+
+/// Apply again the oldest undone operation, i.e. the last one undone
+pub fn redo_next(conn: &Connection) -> SqliteResult<Option<JournalEntry>> {
+    let Some((mut entry, data)) = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM operation_journal WHERE undone = 1 AND {}
+                 ORDER BY id ASC LIMIT 1",
+                ENTRY_COLUMNS, OWNED_BY_ACTIVE
+            ),
+            [],
+            entry_from_row,
+        )
+        .optional()?
+    else {
+        return Ok(None);
+    };
+
+    apply(conn, &parse_operation(&data)?)?;
+    conn.execute(
+        "UPDATE operation_journal SET undone = 0 WHERE id = ?1",
+        params![entry.id],
+    )?;
+    entry.undone = false;
+    Ok(Some(entry))
+}
+
+/// Perform an operation, as when it was first recorded
+pub fn apply(conn: &Connection, operation: &Operation) -> SqliteResult<()> {
+    let now = Utc::now().to_rfc3339();
+
+    match operation {
+        Operation::DeleteNote { deleted } => notes::delete_note(conn, &deleted.note.id).map(|_| ()),
+        Operation::DeleteFolder { folder, .. } => folders::delete_folder(conn, &folder.id),
+        Operation::MoveNotes {
+            folder_id, from, ..
+        } => {
+            for moved in from {
+                conn.execute(
+                    &format!(
+                        "UPDATE notes SET folder_id = (SELECT id FROM folders WHERE id = ?1 AND {0}),
+                            updated_at = ?2, version = version + 1
+                         WHERE id = ?3 AND {0}",
+                        OWNED_BY_ACTIVE
+                    ),
+                    params![folder_id, now, moved.note_id],
+                )?;
+            }
+            Ok(())
+        }
+        Operation::PinNotes { pinned, before } => {
+            let pinned_at = pinned.then(|| now.clone());
+            for pin in before {
+                conn.execute(
+                    &format!(
+                        "UPDATE notes SET is_pinned = ?1, pinned_at = ?2, updated_at = ?3,
+                            version = version + 1
+                         WHERE id = ?4 AND {}",
+                        OWNED_BY_ACTIVE
+                    ),
+                    params![pinned, pinned_at, now, pin.note_id],
+                )?;
+            }
+            Ok(())
+        }
+        Operation::RenameTag { changes, .. } => {
+            for change in changes {
+                replace_content(conn, &change.note_id, &change.before, &change.after)?;
+            }
+            Ok(())
+        }
+        Operation::MergeNotes {
+            into,
+            merged,
+            changes,
+            trash_folder_id,
+            ..
+        } => {
+            for change in changes {
+                replace_content(conn, &change.note_id, &change.before, &change.after)?;
+            }
+            for deleted in merged {
+                let Some(note) = notes::load_note(conn, &deleted.note.id)? else {
+                    continue;
+                };
+                notes::move_note_rows(conn, &note.id, into)?;
+                match trash_folder_id {
+                    Some(trash_id) => {
+                        notes::trash_note_if_version(conn, &note.id, trash_id, note.version)?;
+                    }
+                    None => {
+                        notes::delete_note(conn, &note.id)?;
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Undo an operation
+fn revert(conn: &Connection, operation: &Operation) -> SqliteResult<()> {
+    let now = Utc::now().to_rfc3339();
+
+    match operation {
+        Operation::DeleteNote { deleted } => restore_note(conn, deleted),
+        Operation::DeleteFolder { folder, note_ids } => {
+            folders::save_folder(conn, folder)?;
+            // Notes filed somewhere else since then stay where they are
+            for note_id in note_ids {
+                conn.execute(
+                    &format!(
+                        "UPDATE notes SET folder_id = ?1, version = version + 1
+                         WHERE id = ?2 AND folder_id IS NULL AND {}",
+                        OWNED_BY_ACTIVE
+                    ),
+                    params![folder.id, note_id],
+                )?;
+            }
+            Ok(())
+        }
+        Operation::MoveNotes { from, .. } => {
+            // A folder deleted since then leaves the note unfiled
+            for moved in from {
+                conn.execute(
+                    &format!(
+                        "UPDATE notes SET folder_id = (SELECT id FROM folders WHERE id = ?1 AND {0}),
+                            updated_at = ?2, version = version + 1
+                         WHERE id = ?3 AND {0}",
+                        OWNED_BY_ACTIVE
+                    ),
+                    params![moved.folder_id, now, moved.note_id],
+                )?;
+            }
+            Ok(())
+        }
+        Operation::PinNotes { before, .. } => {
+            for pin in before {
+                conn.execute(
+                    &format!(
+                        "UPDATE notes SET is_pinned = ?1, pinned_at = ?2, updated_at = ?3,
+                            version = version + 1
+                         WHERE id = ?4 AND {}",
+                        OWNED_BY_ACTIVE
+                    ),
+                    params![pin.is_pinned, pin.pinned_at, now, pin.note_id],
+                )?;
+            }
+            Ok(())
+        }
+        Operation::RenameTag { changes, .. } => {
+            for change in changes {
+                replace_content(conn, &change.note_id, &change.after, &change.before)?;
+            }
+            Ok(())
+        }
+        Operation::MergeNotes {
+            into,
+            merged,
+            copied_properties,
+            changes,
+            trash_folder_id,
+            ..
+        } => {
+            for change in changes.iter().rev() {
+                replace_content(conn, &change.note_id, &change.after, &change.before)?;
+            }
+            for key in copied_properties {
+                conn.execute(
+                    "DELETE FROM note_properties WHERE note_id = ?1 AND key = ?2",
+                    params![into, key],
+                )?;
+            }
+            for deleted in merged {
+                take_back_rows(conn, deleted, into)?;
+                match trash_folder_id {
+                    Some(trash_id) => {
+                        untrash_note(conn, deleted, trash_id, &now)?;
+                        if let Some(date) = &deleted.daily_date {
+                            restore_daily_date(conn, date, &deleted.note.id)?;
+                        }
+                    }
+                    None => restore_note(conn, deleted)?,
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Take the reminders that moved to `into` back for a merged note, and
+/// free its daily-note day for it
+fn take_back_rows(conn: &Connection, deleted: &DeletedNote, into: &str) -> SqliteResult<()> {
+    for reminder in &deleted.reminders {
+        conn.execute(
+            "UPDATE reminders SET note_id = ?1 WHERE id = ?2 AND note_id = ?3",
+            params![deleted.note.id, reminder.id, into],
+        )?;
+    }
+    if let Some(date) = &deleted.daily_date {
+        conn.execute(
+            "DELETE FROM daily_notes WHERE date = ?1 AND note_id = ?2",
+            params![date, into],
+        )?;
+    }
+    Ok(())
+}
+
+/// Put a note moved to the trash back in its folder and pin state, if it
+/// is still in the trash
+fn untrash_note(
+    conn: &Connection,
+    deleted: &DeletedNote,
+    trash_id: &str,
+    now: &str,
+) -> SqliteResult<()> {
+    let note = &deleted.note;
+    conn.execute(
+        &format!(
+            "UPDATE notes SET folder_id = (SELECT id FROM folders WHERE id = ?1 AND {0}),
+                is_pinned = ?2, pinned_at = ?3, updated_at = ?4, version = version + 1
+             WHERE id = ?5 AND folder_id = ?6 AND {0}",
+            OWNED_BY_ACTIVE
+        ),
+        params![
+            note.folder_id,
+            note.is_pinned,
+            note.pinned_at,
+            now,
+            note.id,
+            trash_id
+        ],
+    )?;
+    Ok(())
+}
+
+/// Set a note's content to `to` if it is still exactly `from`
+fn replace_content(conn: &Connection, note_id: &str, from: &str, to: &str) -> SqliteResult<()> {
+    let Some(mut note) = notes::load_note(conn, note_id)? else {
+        return Ok(());
+    };
+    if note.content != from {
+        return Ok(());
+    }
+
+    note.content = to.to_string();
+    note.updated_at = Utc::now().to_rfc3339();
+    notes::update_content(conn, &note.id, &note.content, &note.updated_at)?;
+    notes::sync_note_index(conn, &note.id, &note.title, &note.content)
+}
+
+/// Collect what `notes::delete_note` is about to delete
+pub fn snapshot_note(conn: &Connection, id: &str) -> SqliteResult<Option<DeletedNote>> {
+    let Some(note) = notes::load_note(conn, id)? else {
+        return Ok(None);
+    };
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM reminders WHERE note_id = ?1",
+        REMINDER_COLUMNS
+    ))?;
+    let reminders = stmt
+        .query_map(params![id], reminder_from_row)?
+        .collect::<SqliteResult<Vec<_>>>()?;
+    let daily_date = conn
+        .query_row(
+            "SELECT date FROM daily_notes WHERE note_id = ?1",
+            params![id],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    Ok(Some(DeletedNote {
+        properties: load_properties(conn, id)?,
+        note,
+        reminders,
+        daily_date,
+        versions: load_note_versions(conn, id)?,
+    }))
+}
+
+/// Put a deleted note back with its properties, reminders and daily note
+/// date. The note keeps its folder only if that folder still exists.
+fn restore_note(conn: &Connection, deleted: &DeletedNote) -> SqliteResult<()> {
+    let mut note = deleted.note.clone();
+    if notes::load_note(conn, &note.id)?.is_some() {
+        return Ok(());
+    }
+    if let Some(folder_id) = &note.folder_id {
+        let exists = conn
+            .query_row(
+                &format!(
+                    "SELECT 1 FROM folders WHERE id = ?1 AND {}",
+                    OWNED_BY_ACTIVE
+                ),
+                params![folder_id],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        if !exists {
+            note.folder_id = None;
+        }
+    }
+    notes::insert_note(conn, &note)?;
+
+    for (key, value) in &deleted.properties {
+        conn.execute(
+            "INSERT OR REPLACE INTO note_properties (note_id, key, value_type, value)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![note.id, key, value.type_name(), value.to_sql()],
+        )?;
+    }
+    for reminder in &deleted.reminders {
+        conn.execute(
+            "INSERT OR IGNORE INTO reminders (id, note_id, remind_at, repeat, fired_at, completed_at, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                reminder.id,
+                reminder.note_id,
+                reminder.remind_at,
+                reminder.repeat.map(RepeatRule::as_str),
+                reminder.fired_at,
+                reminder.completed_at,
+                reminder.created_at
+            ],
+        )?;
+    }
+    for version in &deleted.versions {
+        insert_note_version(conn, version)?;
+    }
+    if let Some(date) = &deleted.daily_date {
+        restore_daily_date(conn, date, &note.id)?;
+    }
+    Ok(())
+}
+
+/// Make `note_id` the daily note for `date` again, unless another note has
+/// become that day's note in the meantime
+fn restore_daily_date(conn: &Connection, date: &str, note_id: &str) -> SqliteResult<()> {
+    conn.execute(
+        &format!(
+            "INSERT INTO daily_notes (date, note_id, owner_id)
+             SELECT ?1, id, owner_id FROM notes
+             WHERE id = ?2
+               AND NOT EXISTS (SELECT 1 FROM daily_notes WHERE date = ?1 AND {})",
+            OWNED_BY_ACTIVE
+        ),
+        params![date, note_id],
+    )?;
+    Ok(())
+}
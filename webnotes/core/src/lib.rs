@@ -0,0 +1,35 @@
+//! Note model, SQLite storage, full-text search, content conversion and
+//! the note features (tasks, reminders, daily notes, ...) shared by the
+//! desktop app and the terminal client.
+//!
+//! Everything here works on a plain `rusqlite::Connection`, so callers
+//! decide how connections are held and locked. Functions return
+//! `rusqlite::Result` and leave validation and error wording to the front-end.
+
+pub mod accounts;
+pub mod archive;
+pub mod compression;
+pub mod daily_notes;
+pub mod drafts;
+pub mod folders;
+pub mod frecency;
+pub mod html;
+pub mod journal;
+pub mod links;
+pub mod markdown;
+pub mod model;
+pub mod notes;
+pub mod properties;
+pub mod related;
+pub mod reminders;
+pub mod schema;
+pub mod search;
+pub mod settings;
+pub mod smart_folders;
+pub mod tags;
+pub mod tasks;
+pub mod versions;
+
+pub use model::{Account, Folder, Note, NoteVersion};
+pub use rusqlite;
+pub use schema::{migrate, open, open_in_memory};
@@ -0,0 +1,159 @@
+use crate::html::{decode_entities, escape_html, render_tag, scan_tags};
+
+// =============================================================================
+// NOTE LINKS
+// =============================================================================
+//
+// Links are stored two ways: the editor's `[[` picker produces
+// `<a data-note-id="..." data-note-label="...">`, while imported and
+// hand-typed content has plain `[[Title]]` or `[[Title|label]]` text.
+
+/// `[[Title]]` and `[[Title|label]]` references in a run of text. Returns
+/// the byte range of each reference along with its title and label.
+pub fn find_wikilinks(text: &str) -> Vec<(std::ops::Range<usize>, String, String)> {
+    let mut found = Vec::new();
+    let mut i = 0;
+
+    while let Some(open) = text[i..].find("[[") {
+        let start = i + open;
+        let Some(close) = text[start + 2..].find("]]") else {
+            break;
+        };
+        let end = start + 2 + close + 2;
+        let inner = &text[start + 2..end - 2];
+
+        if !inner.contains('[') && !inner.contains('\n') {
+            let (title, label) = inner.split_once('|').unwrap_or((inner, inner));
+            let title = decode_entities(title.trim());
+            if !title.is_empty() {
+                found.push((start..end, title, label.trim().to_string()));
+            }
+        }
+        i = end;
+    }
+
+    found
+}
+
+/// Where a link in note content points
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkTarget {
+    /// An editor link, by note ID
+    Id(String),
+    /// A wikilink, by note title
+    Title(String),
+}
+
+/// Every link in note content, in document order. Code is skipped.
+pub fn outgoing_links(content: &str) -> Vec<LinkTarget> {
+    let mut links = Vec::new();
+    let mut code_depth = 0usize;
+    let mut last = 0;
+
+    for tag in scan_tags(content) {
+        if code_depth == 0 {
+            push_wikilinks(&content[last..tag.span.start], &mut links);
+        }
+        last = tag.span.end;
+
+        match (tag.name.as_str(), tag.closing) {
+            ("pre" | "code", false) => code_depth += 1,
+            ("pre" | "code", true) => code_depth = code_depth.saturating_sub(1),
+            ("a", false) if code_depth == 0 => {
+                if let Some(id) = tag.attr("data-note-id").filter(|id| !id.is_empty()) {
+                    links.push(LinkTarget::Id(id.to_string()));
+                }
+            }
+            _ => {}
+        }
+    }
+    if code_depth == 0 {
+        push_wikilinks(&content[last..], &mut links);
+    }
+
+    links
+}
+
+fn push_wikilinks(text: &str, links: &mut Vec<LinkTarget>) {
+    links.extend(
+        find_wikilinks(text)
+            .into_iter()
+            .map(|(_, title, _)| LinkTarget::Title(title)),
+    );
+}
+
+/// Point links at any of the `from` notes (ID, title) to another note.
+/// Editor links are matched by ID and keep their label; wikilinks are
+/// matched by title, ignoring case, and keep the text readers see. Code is
+/// left alone. Returns `None` when nothing changed.
+pub fn retarget_links(
+    content: &str,
+    from: &[(&str, &str)],
+    to_id: &str,
+    to_title: &str,
+) -> Option<String> {
+    let titles: Vec<String> = from.iter().map(|(_, title)| title.to_lowercase()).collect();
+    let mut out = String::with_capacity(content.len());
+    let mut changed = false;
+    let mut code_depth = 0usize;
+    let mut last = 0;
+
+    for tag in scan_tags(content) {
+        let text = &content[last..tag.span.start];
+        if code_depth == 0 {
+            changed |= retarget_wikilinks(text, &titles, to_title, &mut out);
+        } else {
+            out.push_str(text);
+        }
+
+        let linked_id = tag.attr("data-note-id");
+        if tag.name == "a"
+            && !tag.closing
+            && linked_id.is_some_and(|id| from.iter().any(|(f, _)| *f == id))
+        {
+            let attrs: Vec<(String, String)> = tag
+                .attrs
+                .iter()
+                .map(|(key, value)| match key.as_str() {
+                    "data-note-id" => (key.clone(), to_id.to_string()),
+                    _ => (key.clone(), value.clone()),
+                })
+                .collect();
+            out.push_str(&render_tag("a", &attrs));
+            changed = true;
+        } else {
+            if tag.name == "pre" || tag.name == "code" {
+                if tag.closing {
+                    code_depth = code_depth.saturating_sub(1);
+                } else {
+                    code_depth += 1;
+                }
+            }
+            out.push_str(&content[tag.span.clone()]);
+        }
+        last = tag.span.end;
+    }
+    changed |= retarget_wikilinks(&content[last..], &titles, to_title, &mut out);
+
+    (changed && out != content).then_some(out)
+}
+
+/// Append `text` to `out` with matching wikilinks rewritten; returns
+/// whether any were
+fn retarget_wikilinks(text: &str, titles: &[String], to_title: &str, out: &mut String) -> bool {
+    let mut last = 0;
+    for (range, title, label) in find_wikilinks(text) {
+        if !titles.contains(&title.to_lowercase()) {
+            continue;
+        }
+        out.push_str(&text[last..range.start]);
+        if decode_entities(&label) == to_title {
+            out.push_str(&format!("[[{}]]", escape_html(to_title)));
+        } else {
+            out.push_str(&format!("[[{}|{}]]", escape_html(to_title), label));
+        }
+        last = range.end;
+    }
+    out.push_str(&text[last..]);
+    last > 0
+}
@@ -0,0 +1,2019 @@
+use std::ops::Range;
+
+use crate::html::{decode_entities, escape_html, find_tag_end, scan_tags, Tag, VOID_ELEMENTS};
+use crate::links::find_wikilinks;
+
+// =============================================================================
+// MARKDOWN CONVERSION
+// =============================================================================
+//
+// Converts between the editor's HTML and CommonMark with the GFM table,
+// task list and strikethrough extensions, plus `$…$` and `$$…$$` math.
+// Editor note links become `[[Title]]` wikilinks, which the app resolves by
+// title. Anything Markdown has no syntax for (callouts, underline, merged
+// table cells, ...) is kept as inline HTML so it survives a round trip.
+//
+// `markdown_to_html` produces the same HTML the editor serializes, so
+// converting editor content to Markdown and back gives the original.
+
+/// Elements rendered inside a paragraph; everything else starts a block
+const INLINE_ELEMENTS: &[&str] = &[
+    "a", "abbr", "b", "br", "cite", "code", "del", "em", "i", "img", "ins", "kbd", "mark", "q",
+    "s", "samp", "small", "span", "strike", "strong", "sub", "sup", "u", "var",
+];
+
+const LINK_ATTRS: &str = r#"target="_blank" rel="noopener noreferrer""#;
+const CELL_ATTRS: &str = r#"colspan="1" rowspan="1""#;
+
+/// Language the editor gives code blocks without one
+const PLAIN_LANGUAGE: &str = "text";
+
+// =============================================================================
+// HTML TO MARKDOWN
+// =============================================================================
+
+/// Editor HTML as Markdown
+pub fn html_to_markdown(html: &str) -> String {
+    let nodes = parse_tree(html);
+    let blocks = render_blocks(&nodes, html);
+    let mut markdown = blocks
+        .into_iter()
+        .map(|block| block.markdown)
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    if !markdown.is_empty() {
+        markdown.push('\n');
+    }
+    markdown
+}
+
+enum Node {
+    Element {
+        tag: Tag,
+        children: Vec<Node>,
+        /// Opening tag through closing tag
+        span: Range<usize>,
+    },
+    /// Decoded text
+    Text(String),
+}
+
+impl Node {
+    fn is_inline(&self) -> bool {
+        match self {
+            Node::Text(_) => true,
+            Node::Element { tag, .. } => INLINE_ELEMENTS.contains(&tag.name.as_str()),
+        }
+    }
+
+    /// Images between blocks stand on their own line
+    fn is_image(&self) -> bool {
+        matches!(self, Node::Element { tag, .. } if tag.name == "img")
+    }
+}
+
+/// Build an element tree. Stray closing tags are ignored and unclosed
+/// elements end with the document.
+fn parse_tree(html: &str) -> Vec<Node> {
+    let mut stack: Vec<(Tag, Vec<Node>)> = Vec::new();
+    let mut root = Vec::new();
+    let mut last = 0;
+
+    for tag in scan_tags(html) {
+        if tag.span.start > last {
+            let text = decode_text(&html[last..tag.span.start]);
+            children_of(&mut stack, &mut root).push(Node::Text(text));
+        }
+        last = tag.span.end;
+
+        if tag.closing {
+            let Some(pos) = stack.iter().rposition(|(open, _)| open.name == tag.name) else {
+                continue;
+            };
+            while stack.len() > pos {
+                let end = if stack.len() == pos + 1 {
+                    tag.span.end
+                } else {
+                    tag.span.start
+                };
+                close_element(&mut stack, &mut root, end);
+            }
+        } else if VOID_ELEMENTS.contains(&tag.name.as_str())
+            || html[tag.span.clone()].ends_with("/>")
+        {
+            let span = tag.span.clone();
+            children_of(&mut stack, &mut root).push(Node::Element {
+                tag,
+                children: Vec::new(),
+                span,
+            });
+        } else {
+            stack.push((tag, Vec::new()));
+        }
+    }
+
+    if last < html.len() {
+        let text = decode_text(&html[last..]);
+        children_of(&mut stack, &mut root).push(Node::Text(text));
+    }
+    while !stack.is_empty() {
+        close_element(&mut stack, &mut root, html.len());
+    }
+    root
+}
+
+fn children_of<'a>(
+    stack: &'a mut [(Tag, Vec<Node>)],
+    root: &'a mut Vec<Node>,
+) -> &'a mut Vec<Node> {
+    match stack.last_mut() {
+        Some((_, children)) => children,
+        None => root,
+    }
+}
+
+fn close_element(stack: &mut Vec<(Tag, Vec<Node>)>, root: &mut Vec<Node>, end: usize) {
+    if let Some((tag, children)) = stack.pop() {
+        let span = tag.span.start..end;
+        children_of(stack, root).push(Node::Element {
+            tag,
+            children,
+            span,
+        });
+    }
+}
+
+/// Decode text, keeping non-breaking spaces as such
+fn decode_text(text: &str) -> String {
+    decode_entities(&text.replace("&nbsp;", "\u{a0}"))
+}
+
+fn text_content(nodes: &[Node]) -> String {
+    let mut text = String::new();
+    for node in nodes {
+        match node {
+            Node::Text(t) => text.push_str(t),
+            Node::Element { children, .. } => text.push_str(&text_content(children)),
+        }
+    }
+    text
+}
+
+fn child_elements(nodes: &[Node]) -> impl Iterator<Item = (&Tag, &[Node])> {
+    nodes.iter().filter_map(|node| match node {
+        Node::Element { tag, children, .. } => Some((tag, children.as_slice())),
+        Node::Text(_) => None,
+    })
+}
+
+struct Block {
+    markdown: String,
+    /// Bullet character or ordered delimiter, for lists
+    list_marker: Option<char>,
+}
+
+impl Block {
+    fn new(markdown: String) -> Self {
+        Block {
+            markdown,
+            list_marker: None,
+        }
+    }
+}
+
+fn render_blocks(nodes: &[Node], src: &str) -> Vec<Block> {
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut i = 0;
+
+    while i < nodes.len() {
+        if nodes[i].is_inline() && !nodes[i].is_image() {
+            let start = i;
+            while i < nodes.len() && nodes[i].is_inline() && !nodes[i].is_image() {
+                i += 1;
+            }
+            let text = paragraph(&nodes[start..i], src);
+            if !text.is_empty() {
+                blocks.push(Block::new(text));
+            }
+            continue;
+        }
+
+        let Node::Element {
+            tag,
+            children,
+            span,
+        } = &nodes[i]
+        else {
+            unreachable!("text is inline");
+        };
+        i += 1;
+
+        // Two lists in a row need different markers or Markdown joins them
+        let previous = blocks.last().and_then(|block| block.list_marker);
+        let block = match tag.name.as_str() {
+            "p" => {
+                let text = paragraph(children, src);
+                if text.is_empty() {
+                    continue;
+                }
+                Block::new(text)
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => heading(tag, children, src, span),
+            "ul" if tag.attr("data-type") == Some("taskList") => {
+                let marker = alternate(previous, '-', '*');
+                list(children, src, ListKind::Task, marker, 1)
+            }
+            "ul" => list(
+                children,
+                src,
+                ListKind::Bullet,
+                alternate(previous, '-', '*'),
+                1,
+            ),
+            "ol" => {
+                let start = tag.attr("start").and_then(|s| s.parse().ok()).unwrap_or(1);
+                list(
+                    children,
+                    src,
+                    ListKind::Ordered,
+                    alternate(previous, '.', ')'),
+                    start,
+                )
+            }
+            "pre" => Block::new(code_block(tag, children)),
+            "blockquote" => Block::new(blockquote(children, src)),
+            "hr" => Block::new("---".to_string()),
+            "img" => Block::new(image(tag)),
+            "div" if tag.attr("data-type") == Some("math-block") => {
+                let latex = tag
+                    .attr("data-latex")
+                    .map_or_else(|| text_content(children), str::to_string);
+                if latex.lines().any(|line| line.trim() == "$$") {
+                    Block::new(src[span.clone()].to_string())
+                } else {
+                    Block::new(format!("$$\n{}\n$$", latex))
+                }
+            }
+            "table" => {
+                Block::new(table(children, src).unwrap_or_else(|| src[span.clone()].to_string()))
+            }
+            _ => Block::new(src[span.clone()].to_string()),
+        };
+        blocks.push(block);
+    }
+
+    blocks
+}
+
+fn alternate(previous: Option<char>, first: char, second: char) -> char {
+    if previous == Some(first) {
+        second
+    } else {
+        first
+    }
+}
+
+fn paragraph(nodes: &[Node], src: &str) -> String {
+    let mut text = inline(nodes, src);
+    // A break at the very end has no Markdown form
+    while let Some(stripped) = text.trim_end_matches(' ').strip_suffix("\\\n") {
+        text = stripped.to_string();
+    }
+    escape_line_starts(text.trim_matches(|c| c == ' ' || c == '\n'))
+}
+
+fn heading(tag: &Tag, children: &[Node], src: &str, span: &Range<usize>) -> Block {
+    let level = tag.name[1..].parse::<usize>().unwrap_or(1);
+    let mut text = inline(children, src).trim_matches(' ').to_string();
+    if text.contains('\n') {
+        return Block::new(src[span.clone()].to_string());
+    }
+    // A trailing `#` would read as a closing sequence
+    if text.ends_with('#') {
+        text.insert(text.len() - 1, '\\');
+    }
+
+    let hashes = "#".repeat(level);
+    if text.is_empty() {
+        Block::new(hashes)
+    } else {
+        Block::new(format!("{} {}", hashes, text))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ListKind {
+    Bullet,
+    Ordered,
+    Task,
+}
+
+fn list(items: &[Node], src: &str, kind: ListKind, marker: char, start: u64) -> Block {
+    let mut lines = Vec::new();
+    let mut number = start;
+
+    for (tag, children) in child_elements(items).filter(|(tag, _)| tag.name == "li") {
+        let (prefix, width, content) = match kind {
+            ListKind::Bullet => (marker.to_string(), 2, children),
+            ListKind::Ordered => {
+                let prefix = format!("{}{}", number, marker);
+                number += 1;
+                let width = prefix.len() + 1;
+                (prefix, width, children)
+            }
+            ListKind::Task => {
+                let checked = if tag.attr("data-checked") == Some("true") {
+                    'x'
+                } else {
+                    ' '
+                };
+                // Item content sits in a <div> after the checkbox label
+                let content = child_elements(children)
+                    .find(|(tag, _)| tag.name == "div")
+                    .map_or(children, |(_, content)| content);
+                (format!("{} [{}]", marker, checked), 2, content)
+            }
+        };
+        lines.push(list_item(&prefix, width, &render_blocks(content, src)));
+    }
+
+    Block {
+        markdown: lines.join("\n"),
+        list_marker: Some(marker),
+    }
+}
+
+fn list_item(prefix: &str, width: usize, blocks: &[Block]) -> String {
+    let mut out = prefix.to_string();
+    for (i, block) in blocks.iter().enumerate() {
+        if i == 0 && block.list_marker.is_none() {
+            out.push(' ');
+            out.push_str(&indent(&block.markdown, width, false));
+            continue;
+        }
+        // Nested lists stay tight; other blocks need a blank line
+        out.push_str(if i == 0 || block.list_marker.is_some() {
+            "\n"
+        } else {
+            "\n\n"
+        });
+        out.push_str(&indent(&block.markdown, width, true));
+    }
+    out
+}
+
+fn indent(text: &str, width: usize, first: bool) -> String {
+    let pad = " ".repeat(width);
+    text.split('\n')
+        .enumerate()
+        .map(|(i, line)| {
+            if line.is_empty() || (i == 0 && !first) {
+                line.to_string()
+            } else {
+                format!("{}{}", pad, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn code_block(tag: &Tag, children: &[Node]) -> String {
+    let code = text_content(children);
+    let language = tag
+        .attr("data-language")
+        .map(str::to_string)
+        .or_else(|| {
+            child_elements(children).find_map(|(tag, _)| {
+                tag.attr("class")?
+                    .split_whitespace()
+                    .find_map(|class| class.strip_prefix("language-"))
+                    .map(str::to_string)
+            })
+        })
+        .filter(|language| !language.is_empty() && language != PLAIN_LANGUAGE && language != "null")
+        .unwrap_or_default();
+
+    let fence = "`".repeat(longest_run(&code, '`').max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, language, code, fence)
+}
+
+fn blockquote(children: &[Node], src: &str) -> String {
+    render_blocks(children, src)
+        .into_iter()
+        .map(|block| block.markdown)
+        .collect::<Vec<_>>()
+        .join("\n\n")
+        .split('\n')
+        .map(|line| {
+            if line.is_empty() {
+                ">".to_string()
+            } else {
+                format!("> {}", line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A GFM table, or `None` when the table needs HTML: merged or coloured
+/// cells, header cells outside the first row, or cells with more than one
+/// line of text
+fn table(children: &[Node], src: &str) -> Option<String> {
+    let mut rows: Vec<(&Tag, &[Node])> = Vec::new();
+    for (tag, content) in child_elements(children) {
+        match tag.name.as_str() {
+            "tr" => rows.push((tag, content)),
+            "thead" | "tbody" | "tfoot" => {
+                rows.extend(child_elements(content).filter(|(tag, _)| tag.name == "tr"))
+            }
+            _ => {}
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut columns = 0;
+    for (index, (_, cells)) in rows.iter().enumerate() {
+        let mut row = Vec::new();
+        for (cell, content) in child_elements(cells) {
+            let header = index == 0;
+            let plain = cell.name == if header { "th" } else { "td" }
+                && cell.attr("colspan").map_or(true, |span| span == "1")
+                && cell.attr("rowspan").map_or(true, |span| span == "1")
+                && cell.attr("data-bg-color").is_none();
+            if !plain {
+                return None;
+            }
+            row.push(table_cell(content, src)?);
+        }
+
+        if index == 0 {
+            columns = row.len();
+            if columns == 0 {
+                return None;
+            }
+        } else if row.len() != columns {
+            return None;
+        }
+        lines.push(format!("| {} |", row.join(" | ")));
+        if index == 0 {
+            lines.push(format!("|{}", " --- |".repeat(columns)));
+        }
+    }
+
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+fn table_cell(content: &[Node], src: &str) -> Option<String> {
+    let mut paragraphs = child_elements(content);
+    let text = match (paragraphs.next(), paragraphs.next()) {
+        (None, _) => String::new(),
+        (Some((tag, children)), None) if tag.name == "p" => {
+            inline(children, src).trim_matches(' ').to_string()
+        }
+        _ => return None,
+    };
+    if text.contains('\n') {
+        return None;
+    }
+    Some(text.replace('|', "\\|"))
+}
+
+fn image(tag: &Tag) -> String {
+    let alt = tag
+        .attr("alt")
+        .unwrap_or("")
+        .replace('\\', "\\\\")
+        .replace('[', "\\[")
+        .replace(']', "\\]");
+    let title = tag.attr("title").map_or_else(String::new, |title| {
+        format!(" \"{}\"", title.replace('\\', "\\\\").replace('"', "\\\""))
+    });
+    format!(
+        "![{}]({}{})",
+        alt,
+        destination(tag.attr("src").unwrap_or("")),
+        title
+    )
+}
+
+fn destination(url: &str) -> String {
+    if url.is_empty() || url.contains(|c: char| c.is_whitespace() || c == '(' || c == ')') {
+        format!("<{}>", url)
+    } else {
+        url.to_string()
+    }
+}
+
+fn inline(nodes: &[Node], src: &str) -> String {
+    let mut out = String::new();
+
+    for node in nodes {
+        let (tag, children, span) = match node {
+            Node::Text(text) => {
+                out.push_str(&escape_text(text));
+                continue;
+            }
+            Node::Element {
+                tag,
+                children,
+                span,
+            } => (tag, children, span),
+        };
+
+        match tag.name.as_str() {
+            "strong" | "b" => wrap(&mut out, "**", &inline(children, src)),
+            "em" | "i" => wrap(&mut out, "*", &inline(children, src)),
+            "s" | "del" | "strike" => wrap(&mut out, "~~", &inline(children, src)),
+            "code" => out.push_str(&code_span(&text_content(children))),
+            "br" => out.push_str("\\\n"),
+            "img" => out.push_str(&image(tag)),
+            "a" if tag.attr("data-note-id").is_some() => {
+                let label = tag
+                    .attr("data-note-label")
+                    .filter(|label| !label.is_empty())
+                    .map_or_else(|| text_content(children), str::to_string);
+                if label.trim().is_empty() || label.contains(['[', ']', '|', '\n']) {
+                    raw_inline(&mut out, tag, children, src);
+                } else {
+                    out.push_str(&format!("[[{}]]", label));
+                }
+            }
+            "a" if tag.attr("href").is_some() => {
+                let label = inline(children, src);
+                if label.is_empty() {
+                    raw_inline(&mut out, tag, children, src);
+                } else {
+                    let href = tag.attr("href").unwrap_or_default();
+                    out.push_str(&format!("[{}]({})", label, destination(href)));
+                }
+            }
+            "span" if tag.attr("data-type") == Some("math-inline") => {
+                let latex = tag
+                    .attr("data-latex")
+                    .map_or_else(|| text_content(children), str::to_string);
+                let plain =
+                    !latex.is_empty() && latex.trim() == latex && !latex.contains(['$', '\n']);
+                if plain {
+                    out.push_str(&format!("${}$", latex));
+                } else {
+                    out.push_str(&src[span.clone()]);
+                }
+            }
+            _ => raw_inline(&mut out, tag, children, src),
+        }
+    }
+
+    out
+}
+
+/// Keep an element as HTML, converting what is inside it
+fn raw_inline(out: &mut String, tag: &Tag, children: &[Node], src: &str) {
+    out.push_str(&src[tag.span.clone()]);
+    out.push_str(&inline(children, src));
+    if !VOID_ELEMENTS.contains(&tag.name.as_str()) {
+        out.push_str(&format!("</{}>", tag.name));
+    }
+}
+
+/// Surround text with an emphasis delimiter. Delimiters must touch the
+/// text, so surrounding spaces move outside them.
+fn wrap(out: &mut String, delimiter: &str, text: &str) {
+    let is_space = |c: char| c.is_whitespace() && c != '\n';
+    let core = text.trim_matches(is_space);
+    if core.is_empty() {
+        out.push_str(text);
+        return;
+    }
+    let leading = &text[..text.len() - text.trim_start_matches(is_space).len()];
+    let trailing = &text[text.trim_end_matches(is_space).len()..];
+    out.push_str(&format!(
+        "{}{}{}{}{}",
+        leading, delimiter, core, delimiter, trailing
+    ));
+}
+
+fn code_span(code: &str) -> String {
+    if code.is_empty() {
+        return String::new();
+    }
+    let fence = "`".repeat(longest_run(code, '`') + 1);
+    let padded = code.starts_with('`')
+        || code.ends_with('`')
+        || (code.starts_with(' ') && code.ends_with(' ') && !code.trim().is_empty());
+    if padded {
+        format!("{} {} {}", fence, code, fence)
+    } else {
+        format!("{}{}{}", fence, code, fence)
+    }
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    let mut longest = 0;
+    let mut run = 0;
+    for ch in text.chars() {
+        run = if ch == c { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    longest
+}
+
+/// Escape characters Markdown would read as syntax. Wikilinks are left
+/// as typed.
+fn escape_text(text: &str) -> String {
+    let links: Vec<Range<usize>> = find_wikilinks(text)
+        .into_iter()
+        .map(|(range, _, _)| range)
+        .collect();
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut out = String::with_capacity(text.len());
+
+    for (i, &(at, c)) in chars.iter().enumerate() {
+        if links.iter().any(|range| range.contains(&at)) {
+            out.push(c);
+            continue;
+        }
+        let prev = i.checked_sub(1).map(|p| chars[p].1);
+        let next = chars.get(i + 1).map(|&(_, c)| c);
+        match c {
+            '\\' | '*' | '`' | '$' | '[' | ']' | '<' | '~' => {
+                out.push('\\');
+                out.push(c);
+            }
+            // Intraword underscores are never emphasis
+            '_' if !(prev.is_some_and(char::is_alphanumeric)
+                && next.is_some_and(char::is_alphanumeric)) =>
+            {
+                out.push_str("\\_")
+            }
+            // `!` before a following link would make it an image
+            '!' if next.is_none() => out.push_str("\\!"),
+            '\n' => out.push(' '),
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Escape the start of any line that would otherwise begin a block
+fn escape_line_starts(text: &str) -> String {
+    text.split('\n')
+        .map(|line| {
+            let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            let rest = &line[digits..];
+            let spaced = |rest: &str| rest.is_empty() || rest.starts_with(' ');
+
+            if digits > 0 && (rest.starts_with('.') || rest.starts_with(')')) && spaced(&rest[1..])
+            {
+                format!("{}\\{}", &line[..digits], rest)
+            } else if line.starts_with('>')
+                || (line.starts_with(['#', '-', '+']) && spaced(&line[1..]))
+                || is_thematic_break(line)
+            {
+                format!("\\{}", line)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// =============================================================================
+// MARKDOWN TO HTML
+// =============================================================================
+
+/// Markdown as editor HTML
+pub fn markdown_to_html(markdown: &str) -> String {
+    let text = markdown.replace("\r\n", "\n").replace('\t', "    ");
+    let lines: Vec<String> = text.lines().map(str::to_string).collect();
+    parse_blocks(&lines)
+}
+
+fn parse_blocks(lines: &[String]) -> String {
+    let mut html = String::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = &lines[i];
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() {
+            i += 1;
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+
+        if let Some((fence, language)) = fence_start(trimmed) {
+            i = parse_code_block(lines, i, indent, &fence, language, &mut html);
+        } else if let Some(next) = parse_math_block(lines, i, &mut html) {
+            i = next;
+        } else if let Some((level, text)) = heading_start(trimmed) {
+            html.push_str(&format!("<h{0}>{1}</h{0}>", level, parse_inline(text)));
+            i += 1;
+        } else if is_thematic_break(trimmed) {
+            html.push_str("<hr>");
+            i += 1;
+        } else if trimmed.starts_with('>') {
+            let mut quoted = Vec::new();
+            while let Some(rest) = lines.get(i).and_then(|l| l.trim_start().strip_prefix('>')) {
+                quoted.push(rest.strip_prefix(' ').unwrap_or(rest).to_string());
+                i += 1;
+            }
+            html.push_str(&format!(
+                "<blockquote>{}</blockquote>",
+                parse_blocks(&quoted)
+            ));
+        } else if list_marker(trimmed).is_some() {
+            i = parse_list(lines, i, &mut html);
+        } else if let Some(next) = parse_table(lines, i, &mut html) {
+            i = next;
+        } else if html_block_start(trimmed) {
+            let start = i;
+            while i < lines.len() && !lines[i].trim().is_empty() {
+                i += 1;
+            }
+            html.push_str(&lines[start..i].join("\n"));
+        } else {
+            i = parse_paragraph(lines, i, &mut html);
+        }
+    }
+
+    html
+}
+
+/// Whether a line starts a block, and so ends a paragraph
+fn starts_block(trimmed: &str) -> bool {
+    fence_start(trimmed).is_some()
+        || heading_start(trimmed).is_some()
+        || is_thematic_break(trimmed)
+        || trimmed.starts_with('>')
+        || trimmed.starts_with("$$")
+        || list_marker(trimmed).is_some()
+        || html_block_start(trimmed)
+}
+
+fn fence_start(trimmed: &str) -> Option<(String, &str)> {
+    let c = trimmed.chars().next().filter(|&c| c == '`' || c == '~')?;
+    let len = trimmed.len() - trimmed.trim_start_matches(c).len();
+    let info = trimmed[len..].trim();
+    if len < 3 || (c == '`' && info.contains('`')) {
+        return None;
+    }
+    Some((trimmed[..len].to_string(), info))
+}
+
+fn parse_code_block(
+    lines: &[String],
+    start: usize,
+    indent: usize,
+    fence: &str,
+    info: &str,
+    html: &mut String,
+) -> usize {
+    let fence_char = fence.chars().next().unwrap_or('`');
+    let mut code = Vec::new();
+    let mut i = start + 1;
+
+    while i < lines.len() {
+        let trimmed = lines[i].trim();
+        if trimmed.len() >= fence.len() && trimmed.chars().all(|c| c == fence_char) {
+            i += 1;
+            break;
+        }
+        let line = &lines[i];
+        let strip = line.len() - line.trim_start_matches(' ').len();
+        code.push(&line[strip.min(indent)..]);
+        i += 1;
+    }
+
+    let language = info
+        .split_whitespace()
+        .next()
+        .map_or(PLAIN_LANGUAGE.to_string(), escape_html);
+    html.push_str(&format!(
+        "<pre data-language=\"{0}\"><code class=\"language-{0}\">{1}</code></pre>",
+        language,
+        escape_html_text(&code.join("\n"))
+    ));
+    i
+}
+
+fn parse_math_block(lines: &[String], start: usize, html: &mut String) -> Option<usize> {
+    let trimmed = lines[start].trim();
+    let rest = trimmed.strip_prefix("$$")?;
+
+    let (latex, next) = if let Some(latex) = rest.strip_suffix("$$") {
+        (latex.trim().to_string(), start + 1)
+    } else if rest.trim().is_empty() {
+        let close = start + 1 + lines[start + 1..].iter().position(|l| l.trim() == "$$")?;
+        (lines[start + 1..close].join("\n"), close + 1)
+    } else {
+        return None;
+    };
+
+    html.push_str(&format!(
+        "<div data-latex=\"{}\" data-type=\"math-block\" class=\"math-block\">{}</div>",
+        escape_html(&latex),
+        escape_html_text(&latex)
+    ));
+    Some(next)
+}
+
+fn heading_start(trimmed: &str) -> Option<(usize, &str)> {
+    let level = trimmed.len() - trimmed.trim_start_matches('#').len();
+    let rest = &trimmed[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+
+    // Drop an optional closing sequence of `#`s
+    let text = rest.trim();
+    let without_closing = text.trim_end_matches('#');
+    let text = if without_closing.is_empty() {
+        ""
+    } else if without_closing.ends_with(' ') {
+        without_closing.trim_end()
+    } else {
+        text
+    };
+    Some((level, text))
+}
+
+fn is_thematic_break(trimmed: &str) -> bool {
+    let Some(c) = trimmed
+        .chars()
+        .next()
+        .filter(|c| matches!(c, '-' | '*' | '_'))
+    else {
+        return false;
+    };
+    trimmed.chars().all(|ch| ch == c || ch == ' ') && trimmed.matches(c).count() >= 3
+}
+
+/// A block of raw HTML: a comment or any element that is not inline
+fn html_block_start(trimmed: &str) -> bool {
+    if trimmed.starts_with("<!--") {
+        return true;
+    }
+    let Some(rest) = trimmed.strip_prefix('<') else {
+        return false;
+    };
+    let rest = rest.strip_prefix('/').unwrap_or(rest);
+    let name_len = rest
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+        .unwrap_or(rest.len());
+    let name = rest[..name_len].to_ascii_lowercase();
+    let after = rest[name_len..].chars().next();
+
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && after.map_or(true, |c| c == '>' || c == '/' || c.is_whitespace())
+        && !INLINE_ELEMENTS.contains(&name.as_str())
+}
+
+struct ListMarker {
+    ordered: bool,
+    /// Bullet character, or the `.` or `)` after a number
+    delimiter: char,
+    number: u64,
+    /// Columns from the marker to the item text
+    width: usize,
+}
+
+fn list_marker(trimmed: &str) -> Option<ListMarker> {
+    let digits = trimmed.len()
+        - trimmed
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .len();
+    let (ordered, delimiter, number, len) = if digits > 0 {
+        if digits > 9 {
+            return None;
+        }
+        let delimiter = trimmed[digits..]
+            .chars()
+            .next()
+            .filter(|&c| c == '.' || c == ')')?;
+        (true, delimiter, trimmed[..digits].parse().ok()?, digits + 1)
+    } else {
+        let bullet = trimmed
+            .chars()
+            .next()
+            .filter(|c| matches!(c, '-' | '*' | '+'))?;
+        (false, bullet, 0, 1)
+    };
+
+    let rest = &trimmed[len..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    let spaces = rest.len() - rest.trim_start_matches(' ').len();
+    let width = if rest.trim().is_empty() || spaces > 4 {
+        len + 1
+    } else {
+        len + spaces
+    };
+
+    Some(ListMarker {
+        ordered,
+        delimiter,
+        number,
+        width,
+    })
+}
+
+fn parse_list(lines: &[String], start: usize, html: &mut String) -> usize {
+    let first = list_marker(lines[start].trim_start()).expect("list starts with a marker");
+    let mut items: Vec<Vec<String>> = Vec::new();
+    let mut i = start;
+
+    loop {
+        let line = &lines[i];
+        let indent = line.len() - line.trim_start().len();
+        let marker = list_marker(line.trim_start()).expect("item starts with a marker");
+        let column = indent + marker.width;
+
+        let mut item = vec![line.get(column..).unwrap_or("").to_string()];
+        i += 1;
+        while i < lines.len() {
+            let line = &lines[i];
+            let trimmed = line.trim_start();
+            // Only ASCII indent, so slicing at `column` stays on a char
+            // boundary (trim_start also strips e.g. U+00A0)
+            let line_indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+
+            if trimmed.is_empty() {
+                // Blank lines stay in the item only if it continues after them
+                let next = lines[i..].iter().position(|l| !l.trim().is_empty());
+                let continues = next.is_some_and(|n| {
+                    let l = &lines[i + n];
+                    l.len() - l.trim_start().len() >= column
+                });
+                if !continues {
+                    break;
+                }
+                item.push(line.get(column..).unwrap_or("").to_string());
+            } else if line_indent >= column {
+                item.push(line[column..].to_string());
+            } else if item.last().is_some_and(|l| !l.trim().is_empty()) && !starts_block(trimmed) {
+                // Lazy continuation of the item's paragraph
+                item.push(trimmed.to_string());
+            } else {
+                break;
+            }
+            i += 1;
+        }
+        items.push(item);
+
+        let next = lines[i..].iter().position(|l| !l.trim().is_empty());
+        let same_list = next
+            .and_then(|n| list_marker(lines[i + n].trim_start()))
+            .is_some_and(|m| m.ordered == first.ordered && m.delimiter == first.delimiter);
+        match next {
+            Some(n) if same_list => i += n,
+            _ => break,
+        }
+    }
+
+    let task = |item: &Vec<String>| -> Option<(bool, String)> {
+        let first = &item[0];
+        let checked = match first.get(..3)? {
+            "[ ]" => false,
+            "[x]" | "[X]" => true,
+            _ => return None,
+        };
+        let rest = &first[3..];
+        (rest.is_empty() || rest.starts_with(' ')).then(|| (checked, rest.trim_start().to_string()))
+    };
+
+    if !first.ordered && items.iter().all(|item| task(item).is_some()) {
+        html.push_str(r#"<ul class="task-list" data-type="taskList">"#);
+        for mut item in items {
+            let (checked, text) = task(&item).expect("checked above");
+            item[0] = text;
+            html.push_str(&format!(
+                "<li class=\"task-item\" data-checked=\"{}\" data-type=\"taskItem\"><label><input type=\"checkbox\"{}><span></span></label><div>{}</div></li>",
+                checked,
+                if checked { " checked=\"checked\"" } else { "" },
+                list_item_html(&item)
+            ));
+        }
+        html.push_str("</ul>");
+    } else {
+        let tag = if first.ordered { "ol" } else { "ul" };
+        if first.ordered && first.number != 1 {
+            html.push_str(&format!("<ol start=\"{}\">", first.number));
+        } else {
+            html.push_str(&format!("<{}>", tag));
+        }
+        for item in items {
+            html.push_str(&format!("<li>{}</li>", list_item_html(&item)));
+        }
+        html.push_str(&format!("</{}>", tag));
+    }
+
+    i
+}
+
+fn list_item_html(lines: &[String]) -> String {
+    let html = parse_blocks(lines);
+    if html.is_empty() {
+        "<p></p>".to_string()
+    } else {
+        html
+    }
+}
+
+fn parse_table(lines: &[String], start: usize, html: &mut String) -> Option<usize> {
+    let header = split_row(&lines[start]);
+    let delimiter = split_row(lines.get(start + 1)?);
+    let is_delimiter = |cell: &String| {
+        let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+        !dashes.is_empty() && dashes.chars().all(|c| c == '-')
+    };
+    // A paragraph line ending in a hard break is not a header row
+    if !lines[start].contains('|')
+        || hard_break(&lines[start])
+        || delimiter.len() != header.len()
+        || !delimiter.iter().all(is_delimiter)
+    {
+        return None;
+    }
+
+    html.push_str("<table><tbody>");
+    html.push_str(&table_row(&header, header.len(), "th"));
+    let mut i = start + 2;
+    while i < lines.len() {
+        let trimmed = lines[i].trim_start();
+        if trimmed.is_empty() || starts_block(trimmed) {
+            break;
+        }
+        html.push_str(&table_row(&split_row(&lines[i]), header.len(), "td"));
+        i += 1;
+    }
+    html.push_str("</tbody></table>");
+    Some(i)
+}
+
+/// Cells of a table row. A pipe after a backslash belongs to the cell.
+fn split_row(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = match line.strip_suffix('|') {
+        Some(rest) if !rest.ends_with('\\') => rest,
+        _ => line,
+    };
+
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut escaped = false;
+    for c in line.chars() {
+        match c {
+            '|' if escaped => {
+                cell.pop();
+                cell.push('|');
+            }
+            '|' => cells.push(std::mem::take(&mut cell).trim().to_string()),
+            _ => cell.push(c),
+        }
+        escaped = c == '\\';
+    }
+    cells.push(cell.trim().to_string());
+    cells
+}
+
+fn table_row(cells: &[String], columns: usize, tag: &str) -> String {
+    let mut row = String::from("<tr>");
+    for i in 0..columns {
+        let text = cells
+            .get(i)
+            .map_or(String::new(), |cell| parse_inline(cell));
+        row.push_str(&format!("<{0} {1}><p>{2}</p></{0}>", tag, CELL_ATTRS, text));
+    }
+    row.push_str("</tr>");
+    row
+}
+
+fn parse_paragraph(lines: &[String], start: usize, html: &mut String) -> usize {
+    let mut text = String::new();
+    let mut i = start;
+
+    while i < lines.len() {
+        let line = &lines[i];
+        let trimmed = line.trim();
+        if trimmed.is_empty() || (i > start && starts_block(line.trim_start())) {
+            break;
+        }
+        if i > start {
+            text.push(if hard_break(&lines[i - 1]) { '\n' } else { ' ' });
+        }
+        let trailing_slashes = trimmed.len() - trimmed.trim_end_matches('\\').len();
+        let is_last = lines.get(i + 1).map_or(true, |next| {
+            next.trim().is_empty() || starts_block(next.trim_start())
+        });
+        if trailing_slashes % 2 == 1 && !is_last {
+            text.push_str(&trimmed[..trimmed.len() - 1]);
+        } else {
+            text.push_str(trimmed);
+        }
+        i += 1;
+    }
+
+    match parse_image(&text, 0) {
+        Some((image, end)) if end == text.len() => html.push_str(&image),
+        _ => html.push_str(&format!("<p>{}</p>", parse_inline(&text))),
+    }
+    i
+}
+
+/// A line ending in a backslash or two spaces breaks the line after it
+fn hard_break(line: &str) -> bool {
+    let trimmed = line.trim_end();
+    let slashes = trimmed.len() - trimmed.trim_end_matches('\\').len();
+    slashes % 2 == 1 || line.ends_with("  ")
+}
+
+// =============================================================================
+// INLINE MARKDOWN
+// =============================================================================
+
+enum Piece {
+    Html(String),
+    Run(Run),
+}
+
+/// A run of `*`, `_` or `~`: tags it closes, characters left as text, then
+/// tags it may open
+struct Run {
+    c: char,
+    closes: Vec<&'static str>,
+    literal: usize,
+    /// Tags in nesting order with their delimiter length, and whether a
+    /// later run closed them
+    opens: Vec<(&'static str, usize, bool)>,
+}
+
+/// Inline Markdown as HTML. `\n` marks a hard break.
+///
+/// Emphasis nests strictly: a run first closes the innermost open
+/// delimiters of its kind, then opens new ones, so the delimiters the
+/// serializer writes always pair back up as they were.
+fn parse_inline(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut pieces = Vec::new();
+    let mut literal = String::new();
+    // Open delimiters: piece index, index into its `opens`, character, length
+    let mut open: Vec<(usize, usize, char, usize)> = Vec::new();
+    let mut i = 0;
+
+    let flush = |literal: &mut String, pieces: &mut Vec<Piece>| {
+        if !literal.is_empty() {
+            pieces.push(Piece::Html(escape_html_text(literal)));
+            literal.clear();
+        }
+    };
+
+    while i < text.len() {
+        let b = bytes[i];
+        if let Some(end) = wikilink_at(text, i) {
+            // Wikilinks stay as typed; the app resolves them
+            literal.push_str(&text[i..end]);
+            i = end;
+            continue;
+        }
+        if let Some((html, end)) = atom(text, i) {
+            flush(&mut literal, &mut pieces);
+            pieces.push(Piece::Html(html));
+            i = end;
+            continue;
+        }
+
+        match b {
+            b'\\' if bytes.get(i + 1).is_some_and(u8::is_ascii_punctuation) => {
+                literal.push(bytes[i + 1] as char);
+                i += 2;
+            }
+            b'`' => {
+                // No closing run: the backticks are text
+                let run = text[i..].len() - text[i..].trim_start_matches('`').len();
+                literal.push_str(&text[i..i + run]);
+                i += run;
+            }
+            b'*' | b'_' | b'~' => {
+                let c = b as char;
+                let run = text[i..].len() - text[i..].trim_start_matches(c).len();
+                let prev = text[..i].chars().next_back();
+                let next = text[i + run..].chars().next();
+                i += run;
+
+                let mut can_open = next.is_some_and(|n| !n.is_whitespace());
+                let mut can_close = prev.is_some_and(|p| !p.is_whitespace());
+                if c == '_' {
+                    can_open &= !prev.is_some_and(char::is_alphanumeric);
+                    can_close &= !next.is_some_and(char::is_alphanumeric);
+                }
+                if c == '~' && run != 2 {
+                    literal.extend(std::iter::repeat(c).take(run));
+                    continue;
+                }
+                flush(&mut literal, &mut pieces);
+
+                let mut remaining = run;
+                let mut closes = Vec::new();
+                while can_close && remaining > 0 {
+                    // Unclosed delimiters of other kinds inside stay text
+                    let Some(at) = open.iter().rposition(|&(_, _, oc, _)| oc == c) else {
+                        break;
+                    };
+                    let (piece, index, _, len) = open[at];
+                    if len > remaining {
+                        break;
+                    }
+                    if let Piece::Run(opener) = &mut pieces[piece] {
+                        opener.opens[index].2 = true;
+                        closes.push(opener.opens[index].0);
+                    }
+                    open.truncate(at);
+                    remaining -= len;
+                }
+
+                let mut opens = Vec::new();
+                if can_open && remaining > 0 {
+                    // Bold goes outside italic, as the editor nests them
+                    let tags: &[(&str, usize)] = match (c, remaining) {
+                        ('~', _) => &[("s", 2)],
+                        (_, 1) => &[("em", 1)],
+                        (_, 2) => &[("strong", 2)],
+                        _ => &[("strong", 2), ("em", 1)],
+                    };
+                    for &(tag, len) in tags {
+                        open.push((pieces.len(), opens.len(), c, len));
+                        opens.push((tag, len, false));
+                        remaining -= len;
+                    }
+                }
+
+                pieces.push(Piece::Run(Run {
+                    c,
+                    closes,
+                    literal: remaining,
+                    opens,
+                }));
+            }
+            b'\n' => {
+                flush(&mut literal, &mut pieces);
+                pieces.push(Piece::Html("<br>".to_string()));
+                i += 1;
+            }
+            _ => {
+                let c = text[i..].chars().next().unwrap_or_default();
+                literal.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    flush(&mut literal, &mut pieces);
+
+    let mut html = String::new();
+    for piece in pieces {
+        match piece {
+            Piece::Html(text) => html.push_str(&text),
+            Piece::Run(run) => {
+                for tag in run.closes {
+                    html.push_str(&format!("</{}>", tag));
+                }
+                html.extend(std::iter::repeat(run.c).take(run.literal));
+                for (tag, len, closed) in run.opens {
+                    if closed {
+                        html.push_str(&format!("<{}>", tag));
+                    } else {
+                        html.extend(std::iter::repeat(run.c).take(len));
+                    }
+                }
+            }
+        }
+    }
+    html
+}
+
+/// End of a `[[wikilink]]` starting at `i`
+fn wikilink_at(text: &str, i: usize) -> Option<usize> {
+    if !text[i..].starts_with("[[") {
+        return None;
+    }
+    find_wikilinks(&text[i..])
+        .first()
+        .filter(|(range, _, _)| range.start == 0)
+        .map(|(range, _, _)| i + range.end)
+}
+
+/// A span that is parsed as a whole: code, math, an image or link, or raw
+/// HTML. Returns its HTML and where it ends.
+fn atom(text: &str, i: usize) -> Option<(String, usize)> {
+    match text.as_bytes()[i] {
+        b'`' => code_span_at(text, i),
+        b'$' => math_at(text, i),
+        b'!' => parse_image(text, i),
+        b'[' => {
+            let (label, after) = bracketed(text, i)?;
+            let (href, _, end) = link_target(text, after)?;
+            Some((
+                format!(
+                    "<a href=\"{}\" {}>{}</a>",
+                    escape_html(&href),
+                    LINK_ATTRS,
+                    parse_inline(label)
+                ),
+                end,
+            ))
+        }
+        b'<' => raw_tag_at(text, i),
+        _ => None,
+    }
+}
+
+fn code_span_at(text: &str, i: usize) -> Option<(String, usize)> {
+    let run = text[i..].len() - text[i..].trim_start_matches('`').len();
+    let body_start = i + run;
+    let mut j = body_start;
+
+    while let Some(found) = text[j..].find('`') {
+        let at = j + found;
+        let len = text[at..].len() - text[at..].trim_start_matches('`').len();
+        if len == run {
+            let mut code = text[body_start..at].replace('\n', " ");
+            if code.len() > 1
+                && code.starts_with(' ')
+                && code.ends_with(' ')
+                && !code.trim().is_empty()
+            {
+                code = code[1..code.len() - 1].to_string();
+            }
+            return Some((
+                format!("<code>{}</code>", escape_html_text(&code)),
+                at + len,
+            ));
+        }
+        j = at + len;
+    }
+    None
+}
+
+fn math_at(text: &str, i: usize) -> Option<(String, usize)> {
+    let body = &text[i + 1..];
+    if body.starts_with(|c: char| c.is_whitespace() || c == '$') || body.is_empty() {
+        return None;
+    }
+    let close = body.find('$')?;
+    let latex = &body[..close];
+    if latex.ends_with(char::is_whitespace) {
+        return None;
+    }
+    Some((
+        format!(
+            "<span data-latex=\"{}\" data-type=\"math-inline\" class=\"math-inline\">{}</span>",
+            escape_html(latex),
+            escape_html_text(latex)
+        ),
+        i + 1 + close + 1,
+    ))
+}
+
+fn parse_image(text: &str, i: usize) -> Option<(String, usize)> {
+    if !text[i..].starts_with("![") {
+        return None;
+    }
+    let (label, after) = bracketed(text, i + 1)?;
+    let (src, title, end) = link_target(text, after)?;
+
+    let mut html = format!("<img src=\"{}\"", escape_html(&src));
+    let alt = unescape(label);
+    if !alt.is_empty() {
+        html.push_str(&format!(" alt=\"{}\"", escape_html(&alt)));
+    }
+    if let Some(title) = title {
+        html.push_str(&format!(" title=\"{}\"", escape_html(&title)));
+    }
+    html.push('>');
+    Some((html, end))
+}
+
+/// The text inside `[...]` starting at `i`, and the index after `]`
+fn bracketed(text: &str, i: usize) -> Option<(&str, usize)> {
+    let bytes = text.as_bytes();
+    let mut depth = 0usize;
+    let mut j = i;
+
+    while j < text.len() {
+        match bytes[j] {
+            b'\\' => j += 1,
+            b'`' => {
+                if let Some((_, end)) = code_span_at(text, j) {
+                    j = end;
+                    continue;
+                }
+            }
+            b'[' => depth += 1,
+            b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((&text[i + 1..j], j + 1));
+                }
+            }
+            _ => {}
+        }
+        j += 1;
+    }
+    None
+}
+
+/// `(destination "title")` starting at `i`: the destination, the title and
+/// the index after `)`
+fn link_target(text: &str, i: usize) -> Option<(String, Option<String>, usize)> {
+    let rest = text[i..].strip_prefix('(')?;
+    let mut at = i + 1 + (rest.len() - rest.trim_start().len());
+
+    let destination = if text[at..].starts_with('<') {
+        let close = text[at + 1..].find(['>', '\n'])?;
+        if text.as_bytes()[at + 1 + close] != b'>' {
+            return None;
+        }
+        let url = text[at + 1..at + 1 + close].to_string();
+        at += close + 2;
+        url
+    } else {
+        let mut depth = 0usize;
+        let start = at;
+        for (offset, c) in text[start..].char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 0 => break,
+                ')' => depth -= 1,
+                c if c.is_whitespace() => break,
+                _ => {}
+            }
+            at = start + offset + c.len_utf8();
+        }
+        text[start..at].to_string()
+    };
+
+    let rest = &text[at..];
+    at += rest.len() - rest.trim_start().len();
+
+    let mut title = None;
+    if text[at..].starts_with('"') {
+        let mut value = String::new();
+        let mut chars = text[at + 1..].char_indices();
+        let mut closed = None;
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    if let Some((_, next)) = chars.next() {
+                        if !next.is_ascii_punctuation() {
+                            value.push('\\');
+                        }
+                        value.push(next);
+                    }
+                }
+                '"' => {
+                    closed = Some(at + 1 + offset + 1);
+                    break;
+                }
+                _ => value.push(c),
+            }
+        }
+        at = closed?;
+        title = Some(value);
+        let rest = &text[at..];
+        at += rest.len() - rest.trim_start().len();
+    }
+
+    text[at..]
+        .starts_with(')')
+        .then(|| (destination, title, at + 1))
+}
+
+/// An HTML tag or `<scheme:...>` autolink starting at `i`
+fn raw_tag_at(text: &str, i: usize) -> Option<(String, usize)> {
+    let rest = &text[i + 1..];
+    let name = rest.strip_prefix('/').unwrap_or(rest);
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) && !rest.starts_with("!--") {
+        return None;
+    }
+
+    let scheme_len = name
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '+' && c != '.' && c != '-')
+        .unwrap_or(name.len());
+    if !rest.starts_with('/') && name[scheme_len..].starts_with(':') {
+        let close = rest.find('>')?;
+        let url = &rest[..close];
+        if url.contains(|c: char| c.is_whitespace() || c == '<') {
+            return None;
+        }
+        let html = format!(
+            "<a href=\"{}\" {}>{}</a>",
+            escape_html(url),
+            LINK_ATTRS,
+            escape_html_text(url)
+        );
+        return Some((html, i + 1 + close + 1));
+    }
+
+    let end = if rest.starts_with("!--") {
+        i + 1 + rest.find("-->")? + 3
+    } else {
+        find_tag_end(text, i + 1)? + 1
+    };
+    Some((text[i..end].to_string(), end))
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(next) if c == '\\' && next.is_ascii_punctuation() => {
+                out.push(*next);
+                chars.next();
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escape text content the way the editor serializes it
+fn escape_html_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\u{a0}', "&nbsp;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn converts_blocks() {
+        let html = concat!(
+            "<h2>Plan</h2>",
+            "<p>Some <strong>bold</strong>, <em>italic</em> and <s>gone</s> text.</p>",
+            "<ul><li><p>one</p><ul><li><p>nested</p></li></ul></li><li><p>two</p></li></ul>",
+            r#"<ol start="3"><li><p>three</p></li><li><p>four</p></li></ol>"#,
+            "<blockquote><p>quoted</p></blockquote>",
+            "<hr>",
+        );
+        let markdown = html_to_markdown(html);
+        assert_eq!(
+            markdown,
+            "## Plan\n\nSome **bold**, *italic* and ~~gone~~ text.\n\n- one\n  - nested\n- two\n\n3. three\n4. four\n\n> quoted\n\n---\n"
+        );
+        assert_eq!(markdown_to_html(&markdown), html);
+    }
+
+    #[test]
+    fn converts_editor_nodes() {
+        let html = concat!(
+            r#"<ul class="task-list" data-type="taskList"><li class="task-item" data-checked="true" data-type="taskItem"><label><input type="checkbox" checked="checked"><span></span></label><div><p>done</p></div></li>"#,
+            r#"<li class="task-item" data-checked="false" data-type="taskItem"><label><input type="checkbox"><span></span></label><div><p>todo</p></div></li></ul>"#,
+            r#"<pre data-language="rust"><code class="language-rust">fn main() {}</code></pre>"#,
+            r#"<div data-latex="e^{i\pi}" data-type="math-block" class="math-block">e^{i\pi}</div>"#,
+            r#"<p>Inline <span data-latex="x^2" data-type="math-inline" class="math-inline">x^2</span> and <code>code</code></p>"#,
+        );
+        let markdown = html_to_markdown(html);
+        assert_eq!(
+            markdown,
+            "- [x] done\n- [ ] todo\n\n```rust\nfn main() {}\n```\n\n$$\ne^{i\\pi}\n$$\n\nInline $x^2$ and `code`\n"
+        );
+        assert_eq!(markdown_to_html(&markdown), html);
+    }
+
+    #[test]
+    fn converts_tables() {
+        let html = concat!(
+            r#"<table><tbody><tr><th colspan="1" rowspan="1"><p>Name</p></th><th colspan="1" rowspan="1"><p>Note</p></th></tr>"#,
+            r#"<tr><td colspan="1" rowspan="1"><p>a | b</p></td><td colspan="1" rowspan="1"><p></p></td></tr></tbody></table>"#,
+        );
+        let markdown = html_to_markdown(html);
+        assert_eq!(markdown, "| Name | Note |\n| --- | --- |\n| a \\| b |  |\n");
+        assert_eq!(markdown_to_html(&markdown), html);
+
+        // Merged cells have no Markdown form
+        let merged = r#"<table><tbody><tr><th colspan="2" rowspan="1"><p>Wide</p></th></tr></tbody></table>"#;
+        assert_eq!(html_to_markdown(merged), format!("{}\n", merged));
+        assert_eq!(markdown_to_html(&html_to_markdown(merged)), merged);
+    }
+
+    #[test]
+    fn converts_links_and_images() {
+        let html = concat!(
+            r#"<p>See <a href="https://example.com" target="_blank" rel="noopener noreferrer" class="text-yellow-500">the site</a>, "#,
+            r##"<a data-note-id="n1" data-note-label="Groceries" class="note-link" href="#" onclick="return false">Groceries</a> and [[Recipes|dinner]].</p>"##,
+            r#"<img src="attachments/cat.png" alt="A cat" title="Cat">"#,
+        );
+        assert_eq!(
+            html_to_markdown(html),
+            "See [the site](https://example.com), [[Groceries]] and [[Recipes|dinner]].\n\n![A cat](attachments/cat.png \"Cat\")\n"
+        );
+        assert_eq!(
+            markdown_to_html("[a](<my file.md>) <https://x.org>"),
+            r#"<p><a href="my file.md" target="_blank" rel="noopener noreferrer">a</a> <a href="https://x.org" target="_blank" rel="noopener noreferrer">https://x.org</a></p>"#
+        );
+    }
+
+    #[test]
+    fn keeps_unsupported_html() {
+        let html = r#"<div data-type="callout" data-emoji="💡"><p>Tip</p></div><p>Some <u>underlined</u> text</p>"#;
+        let markdown = html_to_markdown(html);
+        assert_eq!(
+            markdown,
+            "<div data-type=\"callout\" data-emoji=\"💡\"><p>Tip</p></div>\n\nSome <u>underlined</u> text\n"
+        );
+        assert_eq!(markdown_to_html(&markdown), html);
+    }
+
+    #[test]
+    fn escapes_markdown_syntax_in_text() {
+        let html = "<p># not a heading, 2*3 = 6, $5 and snake_case</p><p>1. not a list</p>";
+        let markdown = html_to_markdown(html);
+        assert_eq!(
+            markdown,
+            "\\# not a heading, 2\\*3 = 6, \\$5 and snake_case\n\n1\\. not a list\n"
+        );
+        assert_eq!(markdown_to_html(&markdown), html);
+    }
+
+    #[test]
+    fn reads_common_markdown() {
+        assert_eq!(
+            markdown_to_html("# Title #\n\nline one\nline two  \nbroken\n\n* a\n* b\n\n1) x"),
+            "<h1>Title</h1><p>line one line two<br>broken</p><ul><li><p>a</p></li><li><p>b</p></li></ul><ol><li><p>x</p></li></ol>"
+        );
+        assert_eq!(
+            markdown_to_html("__bold__ _it_ ***both*** ~~s~~ *unclosed"),
+            "<p><strong>bold</strong> <em>it</em> <strong><em>both</em></strong> <s>s</s> *unclosed</p>"
+        );
+        assert_eq!(
+            markdown_to_html("~~~\nraw `code`\n~~~"),
+            r#"<pre data-language="text"><code class="language-text">raw `code`</code></pre>"#
+        );
+    }
+
+    #[test]
+    fn separates_adjacent_lists() {
+        let html = "<ul><li><p>a</p></li></ul><ul><li><p>b</p></li></ul><ol><li><p>c</p></li></ol><ol><li><p>d</p></li></ol>";
+        let markdown = html_to_markdown(html);
+        assert_eq!(markdown, "- a\n\n* b\n\n1. c\n\n1) d\n");
+        assert_eq!(markdown_to_html(&markdown), html);
+    }
+
+    #[test]
+    fn list_items_indented_with_unicode_spaces() {
+        // Found by never_panics: U+00A0 counted as indent sliced inside it
+        let html = markdown_to_html("*\tA\n\t\u{a0}A");
+        assert!(html.starts_with("<ul><li><p>A"), "{}", html);
+    }
+
+    // ===== ROUND TRIPS =====
+    //
+    // Documents are generated as the editor would serialize them, marks
+    // nested in schema order like ProseMirror does, and must survive
+    // HTML → Markdown → HTML unchanged.
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Mark {
+        Strong,
+        Code,
+        Em,
+        Strike,
+        Link(String),
+    }
+
+    #[derive(Debug, Clone)]
+    enum Inline {
+        Text(String, Vec<Mark>),
+        Math(String),
+        Break,
+    }
+
+    #[derive(Debug, Clone)]
+    enum Doc {
+        Paragraph(Vec<Inline>),
+        Heading(usize, Vec<Inline>),
+        Bullets(Vec<Vec<Doc>>),
+        Ordered(u64, Vec<Vec<Doc>>),
+        Tasks(Vec<(bool, Vec<Doc>)>),
+        Code(String, String),
+        Quote(Vec<Doc>),
+        Rule,
+        Math(String),
+        Table(Vec<Vec<Vec<Inline>>>),
+        Image(String, String, Option<String>),
+    }
+
+    const EDGE: &str = r"[a-z0-9*_`$\[\]<>&~#|!.\\é-]";
+
+    fn text() -> impl Strategy<Value = String> {
+        prop_oneof![
+            4 => proptest::string::string_regex(&format!(
+                r"{0}([a-z0-9 *_`$\[\]<>&~#|!.\\é\u{{a0}}-]{{0,6}}{0})?",
+                EDGE
+            ))
+            .unwrap(),
+            1 => r"\[\[[A-Za-z][a-z ]{0,4}\]\]",
+        ]
+    }
+
+    fn marks() -> impl Strategy<Value = Vec<Mark>> {
+        let link = prop_oneof![
+            Just(None),
+            r"[a-z:/.]{1,8}".prop_map(Some),
+            r"[a-z ()]{1,6}".prop_map(Some)
+        ];
+        (any::<[bool; 4]>(), link).prop_map(|(flags, link)| {
+            if flags[1] {
+                return vec![Mark::Code];
+            }
+            let mut marks = Vec::new();
+            if flags[0] {
+                marks.push(Mark::Strong);
+            }
+            if flags[2] {
+                marks.push(Mark::Em);
+            }
+            if flags[3] {
+                marks.push(Mark::Strike);
+            }
+            marks.extend(link.map(Mark::Link));
+            marks
+        })
+    }
+
+    fn inline_content(breaks: bool) -> impl Strategy<Value = Vec<Inline>> {
+        let item = prop_oneof![
+            6 => (text(), marks()).prop_map(|(text, marks)| {
+                if marks == [Mark::Code] {
+                    // Code keeps its text verbatim, spaces and all
+                    Inline::Text(text.replace('\u{a0}', " "), marks)
+                } else {
+                    Inline::Text(text, marks)
+                }
+            }),
+            2 => Just(Inline::Text(" ".to_string(), Vec::new())),
+            1 => r"[a-z0-9+^{}\\]{1,5}".prop_map(Inline::Math),
+            1 => Just(Inline::Break),
+        ];
+        prop::collection::vec(item, 1..7).prop_map(move |items| normalize(items, breaks))
+    }
+
+    /// Merge text with equal marks and drop spaces Markdown can't keep: at
+    /// the ends of a block and around line breaks
+    fn normalize(items: Vec<Inline>, breaks: bool) -> Vec<Inline> {
+        let mut out: Vec<Inline> = Vec::new();
+        for item in items {
+            match (&item, out.last_mut()) {
+                (Inline::Break, _) if !breaks => continue,
+                (Inline::Text(text, marks), Some(Inline::Text(prev, prev_marks)))
+                    if marks == prev_marks =>
+                {
+                    prev.push_str(text)
+                }
+                _ => out.push(item),
+            }
+        }
+
+        for i in 0..out.len() {
+            let after_break = i == 0 || matches!(out[i - 1], Inline::Break);
+            let before_break = i + 1 == out.len() || matches!(out[i + 1], Inline::Break);
+            if let Inline::Text(text, marks) = &mut out[i] {
+                if marks.is_empty() {
+                    if after_break {
+                        *text = text.trim_start_matches(' ').to_string();
+                    }
+                    if before_break {
+                        *text = text.trim_end_matches(' ').to_string();
+                    }
+                }
+            }
+        }
+        out.retain(|item| !matches!(item, Inline::Text(text, _) if text.is_empty()));
+
+        while matches!(out.first(), Some(Inline::Break)) {
+            out.remove(0);
+        }
+        while matches!(out.last(), Some(Inline::Break)) {
+            out.pop();
+        }
+        if out.is_empty() {
+            out.push(Inline::Text("x".to_string(), Vec::new()));
+        }
+        out
+    }
+
+    fn list_items() -> impl Strategy<Value = Vec<Vec<Doc>>> {
+        prop::collection::vec(
+            inline_content(true).prop_map(|content| vec![Doc::Paragraph(content)]),
+            1..4,
+        )
+    }
+
+    fn doc() -> impl Strategy<Value = Vec<Doc>> {
+        let leaf = prop_oneof![
+            4 => inline_content(true).prop_map(Doc::Paragraph),
+            1 => (1..=6usize, inline_content(false)).prop_map(|(level, content)| Doc::Heading(level, content)),
+            1 => (prop_oneof![Just("text"), Just("rust"), Just("js")], r"[a-z `~{}|\n-]{0,16}")
+                .prop_map(|(language, code)| Doc::Code(language.to_string(), code)),
+            1 => Just(Doc::Rule),
+            1 => r"[a-z0-9+^{}\\ \n]{0,12}".prop_map(Doc::Math),
+            1 => (1..4usize).prop_flat_map(|columns| {
+                prop::collection::vec(
+                    prop::collection::vec(
+                        prop_oneof![
+                            3 => inline_content(false),
+                            1 => Just(Vec::new()),
+                        ],
+                        columns,
+                    ),
+                    1..4,
+                )
+            })
+            .prop_map(Doc::Table),
+            1 => (r"[a-z/.:]{1,6}|[a-z ()]{1,6}", r"[a-z \[\]*\\]{0,5}", prop::option::of(r#"[a-z "\\]{0,5}"#))
+                .prop_map(|(src, alt, title)| Doc::Image(src, alt, title)),
+        ];
+
+        let block = leaf.prop_recursive(3, 24, 4, |inner| {
+            let item = (
+                inline_content(true),
+                prop::collection::vec(inner.clone(), 0..3),
+            )
+                .prop_map(|(first, rest)| {
+                    let mut blocks = vec![Doc::Paragraph(first)];
+                    blocks.extend(rest);
+                    blocks
+                })
+                .boxed();
+            prop_oneof![
+                prop::collection::vec(item.clone(), 1..4).prop_map(Doc::Bullets),
+                (0..12u64, prop::collection::vec(item.clone(), 1..4))
+                    .prop_map(|(start, items)| Doc::Ordered(start, items)),
+                prop::collection::vec((any::<bool>(), item), 1..4).prop_map(Doc::Tasks),
+                prop::collection::vec(inner, 1..3).prop_map(Doc::Quote),
+                list_items().prop_map(Doc::Bullets),
+            ]
+        });
+
+        prop::collection::vec(block, 1..6)
+    }
+
+    fn mark_tags(mark: &Mark) -> (String, &'static str) {
+        match mark {
+            Mark::Strong => ("<strong>".to_string(), "</strong>"),
+            Mark::Code => ("<code>".to_string(), "</code>"),
+            Mark::Em => ("<em>".to_string(), "</em>"),
+            Mark::Strike => ("<s>".to_string(), "</s>"),
+            Mark::Link(href) => (
+                format!(r#"<a href="{}" {}>"#, escape_html(href), LINK_ATTRS),
+                "</a>",
+            ),
+        }
+    }
+
+    /// Inline HTML as ProseMirror serializes it: marks that carry on from
+    /// the previous node stay open
+    fn render_inline(items: &[Inline]) -> String {
+        let mut html = String::new();
+        let mut open: Vec<&Mark> = Vec::new();
+        for item in items {
+            let marks: Vec<&Mark> = match item {
+                Inline::Text(_, marks) => marks.iter().collect(),
+                _ => Vec::new(),
+            };
+            let keep = open.iter().zip(&marks).take_while(|(a, b)| a == b).count();
+            while open.len() > keep {
+                html.push_str(mark_tags(open.pop().unwrap()).1);
+            }
+            for mark in &marks[keep..] {
+                html.push_str(&mark_tags(mark).0);
+                open.push(mark);
+            }
+            match item {
+                Inline::Text(text, _) => html.push_str(&escape_html_text(text)),
+                Inline::Math(latex) => html.push_str(&format!(
+                    r#"<span data-latex="{}" data-type="math-inline" class="math-inline">{}</span>"#,
+                    escape_html(latex),
+                    escape_html_text(latex)
+                )),
+                Inline::Break => html.push_str("<br>"),
+            }
+        }
+        while let Some(mark) = open.pop() {
+            html.push_str(mark_tags(mark).1);
+        }
+        html
+    }
+
+    fn render(docs: &[Doc]) -> String {
+        docs.iter().map(render_block).collect()
+    }
+
+    fn render_block(doc: &Doc) -> String {
+        let items = |items: &[Vec<Doc>]| -> String {
+            items
+                .iter()
+                .map(|blocks| format!("<li>{}</li>", render(blocks)))
+                .collect()
+        };
+        match doc {
+            Doc::Paragraph(content) => format!("<p>{}</p>", render_inline(content)),
+            Doc::Heading(level, content) => {
+                format!("<h{0}>{1}</h{0}>", level, render_inline(content))
+            }
+            Doc::Bullets(list) => format!("<ul>{}</ul>", items(list)),
+            Doc::Ordered(1, list) => format!("<ol>{}</ol>", items(list)),
+            Doc::Ordered(start, list) => format!(r#"<ol start="{}">{}</ol>"#, start, items(list)),
+            Doc::Tasks(list) => {
+                let items: String = list
+                    .iter()
+                    .map(|(checked, blocks)| {
+                        format!(
+                            r#"<li class="task-item" data-checked="{}" data-type="taskItem"><label><input type="checkbox"{}><span></span></label><div>{}</div></li>"#,
+                            checked,
+                            if *checked { r#" checked="checked""# } else { "" },
+                            render(blocks)
+                        )
+                    })
+                    .collect();
+                format!(
+                    r#"<ul class="task-list" data-type="taskList">{}</ul>"#,
+                    items
+                )
+            }
+            Doc::Code(language, code) => format!(
+                r#"<pre data-language="{0}"><code class="language-{0}">{1}</code></pre>"#,
+                language,
+                escape_html_text(code)
+            ),
+            Doc::Quote(blocks) => format!("<blockquote>{}</blockquote>", render(blocks)),
+            Doc::Rule => "<hr>".to_string(),
+            Doc::Math(latex) => format!(
+                r#"<div data-latex="{}" data-type="math-block" class="math-block">{}</div>"#,
+                escape_html(latex),
+                escape_html_text(latex)
+            ),
+            Doc::Table(rows) => {
+                let mut html = String::from("<table><tbody>");
+                for (index, row) in rows.iter().enumerate() {
+                    let tag = if index == 0 { "th" } else { "td" };
+                    html.push_str("<tr>");
+                    for cell in row {
+                        html.push_str(&format!(
+                            "<{0} {1}><p>{2}</p></{0}>",
+                            tag,
+                            CELL_ATTRS,
+                            render_inline(cell)
+                        ));
+                    }
+                    html.push_str("</tr>");
+                }
+                html.push_str("</tbody></table>");
+                html
+            }
+            Doc::Image(src, alt, title) => {
+                let mut html = format!(r#"<img src="{}""#, escape_html(src));
+                if !alt.is_empty() {
+                    html.push_str(&format!(r#" alt="{}""#, escape_html(alt)));
+                }
+                if let Some(title) = title {
+                    html.push_str(&format!(r#" title="{}""#, escape_html(title)));
+                }
+                html.push('>');
+                html
+            }
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(512))]
+
+        #[test]
+        fn editor_html_round_trips(docs in doc()) {
+            let html = render(&docs);
+            let markdown = html_to_markdown(&html);
+            prop_assert_eq!(markdown_to_html(&markdown), html, "via {:?}", markdown);
+        }
+
+        #[test]
+        fn markdown_is_stable(docs in doc()) {
+            let markdown = html_to_markdown(&render(&docs));
+            prop_assert_eq!(html_to_markdown(&markdown_to_html(&markdown)), markdown);
+        }
+
+        #[test]
+        fn never_panics(input in r"[ -~\n\t\u{a0}é]{0,80}") {
+            let html = markdown_to_html(&input);
+            html_to_markdown(&html);
+            html_to_markdown(&input);
+        }
+    }
+}
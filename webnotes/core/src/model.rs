@@ -0,0 +1,141 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// =============================================================================
+// DATA TYPES
+// =============================================================================
+//
+// IDs are strings (UUIDs from the apps, cuids from the web backend) and
+// timestamps are RFC3339 strings, exactly as stored and as sent to the
+// frontend. Use the accessors to get `DateTime`s.
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Note {
+    pub id: String,
+    pub title: String,
+    pub content: String,
+    pub folder_id: Option<String>,
+    pub is_pinned: bool,
+    pub pinned_at: Option<String>,
+    pub font: Option<String>,
+    pub updated_at: String,
+    pub created_at: String,
+    /// Bumped by every write, for optimistic locking (Prisma `Note.version`)
+    #[serde(default)]
+    pub version: i64,
+}
+
+impl Note {
+    /// A new unfiled note with a random ID, created now
+    pub fn new(title: impl Into<String>, content: impl Into<String>) -> Self {
+        let now = Utc::now().to_rfc3339();
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: title.into(),
+            content: content.into(),
+            folder_id: None,
+            is_pinned: false,
+            pinned_at: None,
+            font: None,
+            updated_at: now.clone(),
+            created_at: now,
+            version: 0,
+        }
+    }
+
+    /// `updated_at`, or `None` if it is not valid RFC3339
+    pub fn updated(&self) -> Option<DateTime<Utc>> {
+        parse_timestamp(&self.updated_at)
+    }
+
+    /// `created_at`, or `None` if it is not valid RFC3339
+    pub fn created(&self) -> Option<DateTime<Utc>> {
+        parse_timestamp(&self.created_at)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Folder {
+    pub id: String,
+    pub name: String,
+    pub created_at: String,
+}
+
+impl Folder {
+    /// A new folder with a random ID, created now
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.into(),
+            created_at: Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// A saved copy of a note (Prisma `NoteVersion`)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteVersion {
+    pub id: String,
+    pub note_id: String,
+    pub title: String,
+    pub content: String,
+    pub created_at: String,
+    /// "manual" for snapshots taken by the user; Prisma also uses "auto"
+    /// and "restore_backup"
+    pub change_type: String,
+}
+
+/// An account that has signed in on this device (Prisma `User`, as sent by
+/// the desktop sign-in page)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Account {
+    pub id: String,
+    pub email: Option<String>,
+    pub name: Option<String>,
+    pub image: Option<String>,
+    /// Most recent sign-in here; set when signing in
+    #[serde(default)]
+    pub signed_in_at: String,
+}
+
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|at| at.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_with_frontend_field_names() {
+        let mut note = Note::new("Title", "<p>Body</p>");
+        note.folder_id = Some("f1".to_string());
+        let json = serde_json::to_value(&note).unwrap();
+
+        assert_eq!(json["folderId"], "f1");
+        assert_eq!(json["isPinned"], false);
+        assert!(json["updatedAt"].is_string());
+        assert_eq!(serde_json::from_value::<Note>(json).unwrap(), note);
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        let mut note = Note::new("", "");
+        assert!(note.created().is_some());
+
+        note.updated_at = "2024-03-01T10:00:00+02:00".to_string();
+        assert_eq!(
+            note.updated().unwrap().to_rfc3339(),
+            "2024-03-01T08:00:00+00:00"
+        );
+
+        note.updated_at = "yesterday".to_string();
+        assert_eq!(note.updated(), None);
+    }
+}
@@ -0,0 +1,473 @@
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult, Row};
+
+use crate::accounts::{NOTE_TABLES, OWNED_BY_ACTIVE};
+use crate::compression::{encode_content, StoredContent};
+use crate::daily_notes::move_daily_notes;
+use crate::model::Note;
+use crate::properties::copy_properties;
+use crate::related::index_note_vector;
+use crate::reminders::move_reminders;
+use crate::search::{index_note, unindex_note};
+use crate::tasks::index_note_tasks;
+
+// =============================================================================
+// NOTE OPERATIONS
+// =============================================================================
+//
+// Writes keep what is derived from a note's text (its `notes_fts` row, tasks
+// and related-note vector) in step with the notes table, and deleting a note
+// deletes every row kept for it. Callers that write several rows should wrap
+// the calls in a transaction.
+//
+// Every update bumps `version` in the same statement, so a writer holding an
+// older copy can tell that it is stale (see `save_note_if_version`).
+//
+// Only the active account's notes are read or written (see accounts.rs); a
+// note owned by another account looks like one that does not exist.
+
+pub const NOTE_COLUMNS: &str =
+    "id, title, content, folder_id, is_pinned, pinned_at, font, updated_at, created_at, version";
+
+/// Map a row selected with `NOTE_COLUMNS` to a `Note`
+pub fn note_from_row(row: &Row) -> SqliteResult<Note> {
+    Ok(Note {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        content: row.get::<_, StoredContent>(2)?.0,
+        folder_id: row.get(3)?,
+        is_pinned: row.get(4)?,
+        pinned_at: row.get(5)?,
+        font: row.get(6)?,
+        updated_at: row.get(7)?,
+        created_at: row.get(8)?,
+        version: row.get(9)?,
+    })
+}
+
+pub fn load_note(conn: &Connection, id: &str) -> SqliteResult<Option<Note>> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM notes WHERE id = ?1 AND {}",
+            NOTE_COLUMNS, OWNED_BY_ACTIVE
+        ),
+        params![id],
+        note_from_row,
+    )
+    .optional()
+}
+
+/// Every note, pinned first (most recently pinned on top), then newest
+pub fn list_notes(conn: &Connection) -> SqliteResult<Vec<Note>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM notes WHERE {}
+         ORDER BY is_pinned DESC, pinned_at DESC, updated_at DESC",
+        NOTE_COLUMNS, OWNED_BY_ACTIVE
+    ))?;
+    let notes = stmt.query_map([], note_from_row)?;
+    notes.collect::<SqliteResult<Vec<_>>>()
+}
+
+/// Refresh a note's FTS row, extracted tasks and TF-IDF vector
+pub fn sync_note_index(
+    conn: &Connection,
+    id: &str,
+    title: &str,
+    content: &str,
+) -> SqliteResult<()> {
+    index_note(conn, id, title, content)?;
+    index_note_tasks(conn, id, content)?;
+    index_note_vector(conn, id, title, content)
+}
+
+/// Insert a brand new note and its index rows
+pub fn insert_note(conn: &Connection, note: &Note) -> SqliteResult<()> {
+    conn.execute(
+        "INSERT INTO notes (id, title, content, folder_id, is_pinned, pinned_at, font, updated_at, created_at, version)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            note.id,
+            note.title,
+            encode_content(&note.content),
+            note.folder_id,
+            note.is_pinned,
+            note.pinned_at,
+            note.font,
+            note.updated_at,
+            note.created_at,
+            note.version
+        ],
+    )?;
+    sync_note_index(conn, &note.id, &note.title, &note.content)
+}
+
+/// Insert a note, or overwrite the stored one, exactly as given: timestamps
+/// and version included. For restoring notes from elsewhere, not for edits.
+/// Fails with `QueryReturnedNoRows` if the ID belongs to another account.
+pub fn restore_note(conn: &Connection, note: &Note) -> SqliteResult<()> {
+    let written = conn.execute(
+        &format!(
+            "INSERT INTO notes (id, title, content, folder_id, is_pinned, pinned_at, font, updated_at, created_at, version)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+             ON CONFLICT(id) DO UPDATE SET
+                title = excluded.title,
+                content = excluded.content,
+                folder_id = excluded.folder_id,
+                is_pinned = excluded.is_pinned,
+                pinned_at = excluded.pinned_at,
+                font = excluded.font,
+                updated_at = excluded.updated_at,
+                created_at = excluded.created_at,
+                version = excluded.version
+             WHERE notes.{}",
+            OWNED_BY_ACTIVE
+        ),
+        params![
+            note.id,
+            note.title,
+            encode_content(&note.content),
+            note.folder_id,
+            note.is_pinned,
+            note.pinned_at,
+            note.font,
+            note.updated_at,
+            note.created_at,
+            note.version
+        ],
+    )?;
+    if written == 0 {
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+    sync_note_index(conn, &note.id, &note.title, &note.content)
+}
+
+/// Use client-provided updated_at, fallback to server time
+fn stamp_updated_at(note: &Note) -> String {
+    if note.updated_at.is_empty() {
+        Utc::now().to_rfc3339()
+    } else {
+        note.updated_at.clone()
+    }
+}
+
+/// Insert or update a note, whatever version is stored. An empty
+/// `updated_at` is stamped with the current time; `created_at` and
+/// `version` are only written on insert. Fails with `QueryReturnedNoRows`
+/// if the ID belongs to another account.
+pub fn save_note(conn: &Connection, note: &Note) -> SqliteResult<()> {
+    let written = conn.execute(
+        &format!(
+            "INSERT INTO notes (id, title, content, folder_id, is_pinned, pinned_at, font, updated_at, created_at, version)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+             ON CONFLICT(id) DO UPDATE SET
+                title = excluded.title,
+                content = excluded.content,
+                folder_id = excluded.folder_id,
+                is_pinned = excluded.is_pinned,
+                pinned_at = excluded.pinned_at,
+                font = excluded.font,
+                updated_at = excluded.updated_at,
+                version = notes.version + 1
+             WHERE notes.{}",
+            OWNED_BY_ACTIVE
+        ),
+        params![
+            note.id,
+            note.title,
+            encode_content(&note.content),
+            note.folder_id,
+            note.is_pinned,
+            note.pinned_at,
+            note.font,
+            stamp_updated_at(note),
+            note.created_at,
+            note.version
+        ],
+    )?;
+    if written == 0 {
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+
+    sync_note_index(conn, &note.id, &note.title, &note.content)
+}
+
+/// Outcome of `save_note_if_version`
+#[derive(Debug, Clone, PartialEq)]
+pub enum VersionedSave {
+    /// The note as stored, with its new version
+    Saved(Note),
+    /// The stored version has moved on; the note as currently stored
+    Conflict(Note),
+}
+
+/// Update a note only if its stored version is still `expected_version`,
+/// bumping the version in the same statement. A note that does not exist
+/// (yet, or any more) is inserted as given.
+pub fn save_note_if_version(
+    conn: &Connection,
+    note: &Note,
+    expected_version: i64,
+) -> SqliteResult<VersionedSave> {
+    let updated = conn.execute(
+        &format!(
+            "UPDATE notes SET
+                title = ?1,
+                content = ?2,
+                folder_id = ?3,
+                is_pinned = ?4,
+                pinned_at = ?5,
+                font = ?6,
+                updated_at = ?7,
+                version = version + 1
+             WHERE id = ?8 AND version = ?9 AND {}",
+            OWNED_BY_ACTIVE
+        ),
+        params![
+            note.title,
+            encode_content(&note.content),
+            note.folder_id,
+            note.is_pinned,
+            note.pinned_at,
+            note.font,
+            stamp_updated_at(note),
+            note.id,
+            expected_version
+        ],
+    )?;
+
+    if updated == 0 {
+        if let Some(current) = load_note(conn, &note.id)? {
+            return Ok(VersionedSave::Conflict(current));
+        }
+        let mut created = note.clone();
+        created.updated_at = stamp_updated_at(note);
+        insert_note(conn, &created)?;
+    } else {
+        sync_note_index(conn, &note.id, &note.title, &note.content)?;
+    }
+
+    let saved = load_note(conn, &note.id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+    Ok(VersionedSave::Saved(saved))
+}
+
+/// Replace a note's content without touching its index rows, for callers
+/// that refresh those themselves. Bumps `version`, so reload the note
+/// rather than returning a copy loaded before the update.
+pub fn update_content(
+    conn: &Connection,
+    id: &str,
+    content: &str,
+    updated_at: &str,
+) -> SqliteResult<()> {
+    conn.execute(
+        &format!(
+            "UPDATE notes SET content = ?1, updated_at = ?2, version = version + 1
+             WHERE id = ?3 AND {}",
+            OWNED_BY_ACTIVE
+        ),
+        params![encode_content(content), updated_at, id],
+    )?;
+    Ok(())
+}
+
+/// Delete a note with its index rows and everything kept per note (see
+/// `accounts::NOTE_TABLES`). Returns whether the note existed.
+pub fn delete_note(conn: &Connection, id: &str) -> SqliteResult<bool> {
+    let deleted = conn.execute(
+        &format!("DELETE FROM notes WHERE id = ?1 AND {}", OWNED_BY_ACTIVE),
+        params![id],
+    )?;
+    if deleted > 0 {
+        unindex_note(conn, id)?;
+        for table in NOTE_TABLES {
+            conn.execute(
+                &format!("DELETE FROM {} WHERE note_id = ?1", table),
+                params![id],
+            )?;
+        }
+    }
+    Ok(deleted > 0)
+}
+
+/// Flip a note's pinned state, returning the updated note. Fails with
+/// `QueryReturnedNoRows` if there is no such note.
+pub fn toggle_pin(conn: &Connection, id: &str) -> SqliteResult<Note> {
+    let mut note = load_note(conn, id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+
+    let now = Utc::now().to_rfc3339();
+    note.is_pinned = !note.is_pinned;
+    note.pinned_at = note.is_pinned.then(|| now.clone());
+    note.updated_at = now;
+    note.version += 1;
+
+    conn.execute(
+        &format!(
+            "UPDATE notes SET is_pinned = ?1, pinned_at = ?2, updated_at = ?3, version = version + 1
+             WHERE id = ?4 AND {}",
+            OWNED_BY_ACTIVE
+        ),
+        params![note.is_pinned, note.pinned_at, note.updated_at, id],
+    )?;
+    Ok(note)
+}
+
+/// Move a note to the trash folder `trash_id` and unpin it, if its stored
+/// version is still `expected_version`. Returns whether it was moved.
+pub fn trash_note_if_version(
+    conn: &Connection,
+    id: &str,
+    trash_id: &str,
+    expected_version: i64,
+) -> SqliteResult<bool> {
+    let moved = conn.execute(
+        &format!(
+            "UPDATE notes SET folder_id = ?1, is_pinned = 0, pinned_at = NULL, updated_at = ?2,
+                version = version + 1
+             WHERE id = ?3 AND version = ?4 AND {}",
+            OWNED_BY_ACTIVE
+        ),
+        params![trash_id, Utc::now().to_rfc3339(), id, expected_version],
+    )?;
+    Ok(moved > 0)
+}
+
+/// Give `to` the reminders, daily-note days and properties of `from`, as
+/// when merging `from` into it. `to` keeps its own value of a property
+/// both have.
+pub fn move_note_rows(conn: &Connection, from: &str, to: &str) -> SqliteResult<()> {
+    move_reminders(conn, from, to)?;
+    move_daily_notes(conn, from, to)?;
+    copy_properties(conn, from, to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::open_in_memory;
+
+    #[test]
+    fn saves_and_loads() {
+        let conn = open_in_memory().unwrap();
+        let note = Note::new("Title", "<p>Body</p>");
+        save_note(&conn, &note).unwrap();
+
+        assert_eq!(load_note(&conn, &note.id).unwrap(), Some(note));
+        assert_eq!(load_note(&conn, "missing").unwrap(), None);
+    }
+
+    #[test]
+    fn save_updates_but_keeps_created_at() {
+        let conn = open_in_memory().unwrap();
+        let mut note = Note::new("Before", "");
+        note.created_at = "2024-01-01T00:00:00+00:00".to_string();
+        save_note(&conn, &note).unwrap();
+
+        let mut edited = note.clone();
+        edited.title = "After".to_string();
+        edited.created_at = "2030-01-01T00:00:00+00:00".to_string();
+        edited.updated_at = String::new();
+        save_note(&conn, &edited).unwrap();
+
+        let stored = load_note(&conn, &note.id).unwrap().unwrap();
+        assert_eq!(stored.title, "After");
+        assert_eq!(stored.created_at, note.created_at);
+        assert!(stored.updated().is_some());
+    }
+
+    #[test]
+    fn bumps_version_on_every_write() {
+        let conn = open_in_memory().unwrap();
+        let note = Note::new("", "");
+        save_note(&conn, &note).unwrap();
+        save_note(&conn, &note).unwrap();
+        toggle_pin(&conn, &note.id).unwrap();
+        update_content(&conn, &note.id, "<p>edited</p>", &note.updated_at).unwrap();
+
+        assert_eq!(load_note(&conn, &note.id).unwrap().unwrap().version, 3);
+    }
+
+    #[test]
+    fn rejects_stale_versions() {
+        let conn = open_in_memory().unwrap();
+        let mut note = Note::new("First", "");
+        let VersionedSave::Saved(saved) = save_note_if_version(&conn, &note, 0).unwrap() else {
+            panic!("new notes are inserted");
+        };
+        assert_eq!(saved.version, 0);
+
+        note.title = "Second".to_string();
+        let VersionedSave::Saved(saved) = save_note_if_version(&conn, &note, 0).unwrap() else {
+            panic!("expected version matches");
+        };
+        assert_eq!((saved.title.as_str(), saved.version), ("Second", 1));
+
+        note.title = "Stale".to_string();
+        assert_eq!(
+            save_note_if_version(&conn, &note, 0).unwrap(),
+            VersionedSave::Conflict(saved.clone())
+        );
+        assert_eq!(load_note(&conn, &note.id).unwrap(), Some(saved));
+    }
+
+    #[test]
+    fn insert_rejects_existing_ids() {
+        let conn = open_in_memory().unwrap();
+        let note = Note::new("", "");
+        insert_note(&conn, &note).unwrap();
+        assert!(insert_note(&conn, &note).is_err());
+    }
+
+    #[test]
+    fn lists_pinned_then_newest() {
+        let conn = open_in_memory().unwrap();
+        for (title, updated_at) in [
+            ("old", "2024-01-01T00:00:00+00:00"),
+            ("new", "2024-02-01T00:00:00+00:00"),
+            ("pinned", "2023-01-01T00:00:00+00:00"),
+        ] {
+            let mut note = Note::new(title, "");
+            note.updated_at = updated_at.to_string();
+            save_note(&conn, &note).unwrap();
+            if title == "pinned" {
+                toggle_pin(&conn, &note.id).unwrap();
+            }
+        }
+
+        let titles: Vec<String> = list_notes(&conn)
+            .unwrap()
+            .into_iter()
+            .map(|note| note.title)
+            .collect();
+        assert_eq!(titles, ["pinned", "new", "old"]);
+    }
+
+    #[test]
+    fn toggles_pin() {
+        let conn = open_in_memory().unwrap();
+        let note = Note::new("", "");
+        save_note(&conn, &note).unwrap();
+
+        let pinned = toggle_pin(&conn, &note.id).unwrap();
+        assert!(pinned.is_pinned && pinned.pinned_at.is_some());
+        assert_eq!(load_note(&conn, &note.id).unwrap(), Some(pinned));
+
+        let unpinned = toggle_pin(&conn, &note.id).unwrap();
+        assert!(!unpinned.is_pinned && unpinned.pinned_at.is_none());
+
+        assert!(matches!(
+            toggle_pin(&conn, "missing"),
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        ));
+    }
+
+    #[test]
+    fn deletes() {
+        let conn = open_in_memory().unwrap();
+        let note = Note::new("", "");
+        save_note(&conn, &note).unwrap();
+
+        assert!(delete_note(&conn, &note.id).unwrap());
+        assert!(!delete_note(&conn, &note.id).unwrap());
+        assert_eq!(load_note(&conn, &note.id).unwrap(), None);
+    }
+}
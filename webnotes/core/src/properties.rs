@@ -0,0 +1,375 @@
+use chrono::NaiveDate;
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::accounts::{NOTE_OWNED_BY_ACTIVE, OWNED_BY_ACTIVE};
+use crate::model::Note;
+use crate::notes::{load_note, note_from_row};
+
+// =============================================================================
+// NOTE PROPERTIES
+// =============================================================================
+//
+// Typed key/value metadata on notes (status, owner, due, ...). Values are
+// stored with their natural SQLite type so they sort correctly: numbers as
+// REAL, booleans as 0/1, dates as YYYY-MM-DD text and lists as JSON arrays.
+// A key has one type across all notes.
+
+pub const MAX_KEY_LEN: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", content = "value", rename_all = "camelCase")]
+pub enum PropertyValue {
+    Text(String),
+    Number(f64),
+    /// YYYY-MM-DD
+    Date(String),
+    Bool(bool),
+    List(Vec<String>),
+}
+
+impl PropertyValue {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            PropertyValue::Text(_) => "text",
+            PropertyValue::Number(_) => "number",
+            PropertyValue::Date(_) => "date",
+            PropertyValue::Bool(_) => "bool",
+            PropertyValue::List(_) => "list",
+        }
+    }
+
+    /// Trim text, drop empty and repeated list items, and reject values
+    /// that cannot be stored
+    pub fn normalized(self) -> Result<Self, String> {
+        Ok(match self {
+            PropertyValue::Text(text) => PropertyValue::Text(text.trim().to_string()),
+            PropertyValue::Number(n) if !n.is_finite() => {
+                return Err("Number properties must be finite".to_string())
+            }
+            PropertyValue::Date(date) => {
+                let parsed = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                    .map_err(|_| format!("Invalid date '{}', expected YYYY-MM-DD", date))?;
+                PropertyValue::Date(parsed.format("%Y-%m-%d").to_string())
+            }
+            PropertyValue::List(items) => {
+                let mut unique: Vec<String> = Vec::with_capacity(items.len());
+                for item in items {
+                    let item = item.trim().to_string();
+                    if !item.is_empty() && !unique.contains(&item) {
+                        unique.push(item);
+                    }
+                }
+                PropertyValue::List(unique)
+            }
+            other => other,
+        })
+    }
+
+    pub fn to_sql(&self) -> Value {
+        match self {
+            PropertyValue::Text(text) | PropertyValue::Date(text) => Value::Text(text.clone()),
+            PropertyValue::Number(n) => Value::Real(*n),
+            PropertyValue::Bool(b) => Value::Integer(*b as i64),
+            PropertyValue::List(items) => {
+                Value::Text(serde_json::to_string(items).unwrap_or_else(|_| "[]".to_string()))
+            }
+        }
+    }
+
+    pub fn from_sql(value_type: &str, value: ValueRef) -> Result<Self, String> {
+        let text = || {
+            value
+                .as_str()
+                .map(str::to_string)
+                .map_err(|e| e.to_string())
+        };
+        Ok(match (value_type, value) {
+            ("text", _) => PropertyValue::Text(text()?),
+            ("date", _) => PropertyValue::Date(text()?),
+            ("number", ValueRef::Integer(n)) => PropertyValue::Number(n as f64),
+            ("number", ValueRef::Real(n)) => PropertyValue::Number(n),
+            ("bool", ValueRef::Integer(n)) => PropertyValue::Bool(n != 0),
+            ("list", _) => PropertyValue::List(
+                serde_json::from_str(&text()?).map_err(|e| format!("Invalid list: {}", e))?,
+            ),
+            (other, _) => return Err(format!("Unknown property type '{}'", other)),
+        })
+    }
+}
+
+pub fn validate_key(key: &str) -> Result<String, String> {
+    let key = key.trim();
+    if key.is_empty() {
+        return Err("Property name cannot be empty".to_string());
+    }
+    if key.chars().count() > MAX_KEY_LEN {
+        return Err("Property name too long".to_string());
+    }
+    Ok(key.to_string())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    /// Substring of a text property (ignoring case) or member of a list
+    Contains,
+    Exists,
+    NotExists,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertyFilter {
+    pub key: String,
+    pub op: FilterOp,
+    /// Required for every op except `exists` and `notExists`
+    #[serde(default)]
+    pub value: Option<PropertyValue>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertySort {
+    pub key: String,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+/// Filters are combined with AND. Notes without the sort property come last.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NoteQuery {
+    pub folder_id: Option<String>,
+    pub filters: Vec<PropertyFilter>,
+    pub sort: Option<PropertySort>,
+    pub limit: Option<usize>,
+}
+
+/// SQL selecting `columns` from the active account's `notes` for a query,
+/// with its parameters
+pub fn build_query(query: &NoteQuery, columns: &str) -> Result<(String, Vec<Value>), String> {
+    let mut sql = format!("SELECT {} FROM notes", columns);
+    let mut params = Vec::new();
+
+    if let Some(sort) = &query.sort {
+        sql.push_str(
+            " LEFT JOIN note_properties sort_by
+               ON sort_by.note_id = notes.id AND sort_by.key = ?",
+        );
+        params.push(Value::Text(validate_key(&sort.key)?));
+    }
+
+    let mut conditions = vec![format!("notes.{}", OWNED_BY_ACTIVE)];
+    if let Some(folder_id) = &query.folder_id {
+        conditions.push("notes.folder_id = ?".to_string());
+        params.push(Value::Text(folder_id.clone()));
+    }
+
+    for filter in &query.filters {
+        let key = validate_key(&filter.key)?;
+        let exists = "EXISTS (SELECT 1 FROM note_properties p
+                       WHERE p.note_id = notes.id AND p.key = ?";
+
+        let value = match (filter.op, &filter.value) {
+            (FilterOp::Exists | FilterOp::NotExists, _) => None,
+            (_, Some(value)) => Some(value.clone().normalized()?.to_sql()),
+            (_, None) => return Err(format!("Filter on '{}' needs a value", key)),
+        };
+
+        let condition = match filter.op {
+            FilterOp::Exists => format!("{})", exists),
+            FilterOp::NotExists => format!("NOT {})", exists),
+            // Notes without the property count as not equal
+            FilterOp::Ne => format!("NOT {} AND p.value = ?)", exists),
+            FilterOp::Contains => format!(
+                "{} AND ((p.value_type = 'list'
+                          AND EXISTS (SELECT 1 FROM json_each(p.value) j WHERE j.value = ?))
+                      OR (p.value_type = 'text' AND instr(lower(p.value), lower(?)) > 0)))",
+                exists
+            ),
+            op => {
+                let operator = match op {
+                    FilterOp::Eq => "=",
+                    FilterOp::Lt => "<",
+                    FilterOp::Lte => "<=",
+                    FilterOp::Gt => ">",
+                    _ => ">=",
+                };
+                format!("{} AND p.value {} ?)", exists, operator)
+            }
+        };
+
+        conditions.push(condition);
+        params.push(Value::Text(key));
+        if let Some(value) = value {
+            if filter.op == FilterOp::Contains {
+                params.push(value.clone());
+            }
+            params.push(value);
+        }
+    }
+
+    sql.push_str(" WHERE ");
+    sql.push_str(&conditions.join(" AND "));
+
+    match &query.sort {
+        Some(sort) => sql.push_str(&format!(
+            " ORDER BY sort_by.value IS NULL, sort_by.value {}, notes.updated_at DESC",
+            if sort.descending { "DESC" } else { "ASC" }
+        )),
+        None => sql.push_str(
+            " ORDER BY notes.is_pinned DESC, notes.pinned_at DESC, notes.updated_at DESC",
+        ),
+    }
+
+    if let Some(limit) = query.limit {
+        sql.push_str(" LIMIT ?");
+        params.push(Value::Integer(limit as i64));
+    }
+
+    Ok((sql, params))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteWithProperties {
+    pub note: Note,
+    pub properties: BTreeMap<String, PropertyValue>,
+}
+
+/// A property name in use and how many notes have it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertyKey {
+    pub key: String,
+    pub value_type: String,
+    pub note_count: u32,
+}
+
+/// Run a query from `build_query` and load each note's properties
+pub fn run_query(
+    conn: &Connection,
+    sql: &str,
+    values: Vec<Value>,
+) -> SqliteResult<Vec<NoteWithProperties>> {
+    let mut stmt = conn.prepare(sql)?;
+    let notes = stmt
+        .query_map(rusqlite::params_from_iter(values), note_from_row)?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    notes
+        .into_iter()
+        .map(|note| {
+            Ok(NoteWithProperties {
+                properties: load_properties(conn, &note.id)?,
+                note,
+            })
+        })
+        .collect()
+}
+
+/// A note's properties; empty if the note belongs to another account
+pub fn load_properties(
+    conn: &Connection,
+    note_id: &str,
+) -> SqliteResult<BTreeMap<String, PropertyValue>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT key, value_type, value FROM note_properties WHERE note_id = ?1 AND {}",
+        NOTE_OWNED_BY_ACTIVE
+    ))?;
+    let mut rows = stmt.query(params![note_id])?;
+
+    let mut properties = BTreeMap::new();
+    while let Some(row) = rows.next()? {
+        let value_type: String = row.get(1)?;
+        let value = PropertyValue::from_sql(&value_type, row.get_ref(2)?).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, e.into())
+        })?;
+        properties.insert(row.get(0)?, value);
+    }
+    Ok(properties)
+}
+
+/// The type `key` has on the active account's notes other than `note_id`
+pub fn key_type(conn: &Connection, key: &str, note_id: &str) -> SqliteResult<Option<String>> {
+    conn.query_row(
+        &format!(
+            "SELECT value_type FROM note_properties
+             WHERE key = ?1 AND note_id != ?2 AND {}
+             LIMIT 1",
+            NOTE_OWNED_BY_ACTIVE
+        ),
+        params![key, note_id],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Add or replace a property of a normalized value. Fails with
+/// `QueryReturnedNoRows` if the note does not exist or is another account's.
+pub fn set_property(
+    conn: &Connection,
+    note_id: &str,
+    key: &str,
+    value: &PropertyValue,
+) -> SqliteResult<()> {
+    load_note(conn, note_id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+    conn.execute(
+        "INSERT INTO note_properties (note_id, key, value_type, value)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(note_id, key) DO UPDATE SET
+            value_type = excluded.value_type,
+            value = excluded.value",
+        params![note_id, key, value.type_name(), value.to_sql()],
+    )?;
+    Ok(())
+}
+
+pub fn delete_property(conn: &Connection, note_id: &str, key: &str) -> SqliteResult<()> {
+    conn.execute(
+        &format!(
+            "DELETE FROM note_properties WHERE note_id = ?1 AND key = ?2 AND {}",
+            NOTE_OWNED_BY_ACTIVE
+        ),
+        params![note_id, key],
+    )?;
+    Ok(())
+}
+
+/// Give `to` every property of `from` it does not have yet
+pub fn copy_properties(conn: &Connection, from: &str, to: &str) -> SqliteResult<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO note_properties (note_id, key, value_type, value)
+         SELECT ?1, key, value_type, value FROM note_properties WHERE note_id = ?2",
+        params![to, from],
+    )?;
+    Ok(())
+}
+
+/// Every property name the active account uses, with its type
+pub fn list_keys(conn: &Connection) -> SqliteResult<Vec<PropertyKey>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT key, value_type, COUNT(*) FROM note_properties
+         WHERE {}
+         GROUP BY key, value_type
+         ORDER BY key COLLATE NOCASE",
+        NOTE_OWNED_BY_ACTIVE
+    ))?;
+    let keys = stmt.query_map([], |row| {
+        Ok(PropertyKey {
+            key: row.get(0)?,
+            value_type: row.get(1)?,
+            note_count: row.get(2)?,
+        })
+    })?;
+    keys.collect::<SqliteResult<Vec<_>>>()
+}
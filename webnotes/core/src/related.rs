@@ -0,0 +1,341 @@
+use rayon::prelude::*;
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::accounts::{NOTE_OWNED_BY_ACTIVE, OWNED_BY_ACTIVE};
+use crate::compression::StoredContent;
+use crate::html::html_to_text;
+use crate::settings::{read_setting, write_setting};
+
+// =============================================================================
+// TF-IDF VECTORS
+// =============================================================================
+//
+// Each note is reduced to a sparse vector of term weights: sublinear term
+// frequency times smoothed inverse document frequency, normalized to unit
+// length so cosine similarity is a plain dot product.
+
+/// Terms kept per note; the long tail adds storage without changing rankings
+pub const MAX_TERMS: usize = 200;
+
+/// Title words count this many times over a body occurrence
+const TITLE_WEIGHT: usize = 2;
+
+const MIN_TERM_CHARS: usize = 3;
+const MAX_TERM_CHARS: usize = 40;
+
+const STOP_WORDS: &[&str] = &[
+    "about", "above", "after", "again", "all", "also", "and", "any", "are", "because", "been",
+    "before", "being", "below", "between", "both", "but", "can", "could", "did", "does", "doing",
+    "down", "during", "each", "few", "for", "from", "further", "had", "has", "have", "having",
+    "her", "here", "hers", "herself", "him", "himself", "his", "how", "into", "its", "itself",
+    "just", "more", "most", "not", "now", "off", "once", "only", "other", "our", "ours", "out",
+    "over", "own", "same", "she", "should", "some", "such", "than", "that", "the", "their",
+    "theirs", "them", "then", "there", "these", "they", "this", "those", "through", "too", "under",
+    "until", "very", "was", "were", "what", "when", "where", "which", "while", "who", "whom",
+    "why", "will", "with", "would", "you", "your", "yours", "yourself",
+];
+
+/// Lowercased words worth indexing, in document order
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| {
+            let len = word.chars().count();
+            (MIN_TERM_CHARS..=MAX_TERM_CHARS).contains(&len)
+                && !word.chars().all(|c| c.is_ascii_digit())
+        })
+        .map(str::to_lowercase)
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+}
+
+/// Sublinear term frequency (`1 + ln count`) of a note's title and content
+pub fn term_frequencies(title: &str, content: &str) -> HashMap<String, f64> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for term in tokenize(title) {
+        *counts.entry(term).or_default() += TITLE_WEIGHT;
+    }
+    for term in tokenize(&html_to_text(content)) {
+        *counts.entry(term).or_default() += 1;
+    }
+
+    counts
+        .into_iter()
+        .map(|(term, count)| (term, 1.0 + (count as f64).ln()))
+        .collect()
+}
+
+/// Weight term frequencies by IDF over `note_count` notes, keep the
+/// strongest `MAX_TERMS` and normalize to unit length. `document_frequency`
+/// must count the note itself.
+pub fn weigh(
+    frequencies: &HashMap<String, f64>,
+    note_count: usize,
+    document_frequency: impl Fn(&str) -> usize,
+) -> Vec<(String, f64)> {
+    let mut weights: Vec<(String, f64)> = frequencies
+        .iter()
+        .map(|(term, tf)| {
+            let df = document_frequency(term).max(1);
+            let idf = ((note_count.max(df) + 1) as f64 / (df + 1) as f64).ln() + 1.0;
+            (term.clone(), tf * idf)
+        })
+        .collect();
+
+    weights.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    weights.truncate(MAX_TERMS);
+
+    let norm = weights.iter().map(|(_, w)| w * w).sum::<f64>().sqrt();
+    if norm > 0.0 {
+        for (_, weight) in &mut weights {
+            *weight /= norm;
+        }
+    }
+    weights
+}
+
+// =============================================================================
+// RELATED NOTES
+// =============================================================================
+//
+// `note_vectors` holds each note's vector, one row per term. Document
+// frequencies only count notes with the same owner, so one account's notes
+// never shape another's results.
+
+/// Note count at the last full rebuild of `note_vectors`
+const RELATED_INDEX_KEY: &str = "related_index_notes";
+
+const MIN_RELATED_SCORE: f64 = 0.05;
+const SHARED_TERMS_SHOWN: usize = 5;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RelatedNote {
+    pub note_id: String,
+    pub note_title: String,
+    /// Cosine similarity, 0 to 1
+    pub score: f64,
+    /// Strongest terms the two notes have in common
+    pub shared_terms: Vec<String>,
+}
+
+fn insert_note_vector(
+    conn: &Connection,
+    note_id: &str,
+    vector: &[(String, f64)],
+) -> SqliteResult<()> {
+    let mut stmt = conn
+        .prepare_cached("INSERT INTO note_vectors (note_id, term, weight) VALUES (?1, ?2, ?3)")?;
+    for (term, weight) in vector {
+        stmt.execute(params![note_id, term, weight])?;
+    }
+    Ok(())
+}
+
+/// Recompute one note's vector against the document frequencies of its
+/// owner's notes already indexed
+pub fn index_note_vector(
+    conn: &Connection,
+    note_id: &str,
+    title: &str,
+    content: &str,
+) -> SqliteResult<()> {
+    conn.execute(
+        "DELETE FROM note_vectors WHERE note_id = ?1",
+        params![note_id],
+    )?;
+
+    let owner_id: Option<String> = conn
+        .query_row(
+            "SELECT owner_id FROM notes WHERE id = ?1",
+            params![note_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten();
+    let note_count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM notes WHERE owner_id IS ?1",
+        params![owner_id],
+        |row| row.get(0),
+    )?;
+    let frequencies = term_frequencies(title, content);
+
+    let mut df_stmt = conn.prepare_cached(
+        "SELECT COUNT(*) FROM note_vectors v
+         JOIN notes n ON n.id = v.note_id
+         WHERE v.term = ?1 AND n.owner_id IS ?2",
+    )?;
+    let mut document_frequency = HashMap::with_capacity(frequencies.len());
+    for term in frequencies.keys() {
+        let others: i64 = df_stmt.query_row(params![term, owner_id], |row| row.get(0))?;
+        // Count this note too
+        document_frequency.insert(term.as_str(), others as usize + 1);
+    }
+
+    let vector = weigh(&frequencies, note_count as usize, |term| {
+        document_frequency.get(term).copied().unwrap_or(1)
+    });
+    insert_note_vector(conn, note_id, &vector)
+}
+
+/// Vectors saved one at a time use the IDF of the moment, so they drift as
+/// the collection grows or shrinks. Judged on every account's notes
+/// together: a rebuild covers them all.
+pub fn related_index_is_stale(conn: &Connection) -> SqliteResult<bool> {
+    let note_count: i64 = conn.query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0))?;
+    Ok(match read_setting::<i64>(conn, RELATED_INDEX_KEY)? {
+        // More than a quarter added or removed since the last rebuild
+        Some(indexed) => (note_count - indexed).abs() * 4 > indexed.max(1),
+        None => true,
+    })
+}
+
+/// Recompute every note's vector with document frequencies over its
+/// owner's notes. Tokenizing and weighting run in parallel; the writes are
+/// one transaction. Returns the number of notes indexed.
+pub fn rebuild_note_vectors(conn: &Connection) -> SqliteResult<usize> {
+    let notes = {
+        let mut stmt = conn.prepare("SELECT id, title, content, owner_id FROM notes")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, StoredContent>(2)?.0,
+                row.get::<_, Option<String>>(3)?,
+            ))
+        })?;
+        rows.collect::<SqliteResult<Vec<_>>>()?
+    };
+
+    let frequencies: Vec<HashMap<String, f64>> = notes
+        .par_iter()
+        .map(|(_, title, content, _)| term_frequencies(title, content))
+        .collect();
+
+    let mut note_counts: HashMap<Option<&str>, usize> = HashMap::new();
+    let mut document_frequency: HashMap<(Option<&str>, &str), usize> = HashMap::new();
+    for ((_, _, _, owner_id), terms) in notes.iter().zip(&frequencies) {
+        let owner_id = owner_id.as_deref();
+        *note_counts.entry(owner_id).or_default() += 1;
+        for term in terms.keys() {
+            *document_frequency
+                .entry((owner_id, term.as_str()))
+                .or_default() += 1;
+        }
+    }
+
+    let vectors: Vec<Vec<(String, f64)>> = notes
+        .par_iter()
+        .zip(&frequencies)
+        .map(|((_, _, _, owner_id), terms)| {
+            let owner_id = owner_id.as_deref();
+            weigh(terms, note_counts[&owner_id], |term| {
+                document_frequency
+                    .get(&(owner_id, term))
+                    .copied()
+                    .unwrap_or(1)
+            })
+        })
+        .collect();
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM note_vectors", [])?;
+    for ((id, _, _, _), vector) in notes.iter().zip(&vectors) {
+        insert_note_vector(&tx, id, vector)?;
+    }
+    write_setting(&tx, RELATED_INDEX_KEY, &(notes.len() as i64))?;
+    tx.commit()?;
+
+    log::info!("Rebuilt related-note vectors for {} notes", notes.len());
+    Ok(notes.len())
+}
+
+/// The `k` notes most similar to `id` by cosine similarity, best first.
+/// Document frequencies and results both come from the active account's notes.
+pub fn related_notes(conn: &Connection, id: &str, k: usize) -> SqliteResult<Vec<RelatedNote>> {
+    // Vectors are unit length, so the dot product is the cosine
+    let mut stmt = conn.prepare(&format!(
+        "SELECT b.note_id, n.title, SUM(a.weight * b.weight) AS score
+         FROM note_vectors a
+         JOIN note_vectors b ON b.term = a.term AND b.note_id != a.note_id
+         JOIN notes n ON n.id = b.note_id
+         WHERE a.note_id = ?1 AND a.{} AND n.{}
+         GROUP BY b.note_id
+         HAVING score >= ?2
+         ORDER BY score DESC
+         LIMIT ?3",
+        NOTE_OWNED_BY_ACTIVE, OWNED_BY_ACTIVE
+    ))?;
+    let mut related = stmt
+        .query_map(params![id, MIN_RELATED_SCORE, k as i64], |row| {
+            Ok(RelatedNote {
+                note_id: row.get(0)?,
+                note_title: row.get(1)?,
+                score: row.get::<_, f64>(2)?.min(1.0),
+                shared_terms: Vec::new(),
+            })
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    let mut shared_stmt = conn.prepare(
+        "SELECT a.term
+         FROM note_vectors a
+         JOIN note_vectors b ON b.term = a.term
+         WHERE a.note_id = ?1 AND b.note_id = ?2
+         ORDER BY a.weight * b.weight DESC
+         LIMIT ?3",
+    )?;
+    for note in &mut related {
+        note.shared_terms = shared_stmt
+            .query_map(
+                params![id, note.note_id, SHARED_TERMS_SHOWN as i64],
+                |row| row.get(0),
+            )?
+            .collect::<SqliteResult<Vec<String>>>()?;
+    }
+
+    Ok(related)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weights_ignore_other_accounts() {
+        let conn = crate::open_in_memory().unwrap();
+        let add_note = |id: &str, owner: &str, content: &str| {
+            conn.execute(
+                "INSERT INTO notes (id, title, content, updated_at, created_at, owner_id)
+                 VALUES (?1, '', ?2, '2025-01-01', '2025-01-01', ?3)",
+                params![id, content, owner],
+            )
+            .unwrap();
+        };
+        let weights = |conn: &Connection| {
+            let mut stmt = conn
+                .prepare("SELECT term, weight FROM note_vectors WHERE note_id = 'a1' ORDER BY term")
+                .unwrap();
+            stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+            })
+            .unwrap()
+            .collect::<SqliteResult<Vec<_>>>()
+            .unwrap()
+        };
+
+        add_note("a1", "alice", "<p>garden tomatoes basil</p>");
+        add_note("a2", "alice", "<p>garden fences</p>");
+        rebuild_note_vectors(&conn).unwrap();
+        let alone = weights(&conn);
+
+        for i in 0..5 {
+            add_note(&format!("b{}", i), "bob", "<p>tomatoes everywhere</p>");
+        }
+        rebuild_note_vectors(&conn).unwrap();
+        assert_eq!(weights(&conn), alone);
+
+        index_note_vector(&conn, "a1", "", "<p>garden tomatoes basil</p>").unwrap();
+        assert_eq!(weights(&conn), alone);
+    }
+}
@@ -0,0 +1,404 @@
+use chrono::{DateTime, Duration, Months, SecondsFormat, Utc};
+use rusqlite::{params, Connection, Result as SqliteResult, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::accounts::NOTE_OWNED_BY_ACTIVE;
+use crate::notes::load_note;
+
+// =============================================================================
+// REMINDERS
+// =============================================================================
+//
+// Timestamps are UTC RFC3339 with second precision so they compare
+// correctly as strings. A repeating reminder keeps the time it was first
+// set for in `repeat_anchor` and counts its occurrences from there.
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RepeatRule {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Reminder {
+    pub id: String,
+    pub note_id: String,
+    pub remind_at: String,
+    pub repeat: Option<RepeatRule>,
+    pub fired_at: Option<String>,
+    pub completed_at: Option<String>,
+    pub created_at: String,
+}
+
+/// A reminder that fell due, with its note's title for the notification
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DueReminder {
+    pub reminder: Reminder,
+    pub note_title: String,
+}
+
+pub(crate) const REMINDER_COLUMNS: &str =
+    "id, note_id, remind_at, repeat, fired_at, completed_at, created_at";
+
+impl RepeatRule {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RepeatRule::Daily => "daily",
+            RepeatRule::Weekly => "weekly",
+            RepeatRule::Monthly => "monthly",
+            RepeatRule::Yearly => "yearly",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "daily" => Some(RepeatRule::Daily),
+            "weekly" => Some(RepeatRule::Weekly),
+            "monthly" => Some(RepeatRule::Monthly),
+            "yearly" => Some(RepeatRule::Yearly),
+            _ => None,
+        }
+    }
+
+    /// The `n`th occurrence counting from `anchor`. Each is computed from
+    /// the anchor, not the one before, so a reminder on the 31st is back
+    /// on the 31st after a short month.
+    fn occurrence(self, anchor: DateTime<Utc>, n: u32) -> Option<DateTime<Utc>> {
+        match self {
+            RepeatRule::Daily => anchor.checked_add_signed(Duration::days(n.into())),
+            RepeatRule::Weekly => anchor.checked_add_signed(Duration::weeks(n.into())),
+            RepeatRule::Monthly => anchor.checked_add_months(Months::new(n)),
+            RepeatRule::Yearly => anchor.checked_add_months(Months::new(n.checked_mul(12)?)),
+        }
+    }
+
+    /// First occurrence strictly after `now`, so a reminder missed for
+    /// several periods fires once instead of once per missed period
+    pub fn next_after(self, anchor: DateTime<Utc>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        (0..)
+            .map_while(|n| self.occurrence(anchor, n))
+            .find(|at| *at > now)
+    }
+}
+
+/// How reminder times are stored
+pub fn reminder_timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+pub(crate) fn reminder_from_row(row: &Row) -> SqliteResult<Reminder> {
+    let repeat: Option<String> = row.get(3)?;
+    Ok(Reminder {
+        id: row.get(0)?,
+        note_id: row.get(1)?,
+        remind_at: row.get(2)?,
+        repeat: repeat.as_deref().and_then(RepeatRule::parse),
+        fired_at: row.get(4)?,
+        completed_at: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+pub fn load_reminder(conn: &Connection, id: &str) -> SqliteResult<Reminder> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM reminders WHERE id = ?1 AND {}",
+            REMINDER_COLUMNS, NOTE_OWNED_BY_ACTIVE
+        ),
+        params![id],
+        reminder_from_row,
+    )
+}
+
+/// Remind about one of the active account's notes at `at`. Fails with
+/// `QueryReturnedNoRows` if there is no such note.
+pub fn add_reminder(
+    conn: &Connection,
+    note_id: &str,
+    at: DateTime<Utc>,
+    repeat: Option<RepeatRule>,
+) -> SqliteResult<Reminder> {
+    if load_note(conn, note_id)?.is_none() {
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+
+    let reminder = Reminder {
+        id: uuid::Uuid::new_v4().to_string(),
+        note_id: note_id.to_string(),
+        remind_at: reminder_timestamp(at),
+        repeat,
+        fired_at: None,
+        completed_at: None,
+        created_at: Utc::now().to_rfc3339(),
+    };
+
+    conn.execute(
+        "INSERT INTO reminders (id, note_id, remind_at, repeat, fired_at, completed_at, created_at, repeat_anchor)
+         VALUES (?1, ?2, ?3, ?4, NULL, NULL, ?5, ?3)",
+        params![
+            reminder.id,
+            reminder.note_id,
+            reminder.remind_at,
+            reminder.repeat.map(RepeatRule::as_str),
+            reminder.created_at
+        ],
+    )?;
+
+    Ok(reminder)
+}
+
+pub fn delete_reminder(conn: &Connection, id: &str) -> SqliteResult<()> {
+    conn.execute(
+        &format!(
+            "DELETE FROM reminders WHERE id = ?1 AND {}",
+            NOTE_OWNED_BY_ACTIVE
+        ),
+        params![id],
+    )?;
+    Ok(())
+}
+
+/// A note's reminders, soonest first
+pub fn note_reminders(conn: &Connection, note_id: &str) -> SqliteResult<Vec<Reminder>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM reminders WHERE note_id = ?1 AND {} ORDER BY remind_at ASC",
+        REMINDER_COLUMNS, NOTE_OWNED_BY_ACTIVE
+    ))?;
+    let reminders = stmt.query_map(params![note_id], reminder_from_row)?;
+    reminders.collect::<SqliteResult<Vec<_>>>()
+}
+
+/// Open reminders ordered by time, including ones that fired but were
+/// never completed or snoozed
+pub fn upcoming_reminders(conn: &Connection, limit: u32) -> SqliteResult<Vec<Reminder>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM reminders
+         WHERE completed_at IS NULL AND {}
+         ORDER BY remind_at ASC
+         LIMIT ?1",
+        REMINDER_COLUMNS, NOTE_OWNED_BY_ACTIVE
+    ))?;
+    let reminders = stmt.query_map(params![limit], reminder_from_row)?;
+    reminders.collect::<SqliteResult<Vec<_>>>()
+}
+
+/// Move a reminder to `until` and reopen it. Fails with
+/// `QueryReturnedNoRows` if there is no such reminder.
+pub fn snooze_reminder(
+    conn: &Connection,
+    id: &str,
+    until: DateTime<Utc>,
+) -> SqliteResult<Reminder> {
+    let updated = conn.execute(
+        &format!(
+            "UPDATE reminders SET remind_at = ?1, fired_at = NULL, completed_at = NULL
+             WHERE id = ?2 AND {}",
+            NOTE_OWNED_BY_ACTIVE
+        ),
+        params![reminder_timestamp(until), id],
+    )?;
+    if updated == 0 {
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+    load_reminder(conn, id)
+}
+
+/// Fails with `QueryReturnedNoRows` if there is no such reminder
+pub fn complete_reminder(conn: &Connection, id: &str) -> SqliteResult<Reminder> {
+    let updated = conn.execute(
+        &format!(
+            "UPDATE reminders SET completed_at = ?1 WHERE id = ?2 AND {}",
+            NOTE_OWNED_BY_ACTIVE
+        ),
+        params![Utc::now().to_rfc3339(), id],
+    )?;
+    if updated == 0 {
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+    load_reminder(conn, id)
+}
+
+/// Move every reminder of note `from` to note `to`
+pub fn move_reminders(conn: &Connection, from: &str, to: &str) -> SqliteResult<()> {
+    conn.execute(
+        "UPDATE reminders SET note_id = ?1 WHERE note_id = ?2",
+        params![to, from],
+    )?;
+    Ok(())
+}
+
+/// Mark every reminder of the active account due at `now` as fired and
+/// return them. One-off reminders keep `fired_at` so they fire once;
+/// repeating reminders move on to their next occurrence, counted from
+/// their anchor. Other accounts' reminders wait until they sign in again.
+pub fn take_due_reminders(conn: &Connection, now: DateTime<Utc>) -> SqliteResult<Vec<DueReminder>> {
+    let tx = conn.unchecked_transaction()?;
+
+    let due = {
+        let mut stmt = tx.prepare(&format!(
+            "SELECT r.id, r.note_id, r.remind_at, r.repeat, r.fired_at, r.completed_at, r.created_at,
+                    COALESCE(n.title, ''), COALESCE(r.repeat_anchor, r.remind_at)
+             FROM reminders r
+             LEFT JOIN notes n ON n.id = r.note_id
+             WHERE r.completed_at IS NULL AND r.fired_at IS NULL AND r.remind_at <= ?1
+                AND r.{}
+             ORDER BY r.remind_at ASC",
+            NOTE_OWNED_BY_ACTIVE
+        ))?;
+        let rows = stmt.query_map(params![reminder_timestamp(now)], |row| {
+            let item = DueReminder {
+                reminder: reminder_from_row(row)?,
+                note_title: row.get(7)?,
+            };
+            Ok((item, row.get::<_, String>(8)?))
+        })?;
+        rows.collect::<SqliteResult<Vec<_>>>()?
+    };
+
+    let fired_at = reminder_timestamp(now);
+    for (item, anchor) in &due {
+        let next = item.reminder.repeat.and_then(|rule| {
+            DateTime::parse_from_rfc3339(anchor)
+                .ok()
+                .and_then(|at| rule.next_after(at.with_timezone(&Utc), now))
+        });
+
+        match next {
+            Some(next) => tx.execute(
+                "UPDATE reminders SET remind_at = ?1 WHERE id = ?2",
+                params![reminder_timestamp(next), item.reminder.id],
+            )?,
+            None => tx.execute(
+                "UPDATE reminders SET fired_at = ?1 WHERE id = ?2",
+                params![fired_at, item.reminder.id],
+            )?,
+        };
+    }
+
+    tx.commit()?;
+    Ok(due.into_iter().map(|(item, _)| item).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::save_note;
+    use crate::{open_in_memory, Note};
+
+    fn at(timestamp: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(timestamp)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn add_reminder_row(
+        conn: &Connection,
+        id: &str,
+        note_id: &str,
+        remind_at: &str,
+        repeat: Option<RepeatRule>,
+        anchor: &str,
+    ) {
+        conn.execute(
+            "INSERT INTO reminders (id, note_id, remind_at, repeat, created_at, repeat_anchor)
+             VALUES (?1, ?2, ?3, ?4, ?3, ?5)",
+            params![
+                id,
+                note_id,
+                remind_at,
+                repeat.map(RepeatRule::as_str),
+                anchor
+            ],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn monthly_reminders_keep_their_day() {
+        let anchor = at("2025-01-31T09:00:00Z");
+        let rule = RepeatRule::Monthly;
+
+        assert_eq!(
+            rule.next_after(anchor, at("2025-02-01T00:00:00Z")),
+            Some(at("2025-02-28T09:00:00Z"))
+        );
+        assert_eq!(
+            rule.next_after(anchor, at("2025-02-28T09:00:00Z")),
+            Some(at("2025-03-31T09:00:00Z"))
+        );
+        assert_eq!(
+            rule.next_after(anchor, at("2025-04-30T10:00:00Z")),
+            Some(at("2025-05-31T09:00:00Z"))
+        );
+        assert_eq!(
+            RepeatRule::Yearly.next_after(at("2024-02-29T08:00:00Z"), at("2025-01-01T00:00:00Z")),
+            Some(at("2025-02-28T08:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn missed_occurrences_fire_once() {
+        let anchor = at("2025-03-01T08:00:00Z");
+        assert_eq!(
+            RepeatRule::Daily.next_after(anchor, at("2025-03-04T20:00:00Z")),
+            Some(at("2025-03-05T08:00:00Z"))
+        );
+        assert_eq!(
+            RepeatRule::Weekly.next_after(anchor, at("2025-02-01T00:00:00Z")),
+            Some(anchor)
+        );
+    }
+
+    #[test]
+    fn takes_each_due_reminder_once() {
+        let conn = open_in_memory().unwrap();
+        let note = Note::new("Rent", "");
+        save_note(&conn, &note).unwrap();
+        add_reminder_row(
+            &conn,
+            "once",
+            &note.id,
+            "2025-02-28T08:00:00Z",
+            None,
+            "2025-02-28T08:00:00Z",
+        );
+        add_reminder_row(
+            &conn,
+            "monthly",
+            &note.id,
+            "2025-02-28T09:00:00Z",
+            Some(RepeatRule::Monthly),
+            "2025-01-31T09:00:00Z",
+        );
+        add_reminder_row(
+            &conn,
+            "later",
+            &note.id,
+            "2025-03-01T09:00:00Z",
+            None,
+            "2025-03-01T09:00:00Z",
+        );
+
+        let now = at("2025-02-28T10:00:00Z");
+        let due = take_due_reminders(&conn, now).unwrap();
+        let ids: Vec<_> = due.iter().map(|item| item.reminder.id.as_str()).collect();
+        assert_eq!(ids, ["once", "monthly"]);
+        assert!(due.iter().all(|item| item.note_title == "Rent"));
+
+        assert_eq!(
+            load_reminder(&conn, "once").unwrap().fired_at.as_deref(),
+            Some("2025-02-28T10:00:00Z")
+        );
+        let monthly = load_reminder(&conn, "monthly").unwrap();
+        assert_eq!(monthly.remind_at, "2025-03-31T09:00:00Z");
+        assert_eq!(monthly.fired_at, None);
+        assert_eq!(load_reminder(&conn, "later").unwrap().fired_at, None);
+
+        assert!(take_due_reminders(&conn, now).unwrap().is_empty());
+    }
+}
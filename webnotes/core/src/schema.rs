@@ -0,0 +1,438 @@
+use rusqlite::{Connection, Result as SqliteResult};
+
+use crate::accounts::OWNED_TABLES;
+use crate::compression::compress_existing_notes;
+use crate::settings::{read_setting, write_setting};
+use crate::tasks::{backfill_tasks, TASKS_BACKFILLED_KEY};
+use std::path::Path;
+
+// =============================================================================
+// DATABASE INITIALIZATION & MIGRATIONS
+// =============================================================================
+//
+// Creates every table the apps read and write, so the desktop app and the
+// terminal client can open the same database. Every statement is idempotent,
+// so `migrate` runs on each start.
+
+/// Open (creating if needed) and migrate the database at `path`
+pub fn open(path: impl AsRef<Path>) -> SqliteResult<Connection> {
+    let conn = Connection::open(path)?;
+    migrate(&conn)?;
+    Ok(conn)
+}
+
+/// A migrated database that lives only as long as the connection
+pub fn open_in_memory() -> SqliteResult<Connection> {
+    let conn = Connection::open_in_memory()?;
+    migrate(&conn)?;
+    Ok(conn)
+}
+
+pub fn migrate(conn: &Connection) -> SqliteResult<()> {
+    // Lets maintenance return free pages without rewriting the file. Only
+    // takes effect on a new database; older ones are converted by their
+    // first full vacuum.
+    conn.execute_batch("PRAGMA auto_vacuum = INCREMENTAL")?;
+
+    // Create Notes table
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS notes (
+            id TEXT PRIMARY KEY,
+            title TEXT NOT NULL DEFAULT '',
+            content TEXT NOT NULL DEFAULT '',
+            folder_id TEXT,
+            is_pinned INTEGER DEFAULT 0,
+            pinned_at TEXT,
+            font TEXT,
+            updated_at TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            version INTEGER NOT NULL DEFAULT 0,
+            owner_id TEXT
+        )",
+        [],
+    )?;
+
+    // Create Folders table
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS folders (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            owner_id TEXT,
+            is_trash INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+
+    // Saved copies of notes (Prisma NoteVersion); content is compressed
+    // like notes.content
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS note_versions (
+            id TEXT PRIMARY KEY,
+            note_id TEXT NOT NULL,
+            title TEXT NOT NULL DEFAULT '',
+            content TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            change_type TEXT NOT NULL DEFAULT 'auto'
+        )",
+        [],
+    )?;
+
+    // Accounts that have signed in here (Prisma User); see accounts.rs
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS accounts (
+            id TEXT PRIMARY KEY,
+            email TEXT,
+            name TEXT,
+            image TEXT,
+            signed_in_at TEXT NOT NULL,
+            is_active INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_accounts_active ON accounts(is_active) WHERE is_active = 1",
+        [],
+    )?;
+
+    // Key/value settings (JSON values)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS app_settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Create FTS index
+    conn.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS notes_fts USING fts5(id, title, content)",
+        [],
+    )?;
+
+    // Saved searches; the query is JSON (see smart_folders.rs)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS smart_folders (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            query TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            owner_id TEXT
+        )",
+        [],
+    )?;
+
+    // Latest editor state per note; see drafts.rs
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS drafts (
+            note_id TEXT PRIMARY KEY,
+            title TEXT NOT NULL,
+            content TEXT NOT NULL,
+            saved_at TEXT NOT NULL,
+            owner_id TEXT
+        )",
+        [],
+    )?;
+
+    // One note per calendar day and account, enforced by
+    // idx_daily_notes_day below; `owner_id` is the note's owner
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS daily_notes (
+            date TEXT NOT NULL,
+            note_id TEXT NOT NULL,
+            owner_id TEXT
+        )",
+        [],
+    )?;
+
+    // Reminders; see reminders.rs for how times are stored
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS reminders (
+            id TEXT PRIMARY KEY,
+            note_id TEXT NOT NULL,
+            remind_at TEXT NOT NULL,
+            repeat TEXT,
+            fired_at TEXT,
+            completed_at TEXT,
+            created_at TEXT NOT NULL,
+            repeat_anchor TEXT -- occurrences of a repeat are counted from here
+        )",
+        [],
+    )?;
+
+    // Checklist items parsed out of note content on save
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS tasks (
+            note_id TEXT NOT NULL,
+            task_index INTEGER NOT NULL,
+            text TEXT NOT NULL,
+            checked INTEGER NOT NULL DEFAULT 0,
+            due TEXT,
+            priority INTEGER,
+            PRIMARY KEY (note_id, task_index)
+        )",
+        [],
+    )?;
+
+    // Unit-length TF-IDF vectors for related-note lookups
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS note_vectors (
+            note_id TEXT NOT NULL,
+            term TEXT NOT NULL,
+            weight REAL NOT NULL,
+            PRIMARY KEY (note_id, term)
+        )",
+        [],
+    )?;
+
+    // Typed key/value metadata; see properties.rs for how values are stored
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS note_properties (
+            note_id TEXT NOT NULL,
+            key TEXT NOT NULL,
+            value_type TEXT NOT NULL,
+            value,
+            PRIMARY KEY (note_id, key)
+        )",
+        [],
+    )?;
+
+    // Every time a note is opened, and a running frecency score per note
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS note_opens (
+            note_id TEXT NOT NULL,
+            opened_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS note_frecency (
+            note_id TEXT PRIMARY KEY,
+            open_count INTEGER NOT NULL,
+            last_opened_at TEXT NOT NULL,
+            rank REAL NOT NULL
+        )",
+        [],
+    )?;
+
+    // Undoable changes; see journal.rs
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS operation_journal (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            description TEXT NOT NULL,
+            data TEXT NOT NULL, -- JSON of journal::Operation
+            created_at TEXT NOT NULL,
+            undone INTEGER NOT NULL DEFAULT 0,
+            owner_id TEXT
+        )",
+        [],
+    )?;
+
+    // Create indexes for performance
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_notes_folder_id ON notes(folder_id)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_notes_updated_at ON notes(updated_at DESC)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_notes_is_pinned ON notes(is_pinned)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_note_versions_note_id ON note_versions(note_id, created_at)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_reminders_remind_at ON reminders(remind_at)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_reminders_note_id ON reminders(note_id)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_note_vectors_term ON note_vectors(term)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_note_properties_key ON note_properties(key, value)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_note_opens_note_id ON note_opens(note_id, opened_at)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_note_opens_opened_at ON note_opens(opened_at)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_note_frecency_rank ON note_frecency(rank DESC)",
+        [],
+    )?;
+
+    // Migrations for existing databases
+    // SQLite will error if column exists, we ignore that
+    let migrations = [
+        "ALTER TABLE notes ADD COLUMN pinned_at TEXT",
+        "ALTER TABLE notes ADD COLUMN font TEXT",
+        "ALTER TABLE notes ADD COLUMN version INTEGER NOT NULL DEFAULT 0",
+        "ALTER TABLE notes ADD COLUMN owner_id TEXT",
+        "ALTER TABLE folders ADD COLUMN owner_id TEXT",
+        "ALTER TABLE folders ADD COLUMN is_trash INTEGER NOT NULL DEFAULT 0",
+        "ALTER TABLE smart_folders ADD COLUMN owner_id TEXT",
+        "ALTER TABLE drafts ADD COLUMN owner_id TEXT",
+        "ALTER TABLE operation_journal ADD COLUMN owner_id TEXT",
+        "ALTER TABLE daily_notes ADD COLUMN owner_id TEXT",
+        // Reminders from before anchors repeat from their current time
+        "ALTER TABLE reminders ADD COLUMN repeat_anchor TEXT",
+    ];
+
+    for migration in migrations {
+        // Ignore errors (column already exists)
+        let _ = conn.execute(migration, []);
+    }
+
+    // Rows inserted without an owner belong to whoever is signed in
+    for table in OWNED_TABLES {
+        owned_by_active_account(conn, table)?;
+    }
+
+    index_daily_notes_by_day(conn)?;
+
+    // Notes saved before content compression existed
+    compress_existing_notes(conn)?;
+
+    // Notes saved before task extraction existed have no task rows yet
+    if read_setting::<bool>(conn, TASKS_BACKFILLED_KEY)? != Some(true) {
+        backfill_tasks(conn)?;
+        write_setting(conn, TASKS_BACKFILLED_KEY, &true)?;
+    }
+
+    Ok(())
+}
+
+/// Allow one daily note per day and account. Rows from before daily notes
+/// recorded an owner take their note's, and only the first of a day's
+/// notes stays that day's note.
+fn index_daily_notes_by_day(conn: &Connection) -> SqliteResult<()> {
+    let indexed: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE name = 'idx_daily_notes_day'",
+        [],
+        |row| row.get(0),
+    )?;
+    if indexed {
+        return Ok(());
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute_batch(
+        "UPDATE daily_notes
+            SET owner_id = (SELECT owner_id FROM notes WHERE notes.id = daily_notes.note_id)
+            WHERE owner_id IS NULL;
+         DELETE FROM daily_notes WHERE rowid NOT IN (
+            SELECT MIN(rowid) FROM daily_notes GROUP BY date, IFNULL(owner_id, '')
+         );
+         CREATE UNIQUE INDEX idx_daily_notes_day ON daily_notes(date, IFNULL(owner_id, ''));",
+    )?;
+    tx.commit()
+}
+
+/// Index `table.owner_id` and give inserted rows without an owner to the
+/// active account
+pub fn owned_by_active_account(conn: &Connection, table: &str) -> SqliteResult<()> {
+    conn.execute_batch(&format!(
+        "CREATE INDEX IF NOT EXISTS idx_{table}_owner_id ON {table}(owner_id);
+         CREATE TRIGGER IF NOT EXISTS {table}_default_owner
+         AFTER INSERT ON {table} WHEN NEW.owner_id IS NULL
+         BEGIN
+            UPDATE {table} SET owner_id = (SELECT id FROM accounts WHERE is_active = 1)
+            WHERE rowid = NEW.rowid;
+         END;",
+        table = table
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrate_is_idempotent() {
+        let conn = open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+
+        let tables: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master
+                 WHERE name IN ('notes', 'folders', 'note_versions', 'accounts', 'app_settings', 'notes_fts')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(tables, 6);
+    }
+
+    #[test]
+    fn upgrades_databases_without_newer_columns() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE notes (
+                id TEXT PRIMARY KEY,
+                title TEXT NOT NULL DEFAULT '',
+                content TEXT NOT NULL DEFAULT '',
+                folder_id TEXT,
+                is_pinned INTEGER DEFAULT 0,
+                updated_at TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )
+        .unwrap();
+        migrate(&conn).unwrap();
+
+        conn.execute(
+            "INSERT INTO notes (id, updated_at, created_at, pinned_at, font, owner_id)
+             VALUES ('n1', '', '', NULL, 'mono', NULL)",
+            [],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn keeps_one_daily_note_per_day_and_account() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+        conn.execute_batch(
+            "DROP INDEX idx_daily_notes_day;
+             DROP TABLE daily_notes;
+             CREATE TABLE daily_notes (date TEXT NOT NULL, note_id TEXT NOT NULL);
+             INSERT INTO notes (id, updated_at, created_at, owner_id) VALUES
+                ('a1', '', '', 'alice'), ('a2', '', '', 'alice'), ('b1', '', '', 'bob');
+             INSERT INTO daily_notes (date, note_id) VALUES
+                ('2025-01-02', 'a1'), ('2025-01-02', 'a2'), ('2025-01-02', 'b1');",
+        )
+        .unwrap();
+        migrate(&conn).unwrap();
+
+        let mut stmt = conn
+            .prepare("SELECT note_id, owner_id FROM daily_notes ORDER BY note_id")
+            .unwrap();
+        let rows: Vec<(String, String)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<SqliteResult<_>>()
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                ("a1".to_string(), "alice".to_string()),
+                ("b1".to_string(), "bob".to_string())
+            ]
+        );
+    }
+}
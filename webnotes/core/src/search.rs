@@ -0,0 +1,153 @@
+use rusqlite::{params, Connection, Result as SqliteResult};
+
+use crate::accounts::OWNED_BY_ACTIVE;
+use crate::compression::StoredContent;
+use crate::model::Note;
+use crate::notes::note_from_row;
+
+// =============================================================================
+// SEARCH
+// =============================================================================
+//
+// `notes_fts` holds one row per note, whoever owns it; searches only return
+// the active account's. FTS5 tables do not support upserts, so indexing
+// deletes the old row first.
+
+pub const MAX_RESULTS: usize = 50;
+
+/// Notes matching `query` as a prefix phrase, best match first
+pub fn run_search(conn: &Connection, query: &str) -> SqliteResult<Vec<Note>> {
+    if query.trim().is_empty() {
+        return Ok(vec![]);
+    }
+
+    // Sanitize FTS query to prevent injection
+    let sanitized_query = sanitize_fts_query(query);
+    if sanitized_query.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT n.id, n.title, n.content, n.folder_id, n.is_pinned, n.pinned_at, n.font, n.updated_at, n.created_at, n.version
+         FROM notes n
+         JOIN notes_fts f ON n.id = f.id
+         WHERE notes_fts MATCH ?1 AND n.{}
+         ORDER BY rank
+         LIMIT ?2",
+        OWNED_BY_ACTIVE
+    ))?;
+
+    let notes = stmt.query_map(params![sanitized_query, MAX_RESULTS as i64], note_from_row)?;
+    notes.collect::<SqliteResult<Vec<_>>>()
+}
+
+/// Sanitize user input for FTS5 queries
+pub fn sanitize_fts_query(query: &str) -> String {
+    // Remove FTS5 special characters that could cause issues
+    let cleaned: String = query
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .collect();
+
+    let trimmed = cleaned.trim();
+
+    if trimmed.is_empty() {
+        return String::new();
+    }
+
+    // Wrap in quotes for phrase matching, add * for prefix matching
+    format!("\"{}\"*", trimmed)
+}
+
+/// Replace a note's FTS row
+pub fn index_note(conn: &Connection, id: &str, title: &str, content: &str) -> SqliteResult<()> {
+    unindex_note(conn, id)?;
+    conn.execute(
+        "INSERT INTO notes_fts (id, title, content) VALUES (?1, ?2, ?3)",
+        params![id, title, content],
+    )?;
+    Ok(())
+}
+
+pub fn unindex_note(conn: &Connection, id: &str) -> SqliteResult<()> {
+    conn.execute("DELETE FROM notes_fts WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+/// Rebuild `notes_fts` from the notes table
+pub fn rebuild_index(conn: &Connection) -> SqliteResult<usize> {
+    conn.execute("DELETE FROM notes_fts", [])?;
+
+    let mut stmt = conn.prepare("SELECT id, title, content FROM notes")?;
+    let mut rows = stmt.query([])?;
+    let mut count = 0;
+    while let Some(row) = rows.next()? {
+        let content: StoredContent = row.get(2)?;
+        conn.execute(
+            "INSERT INTO notes_fts (id, title, content) VALUES (?1, ?2, ?3)",
+            params![
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                content.0
+            ],
+        )?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::{delete_note, save_note};
+    use crate::open_in_memory;
+
+    fn titles(notes: Vec<Note>) -> Vec<String> {
+        notes.into_iter().map(|note| note.title).collect()
+    }
+
+    #[test]
+    fn sanitizes_queries() {
+        assert_eq!(sanitize_fts_query("rust  "), "\"rust\"*");
+        assert_eq!(sanitize_fts_query("a\" OR b*"), "\"a OR b\"*");
+        assert_eq!(sanitize_fts_query("\"*()"), "");
+    }
+
+    #[test]
+    fn finds_notes_by_prefix_in_title_or_content() {
+        let conn = open_in_memory().unwrap();
+        save_note(&conn, &Note::new("Sourdough", "<p>starter and flour</p>")).unwrap();
+        save_note(&conn, &Note::new("Groceries", "<p>more flour</p>")).unwrap();
+
+        assert_eq!(titles(run_search(&conn, "sour").unwrap()), ["Sourdough"]);
+        assert_eq!(run_search(&conn, "flou").unwrap().len(), 2);
+        assert!(run_search(&conn, "   ").unwrap().is_empty());
+        assert!(run_search(&conn, "\"(").unwrap().is_empty());
+    }
+
+    #[test]
+    fn follows_edits_and_deletes() {
+        let conn = open_in_memory().unwrap();
+        let mut note = Note::new("Draft", "<p>alpha</p>");
+        save_note(&conn, &note).unwrap();
+
+        note.content = "<p>beta</p>".to_string();
+        save_note(&conn, &note).unwrap();
+        assert!(run_search(&conn, "alpha").unwrap().is_empty());
+        assert_eq!(run_search(&conn, "beta").unwrap().len(), 1);
+
+        delete_note(&conn, &note.id).unwrap();
+        assert!(run_search(&conn, "beta").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rebuilds_the_index() {
+        let conn = open_in_memory().unwrap();
+        save_note(&conn, &Note::new("Kept", "")).unwrap();
+        conn.execute("DELETE FROM notes_fts", []).unwrap();
+        assert!(run_search(&conn, "kept").unwrap().is_empty());
+
+        assert_eq!(rebuild_index(&conn).unwrap(), 1);
+        assert_eq!(titles(run_search(&conn, "kept").unwrap()), ["Kept"]);
+    }
+}
@@ -0,0 +1,62 @@
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+
+// =============================================================================
+// SETTINGS
+// =============================================================================
+
+pub fn read_setting<T: for<'de> Deserialize<'de>>(
+    conn: &Connection,
+    key: &str,
+) -> SqliteResult<Option<T>> {
+    let raw: Option<String> = conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    // A malformed value is treated as unset rather than failing the caller
+    Ok(raw.and_then(|v| match serde_json::from_str(&v) {
+        Ok(parsed) => Some(parsed),
+        Err(e) => {
+            log::warn!("Ignoring malformed setting {}: {}", key, e);
+            None
+        }
+    }))
+}
+
+pub fn write_setting<T: Serialize>(conn: &Connection, key: &str, value: &T) -> SqliteResult<()> {
+    let raw = serde_json::to_string(value)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![key, raw],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::open_in_memory;
+
+    #[test]
+    fn round_trips_and_overwrites() {
+        let conn = open_in_memory().unwrap();
+        assert_eq!(read_setting::<u32>(&conn, "answer").unwrap(), None);
+
+        write_setting(&conn, "answer", &41).unwrap();
+        write_setting(&conn, "answer", &42).unwrap();
+        assert_eq!(read_setting::<u32>(&conn, "answer").unwrap(), Some(42));
+    }
+
+    #[test]
+    fn malformed_values_read_as_unset() {
+        let conn = open_in_memory().unwrap();
+        write_setting(&conn, "flag", &"not a bool").unwrap();
+        assert_eq!(read_setting::<bool>(&conn, "flag").unwrap(), None);
+    }
+}
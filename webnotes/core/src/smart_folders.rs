@@ -0,0 +1,233 @@
+use chrono::{DateTime, Local, NaiveDate};
+use rusqlite::{params, Connection, Result as SqliteResult, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::accounts::OWNED_BY_ACTIVE;
+use crate::html::html_to_text;
+use crate::model::Note;
+use crate::notes::note_from_row;
+use crate::search::sanitize_fts_query;
+use crate::tags::find_tags;
+
+// =============================================================================
+// SMART FOLDERS
+// =============================================================================
+//
+// Saved searches shown alongside folders. The query is stored as JSON and
+// evaluated whenever the folder is opened or counted.
+
+/// Filters of a smart folder; all of them must match
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SmartFolderQuery {
+    /// Full-text query, as for `search::run_search`
+    pub text: Option<String>,
+    /// Notes in any of these folders; empty means any folder
+    pub folder_ids: Vec<String>,
+    /// Notes with every one of these tags (or a tag nested under it)
+    pub tags: Vec<String>,
+    pub pinned: Option<bool>,
+    pub updated_after: Option<String>,  // YYYY-MM-DD, inclusive
+    pub updated_before: Option<String>, // YYYY-MM-DD, inclusive
+    pub created_after: Option<String>,  // YYYY-MM-DD, inclusive
+    pub created_before: Option<String>, // YYYY-MM-DD, inclusive
+}
+
+/// A saved search shown alongside folders
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartFolder {
+    pub id: String,
+    pub name: String,
+    pub query: SmartFolderQuery,
+    pub created_at: String,
+}
+
+const SMART_FOLDER_COLUMNS: &str = "id, name, query, created_at";
+
+fn smart_folder_from_row(row: &Row) -> SqliteResult<SmartFolder> {
+    let raw: String = row.get(2)?;
+    let query = serde_json::from_str(&raw).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e))
+    })?;
+    Ok(SmartFolder {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        query,
+        created_at: row.get(3)?,
+    })
+}
+
+/// Inclusive local-date bounds of a query's date filters
+struct DateBounds {
+    updated: (Option<NaiveDate>, Option<NaiveDate>),
+    created: (Option<NaiveDate>, Option<NaiveDate>),
+}
+
+impl DateBounds {
+    fn parse(query: &SmartFolderQuery) -> Result<Self, chrono::ParseError> {
+        let date = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|d| NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d"))
+                .transpose()
+        };
+        Ok(Self {
+            updated: (date(&query.updated_after)?, date(&query.updated_before)?),
+            created: (date(&query.created_after)?, date(&query.created_before)?),
+        })
+    }
+
+    fn contains(bounds: (Option<NaiveDate>, Option<NaiveDate>), timestamp: &str) -> bool {
+        if bounds == (None, None) {
+            return true;
+        }
+        let Ok(at) = DateTime::parse_from_rfc3339(timestamp) else {
+            return false;
+        };
+        let day = at.with_timezone(&Local).date_naive();
+        bounds.0.map_or(true, |after| day >= after) && bounds.1.map_or(true, |before| day <= before)
+    }
+
+    fn matches(&self, note: &Note) -> bool {
+        Self::contains(self.updated, &note.updated_at)
+            && Self::contains(self.created, &note.created_at)
+    }
+}
+
+/// `#Tag` -> `tag`
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').to_lowercase()
+}
+
+/// Run a smart folder's query against the active account's notes. Text,
+/// folder and pinned filters are SQL; tags and dates are checked on the
+/// results.
+pub fn evaluate(conn: &Connection, query: &SmartFolderQuery) -> SqliteResult<Vec<Note>> {
+    let mut sql = "SELECT n.id, n.title, n.content, n.folder_id, n.is_pinned, n.pinned_at, n.font, n.updated_at, n.created_at, n.version
+                   FROM notes n"
+        .to_string();
+    let mut conditions = vec![format!("n.{}", OWNED_BY_ACTIVE)];
+    let mut values: Vec<rusqlite::types::Value> = Vec::new();
+
+    let text = sanitize_fts_query(query.text.as_deref().unwrap_or_default());
+    if !text.is_empty() {
+        sql.push_str(" JOIN notes_fts f ON n.id = f.id");
+        conditions.push("notes_fts MATCH ?".to_string());
+        values.push(text.into());
+    }
+    if !query.folder_ids.is_empty() {
+        conditions.push(format!(
+            "n.folder_id IN ({})",
+            vec!["?"; query.folder_ids.len()].join(", ")
+        ));
+        values.extend(query.folder_ids.iter().cloned().map(Into::into));
+    }
+    if let Some(pinned) = query.pinned {
+        conditions.push("n.is_pinned = ?".to_string());
+        values.push(pinned.into());
+    }
+
+    sql.push_str(" WHERE ");
+    sql.push_str(&conditions.join(" AND "));
+    sql.push_str(" ORDER BY n.is_pinned DESC, n.pinned_at DESC, n.updated_at DESC");
+
+    let mut stmt = conn.prepare(&sql)?;
+    let notes = stmt
+        .query_map(rusqlite::params_from_iter(values), note_from_row)?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    // Validated when the folder was saved; a bad date here matches nothing
+    let Ok(bounds) = DateBounds::parse(query) else {
+        return Ok(Vec::new());
+    };
+    let wanted: Vec<String> = query.tags.iter().map(|t| normalize_tag(t)).collect();
+
+    Ok(notes
+        .into_iter()
+        .filter(|note| bounds.matches(note))
+        .filter(|note| {
+            if wanted.is_empty() {
+                return true;
+            }
+            let found: Vec<String> = find_tags(&html_to_text(&note.content))
+                .into_iter()
+                .map(|(_, tag)| tag)
+                .collect();
+            wanted.iter().all(|want| {
+                found
+                    .iter()
+                    .any(|tag| tag == want || tag.starts_with(&format!("{}/", want)))
+            })
+        })
+        .collect())
+}
+
+pub fn load_smart_folder(conn: &Connection, id: &str) -> SqliteResult<SmartFolder> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM smart_folders WHERE id = ?1 AND {}",
+            SMART_FOLDER_COLUMNS, OWNED_BY_ACTIVE
+        ),
+        params![id],
+        smart_folder_from_row,
+    )
+}
+
+/// The active account's smart folders, newest first
+pub fn load_smart_folders(conn: &Connection) -> SqliteResult<Vec<SmartFolder>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM smart_folders WHERE {} ORDER BY created_at DESC",
+        SMART_FOLDER_COLUMNS, OWNED_BY_ACTIVE
+    ))?;
+    let folders = stmt.query_map([], smart_folder_from_row)?;
+    folders.collect::<SqliteResult<Vec<_>>>()
+}
+
+/// Insert or update a smart folder, normalizing its tags. Callers check
+/// its dates first; a query with an invalid date matches nothing.
+pub fn save_smart_folder(conn: &Connection, folder: &SmartFolder) -> SqliteResult<()> {
+    let mut query = folder.query.clone();
+    query.tags = query
+        .tags
+        .iter()
+        .map(|t| normalize_tag(t))
+        .filter(|t| !t.is_empty())
+        .collect();
+    let raw = serde_json::to_string(&query)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
+    conn.execute(
+        &format!(
+            "INSERT INTO smart_folders (id, name, query, created_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                query = excluded.query
+             WHERE smart_folders.{}",
+            OWNED_BY_ACTIVE
+        ),
+        params![folder.id, folder.name, raw, folder.created_at],
+    )?;
+    Ok(())
+}
+
+pub fn delete_smart_folder(conn: &Connection, id: &str) -> SqliteResult<()> {
+    conn.execute(
+        &format!(
+            "DELETE FROM smart_folders WHERE id = ?1 AND {}",
+            OWNED_BY_ACTIVE
+        ),
+        params![id],
+    )?;
+    Ok(())
+}
+
+/// Number of matching notes for every smart folder, by ID
+pub fn count_matches(conn: &Connection) -> SqliteResult<HashMap<String, usize>> {
+    load_smart_folders(conn)?
+        .into_iter()
+        .map(|folder| Ok((folder.id, evaluate(conn, &folder.query)?.len())))
+        .collect()
+}
@@ -0,0 +1,73 @@
+use std::ops::Range;
+
+use crate::html;
+
+// =============================================================================
+// HASHTAGS
+// =============================================================================
+
+/// Byte range (including the `#`) and lowercased name of each `#tag` in
+/// plain text. A tag must start with a letter and may contain letters,
+/// digits, `-`, `_` and `/` (for nesting).
+pub fn find_tags(text: &str) -> Vec<(Range<usize>, String)> {
+    let mut tags = Vec::new();
+    let mut prev = ' ';
+
+    for (i, c) in text.char_indices() {
+        if c == '#' && (prev.is_whitespace() || prev == '(') {
+            let rest = &text[i + 1..];
+            let len = rest
+                .find(|ch: char| !(ch.is_alphanumeric() || matches!(ch, '-' | '_' | '/')))
+                .unwrap_or(rest.len());
+            let tag = rest[..len].trim_end_matches('/');
+
+            if tag.chars().next().is_some_and(char::is_alphabetic) && !is_hex_color(tag) {
+                tags.push((i..i + 1 + tag.len(), tag.to_lowercase()));
+            }
+        }
+        prev = c;
+    }
+
+    tags
+}
+
+fn is_hex_color(tag: &str) -> bool {
+    matches!(tag.len(), 3 | 6) && tag.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Rename `#from`, and every tag nested under it, to `#to` in the text of
+/// note HTML. Both names are lowercased tag names without the `#`. Returns
+/// `None` when the content has no such tag.
+pub fn rename_tag(content: &str, from: &str, to: &str) -> Option<String> {
+    let mut renamed = String::with_capacity(content.len());
+    let mut changed = false;
+    let mut last = 0;
+
+    let segments = html::scan_tags(content)
+        .into_iter()
+        .map(|tag| tag.span)
+        .chain(std::iter::once(content.len()..content.len()));
+    for span in segments {
+        let text = &content[last..span.start];
+        let mut copied = 0;
+        for (range, name) in find_tags(text) {
+            let Some(rest) = name.strip_prefix(from) else {
+                continue;
+            };
+            if !(rest.is_empty() || rest.starts_with('/')) {
+                continue;
+            }
+            renamed.push_str(&text[copied..range.start]);
+            renamed.push('#');
+            renamed.push_str(to);
+            renamed.push_str(rest);
+            copied = range.end;
+            changed = true;
+        }
+        renamed.push_str(&text[copied..]);
+        renamed.push_str(&content[span.clone()]);
+        last = span.end;
+    }
+
+    changed.then_some(renamed)
+}
@@ -0,0 +1,489 @@
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+use crate::accounts::OWNED_BY_ACTIVE;
+use crate::compression::StoredContent;
+use crate::html::{html_to_text, scan_tags};
+
+// =============================================================================
+// TASK PARSING
+// =============================================================================
+//
+// Notes written in the editor store Tiptap HTML, where a checklist item is
+// `<li data-type="taskItem" data-checked="false">...</li>`. Imported and
+// plain-text notes use Markdown `- [ ] item` lines. Both are recognised and
+// numbered together in document order, which is what `task_index` refers to.
+// Checkbox-like lines inside code (fenced Markdown or `<pre>`) are examples,
+// not tasks.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedTask {
+    pub index: usize,
+    pub text: String,
+    pub checked: bool,
+    pub due: Option<String>,  // YYYY-MM-DD
+    pub priority: Option<u8>, // 1 (high) ..= 3 (low)
+    checkbox: CheckboxSpan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CheckboxSpan {
+    /// Byte offset of the character between `[` and `]`
+    Markdown(usize),
+    /// Byte range of the `data-checked` attribute value
+    Html { start: usize, end: usize },
+}
+
+impl CheckboxSpan {
+    fn offset(&self) -> usize {
+        match *self {
+            CheckboxSpan::Markdown(at) => at,
+            CheckboxSpan::Html { start, .. } => start,
+        }
+    }
+}
+
+/// Extract every checklist item from a note's content
+pub fn extract_tasks(content: &str) -> Vec<ParsedTask> {
+    let mut tasks = markdown_tasks(content);
+    tasks.extend(html_tasks(content));
+    tasks.sort_by_key(|t| t.checkbox.offset());
+
+    for (index, task) in tasks.iter_mut().enumerate() {
+        task.index = index;
+    }
+    tasks
+}
+
+/// Flip the checkbox of task `index`, returning the new content and the
+/// new checked state, or `None` if the note has no such task
+pub fn toggle_task(content: &str, index: usize) -> Option<(String, bool)> {
+    let task = extract_tasks(content).into_iter().nth(index)?;
+    let checked = !task.checked;
+
+    let mut updated = content.to_string();
+    match task.checkbox {
+        CheckboxSpan::Markdown(at) => {
+            updated.replace_range(at..at + 1, if checked { "x" } else { " " });
+        }
+        CheckboxSpan::Html { start, end } => {
+            updated.replace_range(start..end, if checked { "true" } else { "false" });
+        }
+    }
+
+    Some((updated, checked))
+}
+
+fn markdown_tasks(content: &str) -> Vec<ParsedTask> {
+    let mut tasks = Vec::new();
+    let mut line_start = 0;
+    let code = code_blocks(content);
+    let mut in_fence = false;
+
+    for line in content.split_inclusive('\n') {
+        let body = line.trim_end_matches(['\n', '\r']);
+        let trimmed = body.trim_start_matches([' ', '\t']);
+        let indent = body.len() - trimmed.len();
+
+        let fence = trimmed.starts_with("```") || trimmed.starts_with("~~~");
+        let in_pre = code.iter().any(|range| range.contains(&line_start));
+        if fence || in_fence || in_pre {
+            in_fence ^= fence;
+            line_start += line.len();
+            continue;
+        }
+
+        let rest = ["- ", "* ", "+ "]
+            .iter()
+            .find_map(|bullet| trimmed.strip_prefix(bullet));
+
+        if let Some(rest) = rest {
+            let mark = rest.as_bytes().get(1).copied();
+            let is_box = rest.starts_with('[')
+                && rest.as_bytes().get(2) == Some(&b']')
+                && matches!(mark, Some(b' ' | b'x' | b'X'))
+                && matches!(rest.as_bytes().get(3), None | Some(b' ' | b'\t'));
+
+            if is_box {
+                let bullet_len = trimmed.len() - rest.len();
+                let text = rest[3..].trim();
+                tasks.push(build_task(
+                    text,
+                    mark != Some(b' '),
+                    CheckboxSpan::Markdown(line_start + indent + bullet_len + 1),
+                ));
+            }
+        }
+
+        line_start += line.len();
+    }
+
+    tasks
+}
+
+/// Byte ranges of the content of `<pre>` elements
+fn code_blocks(content: &str) -> Vec<Range<usize>> {
+    let mut blocks = Vec::new();
+    let mut open = None;
+    for tag in scan_tags(content) {
+        match (tag.name.as_str(), tag.closing, open) {
+            ("pre", false, None) => open = Some(tag.span.end),
+            ("pre", true, Some(start)) => {
+                blocks.push(start..tag.span.start);
+                open = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = open {
+        blocks.push(start..content.len());
+    }
+    blocks
+}
+
+fn html_tasks(content: &str) -> Vec<ParsedTask> {
+    const MARKER: &str = "data-type=\"taskItem\"";
+    const CHECKED_ATTR: &str = "data-checked=\"";
+
+    let mut tasks = Vec::new();
+    let mut search_from = 0;
+
+    while let Some(found) = content[search_from..].find(MARKER) {
+        let marker_at = search_from + found;
+        search_from = marker_at + MARKER.len();
+
+        let Some(tag_start) = content[..marker_at].rfind("<li") else {
+            continue;
+        };
+        let Some(tag_len) = content[tag_start..].find('>') else {
+            break;
+        };
+        let tag_end = tag_start + tag_len;
+        if tag_end < marker_at {
+            // The marker is not inside an <li> tag
+            continue;
+        }
+
+        let tag = &content[tag_start..tag_end];
+        let Some(attr_at) = tag.find(CHECKED_ATTR) else {
+            continue;
+        };
+        let value_start = tag_start + attr_at + CHECKED_ATTR.len();
+        let Some(value_len) = content[value_start..tag_end].find('"') else {
+            continue;
+        };
+        let value_end = value_start + value_len;
+
+        // Stop at the next list item or nested list so sub-tasks are not
+        // folded into their parent's text
+        let body_start = tag_end + 1;
+        let body_end = ["<li", "</li>", "<ul", "<ol"]
+            .iter()
+            .filter_map(|stop| content[body_start..].find(stop))
+            .min()
+            .map_or(content.len(), |len| body_start + len);
+
+        let text = html_to_text(&content[body_start..body_end]);
+        tasks.push(build_task(
+            &text,
+            &content[value_start..value_end] == "true",
+            CheckboxSpan::Html {
+                start: value_start,
+                end: value_end,
+            },
+        ));
+    }
+
+    tasks
+}
+
+fn build_task(raw: &str, checked: bool, checkbox: CheckboxSpan) -> ParsedTask {
+    let mut due = None;
+    let mut priority = None;
+    let mut words = Vec::new();
+
+    for word in raw.split_whitespace() {
+        if let Some(date) = word
+            .strip_prefix("@due(")
+            .and_then(|w| w.strip_suffix(')'))
+            .filter(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").is_ok())
+        {
+            due = Some(date.to_string());
+        } else if let Some(level) = word.strip_prefix('!').and_then(parse_priority) {
+            priority = Some(level);
+        } else {
+            words.push(word);
+        }
+    }
+
+    ParsedTask {
+        index: 0,
+        text: words.join(" "),
+        checked,
+        due,
+        priority,
+        checkbox,
+    }
+}
+
+fn parse_priority(value: &str) -> Option<u8> {
+    match value.to_ascii_lowercase().as_str() {
+        "high" | "1" => Some(1),
+        "medium" | "med" | "2" => Some(2),
+        "low" | "3" => Some(3),
+        _ => None,
+    }
+}
+
+// =============================================================================
+// TASK INDEX
+// =============================================================================
+//
+// `tasks` holds the parsed items of every note, refreshed whenever the note's
+// content is written (see `notes::sync_note_index`), so lists across notes
+// are plain queries.
+
+/// Set once every note saved before task extraction existed is indexed
+pub(crate) const TASKS_BACKFILLED_KEY: &str = "tasks_backfilled";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskItem {
+    pub note_id: String,
+    pub task_index: usize,
+    pub text: String,
+    pub checked: bool,
+    pub due: Option<String>,
+    pub priority: Option<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TaskFilter {
+    pub include_completed: bool,
+    pub note_id: Option<String>,
+    pub folder_id: Option<String>,
+    pub due_before: Option<String>, // YYYY-MM-DD, inclusive
+    pub priority: Option<u8>,
+}
+
+/// Tasks sharing a note and due date
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskGroup {
+    pub note_id: String,
+    pub note_title: String,
+    /// For `expected_version` when toggling one of the tasks
+    pub note_version: i64,
+    pub due: Option<String>,
+    pub tasks: Vec<TaskItem>,
+}
+
+/// Replace a note's rows in `tasks` with the items in `content`
+pub fn index_note_tasks(conn: &Connection, note_id: &str, content: &str) -> SqliteResult<()> {
+    conn.execute("DELETE FROM tasks WHERE note_id = ?1", params![note_id])?;
+
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO tasks (note_id, task_index, text, checked, due, priority)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    for task in extract_tasks(content) {
+        stmt.execute(params![
+            note_id,
+            task.index as i64,
+            task.text,
+            task.checked,
+            task.due,
+            task.priority
+        ])?;
+    }
+    Ok(())
+}
+
+/// Index the tasks of every note, whoever owns it
+pub(crate) fn backfill_tasks(conn: &Connection) -> SqliteResult<()> {
+    let tx = conn.unchecked_transaction()?;
+    let notes = {
+        let mut stmt = tx.prepare("SELECT id, content FROM notes")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, StoredContent>(1)?.0))
+        })?;
+        rows.collect::<SqliteResult<Vec<_>>>()?
+    };
+    for (id, content) in &notes {
+        index_note_tasks(&tx, id, content)?;
+    }
+    tx.commit()?;
+
+    log::info!("Indexed tasks for {} existing notes", notes.len());
+    Ok(())
+}
+
+/// The active account's tasks, grouped by note and due date. Groups with a
+/// due date come first, soonest first.
+pub fn list_tasks(conn: &Connection, filter: &TaskFilter) -> SqliteResult<Vec<TaskGroup>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT t.note_id, t.task_index, t.text, t.checked, t.due, t.priority, n.title, n.version
+         FROM tasks t
+         JOIN notes n ON n.id = t.note_id
+         WHERE (?1 OR t.checked = 0)
+           AND (?2 IS NULL OR t.note_id = ?2)
+           AND (?3 IS NULL OR n.folder_id = ?3)
+           AND (?4 IS NULL OR (t.due IS NOT NULL AND t.due <= ?4))
+           AND (?5 IS NULL OR t.priority = ?5)
+           AND n.{}
+         ORDER BY t.due IS NULL, t.due ASC, n.title COLLATE NOCASE ASC, t.note_id, t.task_index",
+        OWNED_BY_ACTIVE
+    ))?;
+
+    let rows = stmt.query_map(
+        params![
+            filter.include_completed,
+            filter.note_id,
+            filter.folder_id,
+            filter.due_before,
+            filter.priority
+        ],
+        |row| {
+            Ok((
+                TaskItem {
+                    note_id: row.get(0)?,
+                    task_index: row.get::<_, i64>(1)? as usize,
+                    text: row.get(2)?,
+                    checked: row.get(3)?,
+                    due: row.get(4)?,
+                    priority: row.get(5)?,
+                },
+                row.get::<_, String>(6)?,
+                row.get::<_, i64>(7)?,
+            ))
+        },
+    )?;
+
+    // Rows arrive sorted by (due, note), so each group is contiguous
+    let mut groups: Vec<TaskGroup> = Vec::new();
+    for row in rows {
+        let (task, note_title, note_version) = row?;
+        match groups.last_mut() {
+            Some(group) if group.note_id == task.note_id && group.due == task.due => {
+                group.tasks.push(task)
+            }
+            _ => groups.push(TaskGroup {
+                note_id: task.note_id.clone(),
+                note_title,
+                note_version,
+                due: task.due.clone(),
+                tasks: vec![task],
+            }),
+        }
+    }
+
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(content: &str) -> Vec<(usize, String, bool)> {
+        extract_tasks(content)
+            .into_iter()
+            .map(|t| (t.index, t.text, t.checked))
+            .collect()
+    }
+
+    #[test]
+    fn nested_tasks_keep_their_own_text() {
+        let html = concat!(
+            r#"<ul data-type="taskList"><li data-type="taskItem" data-checked="false">"#,
+            "<p>Parent !high</p>",
+            r#"<ul data-type="taskList"><li data-checked="true" data-type="taskItem">"#,
+            "<p>Child @due(2025-03-01)</p></li></ul></li>",
+            r#"<li data-type="taskItem" data-checked="false"><p>Sibling</p></li></ul>"#,
+        );
+        assert_eq!(
+            summary(html),
+            [
+                (0, "Parent".to_string(), false),
+                (1, "Child".to_string(), true),
+                (2, "Sibling".to_string(), false),
+            ]
+        );
+        let tasks = extract_tasks(html);
+        assert_eq!(tasks[0].priority, Some(1));
+        assert_eq!(tasks[1].due.as_deref(), Some("2025-03-01"));
+
+        let markdown = "- [ ] parent\n  - [x] child\n\t* [ ] tabbed\n- [] not a task\n";
+        assert_eq!(
+            summary(markdown),
+            [
+                (0, "parent".to_string(), false),
+                (1, "child".to_string(), true),
+                (2, "tabbed".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn multibyte_text_before_the_checkbox() {
+        let markdown = "Grüße — 日本語 ✓\n- [ ] café ☕\n";
+        let (toggled, checked) = toggle_task(markdown, 0).unwrap();
+        assert!(checked);
+        assert_eq!(toggled, "Grüße — 日本語 ✓\n- [x] café ☕\n");
+
+        let html = concat!(
+            "<p>Ünïcödé 🎉</p>",
+            r#"<ul data-type="taskList"><li data-type="taskItem" data-checked="false"><p>naïve</p></li></ul>"#,
+        );
+        let (toggled, checked) = toggle_task(html, 0).unwrap();
+        assert!(checked);
+        assert_eq!(toggled, html.replace("\"false\"", "\"true\""));
+        assert_eq!(summary(&toggled), [(0, "naïve".to_string(), true)]);
+    }
+
+    #[test]
+    fn toggles_only_the_task_at_its_index() {
+        let content = concat!(
+            "- [ ] markdown first\n",
+            r#"<ul data-type="taskList"><li data-type="taskItem" data-checked="true"><p>html</p></li></ul>"#,
+            "\n- [x] markdown last\n",
+        );
+        let before = summary(content);
+        assert_eq!(before.len(), 3);
+
+        for index in 0..before.len() {
+            let (toggled, checked) = toggle_task(content, index).unwrap();
+            let after = summary(&toggled);
+            for (old, new) in before.iter().zip(&after) {
+                let flipped = old.0 == index;
+                assert_eq!(
+                    new.2,
+                    old.2 != flipped,
+                    "task {} after toggling {}",
+                    old.0,
+                    index
+                );
+            }
+            assert_eq!(checked, after[index].2);
+        }
+
+        assert_eq!(toggle_task(content, before.len()), None);
+        assert_eq!(toggle_task("no tasks here", 0), None);
+    }
+
+    #[test]
+    fn ignores_checkboxes_in_code_blocks() {
+        let content = concat!(
+            "<pre><code>\n- [ ] example in code\n</code></pre>",
+            r#"<ul data-type="taskList"><li data-type="taskItem" data-checked="false"><p>real</p></li></ul>"#,
+        );
+        assert_eq!(summary(content), [(0, "real".to_string(), false)]);
+        let (toggled, _) = toggle_task(content, 0).unwrap();
+        assert!(toggled.contains("- [ ] example in code"));
+
+        let markdown = "```\n- [ ] example\n```\n- [ ] real\n";
+        assert_eq!(summary(markdown), [(0, "real".to_string(), false)]);
+    }
+}
@@ -0,0 +1,92 @@
+use rusqlite::{params, Connection, Result as SqliteResult, Row};
+
+use crate::accounts::NOTE_OWNED_BY_ACTIVE;
+use crate::compression::{encode_content, StoredContent};
+use crate::model::NoteVersion;
+
+// =============================================================================
+// NOTE VERSIONS
+// =============================================================================
+//
+// Versions are never edited; they are written once and deleted with their
+// note.
+
+pub const NOTE_VERSION_COLUMNS: &str = "id, note_id, title, content, created_at, change_type";
+
+/// Map a row selected with `NOTE_VERSION_COLUMNS` to a `NoteVersion`
+pub fn note_version_from_row(row: &Row) -> SqliteResult<NoteVersion> {
+    Ok(NoteVersion {
+        id: row.get(0)?,
+        note_id: row.get(1)?,
+        title: row.get(2)?,
+        content: row.get::<_, StoredContent>(3)?.0,
+        created_at: row.get(4)?,
+        change_type: row.get(5)?,
+    })
+}
+
+pub fn insert_note_version(conn: &Connection, version: &NoteVersion) -> SqliteResult<()> {
+    conn.execute(
+        "INSERT INTO note_versions (id, note_id, title, content, created_at, change_type)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            version.id,
+            version.note_id,
+            version.title,
+            encode_content(&version.content),
+            version.created_at,
+            version.change_type
+        ],
+    )?;
+    Ok(())
+}
+
+/// A note's versions, newest first. Empty if the note belongs to another
+/// account.
+pub fn load_note_versions(conn: &Connection, note_id: &str) -> SqliteResult<Vec<NoteVersion>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM note_versions
+         WHERE note_id = ?1 AND {}
+         ORDER BY created_at DESC",
+        NOTE_VERSION_COLUMNS, NOTE_OWNED_BY_ACTIVE
+    ))?;
+    let versions = stmt.query_map(params![note_id], note_version_from_row)?;
+    versions.collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::save_note;
+    use crate::{open_in_memory, Note};
+
+    fn version(id: &str, created_at: &str, content: String) -> NoteVersion {
+        NoteVersion {
+            id: id.to_string(),
+            note_id: "n1".to_string(),
+            title: "Title".to_string(),
+            content,
+            created_at: created_at.to_string(),
+            change_type: "manual".to_string(),
+        }
+    }
+
+    #[test]
+    fn loads_versions_newest_first() {
+        let conn = open_in_memory().unwrap();
+        let mut note = Note::new("Title", "");
+        note.id = "n1".to_string();
+        save_note(&conn, &note).unwrap();
+        let old = version("v1", "2024-01-01T00:00:00+00:00", "<p>old</p>".to_string());
+        let large = version(
+            "v2",
+            "2024-02-01T00:00:00+00:00",
+            "<p>large</p>".repeat(10_000),
+        );
+        insert_note_version(&conn, &old).unwrap();
+        insert_note_version(&conn, &large).unwrap();
+
+        assert_eq!(load_note_versions(&conn, "n1").unwrap(), vec![large, old]);
+        assert!(load_note_versions(&conn, "n2").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,421 @@
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tiny_http::{Header, Method, Request, Response, Server};
+use webnotes_core::accounts::OWNED_BY_ACTIVE;
+
+use crate::html::escape_html;
+use crate::{load_folders, load_note, notes, run_search, DbState, Folder, Note};
+
+// =============================================================================
+// LOCAL SCRIPTING API
+// =============================================================================
+//
+// An opt-in JSON API on 127.0.0.1 for scripts, launchers and editor plugins.
+// Every request except the schema needs `Authorization: Bearer <token>`,
+// where the token is read from `api-token` in the app data dir. The endpoints
+// are described in `openapi.json`, served at `/v1/openapi.json`.
+
+pub const TOKEN_FILE: &str = "api-token";
+pub const DEFAULT_PORT: u16 = 27_183;
+
+/// Emitted after the API changes notes so open windows can reload
+pub const NOTES_CHANGED_EVENT: &str = "notes-changed";
+
+const OPENAPI_SCHEMA: &str = include_str!("../openapi.json");
+const MAX_BODY_BYTES: u64 = 5 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LocalApiConfig {
+    pub enabled: bool,
+    pub port: u16,
+}
+
+impl Default for LocalApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_PORT,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Default, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum ContentFormat {
+    /// Plain text, converted to editor paragraphs
+    #[default]
+    Text,
+    /// Editor HTML, stored as-is
+    Html,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateNoteRequest {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    format: ContentFormat,
+    folder_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AppendRequest {
+    content: String,
+    #[serde(default)]
+    format: ContentFormat,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+struct ApiError {
+    status: u16,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<String> for ApiError {
+    fn from(message: String) -> Self {
+        Self::new(500, message)
+    }
+}
+
+type ApiResult = Result<Response<std::io::Cursor<Vec<u8>>>, ApiError>;
+
+/// A running API server; dropping it does not stop the server, `stop` does
+pub struct LocalApi {
+    server: Arc<Server>,
+    worker: JoinHandle<()>,
+    pub port: u16,
+}
+
+impl LocalApi {
+    /// Stop accepting requests and wait for the request being handled, so
+    /// the port is free again on return
+    pub fn stop(self) {
+        self.server.unblock();
+        if self.worker.join().is_err() {
+            log::error!("Local API thread on port {} panicked", self.port);
+        }
+        log::info!("Local API on port {} stopped", self.port);
+    }
+}
+
+/// Read the API token, creating one on first use
+pub fn load_or_create_token(app_data_dir: &Path) -> Result<String, String> {
+    let path = app_data_dir.join(TOKEN_FILE);
+
+    if let Ok(existing) = std::fs::read_to_string(&path) {
+        let token = existing.trim().to_string();
+        if !token.is_empty() {
+            return Ok(token);
+        }
+    }
+
+    regenerate_token(app_data_dir)
+}
+
+pub fn regenerate_token(app_data_dir: &Path) -> Result<String, String> {
+    let path = app_data_dir.join(TOKEN_FILE);
+    let token = format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    );
+
+    std::fs::write(&path, &token).map_err(|e| format!("Failed to write API token: {}", e))?;
+
+    // Only the current user may read the token
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("Failed to restrict API token permissions: {}", e))?;
+    }
+
+    Ok(token)
+}
+
+pub fn start(handle: AppHandle, port: u16, token: String) -> Result<LocalApi, String> {
+    let server = Server::http(("127.0.0.1", port))
+        .map_err(|e| format!("Failed to start local API on port {}: {}", port, e))?;
+    let server = Arc::new(server);
+    let port = server
+        .server_addr()
+        .to_ip()
+        .map(|addr| addr.port())
+        .unwrap_or(port);
+
+    let listener = Arc::clone(&server);
+    let worker = std::thread::Builder::new()
+        .name("local-api".to_string())
+        .spawn(move || {
+            for request in listener.incoming_requests() {
+                handle_request(&handle, &token, port, request);
+            }
+        })
+        .map_err(|e| format!("Failed to spawn local API thread: {}", e))?;
+
+    log::info!("Local API listening on 127.0.0.1:{}", port);
+    Ok(LocalApi {
+        server,
+        worker,
+        port,
+    })
+}
+
+fn handle_request(handle: &AppHandle, token: &str, port: u16, mut request: Request) {
+    let response = route(handle, token, port, &mut request).unwrap_or_else(|e| {
+        json_response(
+            e.status,
+            &ErrorBody {
+                error: e.message.clone(),
+            },
+        )
+        .unwrap_or_else(|_| Response::from_string(e.message).with_status_code(e.status))
+    });
+
+    if let Err(e) = request.respond(response) {
+        log::warn!("Local API failed to respond: {}", e);
+    }
+}
+
+fn route(handle: &AppHandle, token: &str, port: u16, request: &mut Request) -> ApiResult {
+    check_host(request, port)?;
+
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((url.as_str(), ""));
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    if request.method() == &Method::Get && segments == ["v1", "openapi.json"] {
+        return Ok(Response::from_string(OPENAPI_SCHEMA).with_header(content_type_json()));
+    }
+
+    check_token(request, token)?;
+
+    let state = handle.state::<DbState>();
+    let method = request.method().clone();
+
+    match (method, segments.as_slice()) {
+        (Method::Get, ["v1", "health"]) => json_response(
+            200,
+            &serde_json::json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION") }),
+        ),
+
+        (Method::Get, ["v1", "folders"]) => {
+            let folders: Vec<Folder> = state.with_conn(load_folders)?;
+            json_response(200, &folders)
+        }
+
+        (Method::Get, ["v1", "search"]) => {
+            let q = query_param(query, "q").unwrap_or_default();
+            let notes = state.with_conn(|conn| run_search(conn, &q))?;
+            json_response(200, &notes)
+        }
+
+        (Method::Get, ["v1", "notes", id]) => {
+            let id = id.to_string();
+            let note = state
+                .with_conn(|conn| load_note(conn, &id))?
+                .ok_or_else(|| ApiError::new(404, format!("Note not found: {}", id)))?;
+            json_response(200, &note)
+        }
+
+        (Method::Post, ["v1", "notes"]) => {
+            let body: CreateNoteRequest = read_json(request)?;
+            let note = create_note(&state, body)?;
+            notify_changed(handle, &note.id);
+            json_response(201, &note)
+        }
+
+        (Method::Post, ["v1", "notes", id, "append"]) => {
+            let id = id.to_string();
+            let body: AppendRequest = read_json(request)?;
+            let note = append_to_note(&state, &id, body)?;
+            notify_changed(handle, &note.id);
+            json_response(200, &note)
+        }
+
+        _ => Err(ApiError::new(404, format!("No route for {}", path))),
+    }
+}
+
+fn create_note(state: &DbState, body: CreateNoteRequest) -> Result<Note, ApiError> {
+    let now = Utc::now().to_rfc3339();
+    let note = Note {
+        id: uuid::Uuid::new_v4().to_string(),
+        title: body.title.trim().to_string(),
+        content: to_editor_html(&body.content, body.format),
+        folder_id: body.folder_id.filter(|f| !f.is_empty()),
+        is_pinned: false,
+        pinned_at: None,
+        font: None,
+        updated_at: now.clone(),
+        created_at: now,
+        version: 0,
+    };
+
+    if let Some(folder_id) = &note.folder_id {
+        let exists = state
+            .with_conn(|conn| {
+                conn.query_row(
+                    &format!(
+                        "SELECT 1 FROM folders WHERE id = ?1 AND {}",
+                        OWNED_BY_ACTIVE
+                    ),
+                    params![folder_id],
+                    |_| Ok(()),
+                )
+                .optional()
+            })?
+            .is_some();
+        if !exists {
+            return Err(ApiError::new(
+                400,
+                format!("Folder not found: {}", folder_id),
+            ));
+        }
+    }
+
+    state.with_conn(|conn| notes::insert_note(conn, &note))?;
+
+    Ok(note)
+}
+
+fn append_to_note(state: &DbState, id: &str, body: AppendRequest) -> Result<Note, ApiError> {
+    let addition = to_editor_html(&body.content, body.format);
+
+    state
+        .with_conn(|conn| {
+            let tx = conn.unchecked_transaction()?;
+            let Some(mut note) = load_note(&tx, id)? else {
+                return Ok(None);
+            };
+
+            note.content.push_str(&addition);
+            note.updated_at = Utc::now().to_rfc3339();
+
+            notes::update_content(&tx, &note.id, &note.content, &note.updated_at)?;
+            notes::sync_note_index(&tx, &note.id, &note.title, &note.content)?;
+
+            // Reloaded for the version the update gave it
+            let appended = load_note(&tx, &note.id)?;
+            tx.commit()?;
+
+            Ok(appended)
+        })?
+        .ok_or_else(|| ApiError::new(404, format!("Note not found: {}", id)))
+}
+
+/// Plain text becomes one paragraph per line, matching what the editor
+/// produces when text is typed or pasted
+fn to_editor_html(content: &str, format: ContentFormat) -> String {
+    match format {
+        ContentFormat::Html => content.to_string(),
+        ContentFormat::Text => content
+            .lines()
+            .map(|line| format!("<p>{}</p>", escape_html(line)))
+            .collect(),
+    }
+}
+
+fn notify_changed(handle: &AppHandle, id: &str) {
+    if let Err(e) = handle.emit(NOTES_CHANGED_EVENT, vec![id]) {
+        log::warn!("Failed to emit {}: {}", NOTES_CHANGED_EVENT, e);
+    }
+}
+
+/// Reject requests whose Host is not loopback, which blocks DNS rebinding
+/// from web pages
+fn check_host(request: &Request, port: u16) -> Result<(), ApiError> {
+    let host = header_value(request, "Host").unwrap_or_default();
+    let allowed = [format!("127.0.0.1:{}", port), format!("localhost:{}", port)];
+
+    if allowed.iter().any(|a| a == &host) {
+        Ok(())
+    } else {
+        Err(ApiError::new(403, "Invalid Host header"))
+    }
+}
+
+fn check_token(request: &Request, token: &str) -> Result<(), ApiError> {
+    let provided = header_value(request, "Authorization")
+        .and_then(|v| v.strip_prefix("Bearer ").map(|t| t.trim().to_string()))
+        .unwrap_or_default();
+
+    if constant_time_eq(provided.as_bytes(), token.as_bytes()) {
+        Ok(())
+    } else {
+        Err(ApiError::new(401, "Missing or invalid API token"))
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn header_value(request: &Request, name: &'static str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str().to_string())
+}
+
+fn query_param(query: &str, name: &str) -> Option<String> {
+    let url = tauri::Url::parse(&format!("http://localhost/?{}", query)).ok()?;
+    let value = url
+        .query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned());
+    value
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(request: &mut Request) -> Result<T, ApiError> {
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_BYTES + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| ApiError::new(400, format!("Failed to read body: {}", e)))?;
+
+    if body.len() as u64 > MAX_BODY_BYTES {
+        return Err(ApiError::new(413, "Request body too large"));
+    }
+
+    serde_json::from_slice(&body).map_err(|e| ApiError::new(400, format!("Invalid JSON: {}", e)))
+}
+
+fn json_response<T: Serialize>(status: u16, value: &T) -> ApiResult {
+    let body = serde_json::to_vec(value)
+        .map_err(|e| ApiError::new(500, format!("Failed to encode response: {}", e)))?;
+
+    Ok(Response::from_data(body)
+        .with_status_code(status)
+        .with_header(content_type_json()))
+}
+
+fn content_type_json() -> Header {
+    Header::from_bytes("Content-Type", "application/json").expect("static header is valid")
+}
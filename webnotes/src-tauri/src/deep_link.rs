@@ -0,0 +1,190 @@
+use serde::Serialize;
+use tauri::Url;
+
+// =============================================================================
+// DEEP LINK ROUTING
+// =============================================================================
+//
+// Supported URLs:
+//   webnotes://note/<id>
+//   webnotes://note?title=<title>
+//   webnotes://folder/<id>
+//   webnotes://search?q=<query>
+//   webnotes://new?folder=<folder id or name>&title=<title>&content=<content>
+//
+// `webnotes://auth` is handled by the frontend's AuthListener and ignored here.
+
+pub const SCHEME: &str = "webnotes";
+
+const MAX_ID_LEN: usize = 100;
+const MAX_TEXT_LEN: usize = 500;
+const MAX_CONTENT_LEN: usize = 100_000;
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum DeepLinkAction {
+    #[serde(rename_all = "camelCase")]
+    OpenNote { id: String },
+    #[serde(rename_all = "camelCase")]
+    OpenNoteByTitle { title: String },
+    #[serde(rename_all = "camelCase")]
+    OpenFolder { id: String },
+    #[serde(rename_all = "camelCase")]
+    Search { query: String },
+    #[serde(rename_all = "camelCase")]
+    NewNote {
+        folder: Option<String>,
+        title: Option<String>,
+        content: Option<String>,
+    },
+}
+
+/// Parse a deep link. `Ok(None)` means the URL is valid but handled
+/// elsewhere (auth callbacks).
+pub fn parse(url: &Url) -> Result<Option<DeepLinkAction>, String> {
+    if url.scheme() != SCHEME {
+        return Err(format!("Unsupported scheme '{}'", url.scheme()));
+    }
+
+    let host = url.host_str().unwrap_or_default();
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(|s| s.filter(|seg| !seg.is_empty()).collect())
+        .unwrap_or_default();
+
+    let action = match (host, segments.as_slice()) {
+        ("auth", _) => return Ok(None),
+        ("note", [id]) => DeepLinkAction::OpenNote {
+            id: validate_id(id)?,
+        },
+        ("note", []) => DeepLinkAction::OpenNoteByTitle {
+            title: required_param(url, "title")?,
+        },
+        ("folder", [id]) => DeepLinkAction::OpenFolder {
+            id: validate_id(id)?,
+        },
+        ("search", []) => DeepLinkAction::Search {
+            query: required_param(url, "q")?,
+        },
+        ("new", []) => DeepLinkAction::NewNote {
+            folder: optional_param(url, "folder", MAX_TEXT_LEN)?,
+            title: optional_param(url, "title", MAX_TEXT_LEN)?,
+            content: optional_param(url, "content", MAX_CONTENT_LEN)?,
+        },
+        _ => return Err(format!("Unknown deep link '{}'", url)),
+    };
+
+    Ok(Some(action))
+}
+
+fn validate_id(id: &str) -> Result<String, String> {
+    if id.is_empty() {
+        return Err("ID cannot be empty".to_string());
+    }
+    if id.len() > MAX_ID_LEN {
+        return Err("ID too long".to_string());
+    }
+    if !id
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!("Invalid ID '{}'", id));
+    }
+    Ok(id.to_string())
+}
+
+fn optional_param(url: &Url, name: &str, max_len: usize) -> Result<Option<String>, String> {
+    let value = url
+        .query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+
+    match value {
+        Some(v) if v.len() > max_len => Err(format!("Parameter '{}' is too long", name)),
+        Some(v) if v.chars().any(|c| c.is_control() && !c.is_whitespace()) => {
+            Err(format!("Parameter '{}' contains control characters", name))
+        }
+        other => Ok(other),
+    }
+}
+
+fn required_param(url: &Url, name: &str) -> Result<String, String> {
+    optional_param(url, name, MAX_TEXT_LEN)?
+        .ok_or_else(|| format!("Missing required parameter '{}'", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(url: &str) -> Result<Option<DeepLinkAction>, String> {
+        parse(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn parses_every_action() {
+        let cases = [
+            (
+                "webnotes://note/abc-123",
+                DeepLinkAction::OpenNote {
+                    id: "abc-123".to_string(),
+                },
+            ),
+            (
+                "webnotes://note?title=Weekly%20plan",
+                DeepLinkAction::OpenNoteByTitle {
+                    title: "Weekly plan".to_string(),
+                },
+            ),
+            (
+                "webnotes://folder/work_1",
+                DeepLinkAction::OpenFolder {
+                    id: "work_1".to_string(),
+                },
+            ),
+            (
+                "webnotes://search?q=caf%C3%A9+menu",
+                DeepLinkAction::Search {
+                    query: "café menu".to_string(),
+                },
+            ),
+            (
+                "webnotes://new?folder=Inbox&title=%20Idea%20&content=",
+                DeepLinkAction::NewNote {
+                    folder: Some("Inbox".to_string()),
+                    title: Some("Idea".to_string()),
+                    content: None,
+                },
+            ),
+        ];
+        for (url, action) in cases {
+            assert_eq!(parse_str(url), Ok(Some(action)), "{}", url);
+        }
+    }
+
+    #[test]
+    fn leaves_auth_callbacks_to_the_frontend() {
+        assert_eq!(parse_str("webnotes://auth?token=abc"), Ok(None));
+    }
+
+    #[test]
+    fn rejects_malformed_links() {
+        let too_long = format!("webnotes://search?q={}", "a".repeat(MAX_TEXT_LEN + 1));
+        for url in [
+            "https://note/abc",
+            "webnotes://unknown/abc",
+            "webnotes://note/abc/extra",
+            "webnotes://note/..%2Fetc",
+            "webnotes://note?title=",
+            "webnotes://search",
+            "webnotes://search?q=a%00b",
+            too_long.as_str(),
+        ] {
+            assert!(parse_str(url).is_err(), "{}", url);
+        }
+
+        let long_id = format!("webnotes://folder/{}", "a".repeat(MAX_ID_LEN + 1));
+        assert_eq!(parse_str(&long_id), Err("ID too long".to_string()));
+    }
+}
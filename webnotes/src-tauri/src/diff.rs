@@ -0,0 +1,422 @@
+use serde::{Deserialize, Serialize};
+
+// =============================================================================
+// TEXT DIFFS
+// =============================================================================
+//
+// A longest-common-subsequence diff. The common prefix and suffix are
+// stripped first, so typical edits (a few changed lines in a long note) stay
+// cheap. Past `MAX_CELLS` the middle is reported as removed and re-added
+// rather than spending quadratic memory on it.
+
+const MAX_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeKind {
+    Same,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffLine {
+    pub kind: ChangeKind,
+    pub text: String,
+}
+
+/// Turn `old` into `new`, line by line. Removals come before additions
+/// where lines were replaced.
+pub fn diff_lines(old: &[String], new: &[String]) -> Vec<DiffLine> {
+    edit_script(old, new)
+        .into_iter()
+        .map(|(kind, i)| DiffLine {
+            kind,
+            text: match kind {
+                ChangeKind::Added => new[i].clone(),
+                _ => old[i].clone(),
+            },
+        })
+        .collect()
+}
+
+/// Steps turning `old` into `new`, each with the index of its token in
+/// `new` for additions and in `old` otherwise
+fn edit_script<T: PartialEq>(old: &[T], new: &[T]) -> Vec<(ChangeKind, usize)> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut result: Vec<(ChangeKind, usize)> = (0..prefix).map(|i| (ChangeKind::Same, i)).collect();
+
+    if a.len().saturating_mul(b.len()) > MAX_CELLS {
+        result.extend((0..a.len()).map(|i| (ChangeKind::Removed, prefix + i)));
+        result.extend((0..b.len()).map(|j| (ChangeKind::Added, prefix + j)));
+    } else {
+        // lengths[i][j]: LCS length of a[i..] and b[j..]
+        let width = b.len() + 1;
+        let mut lengths = vec![0u32; (a.len() + 1) * width];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lengths[i * width + j] = if a[i] == b[j] {
+                    lengths[(i + 1) * width + j + 1] + 1
+                } else {
+                    lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                result.push((ChangeKind::Same, prefix + i));
+                i += 1;
+                j += 1;
+            } else if j == b.len()
+                || (i < a.len() && lengths[(i + 1) * width + j] >= lengths[i * width + j + 1])
+            {
+                result.push((ChangeKind::Removed, prefix + i));
+                i += 1;
+            } else {
+                result.push((ChangeKind::Added, prefix + j));
+                j += 1;
+            }
+        }
+    }
+
+    result.extend((old.len() - suffix..old.len()).map(|i| (ChangeKind::Same, i)));
+    result
+}
+
+// =============================================================================
+// HUNKS
+// =============================================================================
+//
+// Hunks are the changed lines with a few unchanged ones around them, as in a
+// unified diff. Where lines were replaced, each removed line is paired with
+// the added line in the same position and both get a word-level diff, so a
+// view can highlight just the words that changed.
+//
+// In Markdown mode the units compared are blocks rather than lines: the
+// lines of a paragraph, list item or quote are joined and their whitespace
+// collapsed, so rewrapping a paragraph is not reported as a change.
+
+/// Unchanged lines shown before and after each change
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DiffMode {
+    /// One unit per line of text
+    #[default]
+    Lines,
+    /// One unit per Markdown block, ignoring how paragraphs are wrapped
+    Markdown,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WordChange {
+    pub kind: ChangeKind,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct HunkLine {
+    pub kind: ChangeKind,
+    pub text: String,
+    /// The line split into unchanged and changed words, for a line that
+    /// replaced (or was replaced by) another; empty otherwise
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub words: Vec<WordChange>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffHunk {
+    /// 1-based first line in the old text, and the number of old lines
+    pub old_start: usize,
+    pub old_lines: usize,
+    /// 1-based first line in the new text, and the number of new lines
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<HunkLine>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TextDiff {
+    pub hunks: Vec<DiffHunk>,
+    /// Lines (or blocks) added and removed in total
+    pub added: usize,
+    pub removed: usize,
+}
+
+/// Hunks turning `old` into `new`
+pub fn diff_hunks(old: &[String], new: &[String]) -> TextDiff {
+    let lines = with_word_changes(diff_lines(old, new));
+    let added = lines.iter().filter(|l| l.kind == ChangeKind::Added).count();
+    let removed = lines
+        .iter()
+        .filter(|l| l.kind == ChangeKind::Removed)
+        .count();
+
+    // Line numbers before each entry, 1-based
+    let mut positions = Vec::with_capacity(lines.len());
+    let (mut old_line, mut new_line) = (1, 1);
+    for line in &lines {
+        positions.push((old_line, new_line));
+        match line.kind {
+            ChangeKind::Same => {
+                old_line += 1;
+                new_line += 1;
+            }
+            ChangeKind::Removed => old_line += 1,
+            ChangeKind::Added => new_line += 1,
+        }
+    }
+
+    // Ranges of entries to show, merged where their context overlaps
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        if line.kind == ChangeKind::Same {
+            continue;
+        }
+        let start = i.saturating_sub(CONTEXT_LINES);
+        let end = (i + 1 + CONTEXT_LINES).min(lines.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    let hunks = ranges
+        .into_iter()
+        .map(|(start, end)| {
+            let lines = &lines[start..end];
+            let count = |kind| lines.iter().filter(|l| l.kind != kind).count();
+            DiffHunk {
+                old_start: positions[start].0,
+                old_lines: count(ChangeKind::Added),
+                new_start: positions[start].1,
+                new_lines: count(ChangeKind::Removed),
+                lines: lines.to_vec(),
+            }
+        })
+        .collect();
+
+    TextDiff {
+        hunks,
+        added,
+        removed,
+    }
+}
+
+/// Put each run of changes in removed-then-added order and pair removed
+/// lines with added ones for a word diff
+fn with_word_changes(lines: Vec<DiffLine>) -> Vec<HunkLine> {
+    let mut result = Vec::with_capacity(lines.len());
+    let mut removed: Vec<String> = Vec::new();
+    let mut added: Vec<String> = Vec::new();
+
+    let flush = |result: &mut Vec<HunkLine>, removed: &mut Vec<String>, added: &mut Vec<String>| {
+        let pairs: Vec<(Vec<WordChange>, Vec<WordChange>)> = removed
+            .iter()
+            .zip(added.iter())
+            .map(|(old, new)| diff_words(old, new))
+            .collect();
+        let mut old_words: Vec<Vec<WordChange>> = pairs.iter().map(|p| p.0.clone()).collect();
+        let mut new_words: Vec<Vec<WordChange>> = pairs.into_iter().map(|p| p.1).collect();
+        old_words.resize(removed.len(), Vec::new());
+        new_words.resize(added.len(), Vec::new());
+
+        for (text, words) in removed.drain(..).zip(old_words) {
+            result.push(HunkLine {
+                kind: ChangeKind::Removed,
+                text,
+                words,
+            });
+        }
+        for (text, words) in added.drain(..).zip(new_words) {
+            result.push(HunkLine {
+                kind: ChangeKind::Added,
+                text,
+                words,
+            });
+        }
+    };
+
+    for line in lines {
+        match line.kind {
+            ChangeKind::Removed => removed.push(line.text),
+            ChangeKind::Added => added.push(line.text),
+            ChangeKind::Same => {
+                flush(&mut result, &mut removed, &mut added);
+                result.push(HunkLine {
+                    kind: ChangeKind::Same,
+                    text: line.text,
+                    words: Vec::new(),
+                });
+            }
+        }
+    }
+    flush(&mut result, &mut removed, &mut added);
+    result
+}
+
+/// Words of `old` and of `new`, each marked as kept or changed
+fn diff_words(old: &str, new: &str) -> (Vec<WordChange>, Vec<WordChange>) {
+    let (a, b) = (split_words(old), split_words(new));
+    let mut old_words: Vec<WordChange> = Vec::new();
+    let mut new_words: Vec<WordChange> = Vec::new();
+
+    let push = |words: &mut Vec<WordChange>, kind, text: &str| match words.last_mut() {
+        Some(last) if last.kind == kind => last.text.push_str(text),
+        _ => words.push(WordChange {
+            kind,
+            text: text.to_string(),
+        }),
+    };
+
+    for (kind, i) in edit_script(&a, &b) {
+        match kind {
+            ChangeKind::Same => {
+                push(&mut old_words, kind, a[i]);
+                push(&mut new_words, kind, a[i]);
+            }
+            ChangeKind::Removed => push(&mut old_words, kind, a[i]),
+            ChangeKind::Added => push(&mut new_words, kind, b[i]),
+        }
+    }
+    (old_words, new_words)
+}
+
+/// Runs of word characters, runs of whitespace and single other characters,
+/// which together make up the whole text
+fn split_words(text: &str) -> Vec<&str> {
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            0
+        } else if c.is_whitespace() {
+            1
+        } else {
+            2
+        }
+    };
+
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut prev = None;
+    for (i, c) in text.char_indices() {
+        let current = class(c);
+        if i > start && (prev != Some(current) || current == 2) {
+            words.push(&text[start..i]);
+            start = i;
+        }
+        prev = Some(current);
+    }
+    if start < text.len() {
+        words.push(&text[start..]);
+    }
+    words
+}
+
+/// Split Markdown into the blocks compared in `DiffMode::Markdown`. Code and
+/// math blocks keep one unit per line; other blocks are joined onto one
+/// line with their whitespace collapsed.
+pub fn markdown_units(markdown: &str) -> Vec<String> {
+    let mut units: Vec<String> = Vec::new();
+    // Whether the last unit can continue on the next line, and if so
+    // whether it is a quote
+    let mut open: Option<bool> = None;
+    let mut fence: Option<&str> = None;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+
+        if let Some(marker) = fence {
+            units.push(line.to_string());
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            open = None;
+            continue;
+        }
+
+        let opens_fence = ["```", "~~~", "$$"]
+            .into_iter()
+            .find(|marker| trimmed.starts_with(marker));
+        if let Some(marker) = opens_fence {
+            units.push(line.to_string());
+            open = None;
+            // `$$x$$` on one line is a whole block
+            if !(marker == "$$" && trimmed.len() > 2 && trimmed.ends_with("$$")) {
+                fence = Some(marker);
+            }
+            continue;
+        }
+
+        // A quote continues on lines that are themselves quoted
+        let quoted = trimmed.strip_prefix('>').map(str::trim_start);
+        let continues = match (open, quoted) {
+            (Some(in_quote), Some(body)) => in_quote && !body.is_empty() && !starts_block(body),
+            (Some(in_quote), None) => !in_quote && !starts_block(trimmed),
+            (None, _) => false,
+        };
+
+        let collapsed = trimmed.split_whitespace().collect::<Vec<_>>().join(" ");
+        if continues {
+            let body = quoted.map_or(collapsed.clone(), |body| {
+                body.split_whitespace().collect::<Vec<_>>().join(" ")
+            });
+            let unit = units.last_mut().expect("open block has a unit");
+            unit.push(' ');
+            unit.push_str(&body);
+        } else {
+            units.push(collapsed);
+        }
+        // Headings, rules and table rows never continue on the next line
+        open = (!(trimmed.starts_with('#') || trimmed.starts_with('|') || is_rule(trimmed)))
+            .then_some(quoted.is_some());
+    }
+
+    units
+}
+
+/// Whether a line begins a new block rather than continuing a paragraph
+fn starts_block(line: &str) -> bool {
+    if line.starts_with('#') || line.starts_with('>') || line.starts_with('|') || is_rule(line) {
+        return true;
+    }
+    if ["- ", "* ", "+ "]
+        .iter()
+        .any(|marker| line.starts_with(marker))
+    {
+        return true;
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    digits > 0
+        && matches!(&line[digits..], rest if rest.starts_with(". ") || rest.starts_with(") "))
+}
+
+fn is_rule(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|&c| compact.chars().all(|ch| ch == c))
+}
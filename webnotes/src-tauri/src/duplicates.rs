@@ -0,0 +1,151 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::html::html_to_text;
+
+// =============================================================================
+// NEAR-DUPLICATE DETECTION
+// =============================================================================
+//
+// Notes are reduced to MinHash signatures over 3-word shingles. The share of
+// matching signature slots estimates the Jaccard similarity of the shingle
+// sets. Locality-sensitive hashing over bands of the signature finds
+// candidate pairs without comparing every note with every other.
+
+pub const NUM_HASHES: usize = 128;
+const BANDS: usize = 32;
+const ROWS_PER_BAND: usize = NUM_HASHES / BANDS;
+const SHINGLE_WORDS: usize = 3;
+
+pub type Signature = [u64; NUM_HASHES];
+
+/// A group of notes (indexes into the signature slice) and the lowest
+/// similarity among the pairs that joined it
+#[derive(Debug, Clone)]
+pub struct Cluster {
+    pub members: Vec<usize>,
+    pub similarity: f64,
+}
+
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+fn hash_words(words: &[&str]) -> u64 {
+    // FNV-1a, with a separator so ["ab", "c"] and ["a", "bc"] differ
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for word in words {
+        for byte in word.bytes().chain(std::iter::once(0)) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// MinHash signature of a note's content, or `None` if it has no words
+pub fn signature(content: &str) -> Option<Signature> {
+    let text = html_to_text(content).to_lowercase();
+    let words: Vec<&str> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+
+    let seeds: [u64; NUM_HASHES] = std::array::from_fn(|i| splitmix64(i as u64 + 1));
+    let mut minimums = [u64::MAX; NUM_HASHES];
+
+    // Notes shorter than a shingle become a single shingle
+    for shingle in words.windows(SHINGLE_WORDS.min(words.len())) {
+        let base = hash_words(shingle);
+        for (slot, seed) in minimums.iter_mut().zip(seeds) {
+            *slot = (*slot).min(splitmix64(base ^ seed));
+        }
+    }
+
+    Some(minimums)
+}
+
+/// Estimated Jaccard similarity of the notes behind two signatures
+pub fn similarity(a: &Signature, b: &Signature) -> f64 {
+    let same = a.iter().zip(b).filter(|(x, y)| x == y).count();
+    same as f64 / NUM_HASHES as f64
+}
+
+fn find_root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+/// Group signatures whose estimated similarity is at least `threshold`.
+/// Clusters are transitive: A~B and B~C puts all three together.
+pub fn clusters(signatures: &[Signature], threshold: f64) -> Vec<Cluster> {
+    let mut buckets: HashMap<(usize, u64), Vec<usize>> = HashMap::new();
+    for (index, signature) in signatures.iter().enumerate() {
+        for (band, rows) in signature.chunks(ROWS_PER_BAND).enumerate() {
+            let key = rows
+                .iter()
+                .fold(band as u64, |acc, row| splitmix64(acc ^ row));
+            buckets.entry((band, key)).or_default().push(index);
+        }
+    }
+
+    let mut candidates = HashSet::new();
+    for members in buckets.values() {
+        for (i, &a) in members.iter().enumerate() {
+            for &b in &members[i + 1..] {
+                candidates.insert((a, b));
+            }
+        }
+    }
+
+    let mut parents: Vec<usize> = (0..signatures.len()).collect();
+    let mut joined = Vec::new();
+    for (a, b) in candidates {
+        let score = similarity(&signatures[a], &signatures[b]);
+        if score >= threshold {
+            let (root_a, root_b) = (find_root(&mut parents, a), find_root(&mut parents, b));
+            parents[root_b] = root_a;
+            joined.push((a, score));
+        }
+    }
+
+    let mut groups: HashMap<usize, Cluster> = HashMap::new();
+    for index in 0..signatures.len() {
+        let root = find_root(&mut parents, index);
+        groups
+            .entry(root)
+            .or_insert_with(|| Cluster {
+                members: Vec::new(),
+                similarity: 1.0,
+            })
+            .members
+            .push(index);
+    }
+    for (index, score) in joined {
+        let root = find_root(&mut parents, index);
+        if let Some(cluster) = groups.get_mut(&root) {
+            cluster.similarity = cluster.similarity.min(score);
+        }
+    }
+
+    let mut clusters: Vec<Cluster> = groups
+        .into_values()
+        .filter(|cluster| cluster.members.len() > 1)
+        .collect();
+    clusters.sort_by(|a, b| {
+        b.members
+            .len()
+            .cmp(&a.members.len())
+            .then_with(|| b.similarity.total_cmp(&a.similarity))
+            .then_with(|| a.members.cmp(&b.members))
+    });
+    clusters
+}
@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use crate::links::LinkTarget;
+
+// =============================================================================
+// LINK GRAPH
+// =============================================================================
+//
+// Notes are nodes and links between them are directed edges, weighted by how
+// many times the source links to the target. Filters pick the nodes first;
+// degree, components and orphans then describe the graph that is returned,
+// so a note whose only links leave the filtered set shows up as an orphan.
+
+/// What `build` needs to know about a note
+pub struct NoteLinks {
+    pub id: String,
+    pub title: String,
+    pub folder_id: Option<String>,
+    pub tags: Vec<String>,
+    pub links: Vec<LinkTarget>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GraphFilter {
+    pub folder_id: Option<String>,
+    /// Notes with this tag or one nested under it
+    pub tag: Option<String>,
+    /// Only notes within `depth` links of this one, in either direction
+    pub focus: Option<String>,
+    /// Defaults to 1 when `focus` is set
+    pub depth: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphNode {
+    pub id: String,
+    pub title: String,
+    pub folder_id: Option<String>,
+    pub tags: Vec<String>,
+    /// Edges in plus edges out
+    pub degree: usize,
+    /// Index into `LinkGraph::components`
+    pub component: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    /// Links from source to target
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    /// Note IDs of each connected component, largest first
+    pub components: Vec<Vec<String>>,
+    /// Notes without edges
+    pub orphans: Vec<String>,
+}
+
+pub const DEFAULT_FOCUS_DEPTH: usize = 1;
+
+/// `notes` should be oldest first: a wikilink title shared by several notes
+/// points at the oldest. Links to missing notes and to the note itself are
+/// dropped.
+pub fn build(notes: &[NoteLinks], filter: &GraphFilter) -> LinkGraph {
+    let index: HashMap<&str, usize> = notes
+        .iter()
+        .enumerate()
+        .map(|(i, note)| (note.id.as_str(), i))
+        .collect();
+    let mut by_title: HashMap<String, usize> = HashMap::new();
+    for (i, note) in notes.iter().enumerate() {
+        by_title.entry(note.title.to_lowercase()).or_insert(i);
+    }
+
+    let mut counts: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+    for (source, note) in notes.iter().enumerate() {
+        for link in &note.links {
+            let target = match link {
+                LinkTarget::Id(id) => index.get(id.as_str()).copied(),
+                LinkTarget::Title(title) => by_title.get(&title.to_lowercase()).copied(),
+            };
+            if let Some(target) = target.filter(|&target| target != source) {
+                *counts.entry((source, target)).or_default() += 1;
+            }
+        }
+    }
+
+    let tag = filter
+        .tag
+        .as_deref()
+        .map(|tag| tag.trim().trim_start_matches('#').to_lowercase());
+    let mut included: Vec<bool> = notes
+        .iter()
+        .map(|note| {
+            filter
+                .folder_id
+                .as_ref()
+                .map_or(true, |folder| note.folder_id.as_ref() == Some(folder))
+                && tag.as_ref().map_or(true, |want| {
+                    note.tags
+                        .iter()
+                        .any(|tag| tag == want || tag.starts_with(&format!("{}/", want)))
+                })
+        })
+        .collect();
+    counts.retain(|&(source, target), _| included[source] && included[target]);
+
+    let mut neighbours: Vec<Vec<usize>> = vec![Vec::new(); notes.len()];
+    for &(source, target) in counts.keys() {
+        neighbours[source].push(target);
+        neighbours[target].push(source);
+    }
+
+    if let Some(focus) = filter.focus.as_deref() {
+        let depth = filter.depth.unwrap_or(DEFAULT_FOCUS_DEPTH);
+        let mut reached = vec![false; notes.len()];
+        if let Some(&start) = index.get(focus).filter(|&&start| included[start]) {
+            let mut queue = VecDeque::from([(start, 0)]);
+            reached[start] = true;
+            while let Some((node, distance)) = queue.pop_front() {
+                if distance == depth {
+                    continue;
+                }
+                for &next in &neighbours[node] {
+                    if !reached[next] {
+                        reached[next] = true;
+                        queue.push_back((next, distance + 1));
+                    }
+                }
+            }
+        }
+        included = reached;
+        counts.retain(|&(source, target), _| included[source] && included[target]);
+        for list in &mut neighbours {
+            list.retain(|&n| included[n]);
+        }
+    }
+
+    let mut degree = vec![0usize; notes.len()];
+    for &(source, target) in counts.keys() {
+        degree[source] += 1;
+        degree[target] += 1;
+    }
+
+    // Components by flood fill over the remaining undirected edges
+    let mut component_of: Vec<Option<usize>> = vec![None; notes.len()];
+    let mut components: Vec<Vec<usize>> = Vec::new();
+    for start in (0..notes.len()).filter(|&i| included[i]) {
+        if component_of[start].is_some() {
+            continue;
+        }
+        let component = components.len();
+        let mut members = vec![start];
+        component_of[start] = Some(component);
+        let mut i = 0;
+        while i < members.len() {
+            for &next in &neighbours[members[i]] {
+                if component_of[next].is_none() {
+                    component_of[next] = Some(component);
+                    members.push(next);
+                }
+            }
+            i += 1;
+        }
+        components.push(members);
+    }
+
+    // Largest first; ties keep note order
+    let mut order: Vec<usize> = (0..components.len()).collect();
+    order.sort_by_key(|&c| std::cmp::Reverse(components[c].len()));
+    let mut rank = vec![0; components.len()];
+    for (position, &c) in order.iter().enumerate() {
+        rank[c] = position;
+    }
+
+    let nodes = (0..notes.len())
+        .filter(|&i| included[i])
+        .map(|i| GraphNode {
+            id: notes[i].id.clone(),
+            title: notes[i].title.clone(),
+            folder_id: notes[i].folder_id.clone(),
+            tags: notes[i].tags.clone(),
+            degree: degree[i],
+            component: component_of[i].map_or(0, |c| rank[c]),
+        })
+        .collect();
+
+    let edges = counts
+        .into_iter()
+        .map(|((source, target), count)| GraphEdge {
+            source: notes[source].id.clone(),
+            target: notes[target].id.clone(),
+            count,
+        })
+        .collect();
+
+    let orphans = (0..notes.len())
+        .filter(|&i| included[i] && degree[i] == 0)
+        .map(|i| notes[i].id.clone())
+        .collect();
+
+    let components = order
+        .into_iter()
+        .map(|c| {
+            let mut members = components[c].clone();
+            members.sort_unstable();
+            members.into_iter().map(|i| notes[i].id.clone()).collect()
+        })
+        .collect();
+
+    LinkGraph {
+        nodes,
+        edges,
+        components,
+        orphans,
+    }
+}
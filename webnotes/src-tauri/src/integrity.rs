@@ -0,0 +1,552 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+
+use webnotes_core::compression::StoredContent;
+use webnotes_core::notes::sync_note_index;
+
+// =============================================================================
+// DATABASE INTEGRITY
+// =============================================================================
+//
+// `check` looks for damage SQLite itself reports and for rows the commands
+// cannot read or that point at rows which no longer exist. `repair` fixes
+// what it safely can, then checks again so the report shows what is left.
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum IssueKind {
+    /// Reported by `PRAGMA integrity_check`, or a check that could not run
+    Integrity,
+    /// Reported by `PRAGMA foreign_key_check`
+    ForeignKey,
+    EmptyId,
+    /// A column holds a value of the wrong type (e.g. a NULL title)
+    InvalidValue,
+    BadTimestamp,
+    /// `notes.folder_id` names a folder that does not exist
+    MissingFolder,
+    /// A row in a per-note table (reminders, tasks, ...) for a missing note
+    DanglingReference,
+    /// The full-text index disagrees with `notes`
+    SearchIndex,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseIssue {
+    pub kind: IssueKind,
+    pub table: String,
+    /// ID of the affected row, when it has one
+    pub id: Option<String>,
+    pub detail: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckReport {
+    pub ok: bool,
+    pub issues: Vec<DatabaseIssue>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairReport {
+    pub fixed: Vec<DatabaseIssue>,
+    /// Issues still present after repairing
+    pub remaining: Vec<DatabaseIssue>,
+}
+
+/// Tables with a text `id` primary key
+const ID_TABLES: &[&str] = &["notes", "folders", "reminders"];
+
+/// Tables whose `note_id` must name an existing note
+const NOTE_REFERENCES: &[&str] = &[
+    "reminders",
+    "tasks",
+    "daily_notes",
+    "note_vectors",
+    "note_opens",
+    "note_frecency",
+    "note_properties",
+    "note_versions",
+];
+
+/// (table, column, required) for every RFC3339 column
+const TIMESTAMP_COLUMNS: &[(&str, &str, bool)] = &[
+    ("notes", "updated_at", true),
+    ("notes", "created_at", true),
+    ("notes", "pinned_at", false),
+    ("folders", "created_at", true),
+    ("reminders", "remind_at", true),
+    ("reminders", "fired_at", false),
+    ("reminders", "completed_at", false),
+    ("reminders", "created_at", true),
+    ("note_versions", "created_at", true),
+];
+
+type Check = fn(&Connection, &mut Vec<DatabaseIssue>) -> SqliteResult<()>;
+
+/// rowid, ID and raw value of a row with a bad timestamp
+type BadTimestamp = (i64, Option<String>, Option<String>);
+
+fn issue(kind: IssueKind, table: &str, id: Option<String>, detail: String) -> DatabaseIssue {
+    DatabaseIssue {
+        kind,
+        table: table.to_string(),
+        id,
+        detail,
+    }
+}
+
+fn is_timestamp(value: &str) -> bool {
+    DateTime::parse_from_rfc3339(value).is_ok()
+}
+
+pub fn check(conn: &Connection) -> SqliteResult<CheckReport> {
+    let mut issues = Vec::new();
+
+    // A damaged file can make any of these fail; report that and keep going.
+    // The app's own tables declare no foreign keys (`check_folders` and
+    // `check_references` cover those links), but a database restored from
+    // elsewhere may.
+    let checks: [(&str, Check); 8] = [
+        ("integrity_check", check_integrity),
+        ("foreign_key_check", check_foreign_keys),
+        ("empty IDs", check_empty_ids),
+        ("column types", check_values),
+        ("timestamps", check_timestamps),
+        ("folders", check_folders),
+        ("note references", check_references),
+        ("search index", check_search_index),
+    ];
+    for (name, run) in checks {
+        if let Err(e) = run(conn, &mut issues) {
+            issues.push(issue(
+                IssueKind::Integrity,
+                "",
+                None,
+                format!("Could not run {} check: {}", name, e),
+            ));
+        }
+    }
+
+    Ok(CheckReport {
+        ok: issues.is_empty(),
+        issues,
+    })
+}
+
+fn check_integrity(conn: &Connection, issues: &mut Vec<DatabaseIssue>) -> SqliteResult<()> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check(100)")?;
+    let messages = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<SqliteResult<Vec<_>>>()?;
+    for message in messages.into_iter().filter(|m| m != "ok") {
+        issues.push(issue(IssueKind::Integrity, "", None, message));
+    }
+    Ok(())
+}
+
+fn check_foreign_keys(conn: &Connection, issues: &mut Vec<DatabaseIssue>) -> SqliteResult<()> {
+    let mut stmt = conn.prepare("PRAGMA foreign_key_check")?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<i64>>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+    for (table, rowid, parent) in rows {
+        let row = rowid.map_or_else(|| "A row".to_string(), |r| format!("Row {}", r));
+        issues.push(issue(
+            IssueKind::ForeignKey,
+            &table,
+            None,
+            format!("{} references a missing {} row", row, parent),
+        ));
+    }
+    Ok(())
+}
+
+fn check_empty_ids(conn: &Connection, issues: &mut Vec<DatabaseIssue>) -> SqliteResult<()> {
+    for table in ID_TABLES {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT rowid FROM {} WHERE id IS NULL OR trim(id) = ''",
+            table
+        ))?;
+        let rowids = stmt
+            .query_map([], |row| row.get::<_, i64>(0))?
+            .collect::<SqliteResult<Vec<_>>>()?;
+        for rowid in rowids {
+            issues.push(issue(
+                IssueKind::EmptyId,
+                table,
+                None,
+                format!("Row {} has no ID", rowid),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Rows `note_from_row` and friends would fail to read
+fn check_values(conn: &Connection, issues: &mut Vec<DatabaseIssue>) -> SqliteResult<()> {
+    let queries = [
+        (
+            "notes",
+            "SELECT CAST(id AS TEXT) FROM notes
+             WHERE typeof(title) != 'text' OR typeof(content) NOT IN ('text', 'blob')
+                OR typeof(is_pinned) != 'integer'",
+        ),
+        (
+            "folders",
+            "SELECT CAST(id AS TEXT) FROM folders WHERE typeof(name) != 'text'",
+        ),
+    ];
+    for (table, sql) in queries {
+        let mut stmt = conn.prepare(sql)?;
+        let ids = stmt
+            .query_map([], |row| row.get::<_, Option<String>>(0))?
+            .collect::<SqliteResult<Vec<_>>>()?;
+        for id in ids {
+            issues.push(issue(
+                IssueKind::InvalidValue,
+                table,
+                id,
+                "Has a missing or mistyped value".to_string(),
+            ));
+        }
+    }
+    for id in unreadable_content(conn)? {
+        issues.push(issue(
+            IssueKind::InvalidValue,
+            "notes",
+            id,
+            "Compressed content cannot be read".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// IDs of notes whose compressed content does not decompress
+fn unreadable_content(conn: &Connection) -> SqliteResult<Vec<Option<String>>> {
+    let mut stmt =
+        conn.prepare("SELECT CAST(id AS TEXT), content FROM notes WHERE typeof(content) = 'blob'")?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get(0)?, row.get::<_, StoredContent>(1).is_err()))
+        })?
+        .collect::<SqliteResult<Vec<(Option<String>, bool)>>>()?;
+
+    Ok(rows
+        .into_iter()
+        .filter(|(_, unreadable)| *unreadable)
+        .map(|(id, _)| id)
+        .collect())
+}
+
+/// Rows whose value in a timestamp column is missing (when required) or
+/// does not parse
+fn bad_timestamps(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    required: bool,
+) -> SqliteResult<Vec<BadTimestamp>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT rowid, CAST(id AS TEXT), CAST({} AS TEXT) FROM {}",
+        column, table
+    ))?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<SqliteResult<Vec<BadTimestamp>>>()?;
+
+    Ok(rows
+        .into_iter()
+        .filter(|(_, _, value)| match value {
+            Some(v) => !is_timestamp(v),
+            None => required,
+        })
+        .collect())
+}
+
+fn check_timestamps(conn: &Connection, issues: &mut Vec<DatabaseIssue>) -> SqliteResult<()> {
+    for &(table, column, required) in TIMESTAMP_COLUMNS {
+        for (_, id, value) in bad_timestamps(conn, table, column, required)? {
+            let detail = match value {
+                Some(v) => format!("{} '{}' is not an RFC3339 timestamp", column, v),
+                None => format!("{} is missing", column),
+            };
+            issues.push(issue(IssueKind::BadTimestamp, table, id, detail));
+        }
+    }
+    Ok(())
+}
+
+fn check_folders(conn: &Connection, issues: &mut Vec<DatabaseIssue>) -> SqliteResult<()> {
+    let mut stmt = conn.prepare(
+        "SELECT id, folder_id FROM notes
+         WHERE folder_id IS NOT NULL
+           AND folder_id NOT IN (SELECT id FROM folders WHERE id IS NOT NULL)",
+    )?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get::<_, String>(1)?)))?
+        .collect::<SqliteResult<Vec<(Option<String>, String)>>>()?;
+    for (id, folder_id) in rows {
+        issues.push(issue(
+            IssueKind::MissingFolder,
+            "notes",
+            id,
+            format!("Folder {} does not exist", folder_id),
+        ));
+    }
+    Ok(())
+}
+
+fn check_references(conn: &Connection, issues: &mut Vec<DatabaseIssue>) -> SqliteResult<()> {
+    for table in NOTE_REFERENCES {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT note_id, COUNT(*) FROM {}
+             WHERE note_id NOT IN (SELECT id FROM notes WHERE id IS NOT NULL)
+             GROUP BY note_id",
+            table
+        ))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get::<_, i64>(1)?)))?
+            .collect::<SqliteResult<Vec<(Option<String>, i64)>>>()?;
+        for (note_id, count) in rows {
+            issues.push(issue(
+                IssueKind::DanglingReference,
+                table,
+                note_id,
+                format!("{} row(s) for a note that does not exist", count),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn check_search_index(conn: &Connection, issues: &mut Vec<DatabaseIssue>) -> SqliteResult<()> {
+    let queries = [
+        (
+            "SELECT id FROM notes
+             WHERE id NOT IN (SELECT id FROM notes_fts WHERE id IS NOT NULL)",
+            "Note is missing from the search index",
+        ),
+        (
+            "SELECT id FROM notes_fts
+             WHERE id IS NULL OR id NOT IN (SELECT id FROM notes WHERE id IS NOT NULL)",
+            "Search index has a row for a note that does not exist",
+        ),
+        (
+            "SELECT id FROM notes_fts GROUP BY id HAVING COUNT(*) > 1",
+            "Note is in the search index more than once",
+        ),
+        (
+            "SELECT n.id FROM notes_fts f JOIN notes n ON n.id = f.id
+             WHERE f.title IS NOT n.title
+                OR (typeof(n.content) != 'blob' AND f.content IS NOT n.content)",
+            "Search index is out of date",
+        ),
+    ];
+    for (sql, detail) in queries {
+        let mut stmt = conn.prepare(sql)?;
+        let ids = stmt
+            .query_map([], |row| row.get::<_, Option<String>>(0))?
+            .collect::<SqliteResult<Vec<_>>>()?;
+        for id in ids {
+            issues.push(issue(
+                IssueKind::SearchIndex,
+                "notes_fts",
+                id,
+                detail.to_string(),
+            ));
+        }
+    }
+
+    // The index holds the plain text of compressed notes, which SQL cannot
+    // compare against the stored BLOB. Unreadable ones are reported by
+    // `check_values`.
+    let mut stmt = conn.prepare(
+        "SELECT n.id, f.content, n.content FROM notes_fts f JOIN notes n ON n.id = f.id
+         WHERE typeof(n.content) = 'blob' AND f.title IS n.title",
+    )?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let Ok(content) = row.get::<_, StoredContent>(2) else {
+            continue;
+        };
+        if row.get::<_, Option<String>>(1)?.as_deref() != Some(content.0.as_str()) {
+            issues.push(issue(
+                IssueKind::SearchIndex,
+                "notes_fts",
+                row.get(0)?,
+                "Search index is out of date".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn repair(conn: &Connection) -> SqliteResult<RepairReport> {
+    let before = check(conn)?.issues;
+    if before.is_empty() {
+        return Ok(RepairReport {
+            fixed: Vec::new(),
+            remaining: Vec::new(),
+        });
+    }
+
+    // Rebuilding indexes is the only fix for most integrity_check errors
+    if before.iter().any(|i| i.kind == IssueKind::Integrity) {
+        conn.execute_batch("REINDEX")?;
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    repair_values(&tx)?;
+    repair_empty_ids(&tx)?;
+    repair_timestamps(&tx)?;
+    tx.execute(
+        "UPDATE notes SET folder_id = NULL
+         WHERE folder_id IS NOT NULL
+           AND folder_id NOT IN (SELECT id FROM folders WHERE id IS NOT NULL)",
+        [],
+    )?;
+    for table in NOTE_REFERENCES {
+        tx.execute(
+            &format!(
+                "DELETE FROM {} WHERE note_id NOT IN (SELECT id FROM notes WHERE id IS NOT NULL)",
+                table
+            ),
+            [],
+        )?;
+    }
+    if before.iter().any(|i| i.kind == IssueKind::SearchIndex) {
+        webnotes_core::search::rebuild_index(&tx)?;
+    }
+    tx.commit()?;
+
+    let remaining = check(conn)?.issues;
+    let fixed: Vec<DatabaseIssue> = before
+        .into_iter()
+        .filter(|issue| !remaining.contains(issue))
+        .collect();
+
+    log::info!(
+        "Database repair fixed {} issue(s), {} remaining",
+        fixed.len(),
+        remaining.len()
+    );
+    Ok(RepairReport { fixed, remaining })
+}
+
+/// Give rows without an ID a fresh one. Notes are indexed under the new ID
+/// and their search rows under the empty one are dropped.
+fn repair_empty_ids(conn: &Connection) -> SqliteResult<()> {
+    for table in ID_TABLES {
+        let rows = {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT rowid, id FROM {} WHERE id IS NULL OR trim(id) = ''",
+                table
+            ))?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+            })?;
+            rows.collect::<SqliteResult<Vec<_>>>()?
+        };
+        for (rowid, empty_id) in rows {
+            let id = uuid::Uuid::new_v4().to_string();
+            conn.execute(
+                &format!("UPDATE {} SET id = ?1 WHERE rowid = ?2", table),
+                params![id, rowid],
+            )?;
+            if *table == "notes" {
+                conn.execute("DELETE FROM notes_fts WHERE id IS ?1", params![empty_id])?;
+                let (title, content): (String, StoredContent) = conn.query_row(
+                    "SELECT title, content FROM notes WHERE rowid = ?1",
+                    params![rowid],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )?;
+                sync_note_index(conn, &id, &title, &content.0)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Coerce mistyped values. Compressed content that cannot be decompressed
+/// is lost either way and is emptied so the note can be opened again.
+fn repair_values(conn: &Connection) -> SqliteResult<()> {
+    for id in unreadable_content(conn)? {
+        conn.execute(
+            "UPDATE notes SET content = '' WHERE CAST(id AS TEXT) IS ?1",
+            params![id],
+        )?;
+    }
+    conn.execute_batch(
+        "UPDATE notes SET title = COALESCE(CAST(title AS TEXT), '') WHERE typeof(title) != 'text';
+         UPDATE notes SET content = COALESCE(CAST(content AS TEXT), '')
+            WHERE typeof(content) NOT IN ('text', 'blob');
+         UPDATE notes SET is_pinned = 0 WHERE typeof(is_pinned) != 'integer';
+         UPDATE folders SET name = COALESCE(CAST(name AS TEXT), 'Untitled') WHERE typeof(name) != 'text';",
+    )
+}
+
+/// Replace unparseable timestamps with the row's other timestamp where it
+/// has one, or the current time. Optional ones (when a reminder fired, ...)
+/// are cleared rather than made up. Reminder times cannot be guessed and
+/// are left for the user.
+fn repair_timestamps(conn: &Connection) -> SqliteResult<()> {
+    let now = Utc::now();
+
+    for &(table, column, required) in TIMESTAMP_COLUMNS {
+        if (table, column) == ("reminders", "remind_at") {
+            continue;
+        }
+        if !required {
+            for (rowid, _, _) in bad_timestamps(conn, table, column, required)? {
+                conn.execute(
+                    &format!("UPDATE {} SET {} = NULL WHERE rowid = ?1", table, column),
+                    params![rowid],
+                )?;
+            }
+            continue;
+        }
+        let sibling = match (table, column) {
+            ("notes", "updated_at") => Some("created_at"),
+            ("notes", "created_at") => Some("updated_at"),
+            _ => None,
+        };
+        let fallback = if table == "reminders" {
+            // Reminder timestamps are compared as strings
+            now.to_rfc3339_opts(SecondsFormat::Secs, true)
+        } else {
+            now.to_rfc3339()
+        };
+
+        for (rowid, _, _) in bad_timestamps(conn, table, column, required)? {
+            let replacement = match sibling {
+                Some(other) => conn
+                    .query_row(
+                        &format!(
+                            "SELECT CAST({} AS TEXT) FROM {} WHERE rowid = ?1",
+                            other, table
+                        ),
+                        params![rowid],
+                        |row| row.get::<_, Option<String>>(0),
+                    )?
+                    .filter(|v| is_timestamp(v))
+                    .unwrap_or_else(|| fallback.clone()),
+                None => fallback.clone(),
+            };
+            conn.execute(
+                &format!("UPDATE {} SET {} = ?1 WHERE rowid = ?2", table, column),
+                params![replacement, rowid],
+            )?;
+        }
+    }
+    Ok(())
+}
@@ -1,34 +1,165 @@
-use chrono::Utc;
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use rayon::prelude::*;
 use rusqlite::{params, Connection, Result as SqliteResult};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::Mutex;
-use tauri::{Manager, State};
+use tauri::{AppHandle, Emitter, Manager, State, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+use webnotes_core::accounts::{self, NOTE_OWNED_BY_ACTIVE, OWNED_BY_ACTIVE};
+use webnotes_core::archive;
+use webnotes_core::compression::{self, StoredContent};
+use webnotes_core::daily_notes::{self, DailyNote, DailyNoteConfig};
+use webnotes_core::drafts::{self, Draft};
+use webnotes_core::folders::{self, ensure_trash_folder, load_folders, NOT_IN_TRASH};
+use webnotes_core::frecency::{self, RecentNote, RecentOrder};
+use webnotes_core::journal::{self, ContentChange, JournalEntry, NoteFolder, NotePin, Operation};
+use webnotes_core::markdown::{html_to_markdown, markdown_to_html};
+use webnotes_core::notes::{self, load_note, note_from_row, VersionedSave, NOTE_COLUMNS};
+use webnotes_core::properties::{self, NoteWithProperties, PropertyKey, PropertyValue};
+use webnotes_core::related::{self, RelatedNote};
+use webnotes_core::reminders::{self, DueReminder, Reminder, RepeatRule};
+use webnotes_core::search::run_search;
+use webnotes_core::settings::{read_setting, write_setting};
+use webnotes_core::smart_folders::{self, SmartFolder};
+use webnotes_core::tasks::{self, TaskFilter, TaskGroup};
+use webnotes_core::versions::{
+    insert_note_version, load_note_versions, note_version_from_row, NOTE_VERSION_COLUMNS,
+};
+use webnotes_core::{html, links, tags};
+
+mod api;
+mod deep_link;
+mod diff;
+mod duplicates;
+mod graph;
+mod integrity;
+mod pdf;
+mod publish;
+mod vaults;
+
+use deep_link::DeepLinkAction;
+use vaults::{Vault, VaultRegistry};
+pub use webnotes_core::{Account, Folder, Note, NoteVersion};
 
 // =============================================================================
 // DATA TYPES
 // =============================================================================
 
+/// Error returned by `save_note`
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum SaveNoteError {
+    /// `expected_version` is stale; `current` is the note as stored now
+    Conflict {
+        message: String,
+        current: Box<Note>,
+    },
+    Failed {
+        message: String,
+    },
+}
+
+impl From<String> for SaveNoteError {
+    fn from(message: String) -> Self {
+        SaveNoteError::Failed { message }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DateRange {
+    pub start: String, // YYYY-MM-DD, inclusive
+    pub end: String,   // YYYY-MM-DD, inclusive
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteSize {
+    pub note_id: String,
+    pub note_title: String,
+    /// Size of the content as written
+    pub bytes: u64,
+    /// Space the content takes in the database, less when compressed
+    pub stored_bytes: u64,
+}
+
+/// The database, search index and attachments directory are shared by every
+/// account on the device, so their sizes are the device's. Counts and note
+/// lists are the active account's.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageStats {
+    pub database_bytes: u64,
+    pub page_size: u64,
+    /// Unused pages that maintenance can return to the filesystem
+    pub free_pages: u64,
+    pub search_index_bytes: u64,
+    pub note_count: u64,
+    /// Notes whose content is stored zstd-compressed
+    pub compressed_note_count: u64,
+    /// Space those notes take in the database
+    pub compressed_bytes: u64,
+    /// How much more they would take uncompressed
+    pub compression_saved_bytes: u64,
+    pub folder_count: u64,
+    pub attachment_count: u64,
+    pub attachment_bytes: u64,
+    pub largest_notes: Vec<NoteSize>,
+    pub last_maintenance: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceReport {
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    /// Whether the whole file was rebuilt rather than trimmed
+    pub full_vacuum: bool,
+    pub duration_ms: u64,
+    pub finished_at: String,
+}
+
+/// A draft left newer than its note, e.g. by a crash between saves
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-pub struct Note {
-    pub id: String,
-    pub title: String,
-    pub content: String,
-    pub folder_id: Option<String>,
-    pub is_pinned: bool,
-    pub pinned_at: Option<String>, // FIX #1: Added
-    pub font: Option<String>,      // FIX #1: Added
-    pub updated_at: String,
-    pub created_at: String,
+pub struct RecoverableDraft {
+    pub draft: Draft,
+    /// The stored note, or `None` if it was never saved
+    pub note: Option<Note>,
+    /// Stored text to draft text, one line per block
+    pub diff: Vec<diff::DiffLine>,
 }
 
+/// Near-identical notes, newest first
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-pub struct Folder {
-    pub id: String,
-    pub name: String,
-    pub created_at: String,
+pub struct DuplicateCluster {
+    pub notes: Vec<Note>,
+    /// Lowest estimated similarity between notes in the cluster, 0 to 1
+    pub similarity: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum MergeStrategy {
+    /// Keep the first note and append the others' content in order
+    Concatenate,
+    /// Keep the most recently updated note and append paragraphs from the
+    /// others that it does not already contain
+    KeepNewest,
+}
+
+/// What happens to the notes merged into the survivor
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum MergeDiscard {
+    /// Move them to the Trash folder
+    #[default]
+    Trash,
+    Delete,
 }
 
 // =============================================================================
@@ -37,19 +168,89 @@ pub struct Folder {
 
 struct DbState {
     conn: Mutex<Connection>, // FIX #2: Mutex instead of opening new connections
+    /// Vault the connection belongs to
+    vault: Mutex<ActiveVault>,
+    /// Unix time of the last `with_conn` call, used to find idle periods
+    last_activity: AtomicI64,
+}
+
+#[derive(Clone)]
+struct ActiveVault {
+    id: String,
+    dir: PathBuf,
+}
+
+fn open_database(vault_dir: &Path) -> Result<Connection, String> {
+    std::fs::create_dir_all(vault_dir)
+        .map_err(|e| format!("Failed to create vault directory {:?}: {}", vault_dir, e))?;
+
+    let path = vaults::database_path(vault_dir);
+    log::info!("Database path: {:?}", path);
+    Connection::open(&path).map_err(|e| format!("Failed to open database at {:?}: {}", path, e))
 }
 
 impl DbState {
-    fn new(path: PathBuf) -> Result<Self, String> {
-        let conn = Connection::open(&path)
-            .map_err(|e| format!("Failed to open database at {:?}: {}", path, e))?;
+    fn new(vault_id: &str, vault_dir: PathBuf) -> Result<Self, String> {
+        let conn = open_database(&vault_dir)?;
 
         Ok(Self {
             conn: Mutex::new(conn),
+            vault: Mutex::new(ActiveVault {
+                id: vault_id.to_string(),
+                dir: vault_dir,
+            }),
+            last_activity: AtomicI64::new(Utc::now().timestamp()),
         })
     }
 
+    fn active_vault(&self) -> Result<ActiveVault, String> {
+        self.vault
+            .lock()
+            .map(|vault| vault.clone())
+            .map_err(|e| format!("Failed to acquire vault lock: {}", e))
+    }
+
+    /// Open and migrate another vault's database, then swap it in. Commands
+    /// already holding the connection finish against the old vault first.
+    fn switch_vault(&self, vault_id: &str, vault_dir: PathBuf) -> Result<(), String> {
+        let new_conn = open_database(&vault_dir)?;
+        initialize_database(&new_conn).map_err(|e| format!("Database error: {}", e))?;
+
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|e| format!("Failed to acquire database lock: {}", e))?;
+        let mut vault = self
+            .vault
+            .lock()
+            .map_err(|e| format!("Failed to acquire vault lock: {}", e))?;
+
+        let old_conn = std::mem::replace(&mut *conn, new_conn);
+        *vault = ActiveVault {
+            id: vault_id.to_string(),
+            dir: vault_dir,
+        };
+        drop(vault);
+        drop(conn);
+
+        if let Err((_, e)) = old_conn.close() {
+            log::warn!("Failed to close previous vault database: {}", e);
+        }
+        Ok(())
+    }
+
     fn with_conn<T, F>(&self, f: F) -> Result<T, String>
+    where
+        F: FnOnce(&Connection) -> SqliteResult<T>,
+    {
+        self.last_activity
+            .store(Utc::now().timestamp(), Ordering::Relaxed);
+        self.with_conn_in_background(f)
+    }
+
+    /// Like `with_conn`, but not counted as activity. For background jobs
+    /// that would otherwise keep the app from ever looking idle.
+    fn with_conn_in_background<T, F>(&self, f: F) -> Result<T, String>
     where
         F: FnOnce(&Connection) -> SqliteResult<T>,
     {
@@ -58,168 +259,180 @@ impl DbState {
             .lock()
             .map_err(|e| format!("Failed to acquire database lock: {}", e))?;
 
-        f(&conn).map_err(|e| format!("Database error: {}", e))
+        f(&conn).map_err(|e| match e {
+            // Unreadable rows and file damage can be found by check_database
+            rusqlite::Error::FromSqlConversionFailure(..)
+            | rusqlite::Error::InvalidColumnType(..)
+            | rusqlite::Error::SqliteFailure(
+                rusqlite::ffi::Error {
+                    code: rusqlite::ErrorCode::DatabaseCorrupt,
+                    ..
+                },
+                _,
+            ) => format!(
+                "Database error: {} (the database may be damaged; run a database check)",
+                e
+            ),
+            _ => format!("Database error: {}", e),
+        })
+    }
+
+    fn idle_seconds(&self) -> i64 {
+        Utc::now().timestamp() - self.last_activity.load(Ordering::Relaxed)
+    }
+}
+
+/// Events that can happen before the frontend subscribes to them (e.g.
+/// reminders missed while the app was closed) are held here until it asks
+/// for them, and emitted as they happen from then on
+struct PendingEvents<T> {
+    state: Mutex<Pending<T>>,
+}
+
+struct Pending<T> {
+    listening: bool,
+    held: Vec<T>,
+}
+
+impl<T> Default for PendingEvents<T> {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(Pending {
+                listening: false,
+                held: Vec::new(),
+            }),
+        }
+    }
+}
+
+impl<T: Serialize + Clone> PendingEvents<T> {
+    fn deliver(&self, handle: &AppHandle, event: &str, item: T) {
+        // Checked under the lock so `take` cannot slip in between
+        let Ok(mut pending) = self.state.lock() else {
+            log::warn!("Dropped {} event: lock poisoned", event);
+            return;
+        };
+        if pending.listening {
+            if let Err(e) = handle.emit(event, item) {
+                log::warn!("Failed to emit {}: {}", event, e);
+            }
+        } else {
+            pending.held.push(item);
+        }
+    }
+
+    /// Events held so far; later ones are emitted
+    fn take(&self) -> Result<Vec<T>, String> {
+        let mut pending = self
+            .state
+            .lock()
+            .map_err(|e| format!("Failed to acquire event lock: {}", e))?;
+        pending.listening = true;
+        Ok(std::mem::take(&mut pending.held))
     }
 }
 
+struct VaultState {
+    app_data_dir: PathBuf,
+    /// In-memory copy of `vaults.json`, written back on every change
+    registry: Mutex<VaultRegistry>,
+}
+
+struct LocalApiState {
+    app_data_dir: PathBuf,
+    server: Mutex<Option<api::LocalApi>>,
+}
+
 // =============================================================================
 // DATABASE INITIALIZATION & MIGRATIONS
 // =============================================================================
 
 #[tauri::command]
 fn init_db(state: State<DbState>) -> Result<String, String> {
-    state.with_conn(|conn| {
-        // Create Notes table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS notes (
-                id TEXT PRIMARY KEY,
-                title TEXT NOT NULL DEFAULT '',
-                content TEXT NOT NULL DEFAULT '',
-                folder_id TEXT,
-                is_pinned INTEGER DEFAULT 0,
-                pinned_at TEXT,
-                font TEXT,
-                updated_at TEXT NOT NULL,
-                created_at TEXT NOT NULL
-            )",
-            [],
-        )?;
-
-        // Create Folders table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS folders (
-                id TEXT PRIMARY KEY,
-                name TEXT NOT NULL,
-                created_at TEXT NOT NULL
-            )",
-            [],
-        )?;
-
-        // Create FTS index
-        conn.execute(
-            "CREATE VIRTUAL TABLE IF NOT EXISTS notes_fts USING fts5(id, title, content)",
-            [],
-        )?;
+    state.with_conn(initialize_database)?;
 
-        // Create indexes for performance
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_notes_folder_id ON notes(folder_id)",
-            [],
-        )?;
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_notes_updated_at ON notes(updated_at DESC)",
-            [],
-        )?;
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_notes_is_pinned ON notes(is_pinned)",
-            [],
-        )?;
+    log::info!("Database initialized successfully");
+    Ok("Database initialized".to_string())
+}
 
-        // FIX #4: Migrations for existing databases
-        // SQLite will error if column exists, we ignore that
-        let migrations = [
-            "ALTER TABLE notes ADD COLUMN pinned_at TEXT",
-            "ALTER TABLE notes ADD COLUMN font TEXT",
-        ];
+/// Create or migrate every table the app uses
+fn initialize_database(conn: &Connection) -> SqliteResult<()> {
+    webnotes_core::migrate(conn)?;
 
-        for migration in migrations {
-            // Ignore errors (column already exists)
-            let _ = conn.execute(migration, []);
-        }
+    // Whatever is left over from the last run is either recoverable or
+    // already saved
+    drafts::prune_saved_drafts(conn)?;
 
-        Ok(())
-    })?;
+    // Vectors saved one at a time use the IDF of the moment; rebuild
+    // them all once the collection has grown or shrunk noticeably
+    if related::related_index_is_stale(conn)? {
+        related::rebuild_note_vectors(conn)?;
+    }
 
-    log::info!("Database initialized successfully");
-    Ok("Database initialized".to_string())
+    Ok(())
 }
 
 // =============================================================================
 // NOTE OPERATIONS
 // =============================================================================
 
+/// Save a note and return it as stored. With `expected_version`, the save
+/// only goes through if nobody else has written the note since that
+/// version; otherwise it is last writer wins.
 #[tauri::command]
-fn save_note(note: Note, state: State<DbState>) -> Result<(), String> {
+fn save_note(
+    note: Note,
+    expected_version: Option<i64>,
+    state: State<DbState>,
+) -> Result<Note, SaveNoteError> {
     // Validate input
     if note.id.is_empty() {
-        return Err("Note ID cannot be empty".to_string());
+        return Err("Note ID cannot be empty".to_string().into());
     }
     if note.id.len() > 100 {
-        return Err("Note ID too long".to_string());
+        return Err("Note ID too long".to_string().into());
     }
 
-    state.with_conn(|conn| {
-        // Use client-provided updated_at, fallback to server time
-        let updated_at = if note.updated_at.is_empty() {
-            Utc::now().to_rfc3339()
-        } else {
-            note.updated_at.clone()
+    let outcome = state.with_conn(|conn| {
+        let tx = conn.unchecked_transaction()?;
+
+        let outcome = match expected_version {
+            Some(expected) => notes::save_note_if_version(&tx, &note, expected)?,
+            None => {
+                notes::save_note(&tx, &note)?;
+                let saved =
+                    load_note(&tx, &note.id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+                VersionedSave::Saved(saved)
+            }
         };
 
-        conn.execute(
-            "INSERT INTO notes (id, title, content, folder_id, is_pinned, pinned_at, font, updated_at, created_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
-             ON CONFLICT(id) DO UPDATE SET
-                title = excluded.title,
-                content = excluded.content,
-                folder_id = excluded.folder_id,
-                is_pinned = excluded.is_pinned,
-                pinned_at = excluded.pinned_at,
-                font = excluded.font,
-                updated_at = excluded.updated_at",
-            params![
-                note.id,
-                note.title,
-                note.content,
-                note.folder_id,
-                note.is_pinned,
-                note.pinned_at,
-                note.font,
-                updated_at,
-                note.created_at
-            ],
-        )?;
+        if let VersionedSave::Saved(saved) = &outcome {
+            drafts::clear_saved_draft(&tx, saved)?;
+        }
 
-        // FIX #5: Handle FTS error properly instead of ignoring
-        conn.execute(
-            "INSERT INTO notes_fts (id, title, content) VALUES (?1, ?2, ?3)
-             ON CONFLICT(id) DO UPDATE SET title = excluded.title, content = excluded.content",
-            params![note.id, note.title, note.content],
-        ).map_err(|e| {
-            log::warn!("FTS sync failed for note {}: {}", note.id, e);
-            e
-        })?;
+        tx.commit()?;
+        Ok(outcome)
+    })?;
 
-        Ok(())
-    })
+    saved_or_conflict(outcome, expected_version.unwrap_or_default())
+}
+
+fn saved_or_conflict(outcome: VersionedSave, expected_version: i64) -> Result<Note, SaveNoteError> {
+    match outcome {
+        VersionedSave::Saved(saved) => Ok(saved),
+        VersionedSave::Conflict(current) => Err(SaveNoteError::Conflict {
+            message: format!(
+                "Note was changed elsewhere (expected version {}, found {})",
+                expected_version, current.version
+            ),
+            current: Box::new(current),
+        }),
+    }
 }
 
 #[tauri::command]
 fn get_all_notes(state: State<DbState>) -> Result<Vec<Note>, String> {
-    state.with_conn(|conn| {
-        let mut stmt = conn.prepare(
-            "SELECT id, title, content, folder_id, is_pinned, pinned_at, font, updated_at, created_at
-             FROM notes
-             ORDER BY is_pinned DESC, pinned_at DESC, updated_at DESC"
-        )?;
-
-        let notes = stmt.query_map([], |row| {
-            Ok(Note {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                content: row.get(2)?,
-                folder_id: row.get(3)?,
-                is_pinned: row.get(4)?,
-                pinned_at: row.get(5)?,
-                font: row.get(6)?,
-                updated_at: row.get(7)?,
-                created_at: row.get(8)?,
-            })
-        })?;
-
-        // FIX #2: Proper error collection instead of unwrap
-        notes.collect::<SqliteResult<Vec<_>>>()
-    })
+    state.with_conn(notes::list_notes)
 }
 
 #[tauri::command]
@@ -228,30 +441,7 @@ fn get_note(id: String, state: State<DbState>) -> Result<Option<Note>, String> {
         return Err("Note ID cannot be empty".to_string());
     }
 
-    state.with_conn(|conn| {
-        let mut stmt = conn.prepare(
-            "SELECT id, title, content, folder_id, is_pinned, pinned_at, font, updated_at, created_at
-             FROM notes
-             WHERE id = ?1"
-        )?;
-
-        let mut rows = stmt.query(params![id])?;
-
-        match rows.next()? {
-            Some(row) => Ok(Some(Note {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                content: row.get(2)?,
-                folder_id: row.get(3)?,
-                is_pinned: row.get(4)?,
-                pinned_at: row.get(5)?,
-                font: row.get(6)?,
-                updated_at: row.get(7)?,
-                created_at: row.get(8)?,
-            })),
-            None => Ok(None),
-        }
-    })
+    state.with_conn(|conn| load_note(conn, &id))
 }
 
 #[tauri::command]
@@ -261,17 +451,20 @@ fn delete_note(id: String, state: State<DbState>) -> Result<(), String> {
     }
 
     state.with_conn(|conn| {
-        // Delete from main table
-        let deleted = conn.execute("DELETE FROM notes WHERE id = ?1", params![id])?;
-
-        if deleted == 0 {
+        let tx = conn.unchecked_transaction()?;
+        if let Some(deleted) = journal::snapshot_note(&tx, &id)? {
+            journal::record(
+                &tx,
+                &Operation::DeleteNote {
+                    deleted: Box::new(deleted),
+                },
+            )?;
+        }
+        if !notes::delete_note(&tx, &id)? {
+            // Missing, or another account's
             log::warn!("Attempted to delete non-existent note: {}", id);
         }
-
-        // Delete from FTS (ignore errors - might not exist)
-        let _ = conn.execute("DELETE FROM notes_fts WHERE id = ?1", params![id]);
-
-        Ok(())
+        tx.commit()
     })
 }
 
@@ -281,46 +474,68 @@ fn toggle_pin(id: String, state: State<DbState>) -> Result<Note, String> {
         return Err("Note ID cannot be empty".to_string());
     }
 
-    state.with_conn(|conn| {
-        // Get current state
-        let mut stmt = conn.prepare(
-            "SELECT id, title, content, folder_id, is_pinned, pinned_at, font, updated_at, created_at
-             FROM notes WHERE id = ?1"
-        )?;
+    state.with_conn(|conn| notes::toggle_pin(conn, &id))
+}
 
-        let mut rows = stmt.query(params![id])?;
-        let row = rows.next()?.ok_or_else(|| {
-            rusqlite::Error::QueryReturnedNoRows
-        })?;
+// =============================================================================
+// DRAFTS
+// =============================================================================
 
-        let current_is_pinned: bool = row.get(4)?;
-        let new_is_pinned = !current_is_pinned;
-        let new_pinned_at: Option<String> = if new_is_pinned {
-            Some(Utc::now().to_rfc3339())
-        } else {
-            None
-        };
-        let new_updated_at = Utc::now().to_rfc3339();
+/// Lines compared by the recovery diff: the title, then one per block
+fn draft_lines(title: &str, content: &str) -> Vec<String> {
+    let mut lines = vec![format!("# {}", title)];
+    lines.extend(html::html_to_lines(content));
+    lines
+}
 
-        // Update
-        conn.execute(
-            "UPDATE notes SET is_pinned = ?1, pinned_at = ?2, updated_at = ?3 WHERE id = ?4",
-            params![new_is_pinned, new_pinned_at, new_updated_at, id],
-        )?;
+#[tauri::command]
+fn save_draft(
+    note_id: String,
+    title: String,
+    content: String,
+    state: State<DbState>,
+) -> Result<(), String> {
+    if note_id.is_empty() {
+        return Err("Note ID cannot be empty".to_string());
+    }
+    if note_id.len() > 100 {
+        return Err("Note ID too long".to_string());
+    }
 
-        // Return updated note
-        Ok(Note {
-            id: row.get(0)?,
-            title: row.get(1)?,
-            content: row.get(2)?,
-            folder_id: row.get(3)?,
-            is_pinned: new_is_pinned,
-            pinned_at: new_pinned_at,
-            font: row.get(6)?,
-            updated_at: new_updated_at,
-            created_at: row.get(8)?,
+    state.with_conn(|conn| drafts::save_draft(conn, &note_id, &title, &content))
+}
+
+#[tauri::command]
+fn discard_draft(note_id: String, state: State<DbState>) -> Result<(), String> {
+    if note_id.is_empty() {
+        return Err("Note ID cannot be empty".to_string());
+    }
+
+    state.with_conn(|conn| drafts::discard_draft(conn, &note_id))
+}
+
+/// Drafts holding edits that never reached their note, newest first. Restore
+/// one with save_note, or drop it with discard_draft.
+#[tauri::command]
+fn get_recoverable_drafts(state: State<DbState>) -> Result<Vec<RecoverableDraft>, String> {
+    let drafts = state.with_conn(drafts::load_drafts)?;
+
+    Ok(drafts
+        .into_iter()
+        .filter(|(draft, note)| {
+            !note
+                .as_ref()
+                .is_some_and(|note| drafts::draft_is_saved(draft, note))
         })
-    })
+        .map(|(draft, note)| {
+            let stored = note
+                .as_ref()
+                .map(|note| draft_lines(&note.title, &note.content))
+                .unwrap_or_default();
+            let diff = diff::diff_lines(&stored, &draft_lines(&draft.title, &draft.content));
+            RecoverableDraft { draft, note, diff }
+        })
+        .collect())
 }
 
 // =============================================================================
@@ -333,32 +548,12 @@ fn save_folder(folder: Folder, state: State<DbState>) -> Result<(), String> {
         return Err("Folder ID cannot be empty".to_string());
     }
 
-    state.with_conn(|conn| {
-        conn.execute(
-            "INSERT INTO folders (id, name, created_at) VALUES (?1, ?2, ?3)
-             ON CONFLICT(id) DO UPDATE SET name = excluded.name",
-            params![folder.id, folder.name, folder.created_at],
-        )?;
-        Ok(())
-    })
+    state.with_conn(|conn| folders::save_folder(conn, &folder))
 }
 
 #[tauri::command]
 fn get_all_folders(state: State<DbState>) -> Result<Vec<Folder>, String> {
-    state.with_conn(|conn| {
-        let mut stmt =
-            conn.prepare("SELECT id, name, created_at FROM folders ORDER BY created_at DESC")?;
-
-        let folders = stmt.query_map([], |row| {
-            Ok(Folder {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                created_at: row.get(2)?,
-            })
-        })?;
-
-        folders.collect::<SqliteResult<Vec<_>>>()
-    })
+    state.with_conn(load_folders)
 }
 
 #[tauri::command]
@@ -369,118 +564,1944 @@ fn delete_folder(id: String, state: State<DbState>) -> Result<(), String> {
 
     // FIX #2: Use transaction for multi-step operation
     state.with_conn(|conn| {
-        // Unfile notes first, then delete folder
-        conn.execute(
-            "UPDATE notes SET folder_id = NULL WHERE folder_id = ?1",
-            params![id],
-        )?;
-
-        conn.execute("DELETE FROM folders WHERE id = ?1", params![id])?;
-
-        Ok(())
+        let tx = conn.unchecked_transaction()?;
+        let folder = load_folders(&tx)?.into_iter().find(|f| f.id == id);
+        if let Some(folder) = folder {
+            let note_ids = tx
+                .prepare(&format!(
+                    "SELECT id FROM notes WHERE folder_id = ?1 AND {}",
+                    OWNED_BY_ACTIVE
+                ))?
+                .query_map(params![id], |row| row.get(0))?
+                .collect::<SqliteResult<Vec<String>>>()?;
+            journal::record(&tx, &Operation::DeleteFolder { folder, note_ids })?;
+        }
+        folders::delete_folder(&tx, &id)?;
+        tx.commit()
     })
 }
 
 // =============================================================================
-// SEARCH
+// SMART FOLDERS
 // =============================================================================
 
 #[tauri::command]
-fn search_notes(query: String, state: State<DbState>) -> Result<Vec<Note>, String> {
-    if query.trim().is_empty() {
-        return Ok(vec![]);
+fn save_smart_folder(folder: SmartFolder, state: State<DbState>) -> Result<(), String> {
+    if folder.id.is_empty() {
+        return Err("Folder ID cannot be empty".to_string());
+    }
+    if folder.id.len() > 100 {
+        return Err("Folder ID too long".to_string());
     }
+    if folder.name.trim().is_empty() {
+        return Err("Folder name cannot be empty".to_string());
+    }
+    let query = &folder.query;
+    for date in [
+        &query.updated_after,
+        &query.updated_before,
+        &query.created_after,
+        &query.created_before,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        parse_date(date)?;
+    }
+
+    state.with_conn(|conn| smart_folders::save_smart_folder(conn, &folder))
+}
+
+#[tauri::command]
+fn get_all_smart_folders(state: State<DbState>) -> Result<Vec<SmartFolder>, String> {
+    state.with_conn(smart_folders::load_smart_folders)
+}
 
-    // FIX: Sanitize FTS query to prevent injection
-    let sanitized_query = sanitize_fts_query(&query);
-    if sanitized_query.is_empty() {
-        return Ok(vec![]);
+#[tauri::command]
+fn delete_smart_folder(id: String, state: State<DbState>) -> Result<(), String> {
+    if id.is_empty() {
+        return Err("Folder ID cannot be empty".to_string());
     }
 
-    state.with_conn(|conn| {
-        let mut stmt = conn.prepare(
-            "SELECT n.id, n.title, n.content, n.folder_id, n.is_pinned, n.pinned_at, n.font, n.updated_at, n.created_at
-             FROM notes n
-             JOIN notes_fts f ON n.id = f.id
-             WHERE notes_fts MATCH ?1
-             ORDER BY rank
-             LIMIT 50"
-        )?;
+    state.with_conn(|conn| smart_folders::delete_smart_folder(conn, &id))
+}
 
-        let notes = stmt.query_map(params![sanitized_query], |row| {
-            Ok(Note {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                content: row.get(2)?,
-                folder_id: row.get(3)?,
-                is_pinned: row.get(4)?,
-                pinned_at: row.get(5)?,
-                font: row.get(6)?,
-                updated_at: row.get(7)?,
-                created_at: row.get(8)?,
-            })
-        })?;
+/// Notes currently matching a smart folder
+#[tauri::command]
+fn get_smart_folder_notes(id: String, state: State<DbState>) -> Result<Vec<Note>, String> {
+    if id.is_empty() {
+        return Err("Folder ID cannot be empty".to_string());
+    }
 
-        notes.collect::<SqliteResult<Vec<_>>>()
+    state.with_conn(|conn| {
+        smart_folders::evaluate(conn, &smart_folders::load_smart_folder(conn, &id)?.query)
     })
 }
 
-/// Sanitize user input for FTS5 queries
-fn sanitize_fts_query(query: &str) -> String {
-    // Remove FTS5 special characters that could cause issues
-    let cleaned: String = query
-        .chars()
-        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
-        .collect();
-
-    let trimmed = cleaned.trim();
+/// Number of matching notes for every smart folder, by ID
+#[tauri::command]
+fn get_smart_folder_counts(state: State<DbState>) -> Result<HashMap<String, usize>, String> {
+    state.with_conn(smart_folders::count_matches)
+}
 
-    if trimmed.is_empty() {
-        return String::new();
-    }
+// =============================================================================
+// SEARCH
+// =============================================================================
 
-    // Wrap in quotes for phrase matching, add * for prefix matching
-    format!("\"{}\"*", trimmed)
+#[tauri::command]
+fn search_notes(query: String, state: State<DbState>) -> Result<Vec<Note>, String> {
+    state.with_conn(|conn| run_search(conn, &query))
 }
 
 // =============================================================================
-// SETUP
+// DAILY NOTES
 // =============================================================================
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_shell::init())
-        .plugin(tauri_plugin_deep_link::init())
-        .setup(|app| {
-            // Set up logging
-            env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
-                .init();
+fn parse_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date '{}', expected YYYY-MM-DD", date))
+}
 
-            // Get app data directory
-            let app_data_dir = app
-                .path()
-                .app_data_dir()
-                .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+#[tauri::command]
+fn get_daily_note_config(state: State<DbState>) -> Result<DailyNoteConfig, String> {
+    state.with_conn(daily_notes::load_config)
+}
 
-            // Create directory if it doesn't exist
-            if !app_data_dir.exists() {
-                std::fs::create_dir_all(&app_data_dir)
-                    .map_err(|e| format!("Failed to create app data dir: {}", e))?;
-            }
+#[tauri::command]
+fn set_daily_note_config(config: DailyNoteConfig, state: State<DbState>) -> Result<(), String> {
+    config.validate()?;
+    state.with_conn(|conn| daily_notes::save_config(conn, &config))
+}
 
-            let db_path = app_data_dir.join("webnotes.db");
-            log::info!("Database path: {:?}", db_path);
+/// Return the note for `date` (today if omitted), creating it if needed
+#[tauri::command]
+fn get_or_create_daily_note(date: Option<String>, state: State<DbState>) -> Result<Note, String> {
+    let day = match date {
+        Some(d) => parse_date(&d)?,
+        None => Local::now().date_naive(),
+    };
 
-            // Initialize database state
-            let db_state = DbState::new(db_path)
-                .map_err(|e| format!("Failed to initialize database: {}", e))?;
+    state.with_conn(|conn| daily_notes::get_or_create(conn, day))
+}
 
-            app.manage(db_state);
+#[tauri::command]
+fn list_daily_notes(range: DateRange, state: State<DbState>) -> Result<Vec<DailyNote>, String> {
+    let start = parse_date(&range.start)?;
+    let end = parse_date(&range.end)?;
+    if start > end {
+        return Err("Date range start must not be after its end".to_string());
+    }
 
-            Ok(())
-        })
-        .invoke_handler(tauri::generate_handler![
+    state.with_conn(|conn| daily_notes::list_daily_notes(conn, start, end))
+}
+
+// =============================================================================
+// REMINDERS
+// =============================================================================
+
+const REMINDER_DUE_EVENT: &str = "reminder-due";
+const REMINDER_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+fn parse_reminder_time(value: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(value.trim())
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|_| format!("Invalid reminder time '{}', expected RFC3339", value))
+}
+
+#[tauri::command]
+fn add_reminder(
+    note_id: String,
+    remind_at: String,
+    repeat: Option<RepeatRule>,
+    state: State<DbState>,
+) -> Result<Reminder, String> {
+    if note_id.is_empty() {
+        return Err("Note ID cannot be empty".to_string());
+    }
+    let at = parse_reminder_time(&remind_at)?;
+
+    state.with_conn(|conn| reminders::add_reminder(conn, &note_id, at, repeat))
+}
+
+#[tauri::command]
+fn delete_reminder(id: String, state: State<DbState>) -> Result<(), String> {
+    if id.is_empty() {
+        return Err("Reminder ID cannot be empty".to_string());
+    }
+
+    state.with_conn(|conn| reminders::delete_reminder(conn, &id))
+}
+
+#[tauri::command]
+fn get_note_reminders(note_id: String, state: State<DbState>) -> Result<Vec<Reminder>, String> {
+    state.with_conn(|conn| reminders::note_reminders(conn, &note_id))
+}
+
+/// Open reminders ordered by time, including ones that fired but were
+/// never completed or snoozed
+#[tauri::command]
+fn list_upcoming_reminders(
+    limit: Option<u32>,
+    state: State<DbState>,
+) -> Result<Vec<Reminder>, String> {
+    let limit = limit.unwrap_or(50).min(500);
+
+    state.with_conn(|conn| reminders::upcoming_reminders(conn, limit))
+}
+
+#[tauri::command]
+fn snooze_reminder(id: String, minutes: u32, state: State<DbState>) -> Result<Reminder, String> {
+    if id.is_empty() {
+        return Err("Reminder ID cannot be empty".to_string());
+    }
+    if minutes == 0 {
+        return Err("Snooze duration must be at least one minute".to_string());
+    }
+
+    let until = Utc::now() + Duration::minutes(i64::from(minutes));
+
+    state.with_conn(|conn| reminders::snooze_reminder(conn, &id, until))
+}
+
+#[tauri::command]
+fn complete_reminder(id: String, state: State<DbState>) -> Result<Reminder, String> {
+    if id.is_empty() {
+        return Err("Reminder ID cannot be empty".to_string());
+    }
+
+    state.with_conn(|conn| reminders::complete_reminder(conn, &id))
+}
+
+/// Poll for due reminders and emit `reminder-due` for each. The first
+/// pass runs immediately and finds reminders missed while the app was
+/// closed; they are held until the frontend calls take_pending_reminders.
+fn spawn_reminder_scheduler(handle: AppHandle) {
+    std::thread::Builder::new()
+        .name("reminder-scheduler".to_string())
+        .spawn(move || loop {
+            let state = handle.state::<DbState>();
+            match state
+                .with_conn_in_background(|conn| reminders::take_due_reminders(conn, Utc::now()))
+            {
+                Ok(due) => {
+                    let pending = handle.state::<PendingEvents<DueReminder>>();
+                    for item in due {
+                        log::info!("Reminder due: {}", item.reminder.id);
+                        pending.deliver(&handle, REMINDER_DUE_EVENT, item);
+                    }
+                }
+                // Expected until the frontend has called init_db
+                Err(e) => log::debug!("Reminder check skipped: {}", e),
+            }
+            std::thread::sleep(REMINDER_POLL_INTERVAL);
+        })
+        .expect("failed to spawn reminder scheduler");
+}
+
+/// Called once by the frontend after it subscribes to `reminder-due`.
+/// Returns reminders that fell due before then; later ones are delivered as
+/// events.
+#[tauri::command]
+fn take_pending_reminders(
+    pending: State<PendingEvents<DueReminder>>,
+) -> Result<Vec<DueReminder>, String> {
+    pending.take()
+}
+
+// =============================================================================
+// TASKS
+// =============================================================================
+
+/// Tasks across all notes, grouped by note and due date. Groups with a due
+/// date come first, soonest first.
+#[tauri::command]
+fn list_tasks(filter: Option<TaskFilter>, state: State<DbState>) -> Result<Vec<TaskGroup>, String> {
+    let filter = filter.unwrap_or_default();
+    if let Some(due_before) = &filter.due_before {
+        parse_date(due_before)?;
+    }
+
+    state.with_conn(|conn| tasks::list_tasks(conn, &filter))
+}
+
+/// Flip a checklist item in the note's content and save the note. Fails
+/// with a conflict unless the note is still at `expected_version`, the
+/// version `task_index` was counted in.
+#[tauri::command]
+fn toggle_task(
+    note_id: String,
+    task_index: usize,
+    expected_version: i64,
+    state: State<DbState>,
+) -> Result<Note, SaveNoteError> {
+    if note_id.is_empty() {
+        return Err("Note ID cannot be empty".to_string().into());
+    }
+
+    let outcome = state.with_conn(|conn| {
+        let tx = conn.unchecked_transaction()?;
+
+        let mut note = load_note(&tx, &note_id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+        if note.version != expected_version {
+            return Ok(VersionedSave::Conflict(note));
+        }
+        let (content, _) = tasks::toggle_task(&note.content, task_index)
+            .ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+        note.content = content;
+        note.updated_at = Utc::now().to_rfc3339();
+
+        let outcome = notes::save_note_if_version(&tx, &note, expected_version)?;
+        tx.commit()?;
+        Ok(outcome)
+    })?;
+
+    saved_or_conflict(outcome, expected_version)
+}
+
+// =============================================================================
+// RELATED NOTES
+// =============================================================================
+
+const DEFAULT_RELATED_NOTES: usize = 5;
+const MAX_RELATED_NOTES: usize = 50;
+
+/// The `k` notes most similar to `id` by cosine similarity, best first.
+/// Document frequencies and results both come from the active account's notes.
+#[tauri::command]
+fn get_related_notes(
+    id: String,
+    k: Option<usize>,
+    state: State<DbState>,
+) -> Result<Vec<RelatedNote>, String> {
+    if id.is_empty() {
+        return Err("Note ID cannot be empty".to_string());
+    }
+    let k = k
+        .unwrap_or(DEFAULT_RELATED_NOTES)
+        .clamp(1, MAX_RELATED_NOTES);
+
+    state.with_conn(|conn| related::related_notes(conn, &id, k))
+}
+
+/// Recompute all vectors now; returns the number of notes indexed
+#[tauri::command]
+fn rebuild_related_index(state: State<DbState>) -> Result<usize, String> {
+    state.with_conn(related::rebuild_note_vectors)
+}
+
+// =============================================================================
+// DUPLICATES & MERGING
+// =============================================================================
+
+const DEFAULT_DUPLICATE_THRESHOLD: f64 = 0.8;
+
+/// Clusters of near-identical notes, largest first. Notes in the trash
+/// folder are ignored.
+#[tauri::command]
+fn find_duplicates(
+    threshold: Option<f64>,
+    state: State<DbState>,
+) -> Result<Vec<DuplicateCluster>, String> {
+    let threshold = threshold.unwrap_or(DEFAULT_DUPLICATE_THRESHOLD);
+    if threshold <= 0.0 || threshold > 1.0 {
+        return Err("Threshold must be greater than 0 and at most 1".to_string());
+    }
+
+    let notes = state.with_conn(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM notes WHERE {} AND {}",
+            NOTE_COLUMNS, NOT_IN_TRASH, OWNED_BY_ACTIVE
+        ))?;
+        let rows = stmt.query_map([], note_from_row)?;
+        rows.collect::<SqliteResult<Vec<_>>>()
+    })?;
+
+    // Hashing is the slow part and does not need the database lock
+    let (notes, signatures): (Vec<Note>, Vec<duplicates::Signature>) = notes
+        .into_par_iter()
+        .filter_map(|note| duplicates::signature(&note.content).map(|sig| (note, sig)))
+        .unzip();
+
+    Ok(duplicates::clusters(&signatures, threshold)
+        .into_iter()
+        .map(|cluster| {
+            let mut members: Vec<Note> = cluster
+                .members
+                .into_iter()
+                .map(|index| notes[index].clone())
+                .collect();
+            members.sort_by_key(|note| std::cmp::Reverse(note_updated_at(note)));
+            DuplicateCluster {
+                notes: members,
+                similarity: cluster.similarity,
+            }
+        })
+        .collect())
+}
+
+/// Parsed `updated_at`; unparseable values sort oldest
+fn note_updated_at(note: &Note) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(&note.updated_at)
+        .ok()
+        .map(|at| at.with_timezone(&Utc))
+}
+
+/// Blocks of `others` whose text `base` does not already contain, appended
+/// to `base`. Empty paragraphs are dropped.
+fn append_unique_blocks(base: &str, others: &[&Note]) -> String {
+    let key = |block: &str| {
+        let text = html::html_to_text(block).to_lowercase();
+        if text.is_empty() {
+            block.to_string()
+        } else {
+            text
+        }
+    };
+    let is_blank = |block: &str| {
+        html::html_to_text(block).is_empty()
+            && html::scan_tags(block)
+                .iter()
+                .all(|tag| tag.name == "p" || tag.name == "br")
+    };
+
+    let mut seen: HashSet<String> = html::top_level_blocks(base).into_iter().map(key).collect();
+    let mut merged = base.to_string();
+    for note in others {
+        for block in html::top_level_blocks(&note.content) {
+            if !is_blank(block) && seen.insert(key(block)) {
+                merged.push_str(block);
+            }
+        }
+    }
+    merged
+}
+
+/// Rewrite links to the `from` notes in every note so they point at
+/// `survivor`. Returns the content of each note changed, before and after.
+fn retarget_backlinks(
+    conn: &Connection,
+    from: &[(&str, &str)],
+    survivor: &Note,
+    now: &str,
+) -> SqliteResult<Vec<ContentChange>> {
+    let candidates = {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, title, content FROM notes
+             WHERE (content LIKE '%data-note-id%' OR content LIKE '%[[%'
+                OR typeof(content) = 'blob')
+                AND {}",
+            OWNED_BY_ACTIVE
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, StoredContent>(2)?.0,
+            ))
+        })?;
+        rows.collect::<SqliteResult<Vec<_>>>()?
+    };
+
+    let mut changes = Vec::new();
+    for (id, title, content) in candidates {
+        if let Some(updated) = links::retarget_links(&content, from, &survivor.id, &survivor.title)
+        {
+            notes::update_content(conn, &id, &updated, now)?;
+            notes::sync_note_index(conn, &id, &title, &updated)?;
+            changes.push(ContentChange {
+                note_id: id,
+                before: content,
+                after: updated,
+            });
+        }
+    }
+    Ok(changes)
+}
+
+/// Combine notes into one. Links, reminders, daily-note entries and
+/// properties of the other notes move to the survivor, which is returned.
+/// The whole merge is one journal entry, so a single undo reverts it.
+#[tauri::command]
+fn merge_notes(
+    ids: Vec<String>,
+    strategy: MergeStrategy,
+    discard: Option<MergeDiscard>,
+    state: State<DbState>,
+) -> Result<Note, String> {
+    if ids.len() < 2 {
+        return Err("At least two notes are needed to merge".to_string());
+    }
+    if ids.iter().any(|id| id.is_empty()) {
+        return Err("Note ID cannot be empty".to_string());
+    }
+    if ids.iter().collect::<HashSet<_>>().len() != ids.len() {
+        return Err("Each note can only be merged once".to_string());
+    }
+    let discard = discard.unwrap_or_default();
+
+    state.with_conn(|conn| perform_merge(conn, &ids, strategy, discard))?
+}
+
+/// `merge_notes` on a connection. A note changed while the merge ran fails
+/// it with a message and nothing is written.
+fn perform_merge(
+    conn: &Connection,
+    ids: &[String],
+    strategy: MergeStrategy,
+    discard: MergeDiscard,
+) -> SqliteResult<Result<Note, String>> {
+    let tx = conn.unchecked_transaction()?;
+
+    let mut notes = Vec::with_capacity(ids.len());
+    for id in ids {
+        notes.push(load_note(&tx, id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)?);
+    }
+
+    let survivor_index = match strategy {
+        MergeStrategy::Concatenate => 0,
+        MergeStrategy::KeepNewest => notes
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, note)| note_updated_at(note))
+            .map_or(0, |(index, _)| index),
+    };
+    let original = notes.remove(survivor_index);
+    let others = notes;
+
+    // Taken before anything moves to the survivor, so undo can move it back
+    let mut merged = Vec::with_capacity(others.len());
+    for note in &others {
+        merged.push(
+            journal::snapshot_note(&tx, &note.id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)?,
+        );
+    }
+    let mut own_properties: BTreeSet<String> = properties::load_properties(&tx, &original.id)?
+        .into_keys()
+        .collect();
+    let copied_properties: Vec<String> = merged
+        .iter()
+        .flat_map(|deleted| deleted.properties.keys())
+        .filter(|key| own_properties.insert(key.to_string()))
+        .cloned()
+        .collect();
+
+    let mut survivor = original.clone();
+    survivor.content = match strategy {
+        MergeStrategy::Concatenate => std::iter::once(&survivor)
+            .chain(&others)
+            .map(|note| note.content.trim())
+            .filter(|content| !html::html_to_text(content).is_empty())
+            .collect::<Vec<_>>()
+            .join("<hr>"),
+        MergeStrategy::KeepNewest => {
+            let mut newest_first: Vec<&Note> = others.iter().collect();
+            newest_first.sort_by_key(|note| std::cmp::Reverse(note_updated_at(note)));
+            append_unique_blocks(&survivor.content, &newest_first)
+        }
+    };
+
+    let now = Utc::now().to_rfc3339();
+    survivor.updated_at = now.clone();
+    if let VersionedSave::Conflict(current) =
+        notes::save_note_if_version(&tx, &survivor, original.version)?
+    {
+        return Ok(Err(merge_conflict(&current)));
+    }
+    let mut changes = vec![ContentChange {
+        note_id: survivor.id.clone(),
+        before: original.content.clone(),
+        after: survivor.content.clone(),
+    }];
+
+    let from: Vec<(&str, &str)> = others
+        .iter()
+        .map(|note| (note.id.as_str(), note.title.as_str()))
+        .collect();
+    changes.extend(retarget_backlinks(&tx, &from, &survivor, &now)?);
+    let relinked = changes.len() - 1;
+
+    for note in &others {
+        notes::move_note_rows(&tx, &note.id, &survivor.id)?;
+    }
+
+    let trash_folder_id = match discard {
+        MergeDiscard::Trash => {
+            let trash_id = ensure_trash_folder(&tx)?;
+            for note in &others {
+                // Backlink rewriting may have changed the note; compare
+                // with what is stored now rather than what was loaded
+                let current =
+                    load_note(&tx, &note.id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+                if !notes::trash_note_if_version(&tx, &note.id, &trash_id, current.version)? {
+                    return Ok(Err(merge_conflict(&current)));
+                }
+            }
+            Some(trash_id)
+        }
+        MergeDiscard::Delete => {
+            for note in &others {
+                notes::delete_note(&tx, &note.id)?;
+            }
+            None
+        }
+    };
+
+    journal::record(
+        &tx,
+        &Operation::MergeNotes {
+            into: survivor.id.clone(),
+            into_title: survivor.title.clone(),
+            merged,
+            copied_properties,
+            changes,
+            trash_folder_id,
+        },
+    )?;
+
+    // Backlink rewriting may have touched the survivor too
+    let merged = load_note(&tx, &survivor.id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+    tx.commit()?;
+
+    log::info!(
+        "Merged {} notes into {} and relinked {} notes",
+        others.len(),
+        merged.id,
+        relinked
+    );
+    Ok(Ok(merged))
+}
+
+fn merge_conflict(current: &Note) -> String {
+    format!(
+        "Note '{}' was changed elsewhere during the merge (now version {})",
+        current.title, current.version
+    )
+}
+
+// =============================================================================
+// LINK GRAPH
+// =============================================================================
+
+/// Links, folder and tags of every note outside the trash, oldest first.
+/// See graph.rs for how the graph is built from them.
+#[tauri::command]
+fn get_link_graph(
+    filter: Option<graph::GraphFilter>,
+    state: State<DbState>,
+) -> Result<graph::LinkGraph, String> {
+    let filter = filter.unwrap_or_default();
+    if filter.focus.as_deref() == Some("") {
+        return Err("Note ID cannot be empty".to_string());
+    }
+
+    let notes = state.with_conn(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, title, folder_id, content FROM notes
+             WHERE {} AND {}
+             ORDER BY created_at ASC",
+            NOT_IN_TRASH, OWNED_BY_ACTIVE
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, StoredContent>(3)?.0,
+            ))
+        })?;
+        rows.collect::<SqliteResult<Vec<_>>>()
+    })?;
+
+    if let Some(focus) = &filter.focus {
+        if !notes.iter().any(|(id, ..)| id == focus) {
+            return Err(format!("Note not found: {}", focus));
+        }
+    }
+
+    let notes: Vec<graph::NoteLinks> = notes
+        .into_par_iter()
+        .map(|(id, title, folder_id, content)| graph::NoteLinks {
+            tags: tags::find_tags(&html::html_to_text(&content))
+                .into_iter()
+                .map(|(_, tag)| tag)
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect(),
+            links: links::outgoing_links(&content),
+            id,
+            title,
+            folder_id,
+        })
+        .collect();
+
+    Ok(graph::build(&notes, &filter))
+}
+
+// =============================================================================
+// BULK EDITS & UNDO
+// =============================================================================
+
+/// Recent operations the UI can offer to undo, newest first
+const RECENT_OPERATIONS_LIMIT: usize = 20;
+
+/// Lowercased tag name without the `#`, or an error if it is not a tag
+fn normalize_tag_name(name: &str) -> Result<String, String> {
+    let name = name.trim().trim_start_matches('#').trim_end_matches('/');
+    let hashtag = format!("#{}", name);
+    match tags::find_tags(&hashtag).as_slice() {
+        [(range, tag)] if range.len() == hashtag.len() => Ok(tag.clone()),
+        _ => Err(format!("Invalid tag name '{}'", name)),
+    }
+}
+
+/// Move notes into a folder, or out of every folder with `None`
+#[tauri::command]
+fn move_notes(
+    ids: Vec<String>,
+    folder_id: Option<String>,
+    state: State<DbState>,
+) -> Result<(), String> {
+    if ids.iter().any(String::is_empty) {
+        return Err("Note ID cannot be empty".to_string());
+    }
+
+    state.with_conn(|conn| {
+        let tx = conn.unchecked_transaction()?;
+        let folder_name = match &folder_id {
+            Some(folder_id) => Some(tx.query_row(
+                &format!(
+                    "SELECT name FROM folders WHERE id = ?1 AND {}",
+                    OWNED_BY_ACTIVE
+                ),
+                params![folder_id],
+                |row| row.get::<_, String>(0),
+            )?),
+            None => None,
+        };
+
+        let mut from = Vec::new();
+        for id in &ids {
+            if let Some(note) = load_note(&tx, id)? {
+                from.push(NoteFolder {
+                    note_id: note.id,
+                    folder_id: note.folder_id,
+                });
+            }
+        }
+        if from.is_empty() {
+            return Ok(());
+        }
+
+        let operation = Operation::MoveNotes {
+            folder_id,
+            folder_name,
+            from,
+        };
+        journal::apply(&tx, &operation)?;
+        journal::record(&tx, &operation)?;
+        tx.commit()
+    })
+}
+
+#[tauri::command]
+fn set_notes_pinned(ids: Vec<String>, pinned: bool, state: State<DbState>) -> Result<(), String> {
+    if ids.iter().any(String::is_empty) {
+        return Err("Note ID cannot be empty".to_string());
+    }
+
+    state.with_conn(|conn| {
+        let tx = conn.unchecked_transaction()?;
+        let mut before = Vec::new();
+        for id in &ids {
+            if let Some(note) = load_note(&tx, id)? {
+                before.push(NotePin {
+                    note_id: note.id,
+                    is_pinned: note.is_pinned,
+                    pinned_at: note.pinned_at,
+                });
+            }
+        }
+        if before.is_empty() {
+            return Ok(());
+        }
+
+        let operation = Operation::PinNotes { pinned, before };
+        journal::apply(&tx, &operation)?;
+        journal::record(&tx, &operation)?;
+        tx.commit()
+    })
+}
+
+/// Rename `#from` and every tag nested under it in all notes. Returns the
+/// number of notes changed.
+#[tauri::command]
+fn rename_tag(from: String, to: String, state: State<DbState>) -> Result<usize, String> {
+    let from = normalize_tag_name(&from)?;
+    let to = normalize_tag_name(&to)?;
+    if from == to {
+        return Ok(0);
+    }
+
+    state.with_conn(|conn| {
+        let tx = conn.unchecked_transaction()?;
+        let changes: Vec<journal::ContentChange> = notes::list_notes(&tx)?
+            .into_iter()
+            .filter_map(|note| {
+                tags::rename_tag(&note.content, &from, &to).map(|after| journal::ContentChange {
+                    note_id: note.id,
+                    before: note.content,
+                    after,
+                })
+            })
+            .collect();
+        if changes.is_empty() {
+            return Ok(0);
+        }
+
+        let count = changes.len();
+        let operation = Operation::RenameTag { from, to, changes };
+        journal::apply(&tx, &operation)?;
+        journal::record(&tx, &operation)?;
+        tx.commit()?;
+        Ok(count)
+    })
+}
+
+/// Undo the most recent operation. Returns it, or `None` if there is
+/// nothing left to undo.
+#[tauri::command]
+fn undo_last_operation(state: State<DbState>) -> Result<Option<JournalEntry>, String> {
+    state.with_conn(|conn| {
+        let tx = conn.unchecked_transaction()?;
+        let entry = journal::undo_last(&tx)?;
+        tx.commit()?;
+        Ok(entry)
+    })
+}
+
+/// Redo the most recently undone operation
+#[tauri::command]
+fn redo_operation(state: State<DbState>) -> Result<Option<JournalEntry>, String> {
+    state.with_conn(|conn| {
+        let tx = conn.unchecked_transaction()?;
+        let entry = journal::redo_next(&tx)?;
+        tx.commit()?;
+        Ok(entry)
+    })
+}
+
+#[tauri::command]
+fn get_recent_operations(
+    limit: Option<usize>,
+    state: State<DbState>,
+) -> Result<Vec<JournalEntry>, String> {
+    let limit = limit.unwrap_or(RECENT_OPERATIONS_LIMIT);
+    state.with_conn(|conn| journal::recent(conn, limit))
+}
+
+// =============================================================================
+// NOTE VERSIONS & DIFFS
+// =============================================================================
+
+/// Versions returned by list_note_versions, newest first
+const NOTE_VERSIONS_SHOWN: i64 = 50;
+
+/// Save the note as it is now, to compare or restore later
+#[tauri::command]
+fn snapshot_note_version(note_id: String, state: State<DbState>) -> Result<NoteVersion, String> {
+    if note_id.is_empty() {
+        return Err("Note ID cannot be empty".to_string());
+    }
+
+    state.with_conn(|conn| {
+        let note = load_note(conn, &note_id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+        let version = NoteVersion {
+            id: uuid::Uuid::new_v4().to_string(),
+            note_id: note.id,
+            title: note.title,
+            content: note.content,
+            created_at: Utc::now().to_rfc3339(),
+            change_type: "manual".to_string(),
+        };
+        insert_note_version(conn, &version)?;
+        Ok(version)
+    })
+}
+
+#[tauri::command]
+fn list_note_versions(note_id: String, state: State<DbState>) -> Result<Vec<NoteVersion>, String> {
+    if note_id.is_empty() {
+        return Err("Note ID cannot be empty".to_string());
+    }
+
+    state.with_conn(|conn| {
+        let mut versions = load_note_versions(conn, &note_id)?;
+        versions.truncate(NOTE_VERSIONS_SHOWN as usize);
+        Ok(versions)
+    })
+}
+
+/// The units `mode` compares: the title, then one line per block of text
+/// or one unit per Markdown block
+fn diff_units(title: &str, content: &str, mode: diff::DiffMode) -> Vec<String> {
+    match mode {
+        diff::DiffMode::Lines => draft_lines(title, content),
+        diff::DiffMode::Markdown => {
+            let mut units = vec![format!("# {}", title)];
+            units.extend(diff::markdown_units(&html_to_markdown(content)));
+            units
+        }
+    }
+}
+
+/// Changes that turn note `a` into note `b`
+#[tauri::command]
+fn diff_notes(
+    a: String,
+    b: String,
+    mode: Option<diff::DiffMode>,
+    state: State<DbState>,
+) -> Result<diff::TextDiff, String> {
+    if a.is_empty() || b.is_empty() {
+        return Err("Note ID cannot be empty".to_string());
+    }
+    let mode = mode.unwrap_or_default();
+
+    let (a, b) = state.with_conn(|conn| {
+        let load = |id: &str| load_note(conn, id)?.ok_or(rusqlite::Error::QueryReturnedNoRows);
+        Ok((load(&a)?, load(&b)?))
+    })?;
+
+    Ok(diff::diff_hunks(
+        &diff_units(&a.title, &a.content, mode),
+        &diff_units(&b.title, &b.content, mode),
+    ))
+}
+
+/// Changes from one stored version of a note to another, or to the note as
+/// it is now when `to_version_id` is `None`
+#[tauri::command]
+fn diff_note_versions(
+    note_id: String,
+    from_version_id: String,
+    to_version_id: Option<String>,
+    mode: Option<diff::DiffMode>,
+    state: State<DbState>,
+) -> Result<diff::TextDiff, String> {
+    if note_id.is_empty() {
+        return Err("Note ID cannot be empty".to_string());
+    }
+    let mode = mode.unwrap_or_default();
+
+    let ((from_title, from_content), (to_title, to_content)) = state.with_conn(|conn| {
+        let load_version = |id: &str| {
+            conn.query_row(
+                &format!(
+                    "SELECT {} FROM note_versions WHERE id = ?1 AND note_id = ?2 AND {}",
+                    NOTE_VERSION_COLUMNS, NOTE_OWNED_BY_ACTIVE
+                ),
+                params![id, note_id],
+                note_version_from_row,
+            )
+            .map(|version| (version.title, version.content))
+        };
+
+        let from = load_version(&from_version_id)?;
+        let to = match &to_version_id {
+            Some(id) => load_version(id)?,
+            None => {
+                let note =
+                    load_note(conn, &note_id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+                (note.title, note.content)
+            }
+        };
+        Ok((from, to))
+    })?;
+
+    Ok(diff::diff_hunks(
+        &diff_units(&from_title, &from_content, mode),
+        &diff_units(&to_title, &to_content, mode),
+    ))
+}
+
+// =============================================================================
+// MARKDOWN
+// =============================================================================
+
+/// A note's content as Markdown
+#[tauri::command]
+fn get_note_as_markdown(id: String, state: State<DbState>) -> Result<String, String> {
+    if id.is_empty() {
+        return Err("Note ID cannot be empty".to_string());
+    }
+
+    state.with_conn(|conn| {
+        let note = load_note(conn, &id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+        Ok(html_to_markdown(&note.content))
+    })
+}
+
+/// Replace a note's content with Markdown converted to editor HTML. Fails
+/// with a conflict unless the note is still at `expected_version`.
+#[tauri::command]
+fn save_note_from_markdown(
+    id: String,
+    markdown: String,
+    expected_version: i64,
+    state: State<DbState>,
+) -> Result<Note, SaveNoteError> {
+    if id.is_empty() {
+        return Err("Note ID cannot be empty".to_string().into());
+    }
+
+    let outcome = state.with_conn(|conn| {
+        let tx = conn.unchecked_transaction()?;
+
+        let mut note = load_note(&tx, &id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+        note.content = markdown_to_html(&markdown);
+        note.updated_at = Utc::now().to_rfc3339();
+
+        let outcome = notes::save_note_if_version(&tx, &note, expected_version)?;
+        if let VersionedSave::Saved(saved) = &outcome {
+            drafts::clear_saved_draft(&tx, saved)?;
+        }
+        tx.commit()?;
+        Ok(outcome)
+    })?;
+
+    saved_or_conflict(outcome, expected_version)
+}
+
+// =============================================================================
+// NOTE PROPERTIES
+// =============================================================================
+
+#[tauri::command]
+fn get_note_properties(
+    note_id: String,
+    state: State<DbState>,
+) -> Result<BTreeMap<String, PropertyValue>, String> {
+    if note_id.is_empty() {
+        return Err("Note ID cannot be empty".to_string());
+    }
+
+    state.with_conn(|conn| properties::load_properties(conn, &note_id))
+}
+
+/// Add or replace a property. A key keeps the type it was first given
+/// while other notes of the account still use it.
+#[tauri::command]
+fn set_note_property(
+    note_id: String,
+    key: String,
+    value: PropertyValue,
+    state: State<DbState>,
+) -> Result<(), String> {
+    if note_id.is_empty() {
+        return Err("Note ID cannot be empty".to_string());
+    }
+    let key = properties::validate_key(&key)?;
+    let value = value.normalized()?;
+
+    // Checked and written in one transaction so two notes cannot give a new
+    // key different types
+    state.with_conn(|conn| {
+        let tx = conn.unchecked_transaction()?;
+        let existing_type = properties::key_type(&tx, &key, &note_id)?;
+        if let Some(existing) = existing_type.filter(|t| t != value.type_name()) {
+            return Ok(Err(format!(
+                "Property '{}' is a {} property",
+                key, existing
+            )));
+        }
+        properties::set_property(&tx, &note_id, &key, &value)?;
+        tx.commit()?;
+        Ok(Ok(()))
+    })?
+}
+
+#[tauri::command]
+fn delete_note_property(note_id: String, key: String, state: State<DbState>) -> Result<(), String> {
+    if note_id.is_empty() {
+        return Err("Note ID cannot be empty".to_string());
+    }
+
+    state.with_conn(|conn| properties::delete_property(conn, &note_id, key.trim()))
+}
+
+/// Every property name in use, for column pickers and autocomplete
+#[tauri::command]
+fn list_property_keys(state: State<DbState>) -> Result<Vec<PropertyKey>, String> {
+    state.with_conn(properties::list_keys)
+}
+
+/// Notes with their properties, filtered and sorted by property values
+#[tauri::command]
+fn list_notes(
+    query: Option<properties::NoteQuery>,
+    state: State<DbState>,
+) -> Result<Vec<NoteWithProperties>, String> {
+    let query = query.unwrap_or_default();
+    let (sql, values) = properties::build_query(&query, NOTE_COLUMNS)?;
+
+    state.with_conn(|conn| properties::run_query(conn, &sql, values))
+}
+
+// =============================================================================
+// RECENT NOTES & FRECENCY
+// =============================================================================
+
+/// Maintenance drops entries from the open log after this long
+const NOTE_OPEN_RETENTION_DAYS: i64 = 180;
+const DEFAULT_RECENT_NOTES: usize = 20;
+const MAX_RECENT_NOTES: usize = 200;
+
+#[tauri::command]
+fn record_note_open(id: String, state: State<DbState>) -> Result<(), String> {
+    if id.is_empty() {
+        return Err("Note ID cannot be empty".to_string());
+    }
+
+    state.with_conn(|conn| frecency::record_open(conn, &id, Utc::now()))
+}
+
+/// Opened notes, most recent or most frecent first
+#[tauri::command]
+fn get_recent_notes(
+    limit: Option<usize>,
+    order: Option<RecentOrder>,
+    state: State<DbState>,
+) -> Result<Vec<RecentNote>, String> {
+    let limit = limit
+        .unwrap_or(DEFAULT_RECENT_NOTES)
+        .clamp(1, MAX_RECENT_NOTES);
+
+    state.with_conn(|conn| frecency::recent_notes(conn, limit, order.unwrap_or_default()))
+}
+
+/// Current frecency of every note that has been opened, by note ID, for
+/// sorting lists and quick-switcher results
+#[tauri::command]
+fn get_frecency_scores(state: State<DbState>) -> Result<HashMap<String, f64>, String> {
+    state.with_conn(frecency::frecency_scores)
+}
+
+// =============================================================================
+// DATABASE HEALTH
+// =============================================================================
+
+#[tauri::command]
+fn check_database(state: State<DbState>) -> Result<integrity::CheckReport, String> {
+    state.with_conn(integrity::check)
+}
+
+/// Fix what can be fixed without guessing at user data; the report lists
+/// what was fixed and what still needs attention
+#[tauri::command]
+fn repair_database(state: State<DbState>) -> Result<integrity::RepairReport, String> {
+    state.with_conn(integrity::repair)
+}
+
+// =============================================================================
+// STORAGE & MAINTENANCE
+// =============================================================================
+
+/// RFC3339 time maintenance last finished
+const LAST_MAINTENANCE_KEY: &str = "last_maintenance";
+
+const MAINTENANCE_INTERVAL_DAYS: i64 = 7;
+/// Scheduled maintenance waits until nothing has touched the database for
+/// this long
+const MAINTENANCE_IDLE_SECS: i64 = 10 * 60;
+const MAINTENANCE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+const LARGEST_NOTES_SHOWN: usize = 10;
+
+/// `PRAGMA auto_vacuum` value for incremental mode
+const AUTO_VACUUM_INCREMENTAL: i64 = 2;
+
+fn pragma_u64(conn: &Connection, name: &str) -> SqliteResult<u64> {
+    conn.query_row(&format!("PRAGMA {}", name), [], |row| row.get::<_, i64>(0))
+        .map(|value| value.max(0) as u64)
+}
+
+fn database_bytes(conn: &Connection) -> SqliteResult<u64> {
+    Ok(pragma_u64(conn, "page_count")? * pragma_u64(conn, "page_size")?)
+}
+
+/// Number and total size of the files under `dir`; a missing directory is
+/// empty
+fn directory_usage(dir: &Path) -> (u64, u64) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return (0, 0);
+    };
+
+    entries
+        .flatten()
+        .fold((0, 0), |(count, bytes), entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => {
+                let (c, b) = directory_usage(&entry.path());
+                (count + c, bytes + b)
+            }
+            Ok(meta) => (count + 1, bytes + meta.len()),
+            Err(_) => (count, bytes),
+        })
+}
+
+/// Space taken by the search index. dbstat sees the FTS shadow tables
+/// (notes_fts_data, _idx, ...); SQLite builds without it get the size of
+/// the index's data blocks instead.
+fn search_index_bytes(conn: &Connection) -> SqliteResult<u64> {
+    let bytes: i64 = conn
+        .query_row(
+            "SELECT COALESCE(SUM(pgsize), 0) FROM dbstat WHERE name LIKE 'notes_fts%'",
+            [],
+            |row| row.get(0),
+        )
+        .or_else(|e| {
+            log::debug!("dbstat unavailable, estimating search index size: {}", e);
+            conn.query_row(
+                "SELECT COALESCE(SUM(length(block)), 0) FROM notes_fts_data",
+                [],
+                |row| row.get(0),
+            )
+        })?;
+    Ok(bytes.max(0) as u64)
+}
+
+/// Written and stored size of each of the active account's notes, and
+/// whether it is stored compressed. Only the zstd frame header of
+/// compressed content is read, which records the size it decompresses to.
+fn note_sizes(conn: &Connection) -> SqliteResult<Vec<(NoteSize, bool)>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, title, length(CAST(content AS BLOB)),
+                CASE WHEN typeof(content) = 'blob' THEN substr(content, 1, 18) END
+         FROM notes
+         WHERE {}",
+        OWNED_BY_ACTIVE
+    ))?;
+    let sizes = stmt.query_map([], |row| {
+        let stored_bytes = row.get::<_, i64>(2)?.max(0) as u64;
+        let header: Option<Vec<u8>> = row.get(3)?;
+        let size = NoteSize {
+            note_id: row.get(0)?,
+            note_title: row.get(1)?,
+            bytes: header
+                .as_deref()
+                .and_then(compression::uncompressed_size)
+                .unwrap_or(stored_bytes),
+            stored_bytes,
+        };
+        Ok((size, header.is_some()))
+    })?;
+    sizes.collect()
+}
+
+#[tauri::command]
+fn get_storage_stats(state: State<DbState>) -> Result<StorageStats, String> {
+    let (attachment_count, attachment_bytes) = directory_usage(&attachments_dir(&state)?);
+
+    state.with_conn(|conn| {
+        let count = |table: &str| {
+            conn.query_row(
+                &format!("SELECT COUNT(*) FROM {} WHERE {}", table, OWNED_BY_ACTIVE),
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|n| n as u64)
+        };
+
+        let sizes = note_sizes(conn)?;
+        let (compressed_note_count, compressed_bytes, uncompressed_bytes) = sizes
+            .iter()
+            .filter(|(_, compressed)| *compressed)
+            .fold((0, 0, 0), |(count, stored, bytes), (n, _)| {
+                (count + 1, stored + n.stored_bytes, bytes + n.bytes)
+            });
+        let mut largest_notes: Vec<NoteSize> = sizes.into_iter().map(|(n, _)| n).collect();
+        largest_notes.sort_by_key(|n| std::cmp::Reverse(n.bytes));
+        largest_notes.truncate(LARGEST_NOTES_SHOWN);
+
+        Ok(StorageStats {
+            database_bytes: database_bytes(conn)?,
+            page_size: pragma_u64(conn, "page_size")?,
+            free_pages: pragma_u64(conn, "freelist_count")?,
+            search_index_bytes: search_index_bytes(conn)?,
+            note_count: count("notes")?,
+            compressed_note_count,
+            compressed_bytes,
+            compression_saved_bytes: uncompressed_bytes.saturating_sub(compressed_bytes),
+            folder_count: count("folders")?,
+            attachment_count,
+            attachment_bytes,
+            largest_notes,
+            last_maintenance: read_setting(conn, LAST_MAINTENANCE_KEY)?,
+        })
+    })
+}
+
+/// Merge FTS segments, prune the note-open log, return free pages to the
+/// filesystem and refresh the query planner's statistics. A full vacuum rewrites the whole file; it
+/// also runs when the database is not yet in incremental auto-vacuum mode.
+fn perform_maintenance(conn: &Connection, full: bool) -> SqliteResult<MaintenanceReport> {
+    let started = std::time::Instant::now();
+    let bytes_before = database_bytes(conn)?;
+
+    conn.execute("INSERT INTO notes_fts (notes_fts) VALUES ('optimize')", [])?;
+
+    // Frecency scores live in note_frecency; old opens only take up space
+    frecency::prune_opens(conn, Utc::now() - Duration::days(NOTE_OPEN_RETENTION_DAYS))?;
+
+    let full_vacuum = full || pragma_u64(conn, "auto_vacuum")? as i64 != AUTO_VACUUM_INCREMENTAL;
+    if full_vacuum {
+        // Changing auto_vacuum only applies once VACUUM rebuilds the file
+        conn.execute_batch("PRAGMA auto_vacuum = INCREMENTAL; VACUUM;")?;
+    } else {
+        let mut stmt = conn.prepare("PRAGMA incremental_vacuum")?;
+        let mut rows = stmt.query([])?;
+        while rows.next()?.is_some() {}
+    }
+
+    conn.execute_batch("ANALYZE")?;
+
+    let finished_at = Utc::now().to_rfc3339();
+    write_setting(conn, LAST_MAINTENANCE_KEY, &finished_at)?;
+
+    let report = MaintenanceReport {
+        bytes_before,
+        bytes_after: database_bytes(conn)?,
+        full_vacuum,
+        duration_ms: started.elapsed().as_millis() as u64,
+        finished_at,
+    };
+    log::info!(
+        "Maintenance finished in {}ms: {} -> {} bytes",
+        report.duration_ms,
+        report.bytes_before,
+        report.bytes_after
+    );
+    Ok(report)
+}
+
+#[tauri::command]
+fn run_maintenance(full: Option<bool>, state: State<DbState>) -> Result<MaintenanceReport, String> {
+    state.with_conn(|conn| perform_maintenance(conn, full.unwrap_or(false)))
+}
+
+fn maintenance_due(conn: &Connection, now: DateTime<Utc>) -> SqliteResult<bool> {
+    let last = read_setting::<String>(conn, LAST_MAINTENANCE_KEY)?
+        .and_then(|at| DateTime::parse_from_rfc3339(&at).ok());
+    Ok(match last {
+        Some(at) => now.signed_duration_since(at) >= Duration::days(MAINTENANCE_INTERVAL_DAYS),
+        None => true,
+    })
+}
+
+/// Run maintenance about once a week, when the app has been idle for a while
+fn spawn_maintenance_scheduler(handle: AppHandle) {
+    std::thread::Builder::new()
+        .name("maintenance".to_string())
+        .spawn(move || loop {
+            std::thread::sleep(MAINTENANCE_POLL_INTERVAL);
+
+            let state = handle.state::<DbState>();
+            if state.idle_seconds() < MAINTENANCE_IDLE_SECS {
+                continue;
+            }
+            let result = state.with_conn_in_background(|conn| {
+                if maintenance_due(conn, Utc::now())? {
+                    perform_maintenance(conn, false).map(Some)
+                } else {
+                    Ok(None)
+                }
+            });
+            if let Err(e) = result {
+                // Expected until the frontend has called init_db
+                log::debug!("Scheduled maintenance skipped: {}", e);
+            }
+        })
+        .expect("failed to spawn maintenance scheduler");
+}
+
+// =============================================================================
+// DEEP LINKS
+// =============================================================================
+
+const DEEP_LINK_EVENT: &str = "deep-link";
+const DEEP_LINK_ERROR_EVENT: &str = "deep-link-error";
+
+/// Errors for rejected deep links, held like the links themselves
+#[derive(Default)]
+struct DeepLinkErrors(PendingEvents<String>);
+
+fn route_deep_links(handle: &AppHandle, urls: Vec<Url>) {
+    let links = handle.state::<PendingEvents<DeepLinkAction>>();
+    let errors = handle.state::<DeepLinkErrors>();
+
+    for url in urls {
+        match deep_link::parse(&url) {
+            Ok(Some(action)) => {
+                log::info!("Deep link: {:?}", action);
+                links.deliver(handle, DEEP_LINK_EVENT, action);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                log::warn!("Rejected deep link {}: {}", url, e);
+                errors.0.deliver(handle, DEEP_LINK_ERROR_EVENT, e);
+            }
+        }
+    }
+
+    focus_main_window(handle);
+}
+
+fn focus_main_window(handle: &AppHandle) {
+    if let Some(window) = handle.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// Called once by the frontend after it subscribes to `deep-link`. Returns
+/// links received so far; later links are delivered as events.
+#[tauri::command]
+fn take_pending_deep_links(
+    pending: State<PendingEvents<DeepLinkAction>>,
+) -> Result<Vec<DeepLinkAction>, String> {
+    pending.take()
+}
+
+/// Called once by the frontend after it subscribes to `deep-link-error`.
+/// Returns errors for links rejected so far, such as the one that launched
+/// the app; later errors are delivered as events.
+#[tauri::command]
+fn take_pending_deep_link_errors(errors: State<DeepLinkErrors>) -> Result<Vec<String>, String> {
+    errors.0.take()
+}
+
+// =============================================================================
+// PUBLISHING
+// =============================================================================
+
+/// Where images and other files referenced by the active vault's notes are
+/// stored
+fn attachments_dir(state: &DbState) -> Result<PathBuf, String> {
+    Ok(vaults::attachments_dir(&state.active_vault()?.dir))
+}
+
+/// Render every note in a folder to a self-contained static site
+#[tauri::command]
+fn publish_folder(
+    options: publish::PublishOptions,
+    state: State<DbState>,
+) -> Result<publish::PublishReport, String> {
+    if options.folder_id.is_empty() {
+        return Err("Folder ID cannot be empty".to_string());
+    }
+    if options.output_dir.trim().is_empty() {
+        return Err("Output directory cannot be empty".to_string());
+    }
+
+    let (folder_name, notes) = state.with_conn(|conn| {
+        let folder_name: String = conn.query_row(
+            &format!(
+                "SELECT name FROM folders WHERE id = ?1 AND {}",
+                OWNED_BY_ACTIVE
+            ),
+            params![options.folder_id],
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM notes WHERE folder_id = ?1 AND {}",
+            NOTE_COLUMNS, OWNED_BY_ACTIVE
+        ))?;
+        let notes = stmt
+            .query_map(params![options.folder_id], note_from_row)?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        Ok((folder_name, notes))
+    })?;
+
+    let report = publish::publish(&notes, &folder_name, &attachments_dir(&state)?, &options)?;
+    if !report.missing_attachments.is_empty() {
+        log::warn!(
+            "Publish skipped {} missing attachments",
+            report.missing_attachments.len()
+        );
+    }
+    Ok(report)
+}
+
+/// Typeset a note as a PDF, with its images, code and math
+#[tauri::command]
+fn export_note_pdf(
+    note_id: String,
+    output_path: String,
+    state: State<DbState>,
+) -> Result<pdf::PdfExportReport, String> {
+    if note_id.is_empty() {
+        return Err("Note ID cannot be empty".to_string());
+    }
+    if output_path.trim().is_empty() {
+        return Err("Output path cannot be empty".to_string());
+    }
+
+    let note = state
+        .with_conn(|conn| load_note(conn, &note_id)?.ok_or(rusqlite::Error::QueryReturnedNoRows))?;
+
+    let report = pdf::export_pdf(
+        &note,
+        &attachments_dir(&state)?,
+        Path::new(output_path.trim()),
+    )?;
+    if !report.missing_images.is_empty() || !report.unrendered_math.is_empty() {
+        log::warn!(
+            "PDF export of {} skipped {} images and {} formulas",
+            note_id,
+            report.missing_images.len(),
+            report.unrendered_math.len()
+        );
+    }
+    Ok(report)
+}
+
+// =============================================================================
+// ARCHIVES
+// =============================================================================
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchiveExportReport {
+    path: String,
+    folders: usize,
+    notes: usize,
+    note_versions: usize,
+    attachments: usize,
+    bytes: u64,
+}
+
+/// Write the active vault's notes, folders, versions and attachments to one
+/// JSON file (see `webnotes_core::archive` for the format)
+#[tauri::command]
+fn export_archive(
+    output_path: String,
+    state: State<DbState>,
+) -> Result<ArchiveExportReport, String> {
+    if output_path.trim().is_empty() {
+        return Err("Output path cannot be empty".to_string());
+    }
+
+    let dir = attachments_dir(&state)?;
+    let archive = state
+        .with_conn(|conn| Ok(archive::export_archive(conn, &dir)))?
+        .map_err(|e| e.to_string())?;
+
+    let json = serde_json::to_vec_pretty(&archive)
+        .map_err(|e| format!("Failed to serialize archive: {}", e))?;
+    let path = output_path.trim();
+    std::fs::write(path, &json).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+
+    Ok(ArchiveExportReport {
+        path: path.to_string(),
+        folders: archive.folders.len(),
+        notes: archive.notes.len(),
+        note_versions: archive.note_versions.len(),
+        attachments: archive.attachments.len(),
+        bytes: json.len() as u64,
+    })
+}
+
+/// Read an archive into the active vault. Records whose IDs are already
+/// taken are handled by `mode`; nothing is written unless the whole archive
+/// imports.
+#[tauri::command]
+fn import_archive(
+    input_path: String,
+    mode: archive::ConflictMode,
+    state: State<DbState>,
+) -> Result<archive::ImportReport, String> {
+    if input_path.trim().is_empty() {
+        return Err("Input path cannot be empty".to_string());
+    }
+
+    let path = input_path.trim();
+    let json = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let archive: archive::Archive =
+        serde_json::from_slice(&json).map_err(|e| format!("Invalid archive: {}", e))?;
+
+    let dir = attachments_dir(&state)?;
+    state.with_conn(|conn| {
+        let tx = conn.unchecked_transaction()?;
+        let report = match archive::import_archive(&tx, &archive, &dir, mode) {
+            Ok(report) => report,
+            Err(archive::ArchiveError::Sqlite(e)) => return Err(e),
+            Err(e) => return Ok(Err(e.to_string())),
+        };
+        tx.commit()?;
+        Ok(Ok(report))
+    })?
+}
+
+// =============================================================================
+// ACCOUNTS
+// =============================================================================
+//
+// Each account sees only its own rows; see webnotes_core::accounts.
+
+#[tauri::command]
+fn list_accounts(state: State<DbState>) -> Result<Vec<Account>, String> {
+    state.with_conn(accounts::load_accounts)
+}
+
+#[tauri::command]
+fn get_active_account(state: State<DbState>) -> Result<Option<Account>, String> {
+    state.with_conn(accounts::active_account)
+}
+
+/// Switch to an account, e.g. after the desktop sign-in page hands one
+/// back. The first account to sign in here takes over everything created
+/// while signed out.
+#[tauri::command]
+fn sign_in_account(account: Account, state: State<DbState>) -> Result<accounts::SignIn, String> {
+    if account.id.trim().is_empty() {
+        return Err("Account ID cannot be empty".to_string());
+    }
+
+    state.with_conn(|conn| {
+        let tx = conn.unchecked_transaction()?;
+        let report = accounts::sign_in(&tx, &account)?;
+        tx.commit()?;
+        Ok(report)
+    })
+}
+
+/// Leave no account active; only notes created while signed out are
+/// visible until the next sign-in
+#[tauri::command]
+fn sign_out_account(state: State<DbState>) -> Result<(), String> {
+    state.with_conn(accounts::sign_out)
+}
+
+/// Delete everything one account keeps on this device. Other accounts'
+/// data, and attachments, are left alone.
+#[tauri::command]
+fn wipe_account_data(
+    account_id: String,
+    state: State<DbState>,
+) -> Result<accounts::AccountWipe, String> {
+    if account_id.trim().is_empty() {
+        return Err("Account ID cannot be empty".to_string());
+    }
+
+    state.with_conn(|conn| {
+        let tx = conn.unchecked_transaction()?;
+        let wiped = accounts::wipe_account(&tx, &account_id)?;
+        tx.commit()?;
+        Ok(wiped)
+    })
+}
+
+// =============================================================================
+// VAULTS
+// =============================================================================
+
+const VAULT_SWITCHED_EVENT: &str = "vault-switched";
+
+fn lock_registry(vaults: &VaultState) -> Result<std::sync::MutexGuard<'_, VaultRegistry>, String> {
+    vaults
+        .registry
+        .lock()
+        .map_err(|e| format!("Failed to acquire vault lock: {}", e))
+}
+
+#[tauri::command]
+fn list_vaults(vaults: State<VaultState>) -> Result<Vec<Vault>, String> {
+    Ok(lock_registry(&vaults)?.vaults.clone())
+}
+
+#[tauri::command]
+fn get_active_vault(state: State<DbState>, vaults: State<VaultState>) -> Result<Vault, String> {
+    let active = state.active_vault()?;
+    lock_registry(&vaults)?.get(&active.id).cloned()
+}
+
+/// Create an empty vault. It is not opened; use `switch_vault` for that.
+#[tauri::command]
+fn create_vault(name: String, vaults: State<VaultState>) -> Result<Vault, String> {
+    let mut registry = lock_registry(&vaults)?;
+    let vault = Vault {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: registry.validate_name(&name, None)?,
+        created_at: Utc::now().to_rfc3339(),
+    };
+
+    // Set the database up now, so a vault that cannot be opened is never
+    // listed
+    let conn = open_database(&vaults::vault_dir(&vaults.app_data_dir, &vault.id))?;
+    initialize_database(&conn).map_err(|e| format!("Database error: {}", e))?;
+    drop(conn);
+
+    let mut updated = registry.clone();
+    updated.vaults.push(vault.clone());
+    updated.save(&vaults.app_data_dir)?;
+    *registry = updated;
+
+    log::info!("Created vault {} ({})", vault.name, vault.id);
+    Ok(vault)
+}
+
+#[tauri::command]
+fn rename_vault(id: String, name: String, vaults: State<VaultState>) -> Result<Vault, String> {
+    let mut registry = lock_registry(&vaults)?;
+    registry.get(&id)?;
+    let name = registry.validate_name(&name, Some(&id))?;
+
+    let mut updated = registry.clone();
+    let vault = updated
+        .vaults
+        .iter_mut()
+        .find(|vault| vault.id == id)
+        .expect("vault checked above");
+    vault.name = name;
+    let vault = vault.clone();
+    updated.save(&vaults.app_data_dir)?;
+    *registry = updated;
+
+    Ok(vault)
+}
+
+/// Delete a vault with its notes and attachments. The open vault and the
+/// only vault cannot be deleted.
+#[tauri::command]
+fn delete_vault(
+    id: String,
+    state: State<DbState>,
+    vaults: State<VaultState>,
+) -> Result<(), String> {
+    let mut registry = lock_registry(&vaults)?;
+    registry.get(&id)?;
+    if state.active_vault()?.id == id {
+        return Err("Switch to another vault before deleting this one".to_string());
+    }
+    if registry.vaults.len() == 1 {
+        return Err("Cannot delete the only vault".to_string());
+    }
+
+    let mut updated = registry.clone();
+    updated.vaults.retain(|vault| vault.id != id);
+    updated.save(&vaults.app_data_dir)?;
+    *registry = updated;
+
+    log::info!("Deleted vault {}", id);
+    vaults::remove_vault_files(&vaults.app_data_dir, &id)
+}
+
+/// Close the open vault and open another one. Emits `vault-switched` so
+/// every window reloads its notes, and opens this vault on the next launch.
+#[tauri::command]
+fn switch_vault(
+    id: String,
+    app: AppHandle,
+    state: State<DbState>,
+    vaults: State<VaultState>,
+    api_state: State<LocalApiState>,
+) -> Result<Vault, String> {
+    let mut registry = lock_registry(&vaults)?;
+    let vault = registry.get(&id)?.clone();
+    if state.active_vault()?.id == id {
+        return Ok(vault);
+    }
+
+    state.switch_vault(&id, vaults::vault_dir(&vaults.app_data_dir, &id))?;
+    log::info!("Switched to vault {} ({})", vault.name, vault.id);
+
+    // The API settings are stored per vault
+    let api_config: api::LocalApiConfig = state
+        .with_conn(|conn| read_setting(conn, LOCAL_API_CONFIG_KEY))?
+        .unwrap_or_default();
+    if let Err(e) = apply_local_api_config(&app, &api_state, &api_config) {
+        log::warn!("{}", e);
+    }
+
+    registry.last_vault_id = Some(id);
+    if let Err(e) = registry.save(&vaults.app_data_dir) {
+        log::warn!("Failed to remember the open vault: {}", e);
+    }
+
+    let payload = vaults::VaultSwitched {
+        vault: vault.clone(),
+    };
+    if let Err(e) = app.emit(VAULT_SWITCHED_EVENT, &payload) {
+        log::warn!("Failed to emit vault switch: {}", e);
+    }
+    Ok(vault)
+}
+
+// =============================================================================
+// LOCAL API
+// =============================================================================
+
+const LOCAL_API_CONFIG_KEY: &str = "local_api";
+
+/// Stop any running server and start a new one if the config enables it
+fn apply_local_api_config(
+    handle: &AppHandle,
+    api_state: &LocalApiState,
+    config: &api::LocalApiConfig,
+) -> Result<(), String> {
+    let mut server = api_state
+        .server
+        .lock()
+        .map_err(|e| format!("Failed to acquire local API lock: {}", e))?;
+
+    if let Some(running) = server.take() {
+        running.stop();
+    }
+
+    if config.enabled {
+        let token = api::load_or_create_token(&api_state.app_data_dir)?;
+        *server = Some(api::start(handle.clone(), config.port, token)?);
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+fn get_local_api_config(state: State<DbState>) -> Result<api::LocalApiConfig, String> {
+    state.with_conn(|conn| Ok(read_setting(conn, LOCAL_API_CONFIG_KEY)?.unwrap_or_default()))
+}
+
+#[tauri::command]
+fn set_local_api_config(
+    config: api::LocalApiConfig,
+    app: AppHandle,
+    state: State<DbState>,
+    api_state: State<LocalApiState>,
+) -> Result<(), String> {
+    if config.enabled && config.port < 1024 {
+        return Err("Local API port must be 1024 or higher".to_string());
+    }
+
+    apply_local_api_config(&app, &api_state, &config)?;
+    state.with_conn(|conn| write_setting(conn, LOCAL_API_CONFIG_KEY, &config))
+}
+
+#[tauri::command]
+fn get_local_api_token(api_state: State<LocalApiState>) -> Result<String, String> {
+    api::load_or_create_token(&api_state.app_data_dir)
+}
+
+/// Issue a new token, invalidating the old one immediately
+#[tauri::command]
+fn regenerate_local_api_token(
+    app: AppHandle,
+    state: State<DbState>,
+    api_state: State<LocalApiState>,
+) -> Result<String, String> {
+    let token = api::regenerate_token(&api_state.app_data_dir)?;
+
+    let config: api::LocalApiConfig = state
+        .with_conn(|conn| Ok(read_setting(conn, LOCAL_API_CONFIG_KEY)?.unwrap_or_default()))?;
+    apply_local_api_config(&app, &api_state, &config)?;
+
+    Ok(token)
+}
+
+// =============================================================================
+// SETUP
+// =============================================================================
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    let mut builder = tauri::Builder::default();
+
+    // Must be registered first. A second launch (e.g. from a webnotes:// link)
+    // forwards its arguments here and exits; the deep-link feature turns them
+    // into on_open_url events.
+    #[cfg(desktop)]
+    {
+        builder = builder.plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| {
+            focus_main_window(app);
+        }));
+    }
+
+    builder
+        .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_deep_link::init())
+        .setup(|app| {
+            // Set up logging
+            env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
+                .init();
+
+            // Get app data directory
+            let app_data_dir = app
+                .path()
+                .app_data_dir()
+                .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+
+            // Create directory if it doesn't exist
+            if !app_data_dir.exists() {
+                std::fs::create_dir_all(&app_data_dir)
+                    .map_err(|e| format!("Failed to create app data dir: {}", e))?;
+            }
+
+            let api_state = LocalApiState {
+                app_data_dir: app_data_dir.clone(),
+                server: Mutex::new(None),
+            };
+
+            // Open the vault that was active when the app last closed
+            let registry = VaultRegistry::load(&app_data_dir)?;
+            let vault_id = registry.last_vault().id.clone();
+            let db_state = DbState::new(&vault_id, vaults::vault_dir(&app_data_dir, &vault_id))
+                .map_err(|e| format!("Failed to initialize database: {}", e))?;
+
+            app.manage(db_state);
+            app.manage(VaultState {
+                app_data_dir: app_data_dir.clone(),
+                registry: Mutex::new(registry),
+            });
+
+            app.manage(PendingEvents::<DueReminder>::default());
+            spawn_reminder_scheduler(app.handle().clone());
+            spawn_maintenance_scheduler(app.handle().clone());
+
+            // The settings table does not exist before the first init_db,
+            // in which case the API is simply off
+            let api_config: api::LocalApiConfig = app
+                .state::<DbState>()
+                .with_conn(|conn| read_setting(conn, LOCAL_API_CONFIG_KEY))
+                .ok()
+                .flatten()
+                .unwrap_or_default();
+            if let Err(e) = apply_local_api_config(app.handle(), &api_state, &api_config) {
+                log::warn!("{}", e);
+            }
+            app.manage(api_state);
+
+            app.manage(PendingEvents::<DeepLinkAction>::default());
+            app.manage(DeepLinkErrors::default());
+
+            // Installers register the scheme on Windows; Linux and dev builds
+            // need it at runtime
+            #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+            if let Err(e) = app.deep_link().register_all() {
+                log::warn!("Failed to register deep link scheme: {}", e);
+            }
+
+            let handle = app.handle().clone();
+            app.deep_link()
+                .on_open_url(move |event| route_deep_links(&handle, event.urls()));
+
+            // The URL that launched the app, if any
+            if let Ok(Some(urls)) = app.deep_link().get_current() {
+                route_deep_links(app.handle(), urls);
+            }
+
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
             init_db,
             save_note,
             get_all_notes,
@@ -491,7 +2512,265 @@ pub fn run() {
             get_all_folders,
             delete_folder,
             search_notes,
+            get_daily_note_config,
+            set_daily_note_config,
+            get_or_create_daily_note,
+            list_daily_notes,
+            add_reminder,
+            delete_reminder,
+            get_note_reminders,
+            list_upcoming_reminders,
+            snooze_reminder,
+            complete_reminder,
+            take_pending_reminders,
+            list_tasks,
+            toggle_task,
+            take_pending_deep_links,
+            take_pending_deep_link_errors,
+            get_local_api_config,
+            set_local_api_config,
+            get_local_api_token,
+            regenerate_local_api_token,
+            get_related_notes,
+            rebuild_related_index,
+            find_duplicates,
+            merge_notes,
+            check_database,
+            repair_database,
+            get_storage_stats,
+            run_maintenance,
+            record_note_open,
+            get_recent_notes,
+            get_frecency_scores,
+            get_note_properties,
+            set_note_property,
+            delete_note_property,
+            list_property_keys,
+            list_notes,
+            save_smart_folder,
+            get_all_smart_folders,
+            delete_smart_folder,
+            get_smart_folder_notes,
+            get_smart_folder_counts,
+            save_draft,
+            discard_draft,
+            get_recoverable_drafts,
+            get_link_graph,
+            get_note_as_markdown,
+            save_note_from_markdown,
+            move_notes,
+            set_notes_pinned,
+            rename_tag,
+            undo_last_operation,
+            redo_operation,
+            get_recent_operations,
+            list_vaults,
+            get_active_vault,
+            create_vault,
+            rename_vault,
+            delete_vault,
+            switch_vault,
+            snapshot_note_version,
+            list_note_versions,
+            diff_notes,
+            diff_note_versions,
+            publish_folder,
+            export_note_pdf,
+            export_archive,
+            import_archive,
+            list_accounts,
+            get_active_account,
+            sign_in_account,
+            sign_out_account,
+            wipe_account_data,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        initialize_database(&conn).unwrap();
+        conn
+    }
+
+    fn add_reminder_row(
+        conn: &Connection,
+        id: &str,
+        note_id: &str,
+        remind_at: &str,
+        repeat: Option<RepeatRule>,
+        anchor: &str,
+    ) {
+        conn.execute(
+            "INSERT INTO reminders (id, note_id, remind_at, repeat, created_at, repeat_anchor)
+             VALUES (?1, ?2, ?3, ?4, ?3, ?5)",
+            params![
+                id,
+                note_id,
+                remind_at,
+                repeat.map(RepeatRule::as_str),
+                anchor
+            ],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn note_sizes_read_compressed_notes_at_their_written_size() {
+        let conn = test_db();
+        let repetitive = Note::new("Repetitive", "la ".repeat(100_000));
+        let short = Note::new("Short", "word ".repeat(1_000));
+        notes::save_note(&conn, &repetitive).unwrap();
+        notes::save_note(&conn, &short).unwrap();
+
+        let sizes = note_sizes(&conn).unwrap();
+        let size = |id: &str| sizes.iter().find(|(n, _)| n.note_id == id).unwrap();
+
+        let (big, compressed) = size(&repetitive.id);
+        assert!(compressed);
+        assert_eq!(big.bytes, 300_000);
+        assert!(big.stored_bytes < short.content.len() as u64);
+
+        let (small, compressed) = size(&short.id);
+        assert!(!compressed);
+        assert_eq!((small.bytes, small.stored_bytes), (5_000, 5_000));
+
+        assert!(search_index_bytes(&conn).unwrap() > 0);
+    }
+
+    #[test]
+    fn repair_clears_unreadable_optional_timestamps() {
+        let conn = test_db();
+        conn.execute(
+            "INSERT INTO notes (id, title, content, updated_at, created_at)
+             VALUES ('n1', '', '', '2025-01-01T00:00:00Z', '2025-01-01T00:00:00Z')",
+            [],
+        )
+        .unwrap();
+        add_reminder_row(
+            &conn,
+            "r1",
+            "n1",
+            "2025-01-02T09:00:00Z",
+            None,
+            "2025-01-02T09:00:00Z",
+        );
+        conn.execute(
+            "UPDATE reminders SET fired_at = 'yesterday', completed_at = 'soon'",
+            [],
+        )
+        .unwrap();
+
+        integrity::repair(&conn).unwrap();
+        let (fired_at, completed_at): (Option<String>, Option<String>) = conn
+            .query_row("SELECT fired_at, completed_at FROM reminders", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((fired_at, completed_at), (None, None));
+        assert!(integrity::check(&conn).unwrap().ok);
+    }
+
+    #[test]
+    fn repair_reindexes_notes_without_an_id() {
+        let conn = test_db();
+        conn.execute(
+            "INSERT INTO notes (id, title, content, updated_at, created_at)
+             VALUES ('', 'Lost', '<p>orphan</p>', '2025-01-01T00:00:00Z', '2025-01-01T00:00:00Z')",
+            [],
+        )
+        .unwrap();
+        webnotes_core::search::index_note(&conn, "", "Lost", "<p>orphan</p>").unwrap();
+
+        integrity::repair(&conn).unwrap();
+        let empty: i64 = conn
+            .query_row("SELECT COUNT(*) FROM notes_fts WHERE id = ''", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(empty, 0);
+        assert_eq!(run_search(&conn, "orphan").unwrap().len(), 1);
+        assert!(integrity::check(&conn).unwrap().ok);
+    }
+
+    #[test]
+    fn undoing_a_merge_restores_every_note() {
+        for discard in [MergeDiscard::Trash, MergeDiscard::Delete] {
+            let conn = test_db();
+            let folder = Folder::new("Work");
+            folders::save_folder(&conn, &folder).unwrap();
+            let survivor = Note::new("A", "<p>first</p>");
+            let mut other = Note::new("B", "<p>second</p>");
+            other.folder_id = Some(folder.id.clone());
+            let linking = Note::new("C", "<p>see [[B]]</p>");
+            for note in [&survivor, &other, &linking] {
+                notes::insert_note(&conn, note).unwrap();
+            }
+            properties::set_property(
+                &conn,
+                &other.id,
+                "status",
+                &PropertyValue::Text("draft".to_string()),
+            )
+            .unwrap();
+            add_reminder_row(
+                &conn,
+                "r1",
+                &other.id,
+                "2030-01-01T09:00:00Z",
+                None,
+                "2030-01-01T09:00:00Z",
+            );
+            conn.execute(
+                "INSERT INTO daily_notes (date, note_id) VALUES ('2025-01-02', ?1)",
+                params![other.id],
+            )
+            .unwrap();
+
+            let ids = vec![survivor.id.clone(), other.id.clone()];
+            let merged = perform_merge(&conn, &ids, MergeStrategy::Concatenate, discard)
+                .unwrap()
+                .unwrap();
+            assert_eq!(merged.content, "<p>first</p><hr><p>second</p>");
+            assert_eq!(journal::recent(&conn, 10).unwrap().len(), 1);
+
+            journal::undo_last(&conn).unwrap().unwrap();
+
+            let restored = load_note(&conn, &other.id).unwrap().unwrap();
+            assert_eq!(restored.content, other.content);
+            assert_eq!(restored.folder_id, Some(folder.id.clone()));
+            assert_eq!(
+                load_note(&conn, &survivor.id).unwrap().unwrap().content,
+                survivor.content
+            );
+            assert_eq!(
+                load_note(&conn, &linking.id).unwrap().unwrap().content,
+                linking.content
+            );
+            assert!(properties::load_properties(&conn, &survivor.id)
+                .unwrap()
+                .is_empty());
+            assert_eq!(
+                properties::load_properties(&conn, &other.id).unwrap().len(),
+                1
+            );
+            assert_eq!(
+                reminders::note_reminders(&conn, &other.id).unwrap().len(),
+                1
+            );
+            let daily: String = conn
+                .query_row(
+                    "SELECT note_id FROM daily_notes WHERE date = '2025-01-02'",
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(daily, other.id);
+        }
+    }
+}
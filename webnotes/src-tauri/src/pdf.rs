@@ -0,0 +1,1529 @@
+use chrono::{DateTime, Datelike, Local};
+use comemo::Prehashed;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+use typst::diag::{FileError, FileResult};
+use typst::eval::Tracer;
+use typst::foundations::{Bytes, Datetime, Smart};
+use typst::model::Document;
+use typst::syntax::{FileId, Source, VirtualPath};
+use typst::text::{Font, FontBook};
+use typst::{Library, World};
+
+use crate::html::{decode_entities, scan_tags, Tag, VOID_ELEMENTS};
+use crate::publish::{attachment_file, local_source};
+use crate::Note;
+
+// =============================================================================
+// PDF EXPORT
+// =============================================================================
+//
+// A note is converted from editor HTML to Typst markup and typeset with the
+// typst library, using only the fonts bundled with typst-assets, so export
+// works offline and looks the same on every machine. Typst does the layout,
+// pagination and code highlighting; LaTeX math is translated to Typst math
+// by `latex_to_typst`.
+//
+// Attachments are handed to the compiler as virtual files under /images/.
+
+const MAIN_FILE: &str = "/main.typ";
+
+const PREAMBLE: &str = r#"#set text(size: 11pt)
+#set table(inset: 6pt, stroke: 0.5pt + luma(180))
+#show raw.where(block: true): block.with(fill: luma(246), inset: 8pt, radius: 4pt, width: 100%)
+#show quote.where(block: true): it => block(stroke: (left: 2pt + luma(200)), inset: (left: 10pt, y: 4pt), it.body)
+#show link: underline
+#let task(done) = box(width: 0.7em, height: 0.7em, baseline: 0.05em, stroke: 0.6pt, radius: 1pt, fill: if done { luma(110) } else { none })
+#let callout(body) = block(fill: luma(242), inset: 10pt, radius: 4pt, width: 100%, body)
+"#;
+
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfExportReport {
+    pub path: String,
+    pub pages: usize,
+    pub bytes: u64,
+    /// Image sources that could not be embedded and were replaced by their
+    /// alt text
+    pub missing_images: Vec<String>,
+    /// Formulas printed as LaTeX source because they could not be typeset
+    pub unrendered_math: Vec<String>,
+}
+
+/// Typeset a note and write it to `output`
+pub fn export_pdf(
+    note: &Note,
+    attachments_dir: &Path,
+    output: &Path,
+) -> Result<PdfExportReport, String> {
+    let mut report = PdfExportReport {
+        path: output.to_string_lossy().into_owned(),
+        ..Default::default()
+    };
+
+    let document = match typeset(note, attachments_dir, true, &mut report) {
+        Ok(document) => document,
+        Err(e) => {
+            // A damaged image or a formula Typst rejects should not block
+            // the export, so try again with both printed as text
+            log::warn!("Exporting note {} without images and math: {}", note.id, e);
+            report.missing_images.clear();
+            report.unrendered_math.clear();
+            typeset(note, attachments_dir, false, &mut report)?
+        }
+    };
+
+    let pdf = typst_pdf::pdf(&document, Smart::Auto, None);
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {:?}: {}", parent, e))?;
+    }
+    fs::write(output, &pdf).map_err(|e| format!("Failed to write {:?}: {}", output, e))?;
+
+    report.pages = document.pages.len();
+    report.bytes = pdf.len() as u64;
+    Ok(report)
+}
+
+fn typeset(
+    note: &Note,
+    attachments_dir: &Path,
+    rich: bool,
+    report: &mut PdfExportReport,
+) -> Result<Document, String> {
+    let mut converter = Converter::new(attachments_dir, rich, report);
+    let markup = converter.document(note);
+    let world = NoteWorld::new(markup, converter.files);
+
+    let mut tracer = Tracer::new();
+    typst::compile(&world, &mut tracer).map_err(|errors| {
+        let messages: Vec<String> = errors.iter().map(|e| e.message.to_string()).collect();
+        format!("Failed to typeset note: {}", messages.join("; "))
+    })
+}
+
+// =============================================================================
+// TYPST WORLD
+// =============================================================================
+
+/// Fonts bundled into the binary, parsed once
+fn fonts() -> &'static (Prehashed<FontBook>, Vec<Font>) {
+    static FONTS: OnceLock<(Prehashed<FontBook>, Vec<Font>)> = OnceLock::new();
+    FONTS.get_or_init(|| {
+        let fonts: Vec<Font> = typst_assets::fonts()
+            .flat_map(|data| Font::iter(Bytes::from_static(data)))
+            .collect();
+        (Prehashed::new(FontBook::from_fonts(&fonts)), fonts)
+    })
+}
+
+struct NoteWorld {
+    library: Prehashed<Library>,
+    main: Source,
+    files: HashMap<FileId, Bytes>,
+}
+
+impl NoteWorld {
+    fn new(markup: String, files: HashMap<FileId, Bytes>) -> Self {
+        Self {
+            library: Prehashed::new(Library::default()),
+            main: Source::new(FileId::new(None, VirtualPath::new(MAIN_FILE)), markup),
+            files,
+        }
+    }
+}
+
+impl World for NoteWorld {
+    fn library(&self) -> &Prehashed<Library> {
+        &self.library
+    }
+
+    fn book(&self) -> &Prehashed<FontBook> {
+        &fonts().0
+    }
+
+    fn main(&self) -> Source {
+        self.main.clone()
+    }
+
+    fn source(&self, id: FileId) -> FileResult<Source> {
+        if id == self.main.id() {
+            Ok(self.main.clone())
+        } else {
+            Err(FileError::NotFound(id.vpath().as_rootless_path().into()))
+        }
+    }
+
+    fn file(&self, id: FileId) -> FileResult<Bytes> {
+        self.files
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| FileError::NotFound(id.vpath().as_rootless_path().into()))
+    }
+
+    fn font(&self, index: usize) -> Option<Font> {
+        fonts().1.get(index).cloned()
+    }
+
+    fn today(&self, _offset: Option<i64>) -> Option<Datetime> {
+        let today = Local::now().date_naive();
+        Datetime::from_ymd(today.year(), today.month() as u8, today.day() as u8)
+    }
+}
+
+// =============================================================================
+// HTML TO TYPST MARKUP
+// =============================================================================
+
+/// How the content of an open element is written
+enum FrameKind {
+    /// Typst markup; text is written out
+    Markup,
+    /// Arguments of a Typst call (`#list(`, a table row); only the expected
+    /// child elements are allowed and loose text is dropped
+    Args,
+    /// `#table(`, whose column count is inserted at `columns_at` once every
+    /// row has been seen
+    Table {
+        columns_at: usize,
+        columns: usize,
+        cells: usize,
+    },
+}
+
+struct Frame {
+    name: String,
+    kind: FrameKind,
+    close: String,
+}
+
+/// Code being collected verbatim until its closing tag
+struct RawText {
+    end: &'static str,
+    block: bool,
+    lang: Option<String>,
+    text: String,
+}
+
+struct Converter<'a> {
+    attachments_dir: &'a Path,
+    /// Typeset math and images; off when retrying after a failed compile
+    rich: bool,
+    report: &'a mut PdfExportReport,
+    out: String,
+    stack: Vec<Frame>,
+    /// Element skipped with everything inside it, and how deeply it nests
+    skip: Option<(String, usize)>,
+    raw: Option<RawText>,
+    files: HashMap<FileId, Bytes>,
+    /// Source → virtual path of images already added
+    images: HashMap<String, String>,
+    /// A block just ended; the paragraph break is only written if more
+    /// content follows in the same container, which keeps lists tight
+    pending_break: bool,
+}
+
+impl<'a> Converter<'a> {
+    fn new(attachments_dir: &'a Path, rich: bool, report: &'a mut PdfExportReport) -> Self {
+        Self {
+            attachments_dir,
+            rich,
+            report,
+            out: String::new(),
+            stack: Vec::new(),
+            skip: None,
+            raw: None,
+            files: HashMap::new(),
+            images: HashMap::new(),
+            pending_break: false,
+        }
+    }
+
+    fn document(&mut self, note: &Note) -> String {
+        let title = display_title(note);
+        let date = display_date(&note.updated_at);
+
+        self.out.push_str(&format!(
+            "#set document(title: {})\n",
+            string_literal(title)
+        ));
+        self.out.push_str(PREAMBLE);
+        self.out.push_str(&format!(
+            "#set page(paper: \"a4\", margin: (x: 2.2cm, y: 2.5cm), numbering: \"1\", \
+             header: text(size: 9pt, fill: luma(110))[{} #h(1fr) {}])\n",
+            escape_markup(title),
+            escape_markup(&date)
+        ));
+        self.out.push_str(&format!(
+            "#block(below: 1.4em, text(size: 22pt, weight: \"bold\")[{}])\n",
+            escape_markup(title)
+        ));
+
+        self.content(&note.content);
+        std::mem::take(&mut self.out)
+    }
+
+    fn content(&mut self, html: &str) {
+        let mut last = 0;
+
+        for tag in scan_tags(html) {
+            self.text(&html[last..tag.span.start]);
+            last = tag.span.end;
+
+            if let Some((name, depth)) = &mut self.skip {
+                if tag.name == *name && !VOID_ELEMENTS.contains(&tag.name.as_str()) {
+                    if !tag.closing {
+                        *depth += 1;
+                    } else if *depth == 0 {
+                        self.skip = None;
+                    } else {
+                        *depth -= 1;
+                    }
+                }
+                continue;
+            }
+
+            if self.raw.is_some() {
+                self.raw_tag(&tag);
+            } else if tag.closing {
+                self.close(&tag.name);
+            } else {
+                self.open(&tag, html[tag.span.clone()].ends_with("/>"));
+            }
+        }
+
+        self.text(&html[last..]);
+        if let Some(raw) = self.raw.take() {
+            self.write_raw(raw);
+        }
+        while let Some(frame) = self.stack.pop() {
+            self.finish(frame);
+        }
+    }
+
+    fn write(&mut self, markup: &str) {
+        if self.pending_break {
+            self.out.push_str("\n\n");
+            self.pending_break = false;
+        }
+        self.out.push_str(markup);
+    }
+
+    /// Write a block-level element that has no frame of its own
+    fn write_block(&mut self, markup: &str) {
+        self.write(markup);
+        self.pending_break = true;
+    }
+
+    fn in_markup(&self) -> bool {
+        self.stack
+            .last()
+            .map_or(true, |frame| matches!(frame.kind, FrameKind::Markup))
+    }
+
+    fn parent_name(&self) -> Option<&str> {
+        self.stack.last().map(|frame| frame.name.as_str())
+    }
+
+    fn text(&mut self, text: &str) {
+        if text.is_empty() || self.skip.is_some() {
+            return;
+        }
+        if let Some(raw) = &mut self.raw {
+            raw.text.push_str(&decode_entities(text));
+            return;
+        }
+        if !self.in_markup() {
+            return;
+        }
+
+        let decoded = decode_entities(text);
+        let mut collapsed = String::with_capacity(decoded.len());
+        for c in decoded.chars() {
+            if c.is_whitespace() && c != '\u{a0}' {
+                if !collapsed.ends_with(' ') {
+                    collapsed.push(' ');
+                }
+            } else {
+                collapsed.push(c);
+            }
+        }
+        if collapsed == " " && (self.pending_break || self.out.ends_with('\n')) {
+            return;
+        }
+        self.write(&escape_markup(&collapsed));
+    }
+
+    fn open(&mut self, tag: &Tag, self_closing: bool) {
+        let name = tag.name.as_str();
+
+        if !self.in_markup() {
+            let expected = match self.parent_name() {
+                Some("ul" | "ol") => name == "li",
+                Some("table") => matches!(name, "thead" | "tbody" | "tfoot" | "tr"),
+                Some("thead" | "tbody" | "tfoot") => name == "tr",
+                Some("tr") => matches!(name, "td" | "th"),
+                _ => false,
+            };
+            if !expected {
+                self.skip_element(tag, self_closing);
+                return;
+            }
+        }
+
+        let math = match tag.attr("data-type") {
+            Some("math-inline") => Some(false),
+            Some("math-block") => Some(true),
+            _ => None,
+        };
+        if let Some(block) = math {
+            self.math(tag.attr("data-latex").unwrap_or_default(), block);
+            self.skip_element(tag, self_closing);
+            return;
+        }
+
+        match name {
+            "br" => self.write("#linebreak()"),
+            "hr" => self.write_block("#line(length: 100%)"),
+            "img" => self.image(tag),
+            "pre" => {
+                self.raw = Some(RawText {
+                    end: "pre",
+                    block: true,
+                    lang: code_language(tag),
+                    text: String::new(),
+                });
+            }
+            "code" => {
+                self.raw = Some(RawText {
+                    end: "code",
+                    block: false,
+                    lang: None,
+                    text: String::new(),
+                });
+            }
+            "script" | "style" | "input" | "colgroup" => self.skip_element(tag, self_closing),
+            _ if VOID_ELEMENTS.contains(&name) || self_closing => {}
+            _ => {
+                if matches!(name, "ul" | "ol") && self.parent_name() == Some("li") {
+                    // A sublist directly after an item's text, not a new
+                    // paragraph, or the list stops being tight
+                    self.pending_break = false;
+                }
+                let (kind, open, close) = self.element(tag);
+                self.write(&open);
+                self.stack.push(Frame {
+                    name: tag.name.clone(),
+                    kind,
+                    close,
+                });
+            }
+        }
+    }
+
+    /// How to write an element that has content
+    fn element(&mut self, tag: &Tag) -> (FrameKind, String, String) {
+        let markup = |open: &str, close: &str| (FrameKind::Markup, open.into(), close.into());
+        let args = |open: &str, close: &str| (FrameKind::Args, open.into(), close.into());
+
+        match tag.name.as_str() {
+            "p" => markup("", ""),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                markup(&format!("#heading(level: {})[", &tag.name[1..]), "]")
+            }
+            "strong" | "b" => markup("#strong[", "]"),
+            "em" | "i" => markup("#emph[", "]"),
+            "u" => markup("#underline[", "]"),
+            "s" | "del" | "strike" => markup("#strike[", "]"),
+            "mark" => markup("#highlight[", "]"),
+            "sub" => markup("#sub[", "]"),
+            "sup" => markup("#super[", "]"),
+            "blockquote" => markup("#quote(block: true)[", "]"),
+            "div" if tag.attr("data-type") == Some("callout") => markup("#callout[", "]"),
+            "a" => match tag.attr("href").filter(|href| is_external_link(href)) {
+                Some(href) => markup(&format!("#link({})[", string_literal(href)), "]"),
+                None => markup("", ""),
+            },
+            "ul" if tag.attr("data-type") == Some("taskList") => {
+                args("#list(tight: true, marker: none, ", ")")
+            }
+            "ul" => args("#list(tight: true, ", ")"),
+            "ol" => {
+                let start = tag
+                    .attr("start")
+                    .and_then(|s| s.trim().parse::<u64>().ok())
+                    .unwrap_or(1);
+                args(&format!("#enum(tight: true, start: {}, ", start), ")")
+            }
+            "li" => match tag.attr("data-checked") {
+                Some(checked) => markup(&format!("[#task({}) ", checked == "true"), "], "),
+                None => markup("[", "], "),
+            },
+            "table" => {
+                self.write("#table(columns: ");
+                let columns_at = self.out.len();
+                (
+                    FrameKind::Table {
+                        columns_at,
+                        columns: 0,
+                        cells: 0,
+                    },
+                    ", ".into(),
+                    ")".into(),
+                )
+            }
+            "thead" | "tbody" | "tfoot" => args("", ""),
+            "tr" => {
+                if let Some((_, cells)) = self.table() {
+                    *cells = 0;
+                }
+                args("", "")
+            }
+            "td" | "th" => {
+                let span = tag
+                    .attr("colspan")
+                    .and_then(|s| s.trim().parse::<usize>().ok())
+                    .filter(|&n| n > 1);
+                if let Some((_, cells)) = self.table() {
+                    *cells += span.unwrap_or(1);
+                }
+                let cell = match span {
+                    Some(n) => format!("table.cell(colspan: {})[", n),
+                    None => "[".to_string(),
+                };
+                if tag.name == "th" {
+                    markup(&format!("{}#strong[", cell), "]], ")
+                } else {
+                    markup(&cell, "], ")
+                }
+            }
+            // Spans, labels, note links and wrappers keep only their content
+            _ => markup("", ""),
+        }
+    }
+
+    /// Column count and current row width of the innermost table
+    fn table(&mut self) -> Option<(&mut usize, &mut usize)> {
+        self.stack
+            .iter_mut()
+            .rev()
+            .find_map(|frame| match &mut frame.kind {
+                FrameKind::Table { columns, cells, .. } => Some((columns, cells)),
+                _ => None,
+            })
+    }
+
+    fn close(&mut self, name: &str) {
+        let Some(index) = self.stack.iter().rposition(|frame| frame.name == name) else {
+            return;
+        };
+        while self.stack.len() > index {
+            if let Some(frame) = self.stack.pop() {
+                self.finish(frame);
+            }
+        }
+    }
+
+    fn finish(&mut self, frame: Frame) {
+        if frame.name == "tr" {
+            if let Some((columns, cells)) = self.table() {
+                *columns = (*columns).max(*cells);
+            }
+        }
+        if let FrameKind::Table {
+            columns_at,
+            columns,
+            cells,
+        } = frame.kind
+        {
+            let count = columns.max(cells).max(1);
+            self.out.insert_str(columns_at, &count.to_string());
+        }
+        self.pending_break = false;
+        self.out.push_str(&frame.close);
+        self.pending_break = matches!(
+            frame.name.as_str(),
+            "p" | "h1"
+                | "h2"
+                | "h3"
+                | "h4"
+                | "h5"
+                | "h6"
+                | "blockquote"
+                | "div"
+                | "ul"
+                | "ol"
+                | "table"
+        );
+    }
+
+    fn skip_element(&mut self, tag: &Tag, self_closing: bool) {
+        if !self_closing && !VOID_ELEMENTS.contains(&tag.name.as_str()) {
+            self.skip = Some((tag.name.clone(), 0));
+        }
+    }
+
+    fn raw_tag(&mut self, tag: &Tag) {
+        let Some(raw) = &mut self.raw else {
+            return;
+        };
+        if tag.closing && tag.name == raw.end {
+            if let Some(raw) = self.raw.take() {
+                self.write_raw(raw);
+            }
+        } else if !tag.closing && tag.name == "code" && raw.lang.is_none() {
+            raw.lang = code_language(tag);
+        } else if !tag.closing && tag.name == "br" {
+            raw.text.push('\n');
+        }
+    }
+
+    fn write_raw(&mut self, raw: RawText) {
+        if !raw.block {
+            self.write(&format!("#raw({})", string_literal(&raw.text)));
+            return;
+        }
+
+        let text = raw.text.trim_end_matches('\n');
+        let lang = raw
+            .lang
+            .filter(|lang| lang != "text" && lang != "null")
+            .map(|lang| format!("lang: {}, ", string_literal(&lang)))
+            .unwrap_or_default();
+        self.write_block(&format!(
+            "#raw(block: true, {}{})",
+            lang,
+            string_literal(text)
+        ));
+    }
+
+    fn math(&mut self, latex: &str, block: bool) {
+        let latex = latex.trim();
+        if latex.is_empty() {
+            return;
+        }
+
+        let typst = if self.rich {
+            latex_to_typst(latex)
+        } else {
+            None
+        };
+        match (typst, block) {
+            (Some(math), true) => self.write_block(&format!("$ {} $", math)),
+            (Some(math), false) => self.write(&format!("${}$", math)),
+            (None, block) => {
+                self.report.unrendered_math.push(latex.to_string());
+                let code = format!("raw({})", string_literal(latex));
+                if block {
+                    self.write_block(&format!("#align(center, {})", code));
+                } else {
+                    self.write(&format!("#{}", code));
+                }
+            }
+        }
+    }
+
+    fn image(&mut self, tag: &Tag) {
+        let src = tag.attr("src").unwrap_or_default();
+        let path = match self.images.get(src) {
+            Some(path) => Some(path.clone()),
+            None if self.rich => self.load_image(src),
+            None => None,
+        };
+
+        let Some(path) = path else {
+            self.report.missing_images.push(src.to_string());
+            let alt = tag.attr("alt").map(str::trim).filter(|a| !a.is_empty());
+            let label = format!("[image: {}]", alt.unwrap_or("missing"));
+            self.write(&format!("#emph[{}]", escape_markup(&label)));
+            return;
+        };
+
+        // Editor widths are CSS pixels; images wider than the page are
+        // scaled down by Typst
+        let width = tag
+            .attr("width")
+            .and_then(|w| w.trim().trim_end_matches("px").parse::<f64>().ok())
+            .filter(|w| *w > 0.0)
+            .map(|w| format!(", width: {}pt", w * 0.75))
+            .unwrap_or_default();
+        self.write(&format!("#image({}{})", string_literal(&path), width));
+    }
+
+    /// Add a local image as a virtual file and return its path, or `None`
+    /// if it is remote, missing, outside the attachments directory or in a
+    /// format Typst cannot embed
+    fn load_image(&mut self, src: &str) -> Option<String> {
+        let file = local_source(src, self.attachments_dir)?;
+        let file = attachment_file(&file, self.attachments_dir)?;
+        let data = fs::read(&file)
+            .map_err(|e| log::debug!("Cannot embed image {:?}: {}", file, e))
+            .ok()?;
+        let extension = image_extension(&data, &file)?;
+
+        let path = format!("/images/{}.{}", self.images.len() + 1, extension);
+        self.files.insert(
+            FileId::new(None, VirtualPath::new(&path)),
+            Bytes::from(data),
+        );
+        self.images.insert(src.to_string(), path.clone());
+        Some(path)
+    }
+}
+
+/// Extension Typst needs to decode an image, from its content
+fn image_extension(data: &[u8], path: &Path) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG") {
+        Some("png")
+    } else if data.starts_with(&[0xff, 0xd8, 0xff]) {
+        Some("jpg")
+    } else if data.starts_with(b"GIF8") {
+        Some("gif")
+    } else if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+    {
+        Some("svg")
+    } else {
+        None
+    }
+}
+
+fn code_language(tag: &Tag) -> Option<String> {
+    tag.attr("class")?
+        .split_whitespace()
+        .find_map(|class| class.strip_prefix("language-"))
+        .filter(|lang| !lang.is_empty())
+        .map(str::to_string)
+}
+
+fn is_external_link(href: &str) -> bool {
+    let href = href.trim().to_ascii_lowercase();
+    href.starts_with("http://") || href.starts_with("https://") || href.starts_with("mailto:")
+}
+
+fn display_title(note: &Note) -> &str {
+    let title = note.title.trim();
+    if title.is_empty() {
+        "Untitled"
+    } else {
+        title
+    }
+}
+
+/// Last-edited date for page headers, e.g. "March 4, 2025"
+fn display_date(timestamp: &str) -> String {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|date| date.with_timezone(&Local).format("%B %-d, %Y").to_string())
+        .unwrap_or_else(|_| timestamp.get(..10).unwrap_or(timestamp).to_string())
+}
+
+/// Escape text so Typst prints it as-is. Every markup character is escaped,
+/// including ones that only matter at the start of a line or right after an
+/// embedded expression.
+fn escape_markup(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '/'
+                | '*'
+                | '_'
+                | '`'
+                | '$'
+                | '#'
+                | '<'
+                | '>'
+                | '@'
+                | '['
+                | ']'
+                | '('
+                | '='
+                | '-'
+                | '+'
+                | '~'
+                | '.'
+        ) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn string_literal(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// =============================================================================
+// LATEX TO TYPST MATH
+// =============================================================================
+//
+// Covers the LaTeX people write in notes: fractions, roots, scripts, Greek
+// letters and common symbols, accents, font styles, \left/\right, matrices,
+// cases and aligned environments. Anything else makes the whole formula
+// fall back to its LaTeX source (`None`) rather than typeset wrongly.
+
+/// LaTeX commands with a direct Typst equivalent
+const MATH_SYMBOLS: &[(&str, &str)] = &[
+    ("alpha", "alpha"),
+    ("beta", "beta"),
+    ("gamma", "gamma"),
+    ("delta", "delta"),
+    ("epsilon", "epsilon.alt"),
+    ("varepsilon", "epsilon"),
+    ("zeta", "zeta"),
+    ("eta", "eta"),
+    ("theta", "theta"),
+    ("vartheta", "theta.alt"),
+    ("iota", "iota"),
+    ("kappa", "kappa"),
+    ("lambda", "lambda"),
+    ("mu", "mu"),
+    ("nu", "nu"),
+    ("xi", "xi"),
+    ("pi", "pi"),
+    ("varpi", "pi.alt"),
+    ("rho", "rho"),
+    ("varrho", "rho.alt"),
+    ("sigma", "sigma"),
+    ("varsigma", "sigma.alt"),
+    ("tau", "tau"),
+    ("upsilon", "upsilon"),
+    ("phi", "phi.alt"),
+    ("varphi", "phi"),
+    ("chi", "chi"),
+    ("psi", "psi"),
+    ("omega", "omega"),
+    ("Gamma", "Gamma"),
+    ("Delta", "Delta"),
+    ("Theta", "Theta"),
+    ("Lambda", "Lambda"),
+    ("Xi", "Xi"),
+    ("Pi", "Pi"),
+    ("Sigma", "Sigma"),
+    ("Upsilon", "Upsilon"),
+    ("Phi", "Phi"),
+    ("Psi", "Psi"),
+    ("Omega", "Omega"),
+    ("infty", "infinity"),
+    ("partial", "diff"),
+    ("nabla", "nabla"),
+    ("hbar", "planck.reduce"),
+    ("ell", "ell"),
+    ("emptyset", "nothing"),
+    ("varnothing", "nothing"),
+    ("forall", "forall"),
+    ("exists", "exists"),
+    ("nexists", "exists.not"),
+    ("neg", "not"),
+    ("lnot", "not"),
+    ("land", "and"),
+    ("wedge", "and"),
+    ("lor", "or"),
+    ("vee", "or"),
+    ("cdot", "dot.op"),
+    ("times", "times"),
+    ("div", "div"),
+    ("pm", "plus.minus"),
+    ("mp", "minus.plus"),
+    ("ast", "ast"),
+    ("star", "star"),
+    ("circ", "compose"),
+    ("bullet", "bullet"),
+    ("oplus", "plus.circle"),
+    ("otimes", "times.circle"),
+    ("leq", "lt.eq"),
+    ("le", "lt.eq"),
+    ("geq", "gt.eq"),
+    ("ge", "gt.eq"),
+    ("neq", "eq.not"),
+    ("ne", "eq.not"),
+    ("ll", "lt.double"),
+    ("gg", "gt.double"),
+    ("approx", "approx"),
+    ("equiv", "equiv"),
+    ("sim", "tilde.op"),
+    ("simeq", "tilde.eq"),
+    ("cong", "tilde.equiv"),
+    ("propto", "prop"),
+    ("perp", "perp"),
+    ("parallel", "parallel"),
+    ("mid", "divides"),
+    ("in", "in"),
+    ("notin", "in.not"),
+    ("ni", "in.rev"),
+    ("subset", "subset"),
+    ("subseteq", "subset.eq"),
+    ("supset", "supset"),
+    ("supseteq", "supset.eq"),
+    ("cup", "union"),
+    ("cap", "sect"),
+    ("bigcup", "union.big"),
+    ("bigcap", "sect.big"),
+    ("setminus", "without"),
+    ("to", "arrow.r"),
+    ("rightarrow", "arrow.r"),
+    ("leftarrow", "arrow.l"),
+    ("gets", "arrow.l"),
+    ("leftrightarrow", "arrow.l.r"),
+    ("Rightarrow", "arrow.r.double"),
+    ("Leftarrow", "arrow.l.double"),
+    ("Leftrightarrow", "arrow.l.r.double"),
+    ("implies", "arrow.r.double.long"),
+    ("iff", "arrow.l.r.double.long"),
+    ("mapsto", "arrow.r.bar"),
+    ("uparrow", "arrow.t"),
+    ("downarrow", "arrow.b"),
+    ("longrightarrow", "arrow.r.long"),
+    ("sum", "sum"),
+    ("prod", "product"),
+    ("coprod", "product.co"),
+    ("int", "integral"),
+    ("iint", "integral.double"),
+    ("iiint", "integral.triple"),
+    ("oint", "integral.cont"),
+    ("angle", "angle"),
+    ("langle", "angle.l"),
+    ("rangle", "angle.r"),
+    ("lfloor", "⌊"),
+    ("rfloor", "⌋"),
+    ("lceil", "⌈"),
+    ("rceil", "⌉"),
+    ("vert", "bar.v"),
+    ("Vert", "bar.v.double"),
+    ("|", "bar.v.double"),
+    ("prime", "prime"),
+    ("degree", "degree"),
+    ("dots", "dots.h"),
+    ("ldots", "dots.h"),
+    ("cdots", "dots.c"),
+    ("vdots", "dots.v"),
+    ("ddots", "dots.down"),
+    ("therefore", "therefore"),
+    ("because", "because"),
+    ("top", "top"),
+    ("bot", "bot"),
+    ("aleph", "aleph"),
+    ("Re", "Re"),
+    ("Im", "Im"),
+    ("quad", "quad"),
+    ("qquad", "wide"),
+    (",", "thin"),
+    (":", "med"),
+    (">", "med"),
+    (";", "thick"),
+    (" ", "thin"),
+    ("{", "\\{"),
+    ("}", "\\}"),
+    ("%", "%"),
+    ("$", "\\$"),
+    ("#", "\\#"),
+    ("&", "\\&"),
+    ("_", "\\_"),
+    ("bmod", "mod"),
+    ("mod", "mod"),
+];
+
+/// Operator names Typst typesets upright, like LaTeX's `\sin`
+const MATH_OPERATORS: &[&str] = &[
+    "arccos", "arcsin", "arctan", "arg", "cos", "cosh", "cot", "coth", "csc", "deg", "det", "dim",
+    "exp", "gcd", "inf", "ker", "lg", "lim", "liminf", "limsup", "ln", "log", "max", "min", "Pr",
+    "sec", "sin", "sinh", "sup", "tan", "tanh",
+];
+
+/// Commands taking one argument, and the Typst function they become
+const MATH_FUNCTIONS: &[(&str, &str)] = &[
+    ("sqrt", "sqrt"),
+    ("mathbf", "bold"),
+    ("boldsymbol", "bold"),
+    ("bm", "bold"),
+    ("mathit", "italic"),
+    ("mathrm", "upright"),
+    ("mathbb", "bb"),
+    ("mathcal", "cal"),
+    ("mathfrak", "frak"),
+    ("mathsf", "sans"),
+    ("mathtt", "mono"),
+    ("hat", "hat"),
+    ("widehat", "hat"),
+    ("tilde", "tilde"),
+    ("widetilde", "tilde"),
+    ("bar", "macron"),
+    ("overline", "overline"),
+    ("underline", "underline"),
+    ("vec", "arrow"),
+    ("overrightarrow", "arrow"),
+    ("dot", "dot"),
+    ("ddot", "dot.double"),
+    ("acute", "acute"),
+    ("grave", "grave"),
+    ("breve", "breve"),
+    ("check", "caron"),
+    ("overbrace", "overbrace"),
+    ("underbrace", "underbrace"),
+    ("cancel", "cancel"),
+];
+
+/// Commands that only change sizing or spacing Typst decides itself
+const MATH_IGNORED: &[&str] = &[
+    "displaystyle",
+    "textstyle",
+    "scriptstyle",
+    "limits",
+    "nolimits",
+    "big",
+    "Big",
+    "bigg",
+    "Bigg",
+    "bigl",
+    "bigr",
+    "Bigl",
+    "Bigr",
+    "biggl",
+    "biggr",
+    "Biggl",
+    "Biggr",
+    "!",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LatexToken<'a> {
+    Command(&'a str),
+    Open,
+    Close,
+    Sup,
+    Sub,
+    Align,
+    Newline,
+    /// Only significant inside `\text{...}`
+    Space,
+    Char(char),
+}
+
+fn tokenize_latex(latex: &str) -> Vec<LatexToken<'_>> {
+    let mut tokens = Vec::new();
+    let mut chars = latex.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let token = match c {
+            '\\' => match chars.next() {
+                Some((_, '\\')) => LatexToken::Newline,
+                Some((start, next)) if next.is_ascii_alphabetic() => {
+                    let mut end = start + 1;
+                    while let Some(&(j, c)) = chars.peek() {
+                        if !c.is_ascii_alphabetic() {
+                            break;
+                        }
+                        end = j + 1;
+                        chars.next();
+                    }
+                    LatexToken::Command(&latex[start..end])
+                }
+                Some((start, next)) => LatexToken::Command(&latex[start..start + next.len_utf8()]),
+                None => LatexToken::Char('\\'),
+            },
+            '{' => LatexToken::Open,
+            '}' => LatexToken::Close,
+            '^' => LatexToken::Sup,
+            '_' => LatexToken::Sub,
+            '&' => LatexToken::Align,
+            '~' => LatexToken::Command(" "),
+            c if c.is_whitespace() => LatexToken::Space,
+            _ => LatexToken::Char(latex[i..].chars().next().unwrap_or(c)),
+        };
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+/// How a run of math ended
+#[derive(Debug, PartialEq)]
+enum LatexEnd {
+    Eof,
+    Close,
+    Align,
+    Newline,
+    End,
+    Bracket,
+}
+
+struct LatexParser<'a> {
+    tokens: Vec<LatexToken<'a>>,
+    pos: usize,
+}
+
+/// Translate a LaTeX formula to Typst math, or `None` if it uses anything
+/// this translator does not know
+pub fn latex_to_typst(latex: &str) -> Option<String> {
+    let mut parser = LatexParser {
+        tokens: tokenize_latex(latex),
+        pos: 0,
+    };
+
+    // Top-level `&` and `\\` work as in an aligned environment
+    let mut rows = vec![String::new()];
+    loop {
+        let (part, end) = parser.sequence(false)?;
+        let row = rows.last_mut()?;
+        row.push_str(&part);
+        match end {
+            LatexEnd::Eof => break,
+            LatexEnd::Align => row.push_str(" & "),
+            LatexEnd::Newline => rows.push(String::new()),
+            _ => return None,
+        }
+    }
+
+    Some(rows.join(" \\ ").trim().to_string())
+}
+
+impl<'a> LatexParser<'a> {
+    /// Next token, skipping whitespace
+    fn next(&mut self) -> Option<LatexToken<'a>> {
+        loop {
+            let token = self.next_raw()?;
+            if token != LatexToken::Space {
+                return Some(token);
+            }
+        }
+    }
+
+    fn next_raw(&mut self) -> Option<LatexToken<'a>> {
+        let token = self.tokens.get(self.pos).copied();
+        self.pos += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<LatexToken<'a>> {
+        self.tokens[self.pos.min(self.tokens.len())..]
+            .iter()
+            .copied()
+            .find(|token| *token != LatexToken::Space)
+    }
+
+    /// Atoms up to the end of the current group, cell or row, joined by
+    /// spaces so Typst never reads neighbouring letters as one identifier
+    fn sequence(&mut self, in_bracket: bool) -> Option<(String, LatexEnd)> {
+        let mut atoms: Vec<String> = Vec::new();
+
+        let end = loop {
+            let Some(token) = self.next() else {
+                break LatexEnd::Eof;
+            };
+            match token {
+                LatexToken::Close => break LatexEnd::Close,
+                LatexToken::Align => break LatexEnd::Align,
+                LatexToken::Newline => break LatexEnd::Newline,
+                LatexToken::Char(']') if in_bracket => break LatexEnd::Bracket,
+                LatexToken::Command("end") => {
+                    self.group_text()?;
+                    break LatexEnd::End;
+                }
+                LatexToken::Sup | LatexToken::Sub => {
+                    let argument = self.argument()?;
+                    let base = atoms.pop().unwrap_or_else(|| "\"\"".to_string());
+                    let mark = if token == LatexToken::Sup { '^' } else { '_' };
+                    atoms.push(format!("{}{}({})", base, mark, argument));
+                }
+                LatexToken::Char('\'') => match atoms.last_mut() {
+                    Some(last) => last.push('\''),
+                    None => atoms.push("prime".to_string()),
+                },
+                LatexToken::Char(c) if c.is_ascii_digit() || c == '.' => {
+                    let extends_number = c.is_ascii_digit()
+                        && atoms.last().is_some_and(|last| {
+                            last.chars().all(|d| d.is_ascii_digit() || d == '.') && !last.is_empty()
+                        });
+                    match atoms.last_mut() {
+                        Some(last) if extends_number => last.push(c),
+                        _ => atoms.push(c.to_string()),
+                    }
+                }
+                token => {
+                    let atom = self.atom(token)?;
+                    if !atom.is_empty() {
+                        atoms.push(atom);
+                    }
+                }
+            }
+        };
+
+        Some((atoms.join(" "), end))
+    }
+
+    /// One token's translation; groups and commands consume their arguments
+    fn atom(&mut self, token: LatexToken<'a>) -> Option<String> {
+        match token {
+            LatexToken::Open => {
+                let (inner, end) = self.sequence(false)?;
+                (end == LatexEnd::Close).then_some(inner)
+            }
+            LatexToken::Char(c) => Some(math_char(c)),
+            LatexToken::Command(name) => self.command(name),
+            _ => None,
+        }
+    }
+
+    /// The next group or single token, as the argument of a command or script
+    fn argument(&mut self) -> Option<String> {
+        match self.next()? {
+            LatexToken::Open => {
+                let (inner, end) = self.sequence(false)?;
+                (end == LatexEnd::Close).then_some(inner)
+            }
+            LatexToken::Command(name) if MATH_IGNORED.contains(&name) => self.argument(),
+            token @ (LatexToken::Char(_) | LatexToken::Command(_)) => self.atom(token),
+            _ => None,
+        }
+    }
+
+    /// Raw text of a `{...}` group, for names and text. Inner commands are
+    /// kept as written.
+    fn group_text(&mut self) -> Option<String> {
+        if self.next()? != LatexToken::Open {
+            return None;
+        }
+        let mut text = String::new();
+        let mut depth = 0usize;
+        loop {
+            match self.next_raw()? {
+                LatexToken::Close if depth == 0 => return Some(text),
+                LatexToken::Close => {
+                    depth -= 1;
+                    text.push('}');
+                }
+                LatexToken::Open => {
+                    depth += 1;
+                    text.push('{');
+                }
+                LatexToken::Char(c) => text.push(c),
+                LatexToken::Space | LatexToken::Command(" ") => {
+                    if !text.ends_with(' ') {
+                        text.push(' ');
+                    }
+                }
+                LatexToken::Command(name) => {
+                    text.push('\\');
+                    text.push_str(name);
+                }
+                LatexToken::Sup => text.push('^'),
+                LatexToken::Sub => text.push('_'),
+                LatexToken::Align => text.push('&'),
+                LatexToken::Newline => text.push_str("\\\\"),
+            }
+        }
+    }
+
+    fn command(&mut self, name: &'a str) -> Option<String> {
+        if let Some((_, typst)) = MATH_SYMBOLS.iter().find(|(latex, _)| *latex == name) {
+            return Some(typst.to_string());
+        }
+        if MATH_OPERATORS.contains(&name) {
+            return Some(name.to_string());
+        }
+        if let Some((_, function)) = MATH_FUNCTIONS.iter().find(|(latex, _)| *latex == name) {
+            if name == "sqrt" && self.peek() == Some(LatexToken::Char('[')) {
+                self.next();
+                let (index, end) = self.sequence(true)?;
+                if end != LatexEnd::Bracket {
+                    return None;
+                }
+                return Some(format!("root({}, {})", index, self.argument()?));
+            }
+            return Some(format!("{}({})", function, self.argument()?));
+        }
+        if name == "left" || name == "right" || name == "middle" {
+            return match self.next()? {
+                LatexToken::Char('.') => Some(String::new()),
+                token => self.atom(token),
+            };
+        }
+        if MATH_IGNORED.contains(&name) {
+            return Some(String::new());
+        }
+
+        match name {
+            "frac" | "dfrac" | "tfrac" | "cfrac" => {
+                let numerator = self.argument()?;
+                Some(format!("frac({}, {})", numerator, self.argument()?))
+            }
+            "binom" | "dbinom" | "tbinom" => {
+                let n = self.argument()?;
+                Some(format!("binom({}, {})", n, self.argument()?))
+            }
+            "text" | "textrm" | "textnormal" | "mbox" | "textit" | "textbf" => {
+                let text = string_literal(&self.group_text()?);
+                Some(match name {
+                    "textbf" => format!("bold(upright({}))", text),
+                    "textit" => format!("italic({})", text),
+                    _ => text,
+                })
+            }
+            "operatorname" => Some(format!("op({})", string_literal(&self.group_text()?))),
+            "overset" | "stackrel" => {
+                let over = self.argument()?;
+                Some(format!("limits({})^({})", self.argument()?, over))
+            }
+            "underset" => {
+                let under = self.argument()?;
+                Some(format!("limits({})_({})", self.argument()?, under))
+            }
+            "not" => Some(format!("cancel({})", self.argument()?)),
+            "pmod" => Some(format!("(mod {})", self.argument()?)),
+            "color" => {
+                self.group_text()?;
+                Some(String::new())
+            }
+            "textcolor" => {
+                self.group_text()?;
+                self.argument()
+            }
+            "begin" => self.environment(),
+            _ => None,
+        }
+    }
+
+    fn environment(&mut self) -> Option<String> {
+        let name = self.group_text()?;
+        let delim = match name.as_str() {
+            "matrix" | "smallmatrix" | "array" => Some("#none"),
+            "pmatrix" => Some("\"(\""),
+            "bmatrix" => Some("\"[\""),
+            "Bmatrix" => Some("\"{\""),
+            "vmatrix" => Some("\"|\""),
+            "Vmatrix" => Some("\"||\""),
+            _ => None,
+        };
+        if name == "array" {
+            // Column spec
+            self.group_text()?;
+        }
+
+        let mut rows: Vec<Vec<String>> = vec![Vec::new()];
+        loop {
+            let (cell, end) = self.sequence(false)?;
+            if let Some(row) = rows.last_mut() {
+                row.push(cell);
+            }
+            match end {
+                LatexEnd::Align => {}
+                LatexEnd::Newline => rows.push(Vec::new()),
+                LatexEnd::End => break,
+                _ => return None,
+            }
+        }
+        // A trailing \\ leaves an empty last row
+        if rows.len() > 1
+            && rows
+                .last()
+                .is_some_and(|row| row.iter().all(String::is_empty))
+        {
+            rows.pop();
+        }
+
+        match (name.as_str(), delim) {
+            (_, Some(delim)) => {
+                let rows: Vec<String> = rows.iter().map(|row| row.join(", ")).collect();
+                Some(format!("mat(delim: {}, {})", delim, rows.join("; ")))
+            }
+            ("cases" | "dcases", None) => {
+                let rows: Vec<String> = rows.iter().map(|row| row.join(" & ")).collect();
+                Some(format!("cases({})", rows.join(", ")))
+            }
+            (
+                "aligned" | "align" | "align*" | "alignat" | "alignat*" | "split" | "gathered"
+                | "gather" | "gather*" | "equation" | "equation*" | "eqnarray" | "eqnarray*",
+                None,
+            ) => {
+                let rows: Vec<String> = rows.iter().map(|row| row.join(" & ")).collect();
+                Some(rows.join(" \\ "))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A single character in math, escaped where Typst would read it as syntax.
+/// Commas and semicolons are escaped so they never split function arguments.
+fn math_char(c: char) -> String {
+    match c {
+        '/' | '"' | '#' | '$' | ',' | ';' | '\\' | '@' => format!("\\{}", c),
+        c => c.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("webnotes-{}", uuid::Uuid::new_v4()));
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// A 1x1 grayscale PNG
+    const PIXEL_PNG: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x00, 0x00, 0x00, 0x00, 0x3a,
+        0x7e, 0x9b, 0x55, 0x00, 0x00, 0x00, 0x0a, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x60,
+        0x00, 0x00, 0x00, 0x02, 0x00, 0x01, 0x48, 0xaf, 0xa4, 0x71, 0x00, 0x00, 0x00, 0x00, 0x49,
+        0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+
+    #[test]
+    fn escapes_markup_in_note_text() {
+        assert_eq!(
+            escape_markup("#tag costs $5 *now* @me <b>"),
+            r"\#tag costs \$5 \*now\* \@me \<b\>"
+        );
+        // Only special at the start of a line, but escaped everywhere
+        assert_eq!(escape_markup("1. - + = a"), r"1\. \- \+ \= a");
+        assert_eq!(escape_markup("plain text, é"), "plain text, é");
+    }
+
+    #[test]
+    fn string_literals_escape_quotes_and_control_characters() {
+        assert_eq!(
+            string_literal("say \"hi\"\\\n\tok"),
+            r#""say \"hi\"\\\n\tok""#
+        );
+        assert_eq!(string_literal("#$*@<"), "\"#$*@<\"");
+    }
+
+    #[test]
+    fn tokenizes_commands_and_escapes() {
+        use LatexToken::*;
+        assert_eq!(
+            tokenize_latex(r"\alpha_{1}^\prime\\ a&b~\,é"),
+            [
+                Command("alpha"),
+                Sub,
+                Open,
+                Char('1'),
+                Close,
+                Sup,
+                Command("prime"),
+                Newline,
+                Space,
+                Char('a'),
+                Align,
+                Char('b'),
+                Command(" "),
+                Command(","),
+                Char('é'),
+            ]
+        );
+    }
+
+    #[test]
+    fn translates_common_formulas() {
+        let cases = [
+            (r"E = mc^2", "E = m c^(2)"),
+            (r"\frac{a}{b}", "frac(a, b)"),
+            (r"\sqrt{x} + \sqrt[3]{y}", "sqrt(x) + root(3, y)"),
+            (r"\alpha + \beta", "alpha + beta"),
+            (r"\sum_{i=1}^{n} i^2", "sum_(i = 1)^(n) i^(2)"),
+            (
+                r"\int_0^\infty e^{-x} \, dx",
+                "integral_(0)^(infinity) e^(- x) thin d x",
+            ),
+            (
+                r"\lim_{x \to 0} \frac{\sin x}{x} = 1",
+                "lim_(x arrow.r 0) frac(sin x, x) = 1",
+            ),
+            (r"a \leq b \neq c", "a lt.eq b eq.not c"),
+            (r"\mathbb{R} \hat{x}", "bb(R) hat(x)"),
+            (
+                r"\begin{pmatrix} a & b \\ c & d \end{pmatrix}",
+                r#"mat(delim: "(", a, b; c, d)"#,
+            ),
+            (
+                r"\begin{cases} 1 & x > 0 \\ 0 & \text{otherwise} \end{cases}",
+                r#"cases(1 & x > 0, 0 & "otherwise")"#,
+            ),
+            (r"a &= b \\ c &= d", r"a & = b \ c & = d"),
+        ];
+        for (latex, typst) in cases {
+            assert_eq!(latex_to_typst(latex).as_deref(), Some(typst), "{}", latex);
+        }
+    }
+
+    #[test]
+    fn gives_up_on_unknown_or_unbalanced_latex() {
+        for latex in [
+            r"\unknowncommand{x}",
+            r"\frac{a}{",
+            r"x}",
+            r"\begin{tikzpicture}",
+        ] {
+            assert_eq!(latex_to_typst(latex), None, "{}", latex);
+        }
+    }
+
+    #[test]
+    fn typesets_every_block_type() {
+        let dir = TempDir::new();
+        fs::write(dir.0.join("pixel.png"), PIXEL_PNG).unwrap();
+
+        let content = concat!(
+            "<h1>Heading #1</h1><h3>Sub $heading$</h3>",
+            "<p>Costs $5 *not bold* @me &lt;tag&gt; <strong>bold</strong> <em>em</em> ",
+            "<u>u</u> <s>s</s> <mark>mark</mark> H<sub>2</sub>O x<sup>2</sup> ",
+            "<a href=\"https://example.com\">link</a> <code>let x = \"#y\";</code><br>next</p>",
+            "<ul><li><p>one</p><ul><li><p>nested</p></li></ul></li><li><p>two</p></li></ul>",
+            "<ol start=\"3\"><li><p>three</p></li></ol>",
+            "<ul data-type=\"taskList\"><li data-checked=\"true\"><p>done</p></li>",
+            "<li data-checked=\"false\"><p>todo</p></li></ul>",
+            "<blockquote><p>quoted</p></blockquote>",
+            "<div data-type=\"callout\"><p>note this</p></div>",
+            "<pre><code class=\"language-rust\">fn main() {}\n</code></pre>",
+            "<table><tbody><tr><th><p>A</p></th><th><p>B</p></th></tr>",
+            "<tr><td><p>1</p></td><td><p>2</p></td></tr></tbody></table>",
+            "<hr>",
+            "<p><img src=\"pixel.png\" alt=\"dot\" width=\"20\"></p>",
+            "<p>Inline <span data-type=\"math-inline\" data-latex=\"x^2\"></span></p>",
+            "<div data-type=\"math-block\" data-latex=\"\\frac{a}{b}\"></div>",
+        );
+        let note = Note::new("All blocks", content);
+
+        let mut report = PdfExportReport::default();
+        let document = typeset(&note, &dir.0, true, &mut report).unwrap();
+        assert!(!document.pages.is_empty());
+        assert!(report.missing_images.is_empty());
+        assert!(report.unrendered_math.is_empty());
+
+        let output = dir.0.join("out").join("note.pdf");
+        let report = export_pdf(&note, &dir.0, &output).unwrap();
+        assert!(fs::read(&output).unwrap().starts_with(b"%PDF"));
+        assert_eq!(report.bytes, fs::metadata(&output).unwrap().len());
+    }
+}
@@ -0,0 +1,952 @@
+use latex2mathml::{latex_to_mathml, DisplayStyle};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::html::{escape_html, render_tag, scan_tags};
+use crate::links::find_wikilinks;
+use crate::tags::find_tags;
+use crate::Note;
+
+// =============================================================================
+// STATIC SITE PUBLISHING
+// =============================================================================
+//
+// Layout of a published site:
+//   index.html          all pages and tags
+//   <slug>.html         one page per note
+//   tags/<tag>.html     one page per #tag
+//   assets/             copied attachments
+//   style.css, sitemap.xml
+
+/// Written into every published site; an existing non-empty directory
+/// without it is never written to
+const SITE_MARKER: &str = ".webnotes-site";
+
+/// Elements the editor produces. Anything else in a note (from the local
+/// API, a deep link or pasted HTML) is dropped, keeping its text.
+const ALLOWED_TAGS: &[&str] = &[
+    "a",
+    "b",
+    "blockquote",
+    "br",
+    "code",
+    "col",
+    "colgroup",
+    "del",
+    "div",
+    "em",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "input",
+    "label",
+    "li",
+    "mark",
+    "ol",
+    "p",
+    "pre",
+    "s",
+    "span",
+    "strike",
+    "strong",
+    "sub",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "u",
+    "ul",
+];
+
+/// Disallowed elements whose content is not text either
+const DROPPED_WITH_CONTENT: &[&str] = &[
+    "iframe", "math", "noembed", "noframes", "noscript", "object", "script", "select", "style",
+    "svg", "template", "textarea", "title", "xmp",
+];
+
+/// Attributes kept on allowed elements, besides `data-*`
+const ALLOWED_ATTRS: &[&str] = &[
+    "alt", "checked", "class", "colspan", "disabled", "height", "href", "rel", "rowspan", "src",
+    "start", "target", "title", "type", "width",
+];
+
+const STYLESHEET: &str = r#"*{box-sizing:border-box}
+body{margin:0;font:17px/1.65 -apple-system,BlinkMacSystemFont,"Segoe UI",Roboto,sans-serif;color:#1f2328;background:#fff}
+header,main,footer{max-width:46rem;margin:0 auto;padding:1rem 1.25rem}
+header{border-bottom:1px solid #e5e7eb}
+header a{color:inherit;font-weight:600;text-decoration:none}
+footer{color:#6b7280;font-size:.85rem;border-top:1px solid #e5e7eb}
+a{color:#2563eb}
+a.note-link{text-decoration:none;border-bottom:1px dashed currentColor}
+span.note-link.missing{color:#9ca3af;text-decoration:line-through}
+a.tag{color:#7c3aed;text-decoration:none}
+pre{background:#f6f8fa;padding:1rem;overflow-x:auto;border-radius:6px}
+code{font-family:ui-monospace,SFMono-Regular,Menlo,monospace;font-size:.9em}
+blockquote{margin:0;padding-left:1rem;border-left:3px solid #d1d5db;color:#4b5563}
+table{border-collapse:collapse}td,th{border:1px solid #d1d5db;padding:.35rem .6rem}
+img{max-width:100%}
+ul.task-list,ul[data-type=taskList]{list-style:none;padding-left:.25rem}
+li[data-checked=true]>div{text-decoration:line-through;color:#6b7280}
+math[display=block]{margin:1rem 0}
+.math-error{color:#b91c1c}
+.backlinks,.tags{margin-top:2.5rem;padding-top:1rem;border-top:1px solid #e5e7eb;font-size:.95rem}
+@media (prefers-color-scheme:dark){body{background:#111;color:#e5e7eb}pre{background:#1f2937}header,footer,.backlinks,.tags{border-color:#374151}}
+"#;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishOptions {
+    pub folder_id: String,
+    pub output_dir: String,
+    /// Defaults to the folder name
+    pub site_title: Option<String>,
+    /// Absolute URL the site will be served from, used for sitemap.xml
+    pub base_url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishReport {
+    pub output_dir: String,
+    pub pages: usize,
+    pub tag_pages: usize,
+    pub attachments: usize,
+    /// Image sources that pointed at local files which could not be found,
+    /// or that are outside the attachments directory
+    pub missing_attachments: Vec<String>,
+    /// Link targets that are not part of the published folder
+    pub unresolved_links: Vec<String>,
+}
+
+struct Page<'a> {
+    note: &'a Note,
+    slug: String,
+    tags: Vec<String>,
+}
+
+struct Publisher<'a> {
+    out: PathBuf,
+    attachments_dir: &'a Path,
+    slug_by_id: HashMap<&'a str, String>,
+    id_by_title: HashMap<String, &'a str>,
+    /// Source → file name under assets/
+    copied: HashMap<String, String>,
+    report: PublishReport,
+}
+
+pub fn publish(
+    notes: &[Note],
+    folder_name: &str,
+    attachments_dir: &Path,
+    options: &PublishOptions,
+) -> Result<PublishReport, String> {
+    let out = PathBuf::from(options.output_dir.trim());
+    prepare_output_dir(&out)?;
+
+    let site_title = options
+        .site_title
+        .as_deref()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .unwrap_or(folder_name)
+        .to_string();
+
+    let mut pages: Vec<Page> = Vec::with_capacity(notes.len());
+    // index.html is the site's own page
+    let mut taken = HashSet::from(["index".to_string()]);
+    for note in notes {
+        let base = slugify(display_title(note));
+        let mut slug = base.clone();
+        let mut count = 1;
+        while taken.contains(&slug) {
+            count += 1;
+            slug = format!("{}-{}", base, count);
+        }
+        taken.insert(slug.clone());
+        pages.push(Page {
+            note,
+            slug,
+            tags: Vec::new(),
+        });
+    }
+    pages.sort_by_key(|p| display_title(p.note).to_lowercase());
+
+    let mut publisher = Publisher {
+        out: out.clone(),
+        attachments_dir,
+        slug_by_id: pages
+            .iter()
+            .map(|p| (p.note.id.as_str(), p.slug.clone()))
+            .collect(),
+        id_by_title: HashMap::new(),
+        copied: HashMap::new(),
+        report: PublishReport {
+            output_dir: out.to_string_lossy().into_owned(),
+            ..Default::default()
+        },
+    };
+    for page in &pages {
+        publisher
+            .id_by_title
+            .entry(display_title(page.note).to_lowercase())
+            .or_insert(page.note.id.as_str());
+    }
+
+    // Render every body first so backlinks are known before pages are written
+    let mut bodies = Vec::with_capacity(pages.len());
+    let mut backlinks: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, page) in pages.iter_mut().enumerate() {
+        let mut tags = BTreeSet::new();
+        let (body, targets) = publisher.render_content(&page.note.content, &mut tags)?;
+        for target in targets {
+            let sources = backlinks.entry(target).or_default();
+            if !sources.contains(&index) && target != page.note.id {
+                sources.push(index);
+            }
+        }
+        page.tags = tags.into_iter().collect();
+        bodies.push(body);
+    }
+
+    for (page, body) in pages.iter().zip(&bodies) {
+        let mut html = format!(
+            "<article>\n<h1>{}</h1>\n{}\n</article>\n",
+            escape_html(display_title(page.note)),
+            body
+        );
+
+        if !page.tags.is_empty() {
+            html.push_str("<nav class=\"tags\">Tags: ");
+            html.push_str(&tag_links(&page.tags, ""));
+            html.push_str("</nav>\n");
+        }
+
+        if let Some(sources) = backlinks.get(page.note.id.as_str()) {
+            html.push_str("<section class=\"backlinks\">\n<h2>Linked from</h2>\n<ul>\n");
+            for &source in sources {
+                let from = &pages[source];
+                html.push_str(&format!(
+                    "<li><a href=\"{}.html\">{}</a></li>\n",
+                    from.slug,
+                    escape_html(display_title(from.note))
+                ));
+            }
+            html.push_str("</ul>\n</section>\n");
+        }
+
+        let updated = date_part(&page.note.updated_at);
+        write_page(
+            &out.join(format!("{}.html", page.slug)),
+            &site_title,
+            display_title(page.note),
+            "",
+            &html,
+            &format!("Updated {}", updated),
+        )?;
+        publisher.report.pages += 1;
+    }
+
+    // Tag pages
+    let mut by_tag: BTreeMap<&str, Vec<&Page>> = BTreeMap::new();
+    for page in &pages {
+        for tag in &page.tags {
+            by_tag.entry(tag.as_str()).or_default().push(page);
+        }
+    }
+    if !by_tag.is_empty() {
+        create_dir(&out.join("tags"))?;
+    }
+    for (tag, tagged) in &by_tag {
+        let mut html = format!("<h1>#{}</h1>\n<ul>\n", escape_html(tag));
+        for page in tagged {
+            html.push_str(&format!(
+                "<li><a href=\"../{}.html\">{}</a></li>\n",
+                page.slug,
+                escape_html(display_title(page.note))
+            ));
+        }
+        html.push_str("</ul>\n");
+        write_page(
+            &out.join("tags").join(format!("{}.html", slugify(tag))),
+            &site_title,
+            &format!("#{}", tag),
+            "../",
+            &html,
+            "",
+        )?;
+        publisher.report.tag_pages += 1;
+    }
+
+    // Index
+    let mut index = format!("<h1>{}</h1>\n<ul>\n", escape_html(&site_title));
+    for page in &pages {
+        index.push_str(&format!(
+            "<li><a href=\"{}.html\">{}</a></li>\n",
+            page.slug,
+            escape_html(display_title(page.note))
+        ));
+    }
+    index.push_str("</ul>\n");
+    if !by_tag.is_empty() {
+        let tags: Vec<String> = by_tag.keys().map(|t| t.to_string()).collect();
+        index.push_str("<nav class=\"tags\">Tags: ");
+        index.push_str(&tag_links(&tags, ""));
+        index.push_str("</nav>\n");
+    }
+    write_page(
+        &out.join("index.html"),
+        &site_title,
+        &site_title,
+        "",
+        &index,
+        &format!("{} pages", pages.len()),
+    )?;
+
+    write_file(&out.join("style.css"), STYLESHEET)?;
+    write_file(
+        &out.join("sitemap.xml"),
+        &sitemap(options.base_url.as_deref(), &pages, by_tag.keys()),
+    )?;
+    write_file(&out.join(SITE_MARKER), "")?;
+
+    let mut report = publisher.report;
+    report.unresolved_links.sort();
+    report.unresolved_links.dedup();
+    log::info!("Published {} pages to {}", report.pages, report.output_dir);
+    Ok(report)
+}
+
+impl<'a> Publisher<'a> {
+    /// Rewrite stored editor HTML for the static site. Returns the HTML and
+    /// the IDs of published notes it links to; tags outside code are added
+    /// to `tags`.
+    fn render_content(
+        &mut self,
+        content: &str,
+        tags: &mut BTreeSet<String>,
+    ) -> Result<(String, Vec<&'a str>), String> {
+        let mut out = String::with_capacity(content.len());
+        let mut targets = Vec::new();
+        let mut last = 0;
+        let mut skip_until_close: Option<String> = None;
+        let mut code_depth = 0usize;
+        let mut open_note_link: Option<&'static str> = None;
+
+        for tag in scan_tags(content) {
+            if let Some(name) = &skip_until_close {
+                if tag.closing && &tag.name == name {
+                    skip_until_close = None;
+                    last = tag.span.end;
+                }
+                continue;
+            }
+
+            let text = escape_stray_markup(&content[last..tag.span.start]);
+            if code_depth > 0 || open_note_link.is_some() {
+                out.push_str(&text);
+            } else {
+                out.push_str(&self.render_text(&text, &mut targets, tags));
+            }
+            last = tag.span.end;
+
+            let math = tag.attr("data-type").and_then(|t| match t {
+                "math-inline" => Some(DisplayStyle::Inline),
+                "math-block" => Some(DisplayStyle::Block),
+                _ => None,
+            });
+
+            if let (false, Some(display)) = (tag.closing, math) {
+                out.push_str(&render_math(
+                    tag.attr("data-latex").unwrap_or_default(),
+                    display,
+                ));
+                skip_until_close = Some(tag.name.clone());
+                continue;
+            }
+
+            if !tag.closing && DROPPED_WITH_CONTENT.contains(&tag.name.as_str()) {
+                skip_until_close = Some(tag.name.clone());
+                continue;
+            }
+            if !ALLOWED_TAGS.contains(&tag.name.as_str())
+                || (tag.name == "input" && tag.attr("type") != Some("checkbox"))
+            {
+                continue;
+            }
+
+            match (tag.name.as_str(), tag.closing) {
+                ("a", false) if tag.attr("data-note-id").is_some() => {
+                    let id = tag.attr("data-note-id").unwrap_or_default();
+                    match self.slug_by_id.get_key_value(id) {
+                        Some((&id, slug)) => {
+                            out.push_str(&format!(
+                                "<a class=\"note-link\" href=\"{}.html\">",
+                                slug
+                            ));
+                            targets.push(id);
+                            open_note_link = Some("</a>");
+                        }
+                        None => {
+                            self.report.unresolved_links.push(
+                                tag.attr("data-note-label")
+                                    .filter(|l| !l.is_empty())
+                                    .unwrap_or(id)
+                                    .to_string(),
+                            );
+                            out.push_str("<span class=\"note-link missing\">");
+                            open_note_link = Some("</span>");
+                        }
+                    }
+                }
+                ("a", true) if open_note_link.is_some() => {
+                    out.push_str(open_note_link.take().unwrap_or("</a>"));
+                }
+                ("img", false) => {
+                    let mut attrs = tag.attrs.clone();
+                    if let Some(index) = attrs.iter().position(|(k, _)| k == "src") {
+                        match self.copy_attachment(&attrs[index].1)? {
+                            Some(src) => attrs[index].1 = src,
+                            None => {
+                                attrs.remove(index);
+                            }
+                        }
+                    }
+                    out.push_str(&render_tag("img", &safe_attrs("img", &attrs)));
+                }
+                (name, closing) => {
+                    if name == "pre" || name == "code" {
+                        if closing {
+                            code_depth = code_depth.saturating_sub(1);
+                        } else {
+                            code_depth += 1;
+                        }
+                    }
+                    if closing {
+                        out.push_str(&format!("</{}>", name));
+                    } else {
+                        out.push_str(&render_tag(name, &safe_attrs(name, &tag.attrs)));
+                    }
+                }
+            }
+        }
+
+        if skip_until_close.is_none() {
+            let rest = escape_stray_markup(&content[last..]);
+            out.push_str(&self.render_text(&rest, &mut targets, tags));
+        }
+
+        Ok((out, targets))
+    }
+
+    /// Resolve `[[wikilinks]]` and link `#tags` in a run of (escaped) text
+    fn render_text(
+        &mut self,
+        text: &str,
+        targets: &mut Vec<&'a str>,
+        tags: &mut BTreeSet<String>,
+    ) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut last = 0;
+
+        for (range, title, label) in find_wikilinks(text) {
+            out.push_str(&link_tags(&text[last..range.start], tags));
+            match self.id_by_title.get(&title.to_lowercase()) {
+                Some(&id) => {
+                    out.push_str(&format!(
+                        "<a class=\"note-link\" href=\"{}.html\">{}</a>",
+                        self.slug_by_id[id], label
+                    ));
+                    targets.push(id);
+                }
+                None => {
+                    self.report.unresolved_links.push(title);
+                    out.push_str(&format!(
+                        "<span class=\"note-link missing\">{}</span>",
+                        label
+                    ));
+                }
+            }
+            last = range.end;
+        }
+        out.push_str(&link_tags(&text[last..], tags));
+
+        out
+    }
+
+    /// Copy a local image into assets/ and return its new relative path.
+    /// Remote and inline images are left alone. `None` for local files that
+    /// are missing or outside the attachments directory, which are not
+    /// published.
+    fn copy_attachment(&mut self, src: &str) -> Result<Option<String>, String> {
+        let Some(path) = local_source(src, self.attachments_dir) else {
+            return Ok(Some(src.to_string()));
+        };
+
+        if let Some(name) = self.copied.get(src) {
+            return Ok(Some(format!("assets/{}", name)));
+        }
+        let Some(path) = attachment_file(&path, self.attachments_dir) else {
+            self.report.missing_attachments.push(src.to_string());
+            return Ok(None);
+        };
+
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "attachment".to_string());
+        let name = format!(
+            "{}-{}",
+            self.copied.len() + 1,
+            sanitize_file_name(&file_name)
+        );
+
+        create_dir(&self.out.join("assets"))?;
+        fs::copy(&path, self.out.join("assets").join(&name))
+            .map_err(|e| format!("Failed to copy attachment {:?}: {}", path, e))?;
+
+        self.report.attachments += 1;
+        self.copied.insert(src.to_string(), name.clone());
+        Ok(Some(format!("assets/{}", name)))
+    }
+}
+
+/// Map an image `src` to a path on disk, or `None` for remote/inline images.
+/// The path may point anywhere; check it with `attachment_file` before
+/// reading it.
+pub(crate) fn local_source(src: &str, attachments_dir: &Path) -> Option<PathBuf> {
+    let src = src.trim();
+    if src.is_empty() || src.starts_with("data:") {
+        return None;
+    }
+
+    // convertFileSrc() output: asset://localhost/<encoded path> on macOS and
+    // Linux, http://asset.localhost/<encoded path> on Windows
+    for prefix in [
+        "asset://localhost/",
+        "http://asset.localhost/",
+        "https://asset.localhost/",
+    ] {
+        if let Some(encoded) = src.strip_prefix(prefix) {
+            return Some(PathBuf::from(percent_decode(encoded)));
+        }
+    }
+    if let Some(path) = src.strip_prefix("file://") {
+        return Some(PathBuf::from(percent_decode(path)));
+    }
+    if src.contains("://") || src.starts_with("//") {
+        return None;
+    }
+
+    let path = PathBuf::from(percent_decode(src));
+    if path.is_absolute() {
+        Some(path)
+    } else {
+        Some(attachments_dir.join(path))
+    }
+}
+
+/// The file at `path`, resolved, if it exists inside `attachments_dir`.
+/// Notes can come from the local API or deep links, so an image source must
+/// not be able to pull in any other file.
+pub(crate) fn attachment_file(path: &Path, attachments_dir: &Path) -> Option<PathBuf> {
+    let root = attachments_dir.canonicalize().ok()?;
+    let file = path.canonicalize().ok()?;
+    if !file.starts_with(&root) || !file.is_file() {
+        log::debug!("Not an attachment: {:?}", path);
+        return None;
+    }
+    Some(file)
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn render_math(latex: &str, display: DisplayStyle) -> String {
+    match latex_to_mathml(latex, display) {
+        Ok(mathml) => mathml,
+        Err(e) => {
+            log::debug!("Failed to convert LaTeX '{}': {}", latex, e);
+            format!("<code class=\"math-error\">{}</code>", escape_html(latex))
+        }
+    }
+}
+
+/// Keep the attributes the editor uses, and links only to http(s),
+/// relative or in-page URLs. Images may also be inline data.
+fn safe_attrs(tag: &str, attrs: &[(String, String)]) -> Vec<(String, String)> {
+    attrs
+        .iter()
+        .filter(|(key, value)| {
+            let known = ALLOWED_ATTRS.contains(&key.as_str())
+                || key.strip_prefix("data-").is_some_and(|rest| {
+                    rest.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                });
+            match key.as_str() {
+                "href" => is_safe_url(value),
+                "src" => is_safe_url(value) || (tag == "img" && is_inline_image(value)),
+                _ => known,
+            }
+        })
+        .cloned()
+        .collect()
+}
+
+/// Whether a URL is http(s), relative or a fragment. Browsers ignore
+/// whitespace and control characters in schemes, so they are removed first.
+fn is_safe_url(url: &str) -> bool {
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_control())
+        .collect();
+    match url.find([':', '/', '?', '#']) {
+        Some(end) if url[end..].starts_with(':') => {
+            let scheme = url[..end].to_ascii_lowercase();
+            scheme == "http" || scheme == "https"
+        }
+        _ => true,
+    }
+}
+
+fn is_inline_image(src: &str) -> bool {
+    let src = src.trim_start().to_ascii_lowercase();
+    ["png", "jpeg", "jpg", "gif", "webp"]
+        .iter()
+        .any(|kind| src.starts_with(&format!("data:image/{};", kind)))
+}
+
+/// Text between tags is escaped by the editor; anything that still looks
+/// like markup (comments, stray `<`) is shown as text
+fn escape_stray_markup(text: &str) -> std::borrow::Cow<'_, str> {
+    if text.contains('<') {
+        text.replace('<', "&lt;").into()
+    } else {
+        text.into()
+    }
+}
+
+fn link_tags(text: &str, found: &mut BTreeSet<String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+
+    for (range, tag) in find_tags(text) {
+        out.push_str(&text[last..range.start]);
+        out.push_str(&format!(
+            "<a class=\"tag\" href=\"tags/{}.html\">{}</a>",
+            slugify(&tag),
+            &text[range.clone()]
+        ));
+        found.insert(tag);
+        last = range.end;
+    }
+    out.push_str(&text[last..]);
+
+    out
+}
+
+fn tag_links(tags: &[String], root: &str) -> String {
+    tags.iter()
+        .map(|tag| {
+            format!(
+                "<a class=\"tag\" href=\"{}tags/{}.html\">#{}</a>",
+                root,
+                slugify(tag),
+                escape_html(tag)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn sitemap<'t>(
+    base_url: Option<&str>,
+    pages: &[Page],
+    tags: impl Iterator<Item = &'t &'t str>,
+) -> String {
+    let base = base_url
+        .map(|b| format!("{}/", b.trim().trim_end_matches('/')))
+        .unwrap_or_default();
+
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    xml.push_str(&format!(
+        "  <url><loc>{}index.html</loc></url>\n",
+        escape_html(&base)
+    ));
+    for page in pages {
+        xml.push_str(&format!(
+            "  <url><loc>{}{}.html</loc><lastmod>{}</lastmod></url>\n",
+            escape_html(&base),
+            page.slug,
+            date_part(&page.note.updated_at)
+        ));
+    }
+    for tag in tags {
+        xml.push_str(&format!(
+            "  <url><loc>{}tags/{}.html</loc></url>\n",
+            escape_html(&base),
+            slugify(tag)
+        ));
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+fn write_page(
+    path: &Path,
+    site_title: &str,
+    title: &str,
+    root: &str,
+    body: &str,
+    footer: &str,
+) -> Result<(), String> {
+    let page = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{title} · {site}</title>\n<link rel=\"stylesheet\" href=\"{root}style.css\">\n\
+         </head>\n<body>\n<header><a href=\"{root}index.html\">{site}</a></header>\n\
+         <main>\n{body}</main>\n<footer>{footer}</footer>\n</body>\n</html>\n",
+        title = escape_html(title),
+        site = escape_html(site_title),
+        root = root,
+        body = body,
+        footer = escape_html(footer),
+    );
+    write_file(path, &page)
+}
+
+fn prepare_output_dir(out: &Path) -> Result<(), String> {
+    if out.as_os_str().is_empty() {
+        return Err("Output directory cannot be empty".to_string());
+    }
+
+    if out.exists() {
+        let mut entries =
+            fs::read_dir(out).map_err(|e| format!("Failed to read {:?}: {}", out, e))?;
+        if entries.next().is_some() && !out.join(SITE_MARKER).exists() {
+            return Err(format!(
+                "{:?} is not empty and was not created by a previous publish",
+                out
+            ));
+        }
+    }
+
+    create_dir(out)
+}
+
+fn create_dir(path: &Path) -> Result<(), String> {
+    fs::create_dir_all(path).map_err(|e| format!("Failed to create {:?}: {}", path, e))
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<(), String> {
+    fs::write(path, contents).map_err(|e| format!("Failed to write {:?}: {}", path, e))
+}
+
+fn display_title(note: &Note) -> &str {
+    let title = note.title.trim();
+    if title.is_empty() {
+        "Untitled"
+    } else {
+        title
+    }
+}
+
+fn date_part(timestamp: &str) -> &str {
+    timestamp.get(..10).unwrap_or(timestamp)
+}
+
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        "untitled".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the system temp dir, removed on drop
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("webnotes-{}", uuid::Uuid::new_v4()));
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn publish_to(dir: &TempDir, attachments: &Path, notes: &[Note]) -> PublishReport {
+        let options = PublishOptions {
+            folder_id: "f".to_string(),
+            output_dir: dir.0.join("site").to_string_lossy().into_owned(),
+            site_title: None,
+            base_url: None,
+        };
+        publish(notes, "Site", attachments, &options).unwrap()
+    }
+
+    fn render(content: &str, attachments: &Path) -> (String, PublishReport) {
+        let mut publisher = Publisher {
+            out: std::env::temp_dir().join("unused"),
+            attachments_dir: attachments,
+            slug_by_id: HashMap::new(),
+            id_by_title: HashMap::new(),
+            copied: HashMap::new(),
+            report: PublishReport::default(),
+        };
+        let (html, _) = publisher
+            .render_content(content, &mut BTreeSet::new())
+            .unwrap();
+        (html, publisher.report)
+    }
+
+    #[test]
+    fn drops_elements_the_editor_does_not_produce() {
+        let (html, _) = render(
+            "<p>Hi<script>alert(1)</script></p><iframe src=\"https://x\"></iframe>\
+             <form action=\"https://x\"><p>kept</p></form><style>p{}</style>\
+             <object data=\"x\"></object><!--x--!><img src=x onerror=alert(1)>-->",
+            Path::new("/nonexistent"),
+        );
+
+        assert!(html.starts_with("<p>Hi</p>"), "{}", html);
+        assert!(html.contains("<p>kept</p>"));
+        for banned in ["<script", "<iframe", "<form", "<style", "<object", "<img"] {
+            assert!(!html.contains(banned), "{} in {}", banned, html);
+        }
+    }
+
+    #[test]
+    fn only_links_to_web_relative_and_fragment_urls() {
+        let (html, _) = render(
+            "<p><a href=\"java\tscript:alert(1)\">a</a><a href=\"vbscript:x\">b</a>\
+             <a href=\"https://example.com\">c</a><a href=\"other.html#top\">d</a>\
+             <a href=\"#top\" onclick=\"x()\" style=\"color:red\">e</a></p>",
+            Path::new("/nonexistent"),
+        );
+
+        assert!(html.contains("<a>a</a><a>b</a>"), "{}", html);
+        assert!(html.contains("<a href=\"https://example.com\">c</a>"));
+        assert!(html.contains("<a href=\"other.html#top\">d</a>"));
+        assert!(html.contains("<a href=\"#top\">e</a>"));
+        assert!(is_safe_url("//cdn.example.com/a.png"));
+        assert!(!is_safe_url(" JavaScript:alert(1)"));
+    }
+
+    #[test]
+    fn never_publishes_files_outside_attachments() {
+        let dir = TempDir::new();
+        let attachments = dir.0.join("attachments");
+        fs::create_dir_all(&attachments).unwrap();
+        fs::write(attachments.join("cat.png"), b"png").unwrap();
+        fs::write(dir.0.join("secret.txt"), b"secret").unwrap();
+
+        let secret = dir.0.join("secret.txt").to_string_lossy().into_owned();
+        let content = format!(
+            "<p><img src=\"cat.png\"><img src=\"{0}\"><img src=\"file://{0}\">\
+             <img src=\"../secret.txt\"><img src=\"asset://localhost/{0}\"></p>",
+            secret
+        );
+        let report = publish_to(&dir, &attachments, &[Note::new("Pics", content)]);
+
+        assert_eq!(report.attachments, 1);
+        assert_eq!(report.missing_attachments.len(), 4);
+        let assets: Vec<_> = fs::read_dir(dir.0.join("site/assets"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(assets, ["1-cat.png"]);
+        let page = fs::read_to_string(dir.0.join("site/pics.html")).unwrap();
+        assert!(!page.contains("secret"));
+    }
+
+    #[test]
+    fn slugs_never_collide() {
+        let dir = TempDir::new();
+        let notes = [
+            Note::new("Foo Bar", "<p>one</p>"),
+            Note::new("Foo Bar", "<p>two</p>"),
+            Note::new("Foo Bar 2", "<p>three</p>"),
+            Note::new("Index", "<p>four</p>"),
+        ];
+        let report = publish_to(&dir, &dir.0, &notes);
+
+        assert_eq!(report.pages, 4);
+        let site = dir.0.join("site");
+        for (slug, body) in [
+            ("foo-bar", "one"),
+            ("foo-bar-2", "two"),
+            ("foo-bar-2-2", "three"),
+            ("index-2", "four"),
+        ] {
+            let page = fs::read_to_string(site.join(format!("{}.html", slug))).unwrap();
+            assert!(page.contains(body), "{} should hold {}", slug, body);
+        }
+        let index = fs::read_to_string(site.join("index.html")).unwrap();
+        assert!(index.contains("<a href=\"index-2.html\">Index</a>"));
+    }
+}
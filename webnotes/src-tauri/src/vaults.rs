@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// =============================================================================
+// VAULTS
+// =============================================================================
+//
+// A vault is an independent set of notes with its own database and
+// attachments directory. The registry is a JSON file in the app data
+// directory, outside every vault, so it can say which one to open at launch.
+//
+// The default vault lives directly in the app data directory, where the
+// single database was kept before vaults existed; others live in
+// `vaults/<id>/`.
+//
+//   vaults.json
+//   webnotes.db, attachments/       default vault
+//   vaults/<id>/webnotes.db, ...    other vaults
+
+const REGISTRY_FILE: &str = "vaults.json";
+const VAULTS_DIR: &str = "vaults";
+const DATABASE_FILE: &str = "webnotes.db";
+const ATTACHMENTS_DIR: &str = "attachments";
+
+pub const DEFAULT_VAULT_ID: &str = "default";
+const DEFAULT_VAULT_NAME: &str = "Notes";
+const MAX_NAME_LEN: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Vault {
+    pub id: String,
+    pub name: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultRegistry {
+    pub vaults: Vec<Vault>,
+    /// Opened at launch; falls back to the first vault if it is gone
+    pub last_vault_id: Option<String>,
+}
+
+/// Payload of the `vault-switched` event
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultSwitched {
+    pub vault: Vault,
+}
+
+impl VaultRegistry {
+    /// Read the registry, or start one with just the default vault
+    pub fn load(app_data_dir: &Path) -> Result<Self, String> {
+        let path = app_data_dir.join(REGISTRY_FILE);
+        let registry = match std::fs::read_to_string(&path) {
+            Ok(raw) => serde_json::from_str::<Self>(&raw)
+                .map_err(|e| format!("Failed to read vault list {:?}: {}", path, e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self {
+                vaults: Vec::new(),
+                last_vault_id: None,
+            },
+            Err(e) => return Err(format!("Failed to read vault list {:?}: {}", path, e)),
+        };
+
+        Ok(registry.with_default_vault())
+    }
+
+    /// Write the registry atomically, so a crash never leaves it half written
+    pub fn save(&self, app_data_dir: &Path) -> Result<(), String> {
+        let path = app_data_dir.join(REGISTRY_FILE);
+        let tmp = path.with_extension("json.tmp");
+        let raw = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+
+        std::fs::write(&tmp, raw)
+            .and_then(|_| std::fs::rename(&tmp, &path))
+            .map_err(|e| format!("Failed to save vault list: {}", e))
+    }
+
+    fn with_default_vault(mut self) -> Self {
+        if self.vaults.is_empty() {
+            self.vaults.push(Vault {
+                id: DEFAULT_VAULT_ID.to_string(),
+                name: DEFAULT_VAULT_NAME.to_string(),
+                created_at: chrono::Utc::now().to_rfc3339(),
+            });
+        }
+        self
+    }
+
+    pub fn get(&self, id: &str) -> Result<&Vault, String> {
+        self.vaults
+            .iter()
+            .find(|vault| vault.id == id)
+            .ok_or_else(|| format!("Vault not found: {}", id))
+    }
+
+    /// The vault to open at launch
+    pub fn last_vault(&self) -> &Vault {
+        self.last_vault_id
+            .as_deref()
+            .and_then(|id| self.get(id).ok())
+            .unwrap_or(&self.vaults[0])
+    }
+
+    /// Trimmed name, unique among the other vaults regardless of case
+    pub fn validate_name(&self, name: &str, except_id: Option<&str>) -> Result<String, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Vault name cannot be empty".to_string());
+        }
+        if name.len() > MAX_NAME_LEN {
+            return Err("Vault name too long".to_string());
+        }
+        let taken = self.vaults.iter().any(|vault| {
+            Some(vault.id.as_str()) != except_id && vault.name.to_lowercase() == name.to_lowercase()
+        });
+        if taken {
+            return Err(format!("A vault named '{}' already exists", name));
+        }
+        Ok(name.to_string())
+    }
+}
+
+/// Directory holding a vault's database and attachments
+pub fn vault_dir(app_data_dir: &Path, id: &str) -> PathBuf {
+    if id == DEFAULT_VAULT_ID {
+        app_data_dir.to_path_buf()
+    } else {
+        app_data_dir.join(VAULTS_DIR).join(id)
+    }
+}
+
+pub fn database_path(vault_dir: &Path) -> PathBuf {
+    vault_dir.join(DATABASE_FILE)
+}
+
+pub fn attachments_dir(vault_dir: &Path) -> PathBuf {
+    vault_dir.join(ATTACHMENTS_DIR)
+}
+
+/// Remove a vault's database and attachments from disk
+pub fn remove_vault_files(app_data_dir: &Path, id: &str) -> Result<(), String> {
+    let dir = vault_dir(app_data_dir, id);
+    let result = if id == DEFAULT_VAULT_ID {
+        // Shares its directory with the registry and API token
+        let database = database_path(&dir);
+        ["", "-journal", "-wal", "-shm"]
+            .iter()
+            .map(|suffix| PathBuf::from(format!("{}{}", database.display(), suffix)))
+            .filter(|path| path.exists())
+            .try_for_each(std::fs::remove_file)
+            .and_then(|_| match std::fs::remove_dir_all(attachments_dir(&dir)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            })
+    } else {
+        match std::fs::remove_dir_all(&dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    };
+
+    result.map_err(|e| format!("Failed to delete vault files: {}", e))
+}
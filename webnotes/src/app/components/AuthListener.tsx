@@ -32,8 +32,6 @@ export default function AuthListener() {
                 const user = JSON.parse(decodeURIComponent(userParam));
                 setUser(user);
                 toast.success(`Welcome back, ${user.name}!`);
-
-                // Optional: Force a sync or data reload here
               }
             } catch (e) {
               console.error("Auth parsing failed", e);
@@ -1,9 +1,10 @@
 "use client";
 import { useState, useEffect, useRef } from "react";
 import React from "react";
-import type { Note, Folder } from "@/lib/storage/types";
+import type { Note, Folder, SmartFolder } from "@/lib/storage/types";
 import {
   FileText,
+  FolderSearch,
   Trash2,
   Edit,
   Check,
@@ -37,6 +38,8 @@ const sectionLabelClass =
 
 interface NoteListProps {
   folders: Folder[];
+  smartFolders: SmartFolder[];
+  smartFolderCounts: Record<string, number>;
   notesInFolders: Map<string, Note[]>;
   unfiledNotes: Note[];
   activeNoteId: string | null;
@@ -77,6 +80,8 @@ function AnimatedFolderIcon({ isOpen }: { isOpen: boolean }) {
 
 export default function NoteList({
   folders,
+  smartFolders,
+  smartFolderCounts,
   notesInFolders,
   unfiledNotes,
   activeNoteId,
@@ -733,9 +738,37 @@ export default function NoteList({
     );
   };
 
+  const renderSmartFolder = (folder: SmartFolder) => (
+    <div
+      key={folder.id}
+      className="flex items-center gap-1.5 rounded-lg px-2 py-1.5 text-zinc-300"
+    >
+      <div className="flex h-7 w-7 flex-shrink-0 items-center justify-center rounded-md">
+        <FolderSearch size={15} aria-hidden="true" className="text-zinc-500" />
+      </div>
+      <div className="min-w-0 flex-1">
+        <span className="truncate text-[0.89rem] font-medium tracking-[-0.01em] leading-5">
+          {folder.name}
+        </span>
+      </div>
+      <span className="text-xs tabular-nums text-zinc-500">
+        {smartFolderCounts[folder.id] ?? 0}
+      </span>
+    </div>
+  );
+
   return (
     <>
       <div className="p-2 space-y-1">
+        {smartFolders.length > 0 && (
+          <>
+            <div className={`${sectionLabelClass} mt-1`}>Smart Folders</div>
+            {smartFolders.map((folder) => renderSmartFolder(folder))}
+            {folders.length > 0 && (
+              <div className={`${sectionLabelClass} mt-3`}>Folders</div>
+            )}
+          </>
+        )}
         {folders.map((folder) => renderFolder(folder))}
 
         {!isMobile && (
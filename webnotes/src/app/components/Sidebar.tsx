@@ -18,11 +18,13 @@ import {
   TooltipProvider,
   TooltipTrigger,
 } from "@/app/components/ui/tooltip";
-import type { SyncStatus } from "@/lib/storage/types";
+import type { SmartFolder, SyncStatus } from "@/lib/storage/types";
 
 interface SidebarProps {
   notes: Note[];
   folders: FolderWithNotes[];
+  smartFolders: SmartFolder[];
+  smartFolderCounts: Record<string, number>;
   activeNoteId: string | null;
   setActiveNoteId: (id: string) => void;
   createNote: (folderId?: string | null) => void;
@@ -40,6 +42,8 @@ interface SidebarProps {
 export default function Sidebar({
   notes,
   folders,
+  smartFolders,
+  smartFolderCounts,
   activeNoteId,
   setActiveNoteId,
   createNote,
@@ -315,6 +319,8 @@ export default function Sidebar({
           <div className="relative z-10 flex-1 overflow-y-auto min-h-0 px-2 pb-2 custom-scrollbar">
             <NoteList
               folders={folders}
+              smartFolders={smartFolders}
+              smartFolderCounts={smartFolderCounts}
               notesInFolders={notesInFolders}
               unfiledNotes={unfiledNotes}
               activeNoteId={activeNoteId}
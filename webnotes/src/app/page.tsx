@@ -42,6 +42,9 @@ export default function Home() {
     setActiveNote,
     createFolder,
     deleteFolder,
+    smartFolders,
+    smartFolderCounts,
+    refreshSmartFolders,
   } = useNotesStore();
 
   const [isSaving, setIsSaving] = useState(false);
@@ -58,6 +61,13 @@ export default function Home() {
     loadData();
   }, [loadData]);
 
+  // Recount smart folders once edits settle
+  useEffect(() => {
+    if (isLoading) return;
+    const timer = setTimeout(() => refreshSmartFolders(), 1000);
+    return () => clearTimeout(timer);
+  }, [notes, isLoading, refreshSmartFolders]);
+
   // Windows Drag Fix
   useEffect(() => {
     if (typeof window === "undefined") return;
@@ -329,6 +339,8 @@ export default function Home() {
         <Sidebar
           notes={notes}
           folders={foldersWithNotes}
+          smartFolders={smartFolders}
+          smartFolderCounts={smartFolderCounts}
           activeNoteId={activeNoteId}
           setActiveNoteId={handleSetActiveNote}
           createNote={handleCreateNote}
@@ -358,6 +370,8 @@ export default function Home() {
             <Sidebar
               notes={notes}
               folders={foldersWithNotes}
+              smartFolders={smartFolders}
+              smartFolderCounts={smartFolderCounts}
               activeNoteId={activeNoteId}
               setActiveNoteId={handleSetActiveNote}
               createNote={handleCreateNote}
@@ -0,0 +1,116 @@
+// src/lib/logic/smart-folders.ts
+import type { Note, SmartFolder, SmartFolderQuery } from "@/lib/storage/types";
+
+// Smart folders are evaluated by the Rust backend in the desktop app. The web
+// app (local and cloud storage) only stores the definitions, so it matches
+// them against the notes it has loaded here. Text search is a case-insensitive
+// match of every word rather than SQLite's full-text search.
+
+// Plain text of note HTML
+function noteText(html: string): string {
+  return html
+    .replace(/<[^>]*>/g, " ")
+    .replace(/&nbsp;/g, " ")
+    .replace(/&lt;/g, "<")
+    .replace(/&gt;/g, ">")
+    .replace(/&quot;/g, '"')
+    .replace(/&#39;/g, "'")
+    .replace(/&amp;/g, "&");
+}
+
+// `#Tag` -> `tag`
+export function normalizeTag(tag: string): string {
+  return tag.trim().replace(/^#+/, "").toLowerCase();
+}
+
+const LETTER = new RegExp("\\p{L}", "u");
+const isLetter = (ch: string) => LETTER.test(ch);
+const isTagChar = (ch: string) =>
+  isLetter(ch) || (ch >= "0" && ch <= "9") || ch === "-" || ch === "_" || ch === "/";
+
+// Lowercased `#tags` in plain text, found as the backend finds them: a tag
+// follows whitespace or `(`, starts with a letter and is not a hex color
+export function findTags(text: string): string[] {
+  const chars = Array.from(text);
+  const tags: string[] = [];
+
+  chars.forEach((ch, i) => {
+    const prev = i > 0 ? chars[i - 1] : " ";
+    if (ch !== "#" || !(/\s/.test(prev) || prev === "(")) return;
+
+    let end = i + 1;
+    while (end < chars.length && isTagChar(chars[end])) end++;
+    const tag = chars.slice(i + 1, end).join("").replace(/\/+$/, "");
+
+    const isHexColor = /^([0-9a-f]{3}|[0-9a-f]{6})$/i.test(tag);
+    if (tag.length > 0 && isLetter(tag[0]) && !isHexColor) {
+      tags.push(tag.toLowerCase());
+    }
+  });
+
+  return tags;
+}
+
+// Local YYYY-MM-DD of a date
+function localDay(date: Date): string {
+  const pad = (n: number) => String(n).padStart(2, "0");
+  return `${date.getFullYear()}-${pad(date.getMonth() + 1)}-${pad(date.getDate())}`;
+}
+
+function withinDays(
+  date: Date,
+  after: string | null | undefined,
+  before: string | null | undefined
+): boolean {
+  if (!after && !before) return true;
+  const day = localDay(new Date(date));
+  return (!after || day >= after) && (!before || day <= before);
+}
+
+export function matchesSmartFolder(note: Note, query: SmartFolderQuery): boolean {
+  const folderIds = query.folderIds ?? [];
+  if (folderIds.length > 0 && !folderIds.includes(note.folderId ?? "")) {
+    return false;
+  }
+  if (query.pinned != null && (note.isPinned ?? false) !== query.pinned) {
+    return false;
+  }
+  if (
+    !withinDays(note.updatedAt, query.updatedAfter, query.updatedBefore) ||
+    !withinDays(note.createdAt, query.createdAfter, query.createdBefore)
+  ) {
+    return false;
+  }
+
+  const text = noteText(note.content ?? "");
+
+  const words = (query.text ?? "").toLowerCase().split(/\s+/).filter(Boolean);
+  if (words.length > 0) {
+    const haystack = `${note.title ?? ""} ${text}`.toLowerCase();
+    if (!words.every((word) => haystack.includes(word))) return false;
+  }
+
+  const wanted = (query.tags ?? []).map(normalizeTag).filter(Boolean);
+  if (wanted.length > 0) {
+    const found = findTags(text);
+    const hasTag = (want: string) =>
+      found.some((tag) => tag === want || tag.startsWith(`${want}/`));
+    if (!wanted.every(hasTag)) return false;
+  }
+
+  return true;
+}
+
+// Number of matching notes for every smart folder, by ID
+export function countSmartFolders(
+  folders: SmartFolder[],
+  notes: Note[]
+): Record<string, number> {
+  const counts: Record<string, number> = {};
+  for (const folder of folders) {
+    counts[folder.id] = notes.filter((note) =>
+      matchesSmartFolder(note, folder.query)
+    ).length;
+  }
+  return counts;
+}
@@ -1,7 +1,7 @@
 // src/lib/storage/cloud-storage.ts
 
 import { trpcVanilla } from "@/lib/trpc/client";
-import type { Note, Folder, UserSettings } from "./types";
+import type { Note, Folder, SmartFolder, SmartFolderQuery, UserSettings } from "./types";
 
 export class CloudStorageAdapter {
   async init() {
@@ -146,6 +146,58 @@ export class CloudStorageAdapter {
     await trpcVanilla.folders.delete.mutate({ id });
   }
 
+  // ==========================================================================
+  // SMART FOLDERS
+  // ==========================================================================
+
+  async getSmartFolders(): Promise<SmartFolder[]> {
+    const folders = await trpcVanilla.smartFolders.list.query();
+
+    return folders.map((f) => ({
+      id: f.id,
+      name: f.name,
+      query: f.query as SmartFolderQuery,
+      createdAt: f.createdAt,
+    }));
+  }
+
+  async createSmartFolder(data: Partial<SmartFolder>): Promise<SmartFolder> {
+    const folder = await trpcVanilla.smartFolders.create.mutate({
+      id: data.id,
+      name: data.name ?? "New Smart Folder",
+      query: data.query ?? {},
+    });
+
+    return {
+      id: folder.id,
+      name: folder.name,
+      query: folder.query as SmartFolderQuery,
+      createdAt: folder.createdAt,
+    };
+  }
+
+  async updateSmartFolder(
+    id: string,
+    data: Partial<SmartFolder>
+  ): Promise<SmartFolder> {
+    const folder = await trpcVanilla.smartFolders.update.mutate({
+      id,
+      name: data.name,
+      query: data.query,
+    });
+
+    return {
+      id: folder.id,
+      name: folder.name,
+      query: folder.query as SmartFolderQuery,
+      createdAt: folder.createdAt,
+    };
+  }
+
+  async deleteSmartFolder(id: string): Promise<void> {
+    await trpcVanilla.smartFolders.delete.mutate({ id });
+  }
+
   // ==========================================================================
   // SETTINGS
   // ==========================================================================
@@ -2,7 +2,8 @@ import { LocalStorageAdapter } from "./local-storage";
 import { CloudStorageAdapter } from "./cloud-storage";
 import { TauriStorageAdapter } from "./tauri-storage"; // <--- NEW
 import { isTauri } from "@/lib/tauri"; // <--- NEW
-import type { Note, Folder, UserSettings } from "./types";
+import { countSmartFolders, matchesSmartFolder } from "@/lib/logic/smart-folders";
+import type { Note, Folder, SmartFolder, UserSettings } from "./types";
 
 export class HybridStorageAdapter {
   private local: LocalStorageAdapter;
@@ -198,6 +199,79 @@ export class HybridStorageAdapter {
     }
   }
 
+  // --- SMART FOLDERS ---
+
+  async getSmartFolders(): Promise<SmartFolder[]> {
+    if (isTauri) return await this.tauri.getSmartFolders();
+
+    if (this.shouldUseCloud()) {
+      try {
+        return await this.cloud.getSmartFolders();
+      } catch {
+        return this.local.getSmartFolders();
+      }
+    }
+    return this.local.getSmartFolders();
+  }
+
+  async createSmartFolder(folder: Partial<SmartFolder>): Promise<SmartFolder> {
+    if (isTauri) return await this.tauri.createSmartFolder(folder);
+
+    if (this.shouldUseCloud()) {
+      try {
+        return await this.cloud.createSmartFolder(folder);
+      } catch {
+        return this.local.createSmartFolder(folder);
+      }
+    }
+    return this.local.createSmartFolder(folder);
+  }
+
+  async updateSmartFolder(
+    id: string,
+    data: Partial<SmartFolder>
+  ): Promise<SmartFolder> {
+    if (isTauri) return await this.tauri.updateSmartFolder(id, data);
+
+    if (this.shouldUseCloud()) {
+      try {
+        return await this.cloud.updateSmartFolder(id, data);
+      } catch {
+        return this.local.updateSmartFolder(id, data);
+      }
+    }
+    return this.local.updateSmartFolder(id, data);
+  }
+
+  async deleteSmartFolder(id: string): Promise<void> {
+    if (isTauri) return await this.tauri.deleteSmartFolder(id);
+
+    if (this.shouldUseCloud()) {
+      try {
+        await this.cloud.deleteSmartFolder(id);
+      } catch {
+        await this.local.deleteSmartFolder(id);
+      }
+    } else {
+      await this.local.deleteSmartFolder(id);
+    }
+  }
+
+  // Matching notes are found by the backend in Tauri; elsewhere only the
+  // definitions are stored, so they are matched against the given notes
+  async getSmartFolderNotes(folder: SmartFolder, notes: Note[]): Promise<Note[]> {
+    if (isTauri) return await this.tauri.getSmartFolderNotes(folder.id);
+    return notes.filter((note) => matchesSmartFolder(note, folder.query));
+  }
+
+  async getSmartFolderCounts(
+    folders: SmartFolder[],
+    notes: Note[]
+  ): Promise<Record<string, number>> {
+    if (isTauri) return await this.tauri.getSmartFolderCounts();
+    return countSmartFolders(folders, notes);
+  }
+
   // --- SETTINGS ---
 
   async getSettings(): Promise<UserSettings> {
@@ -1,6 +1,6 @@
 // src/lib/storage/local-storage.ts
 import { v4 as uuidv4 } from 'uuid';
-import type { Note, Folder, UserSettings } from './types';
+import type { Note, Folder, SmartFolder, UserSettings } from './types';
 
 export class LocalStorageAdapter {
   private getNotesKey() {
@@ -11,6 +11,10 @@ export class LocalStorageAdapter {
     return 'webnotes_folders_v1';
   }
 
+  private getSmartFoldersKey() {
+    return 'webnotes_smart_folders_v1';
+  }
+
   private getSettingsKey() {
     return 'webnotes_settings_v1';
   }
@@ -140,6 +144,40 @@ export class LocalStorageAdapter {
     this.save(this.getNotesKey(), updatedNotes);
   }
 
+  // Smart folders (only the definitions; see src/lib/logic/smart-folders.ts)
+  async getSmartFolders(): Promise<SmartFolder[]> {
+    return this.load(this.getSmartFoldersKey(), []);
+  }
+
+  async createSmartFolder(folder: Partial<SmartFolder>): Promise<SmartFolder> {
+    const newFolder: SmartFolder = {
+      id: folder.id || uuidv4(),
+      name: folder.name || 'New Smart Folder',
+      query: folder.query || {},
+      createdAt: folder.createdAt || new Date(),
+    };
+
+    const folders = await this.getSmartFolders();
+    this.save(this.getSmartFoldersKey(), [newFolder, ...folders]);
+
+    return newFolder;
+  }
+
+  async updateSmartFolder(id: string, data: Partial<SmartFolder>): Promise<SmartFolder> {
+    const folders = await this.getSmartFolders();
+    const updatedFolders = folders.map(folder =>
+      folder.id === id ? { ...folder, ...data } : folder
+    );
+    this.save(this.getSmartFoldersKey(), updatedFolders);
+
+    return updatedFolders.find(f => f.id === id)!;
+  }
+
+  async deleteSmartFolder(id: string): Promise<void> {
+    const folders = await this.getSmartFolders();
+    this.save(this.getSmartFoldersKey(), folders.filter(folder => folder.id !== id));
+  }
+
   // Settings
   async getSettings(): Promise<UserSettings> {
     return this.load(this.getSettingsKey(), {
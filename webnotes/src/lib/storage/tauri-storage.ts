@@ -1,5 +1,10 @@
-import { TauriDB, type TauriNote, type TauriFolder } from "@/lib/tauri";
-import type { Note, Folder, UserSettings } from "./types";
+import {
+  TauriDB,
+  type TauriNote,
+  type TauriFolder,
+  type TauriSmartFolder,
+} from "@/lib/tauri";
+import type { Note, Folder, SmartFolder, UserSettings } from "./types";
 
 export class TauriStorageAdapter {
   async init(): Promise<void> {
@@ -108,6 +113,59 @@ export class TauriStorageAdapter {
     await TauriDB.deleteFolder(id);
   }
 
+  // ==========================================================================
+  // SMART FOLDERS
+  // ==========================================================================
+
+  async getSmartFolders(): Promise<SmartFolder[]> {
+    const folders = await TauriDB.getAllSmartFolders();
+    return folders.map((f) => this.tauriSmartFolderToSmartFolder(f));
+  }
+
+  async createSmartFolder(data: Partial<SmartFolder>): Promise<SmartFolder> {
+    const folder: SmartFolder = {
+      id: data.id ?? crypto.randomUUID(),
+      name: data.name ?? "New Smart Folder",
+      query: data.query ?? {},
+      createdAt: data.createdAt ?? new Date(),
+    };
+
+    await TauriDB.saveSmartFolder(this.smartFolderToTauriSmartFolder(folder));
+    return folder;
+  }
+
+  async updateSmartFolder(
+    id: string,
+    data: Partial<SmartFolder>
+  ): Promise<SmartFolder> {
+    const folders = await this.getSmartFolders();
+    const current = folders.find((f) => f.id === id);
+    if (!current) {
+      throw new Error(`Smart folder not found: ${id}`);
+    }
+
+    const updated: SmartFolder = {
+      ...current,
+      ...data,
+    };
+
+    await TauriDB.saveSmartFolder(this.smartFolderToTauriSmartFolder(updated));
+    return updated;
+  }
+
+  async deleteSmartFolder(id: string): Promise<void> {
+    await TauriDB.deleteSmartFolder(id);
+  }
+
+  async getSmartFolderNotes(id: string): Promise<Note[]> {
+    const notes = await TauriDB.getSmartFolderNotes(id);
+    return notes.map((n) => this.tauriNoteToNote(n));
+  }
+
+  async getSmartFolderCounts(): Promise<Record<string, number>> {
+    return await TauriDB.getSmartFolderCounts();
+  }
+
   // ==========================================================================
   // SETTINGS
   // ==========================================================================
@@ -197,4 +255,22 @@ export class TauriStorageAdapter {
       createdAt: f.createdAt.toISOString(),
     };
   }
+
+  private tauriSmartFolderToSmartFolder(f: TauriSmartFolder): SmartFolder {
+    return {
+      id: f.id,
+      name: f.name,
+      query: f.query,
+      createdAt: new Date(f.createdAt),
+    };
+  }
+
+  private smartFolderToTauriSmartFolder(f: SmartFolder): TauriSmartFolder {
+    return {
+      id: f.id,
+      name: f.name,
+      query: f.query,
+      createdAt: f.createdAt.toISOString(),
+    };
+  }
 }
@@ -1,4 +1,7 @@
 // src/lib/storage/types.ts
+import type { SmartFolderQuery } from "@/lib/tauri";
+
+export type { SmartFolderQuery };
 
 export interface Note {
   id: string;
@@ -24,6 +27,14 @@ export interface Folder {
   createdAt: Date;
 }
 
+export interface SmartFolder {
+  id: string;
+  name: string;
+  query: SmartFolderQuery;
+  userId?: string;
+  createdAt: Date;
+}
+
 export type SyncStatus = "synced" | "syncing" | "unsynced";
 
 export interface UserSettings {
@@ -11,6 +11,23 @@ export interface TauriNote {
   font: string | null;
   updatedAt: string;
   createdAt: string;
+  // Bumped on every write; pass it back as expectedVersion
+  version?: number;
+}
+
+// Rejection value of save_note, toggle_task and save_note_from_markdown
+export type SaveNoteError =
+  | { kind: "conflict"; message: string; current: TauriNote }
+  | { kind: "failed"; message: string };
+
+export function isSaveConflict(
+  error: unknown,
+): error is Extract<SaveNoteError, { kind: "conflict" }> {
+  return (
+    typeof error === "object" &&
+    error !== null &&
+    (error as SaveNoteError).kind === "conflict"
+  );
 }
 
 export interface TauriFolder {
@@ -19,6 +36,402 @@ export interface TauriFolder {
   createdAt: string;
 }
 
+// Every set filter must match; dates are YYYY-MM-DD and inclusive
+export interface SmartFolderQuery {
+  text?: string | null;
+  folderIds?: string[];
+  tags?: string[];
+  pinned?: boolean | null;
+  updatedAfter?: string | null;
+  updatedBefore?: string | null;
+  createdAfter?: string | null;
+  createdBefore?: string | null;
+}
+
+export interface TauriSmartFolder {
+  id: string;
+  name: string;
+  query: SmartFolderQuery;
+  createdAt: string;
+}
+
+export interface DailyNoteConfig {
+  folderName: string;
+  titleFormat: string; // chrono strftime format, e.g. "%Y-%m-%d"
+  template: string | null;
+}
+
+export interface DailyNote {
+  date: string; // YYYY-MM-DD
+  note: TauriNote;
+}
+
+export type RepeatRule = "daily" | "weekly" | "monthly" | "yearly";
+
+export interface TauriReminder {
+  id: string;
+  noteId: string;
+  remindAt: string;
+  repeat: RepeatRule | null;
+  firedAt: string | null;
+  completedAt: string | null;
+  createdAt: string;
+}
+
+// Payload of the "reminder-due" event
+export interface DueReminder {
+  reminder: TauriReminder;
+  noteTitle: string;
+}
+
+export interface TauriTask {
+  noteId: string;
+  taskIndex: number;
+  text: string;
+  checked: boolean;
+  due: string | null; // YYYY-MM-DD
+  priority: number | null; // 1 (high) to 3 (low)
+}
+
+export interface TaskFilter {
+  includeCompleted?: boolean;
+  noteId?: string | null;
+  folderId?: string | null;
+  dueBefore?: string | null;
+  priority?: number | null;
+}
+
+export interface TaskGroup {
+  noteId: string;
+  noteTitle: string;
+  noteVersion: number;
+  due: string | null;
+  tasks: TauriTask[];
+}
+
+// Payload of the "deep-link" event (webnotes://note/<id>, ...)
+export type DeepLinkAction =
+  | { action: "openNote"; id: string }
+  | { action: "openNoteByTitle"; title: string }
+  | { action: "openFolder"; id: string }
+  | { action: "search"; query: string }
+  | {
+      action: "newNote";
+      folder: string | null;
+      title: string | null;
+      content: string | null;
+    };
+
+export interface LocalApiConfig {
+  enabled: boolean;
+  port: number;
+}
+
+export interface RelatedNote {
+  noteId: string;
+  noteTitle: string;
+  score: number;
+  sharedTerms: string[];
+}
+
+export interface TauriDraft {
+  noteId: string;
+  title: string;
+  content: string;
+  savedAt: string;
+}
+
+export interface DiffLine {
+  kind: "same" | "added" | "removed";
+  text: string;
+}
+
+export interface RecoverableDraft {
+  draft: TauriDraft;
+  note: TauriNote | null; // null if the note was never saved
+  diff: DiffLine[]; // stored -> draft, one line per block
+}
+
+export interface GraphFilter {
+  folderId?: string | null;
+  tag?: string | null; // also matches nested tags
+  focus?: string | null; // note ID
+  depth?: number | null; // links from focus, default 1
+}
+
+export interface GraphNode {
+  id: string;
+  title: string;
+  folderId: string | null;
+  tags: string[];
+  degree: number; // edges in + edges out
+  component: number; // index into LinkGraph.components
+}
+
+export interface GraphEdge {
+  source: string;
+  target: string;
+  count: number;
+}
+
+export interface LinkGraph {
+  nodes: GraphNode[];
+  edges: GraphEdge[];
+  components: string[][]; // note IDs, largest first
+  orphans: string[];
+}
+
+export interface DuplicateCluster {
+  notes: TauriNote[];
+  similarity: number;
+}
+
+export type MergeStrategy = "concatenate" | "keepNewest";
+
+export type MergeDiscard = "trash" | "delete";
+
+export type DatabaseIssueKind =
+  | "integrity"
+  | "foreignKey"
+  | "emptyId"
+  | "invalidValue"
+  | "badTimestamp"
+  | "missingFolder"
+  | "danglingReference"
+  | "searchIndex";
+
+export interface DatabaseIssue {
+  kind: DatabaseIssueKind;
+  table: string;
+  id: string | null;
+  detail: string;
+}
+
+export interface DatabaseCheckReport {
+  ok: boolean;
+  issues: DatabaseIssue[];
+}
+
+export interface DatabaseRepairReport {
+  fixed: DatabaseIssue[];
+  remaining: DatabaseIssue[];
+}
+
+export type PropertyValue =
+  | { type: "text"; value: string }
+  | { type: "number"; value: number }
+  | { type: "date"; value: string } // YYYY-MM-DD
+  | { type: "bool"; value: boolean }
+  | { type: "list"; value: string[] };
+
+export type PropertyType = PropertyValue["type"];
+
+export type PropertyFilterOp =
+  | "eq"
+  | "ne"
+  | "lt"
+  | "lte"
+  | "gt"
+  | "gte"
+  | "contains"
+  | "exists"
+  | "notExists";
+
+export interface PropertyFilter {
+  key: string;
+  op: PropertyFilterOp;
+  value?: PropertyValue | null;
+}
+
+export interface NoteQuery {
+  folderId?: string | null;
+  filters?: PropertyFilter[];
+  sort?: { key: string; descending?: boolean } | null;
+  limit?: number | null;
+}
+
+export interface NoteWithProperties {
+  note: TauriNote;
+  properties: Record<string, PropertyValue>;
+}
+
+export interface PropertyKey {
+  key: string;
+  valueType: PropertyType;
+  noteCount: number;
+}
+
+export interface RecentNote {
+  note: TauriNote;
+  lastOpenedAt: string;
+  openCount: number;
+  frecency: number;
+}
+
+export type RecentOrder = "recent" | "frecency";
+
+export interface NoteSize {
+  noteId: string;
+  noteTitle: string;
+  bytes: number; // as written
+  storedBytes: number; // in the database, less when compressed
+}
+
+export interface StorageStats {
+  databaseBytes: number;
+  pageSize: number;
+  freePages: number;
+  searchIndexBytes: number;
+  noteCount: number;
+  compressedNoteCount: number;
+  compressedBytes: number;
+  compressionSavedBytes: number;
+  folderCount: number;
+  attachmentCount: number;
+  attachmentBytes: number;
+  largestNotes: NoteSize[];
+  lastMaintenance: string | null;
+}
+
+export interface MaintenanceReport {
+  bytesBefore: number;
+  bytesAfter: number;
+  fullVacuum: boolean;
+  durationMs: number;
+  finishedAt: string;
+}
+
+export type OperationKind =
+  | "deleteNote"
+  | "deleteFolder"
+  | "moveNotes"
+  | "pinNotes"
+  | "renameTag"
+  | "mergeNotes";
+
+export interface JournalEntry {
+  id: number;
+  kind: OperationKind;
+  description: string;
+  createdAt: string;
+  undone: boolean;
+}
+
+export interface Vault {
+  id: string;
+  name: string;
+  createdAt: string;
+}
+
+// Payload of the "vault-switched" event
+export interface VaultSwitched {
+  vault: Vault;
+}
+
+export type DiffMode = "lines" | "markdown";
+
+export interface WordChange {
+  kind: DiffLine["kind"];
+  text: string;
+}
+
+export interface HunkLine {
+  kind: DiffLine["kind"];
+  text: string;
+  // Present on lines that replaced, or were replaced by, another line
+  words?: WordChange[];
+}
+
+export interface DiffHunk {
+  oldStart: number;
+  oldLines: number;
+  newStart: number;
+  newLines: number;
+  lines: HunkLine[];
+}
+
+export interface TextDiff {
+  hunks: DiffHunk[];
+  added: number;
+  removed: number;
+}
+
+export interface NoteVersion {
+  id: string;
+  noteId: string;
+  title: string;
+  content: string;
+  createdAt: string;
+  changeType: string;
+}
+
+export interface PdfExportReport {
+  path: string;
+  pages: number;
+  bytes: number;
+  // Sources of images printed as their alt text instead
+  missingImages: string[];
+  // Formulas printed as LaTeX source instead
+  unrenderedMath: string[];
+}
+
+export interface ArchiveExportReport {
+  path: string;
+  folders: number;
+  notes: number;
+  noteVersions: number;
+  attachments: number;
+  bytes: number;
+}
+
+// What to do with archived records whose IDs are already taken
+export type ArchiveConflictMode = "skip" | "overwrite" | "duplicate";
+
+export interface ArchiveImportReport {
+  imported: number;
+  overwritten: number;
+  duplicated: number;
+  skipped: number;
+  noteIds: string[];
+}
+
+export interface Account {
+  id: string;
+  email?: string | null;
+  name?: string | null;
+  image?: string | null;
+  signedInAt?: string;
+}
+
+export interface AccountSignIn {
+  account: Account | null;
+  claimedUnowned: boolean;
+  claimedNotes: number;
+  claimedFolders: number;
+}
+
+export interface AccountWipe {
+  notes: number;
+  folders: number;
+  noteVersions: number;
+}
+
+export interface PublishOptions {
+  folderId: string;
+  outputDir: string;
+  siteTitle?: string | null;
+  baseUrl?: string | null;
+}
+
+export interface PublishReport {
+  outputDir: string;
+  pages: number;
+  tagPages: number;
+  attachments: number;
+  missingAttachments: string[];
+  unresolvedLinks: string[];
+}
+
 // Check if running in Tauri
 export const isTauri = typeof window !== "undefined" && "__TAURI__" in window;
 
@@ -29,9 +442,13 @@ export const TauriDB = {
     await invoke<string>("init_db");
   },
 
-  async saveNote(note: TauriNote): Promise<void> {
-    if (!isTauri) return;
-    await invoke("save_note", { note });
+  // Rejects with a SaveNoteError; a conflict means expectedVersion is stale
+  async saveNote(note: TauriNote, expectedVersion?: number): Promise<TauriNote | null> {
+    if (!isTauri) return null;
+    return await invoke<TauriNote>("save_note", {
+      note,
+      expectedVersion: expectedVersion ?? null,
+    });
   },
 
   async getAllNotes(): Promise<TauriNote[]> {
@@ -49,6 +466,21 @@ export const TauriDB = {
     await invoke("delete_note", { id });
   },
 
+  async saveDraft(noteId: string, title: string, content: string): Promise<void> {
+    if (!isTauri) return;
+    await invoke("save_draft", { noteId, title, content });
+  },
+
+  async discardDraft(noteId: string): Promise<void> {
+    if (!isTauri) return;
+    await invoke("discard_draft", { noteId });
+  },
+
+  async getRecoverableDrafts(): Promise<RecoverableDraft[]> {
+    if (!isTauri) return [];
+    return await invoke<RecoverableDraft[]>("get_recoverable_drafts");
+  },
+
   async togglePin(id: string): Promise<TauriNote> {
     if (!isTauri) throw new Error("Not in Tauri environment");
     return await invoke<TauriNote>("toggle_pin", { id });
@@ -69,8 +501,408 @@ export const TauriDB = {
     await invoke("delete_folder", { id });
   },
 
+  async saveSmartFolder(folder: TauriSmartFolder): Promise<void> {
+    if (!isTauri) return;
+    await invoke("save_smart_folder", { folder });
+  },
+
+  async getAllSmartFolders(): Promise<TauriSmartFolder[]> {
+    if (!isTauri) return [];
+    return await invoke<TauriSmartFolder[]>("get_all_smart_folders");
+  },
+
+  async deleteSmartFolder(id: string): Promise<void> {
+    if (!isTauri) return;
+    await invoke("delete_smart_folder", { id });
+  },
+
+  async getSmartFolderNotes(id: string): Promise<TauriNote[]> {
+    if (!isTauri) return [];
+    return await invoke<TauriNote[]>("get_smart_folder_notes", { id });
+  },
+
+  async getSmartFolderCounts(): Promise<Record<string, number>> {
+    if (!isTauri) return {};
+    return await invoke<Record<string, number>>("get_smart_folder_counts");
+  },
+
   async searchNotes(query: string): Promise<TauriNote[]> {
     if (!isTauri) return [];
     return await invoke<TauriNote[]>("search_notes", { query });
   },
+
+  async getDailyNoteConfig(): Promise<DailyNoteConfig | null> {
+    if (!isTauri) return null;
+    return await invoke<DailyNoteConfig>("get_daily_note_config");
+  },
+
+  async setDailyNoteConfig(config: DailyNoteConfig): Promise<void> {
+    if (!isTauri) return;
+    await invoke("set_daily_note_config", { config });
+  },
+
+  async getOrCreateDailyNote(date?: string): Promise<TauriNote> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<TauriNote>("get_or_create_daily_note", {
+      date: date ?? null,
+    });
+  },
+
+  async listDailyNotes(start: string, end: string): Promise<DailyNote[]> {
+    if (!isTauri) return [];
+    return await invoke<DailyNote[]>("list_daily_notes", {
+      range: { start, end },
+    });
+  },
+
+  async addReminder(
+    noteId: string,
+    remindAt: string,
+    repeat: RepeatRule | null = null
+  ): Promise<TauriReminder> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<TauriReminder>("add_reminder", {
+      noteId,
+      remindAt,
+      repeat,
+    });
+  },
+
+  async deleteReminder(id: string): Promise<void> {
+    if (!isTauri) return;
+    await invoke("delete_reminder", { id });
+  },
+
+  async getNoteReminders(noteId: string): Promise<TauriReminder[]> {
+    if (!isTauri) return [];
+    return await invoke<TauriReminder[]>("get_note_reminders", { noteId });
+  },
+
+  async listUpcomingReminders(limit?: number): Promise<TauriReminder[]> {
+    if (!isTauri) return [];
+    return await invoke<TauriReminder[]>("list_upcoming_reminders", {
+      limit: limit ?? null,
+    });
+  },
+
+  async snoozeReminder(id: string, minutes: number): Promise<TauriReminder> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<TauriReminder>("snooze_reminder", { id, minutes });
+  },
+
+  async completeReminder(id: string): Promise<TauriReminder> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<TauriReminder>("complete_reminder", { id });
+  },
+
+  // Call once after subscribing to the "reminder-due" event
+  async takePendingReminders(): Promise<DueReminder[]> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<DueReminder[]>("take_pending_reminders");
+  },
+
+  async listTasks(filter: TaskFilter = {}): Promise<TaskGroup[]> {
+    if (!isTauri) return [];
+    return await invoke<TaskGroup[]>("list_tasks", { filter });
+  },
+
+  // Rejects with a SaveNoteError; a conflict means expectedVersion is stale
+  async toggleTask(
+    noteId: string,
+    taskIndex: number,
+    expectedVersion: number,
+  ): Promise<TauriNote> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<TauriNote>("toggle_task", { noteId, taskIndex, expectedVersion });
+  },
+
+  // Call once after subscribing to the "deep-link" event
+  async takePendingDeepLinks(): Promise<DeepLinkAction[]> {
+    if (!isTauri) return [];
+    return await invoke<DeepLinkAction[]>("take_pending_deep_links");
+  },
+
+  // Call once after subscribing to the "deep-link-error" event
+  async takePendingDeepLinkErrors(): Promise<string[]> {
+    if (!isTauri) return [];
+    return await invoke<string[]>("take_pending_deep_link_errors");
+  },
+
+  async getLocalApiConfig(): Promise<LocalApiConfig | null> {
+    if (!isTauri) return null;
+    return await invoke<LocalApiConfig>("get_local_api_config");
+  },
+
+  async setLocalApiConfig(config: LocalApiConfig): Promise<void> {
+    if (!isTauri) return;
+    await invoke("set_local_api_config", { config });
+  },
+
+  async getLocalApiToken(): Promise<string | null> {
+    if (!isTauri) return null;
+    return await invoke<string>("get_local_api_token");
+  },
+
+  async regenerateLocalApiToken(): Promise<string> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<string>("regenerate_local_api_token");
+  },
+
+  async getRelatedNotes(id: string, k?: number): Promise<RelatedNote[]> {
+    if (!isTauri) return [];
+    return await invoke<RelatedNote[]>("get_related_notes", { id, k });
+  },
+
+  async rebuildRelatedIndex(): Promise<number> {
+    if (!isTauri) return 0;
+    return await invoke<number>("rebuild_related_index");
+  },
+
+  async findDuplicates(threshold?: number): Promise<DuplicateCluster[]> {
+    if (!isTauri) return [];
+    return await invoke<DuplicateCluster[]>("find_duplicates", { threshold });
+  },
+
+  async mergeNotes(
+    ids: string[],
+    strategy: MergeStrategy,
+    discard?: MergeDiscard
+  ): Promise<TauriNote> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<TauriNote>("merge_notes", { ids, strategy, discard });
+  },
+
+  async getNoteProperties(
+    noteId: string
+  ): Promise<Record<string, PropertyValue>> {
+    if (!isTauri) return {};
+    return await invoke<Record<string, PropertyValue>>("get_note_properties", {
+      noteId,
+    });
+  },
+
+  async setNoteProperty(
+    noteId: string,
+    key: string,
+    value: PropertyValue
+  ): Promise<void> {
+    if (!isTauri) return;
+    await invoke("set_note_property", { noteId, key, value });
+  },
+
+  async deleteNoteProperty(noteId: string, key: string): Promise<void> {
+    if (!isTauri) return;
+    await invoke("delete_note_property", { noteId, key });
+  },
+
+  async listPropertyKeys(): Promise<PropertyKey[]> {
+    if (!isTauri) return [];
+    return await invoke<PropertyKey[]>("list_property_keys");
+  },
+
+  async listNotes(query?: NoteQuery): Promise<NoteWithProperties[]> {
+    if (!isTauri) return [];
+    return await invoke<NoteWithProperties[]>("list_notes", { query });
+  },
+
+  async recordNoteOpen(id: string): Promise<void> {
+    if (!isTauri) return;
+    await invoke("record_note_open", { id });
+  },
+
+  async getRecentNotes(
+    limit?: number,
+    order?: RecentOrder
+  ): Promise<RecentNote[]> {
+    if (!isTauri) return [];
+    return await invoke<RecentNote[]>("get_recent_notes", { limit, order });
+  },
+
+  async getFrecencyScores(): Promise<Record<string, number>> {
+    if (!isTauri) return {};
+    return await invoke<Record<string, number>>("get_frecency_scores");
+  },
+
+  async checkDatabase(): Promise<DatabaseCheckReport | null> {
+    if (!isTauri) return null;
+    return await invoke<DatabaseCheckReport>("check_database");
+  },
+
+  async repairDatabase(): Promise<DatabaseRepairReport> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<DatabaseRepairReport>("repair_database");
+  },
+
+  async getStorageStats(): Promise<StorageStats | null> {
+    if (!isTauri) return null;
+    return await invoke<StorageStats>("get_storage_stats");
+  },
+
+  async runMaintenance(full?: boolean): Promise<MaintenanceReport> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<MaintenanceReport>("run_maintenance", { full });
+  },
+
+  async getLinkGraph(filter?: GraphFilter): Promise<LinkGraph> {
+    if (!isTauri) return { nodes: [], edges: [], components: [], orphans: [] };
+    return await invoke<LinkGraph>("get_link_graph", { filter: filter ?? null });
+  },
+
+  async getNoteAsMarkdown(id: string): Promise<string> {
+    if (!isTauri) return "";
+    return await invoke<string>("get_note_as_markdown", { id });
+  },
+
+  // Rejects with a SaveNoteError; a conflict means expectedVersion is stale
+  async saveNoteFromMarkdown(
+    id: string,
+    markdown: string,
+    expectedVersion: number,
+  ): Promise<TauriNote> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<TauriNote>("save_note_from_markdown", {
+      id,
+      markdown,
+      expectedVersion,
+    });
+  },
+
+  async moveNotes(ids: string[], folderId: string | null): Promise<void> {
+    if (!isTauri) return;
+    await invoke("move_notes", { ids, folderId });
+  },
+
+  async setNotesPinned(ids: string[], pinned: boolean): Promise<void> {
+    if (!isTauri) return;
+    await invoke("set_notes_pinned", { ids, pinned });
+  },
+
+  async renameTag(from: string, to: string): Promise<number> {
+    if (!isTauri) return 0;
+    return await invoke<number>("rename_tag", { from, to });
+  },
+
+  async undoLastOperation(): Promise<JournalEntry | null> {
+    if (!isTauri) return null;
+    return await invoke<JournalEntry | null>("undo_last_operation");
+  },
+
+  async redoOperation(): Promise<JournalEntry | null> {
+    if (!isTauri) return null;
+    return await invoke<JournalEntry | null>("redo_operation");
+  },
+
+  async getRecentOperations(limit?: number): Promise<JournalEntry[]> {
+    if (!isTauri) return [];
+    return await invoke<JournalEntry[]>("get_recent_operations", { limit });
+  },
+
+  async listVaults(): Promise<Vault[]> {
+    if (!isTauri) return [];
+    return await invoke<Vault[]>("list_vaults");
+  },
+
+  async getActiveVault(): Promise<Vault | null> {
+    if (!isTauri) return null;
+    return await invoke<Vault>("get_active_vault");
+  },
+
+  async createVault(name: string): Promise<Vault> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<Vault>("create_vault", { name });
+  },
+
+  async renameVault(id: string, name: string): Promise<Vault> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<Vault>("rename_vault", { id, name });
+  },
+
+  async deleteVault(id: string): Promise<void> {
+    if (!isTauri) return;
+    await invoke("delete_vault", { id });
+  },
+
+  async switchVault(id: string): Promise<Vault> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<Vault>("switch_vault", { id });
+  },
+
+  async snapshotNoteVersion(noteId: string): Promise<NoteVersion> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<NoteVersion>("snapshot_note_version", { noteId });
+  },
+
+  async listNoteVersions(noteId: string): Promise<NoteVersion[]> {
+    if (!isTauri) return [];
+    return await invoke<NoteVersion[]>("list_note_versions", { noteId });
+  },
+
+  async diffNotes(a: string, b: string, mode?: DiffMode): Promise<TextDiff> {
+    if (!isTauri) return { hunks: [], added: 0, removed: 0 };
+    return await invoke<TextDiff>("diff_notes", { a, b, mode: mode ?? null });
+  },
+
+  // Compares with the current note when toVersionId is omitted
+  async diffNoteVersions(
+    noteId: string,
+    fromVersionId: string,
+    toVersionId?: string,
+    mode?: DiffMode,
+  ): Promise<TextDiff> {
+    if (!isTauri) return { hunks: [], added: 0, removed: 0 };
+    return await invoke<TextDiff>("diff_note_versions", {
+      noteId,
+      fromVersionId,
+      toVersionId: toVersionId ?? null,
+      mode: mode ?? null,
+    });
+  },
+
+  async exportNotePdf(noteId: string, outputPath: string): Promise<PdfExportReport> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<PdfExportReport>("export_note_pdf", { noteId, outputPath });
+  },
+
+  async exportArchive(outputPath: string): Promise<ArchiveExportReport> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<ArchiveExportReport>("export_archive", { outputPath });
+  },
+
+  async importArchive(
+    inputPath: string,
+    mode: ArchiveConflictMode = "skip"
+  ): Promise<ArchiveImportReport> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<ArchiveImportReport>("import_archive", { inputPath, mode });
+  },
+
+  async listAccounts(): Promise<Account[]> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<Account[]>("list_accounts");
+  },
+
+  async getActiveAccount(): Promise<Account | null> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<Account | null>("get_active_account");
+  },
+
+  async signInAccount(account: Account): Promise<AccountSignIn> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<AccountSignIn>("sign_in_account", { account });
+  },
+
+  async signOutAccount(): Promise<void> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<void>("sign_out_account");
+  },
+
+  async wipeAccountData(accountId: string): Promise<AccountWipe> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<AccountWipe>("wipe_account_data", { accountId });
+  },
+
+  async publishFolder(options: PublishOptions): Promise<PublishReport> {
+    if (!isTauri) throw new Error("Not in Tauri environment");
+    return await invoke<PublishReport>("publish_folder", { options });
+  },
 };
@@ -2,11 +2,13 @@
 import { router } from "../init";
 import { notesRouter } from "./notes";
 import { foldersRouter } from "./folders";
+import { smartFoldersRouter } from "./smart-folders";
 import { versionsRouter } from "./versions"; // <-- Import
 
 export const appRouter = router({
   notes: notesRouter,
   folders: foldersRouter,
+  smartFolders: smartFoldersRouter,
   versions: versionsRouter, // <-- Add to router
 });
 
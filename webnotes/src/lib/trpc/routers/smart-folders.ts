@@ -0,0 +1,82 @@
+import { z } from "zod";
+import { router, protectedProcedure } from "../init";
+import { TRPCError } from "@trpc/server";
+import type { Prisma } from "@prisma/client";
+
+// Mirrors SmartFolderQuery in src/lib/tauri.ts
+const queryInput = z.object({
+  text: z.string().nullish(),
+  folderIds: z.array(z.string()).optional(),
+  tags: z.array(z.string()).optional(),
+  pinned: z.boolean().nullish(),
+  updatedAfter: z.iso.date().nullish(),
+  updatedBefore: z.iso.date().nullish(),
+  createdAfter: z.iso.date().nullish(),
+  createdBefore: z.iso.date().nullish(),
+});
+
+export const smartFoldersRouter = router({
+  list: protectedProcedure.query(async ({ ctx }) => {
+    return ctx.prisma.smartFolder.findMany({
+      where: { userId: ctx.userId },
+      orderBy: { createdAt: "desc" },
+    });
+  }),
+
+  create: protectedProcedure
+    .input(
+      z.object({
+        id: z.string().optional(),
+        name: z.string().min(1),
+        query: queryInput,
+      })
+    )
+    .mutation(async ({ ctx, input }) => {
+      return ctx.prisma.smartFolder.create({
+        data: {
+          id: input.id,
+          name: input.name,
+          query: input.query as Prisma.InputJsonObject,
+          userId: ctx.userId,
+        },
+      });
+    }),
+
+  update: protectedProcedure
+    .input(
+      z.object({
+        id: z.string(),
+        name: z.string().min(1).optional(),
+        query: queryInput.optional(),
+      })
+    )
+    .mutation(async ({ ctx, input }) => {
+      const result = await ctx.prisma.smartFolder.updateMany({
+        where: { id: input.id, userId: ctx.userId },
+        data: {
+          name: input.name,
+          query: input.query as Prisma.InputJsonObject | undefined,
+        },
+      });
+
+      if (result.count === 0) {
+        throw new TRPCError({ code: "NOT_FOUND", message: "Smart folder not found" });
+      }
+
+      return ctx.prisma.smartFolder.findUniqueOrThrow({ where: { id: input.id } });
+    }),
+
+  delete: protectedProcedure
+    .input(z.object({ id: z.string() }))
+    .mutation(async ({ ctx, input }) => {
+      const result = await ctx.prisma.smartFolder.deleteMany({
+        where: { id: input.id, userId: ctx.userId },
+      });
+
+      if (result.count === 0) {
+        throw new TRPCError({ code: "NOT_FOUND", message: "Smart folder not found" });
+      }
+
+      return { success: true };
+    }),
+});
@@ -6,6 +6,7 @@ import { storage } from "@/lib/storage";
 import type {
   Note,
   Folder,
+  SmartFolder,
   UserSettings,
   SyncStatus,
 } from "@/lib/storage/types";
@@ -16,6 +17,7 @@ import { v4 as uuidv4 } from "uuid";
 // =============================================================================
 
 interface User {
+  id?: string;
   name?: string | null;
   email?: string | null;
   image?: string | null;
@@ -31,6 +33,9 @@ interface NotesState {
   // Data
   notes: Note[];
   folders: Folder[];
+  smartFolders: SmartFolder[];
+  // Matching notes per smart folder, by ID
+  smartFolderCounts: Record<string, number>;
   settings: UserSettings;
   activeNoteId: string | null;
 
@@ -60,6 +65,9 @@ interface NotesState {
   updateFolder: (id: string, data: Partial<Folder>) => Promise<Folder>;
   deleteFolder: (id: string) => Promise<void>;
 
+  // Smart Folder Operations
+  refreshSmartFolders: () => Promise<void>;
+
   // Settings
   updateSettings: (settings: Partial<UserSettings>) => Promise<void>;
   setSyncStatus: (status: SyncStatus) => void;
@@ -133,6 +141,8 @@ export const useNotesStore = create<NotesState>()(
     // =========================================================================
     notes: [],
     folders: [],
+    smartFolders: [],
+    smartFolderCounts: {},
     settings: {
       theme: "dark",
       fontSize: "medium",
@@ -190,6 +200,12 @@ export const useNotesStore = create<NotesState>()(
       if (typeof window !== "undefined" && isTauri) {
         if (user) {
           localStorage.setItem("webnotes_user", JSON.stringify(user));
+          // Each account has its own notes on this device
+          if (user.id) {
+            TauriDB.signInAccount({ ...user, id: user.id })
+              .then(() => get().loadData())
+              .catch((e) => console.error("Failed to switch account:", e));
+          }
         } else {
           localStorage.removeItem("webnotes_user");
         }
@@ -206,6 +222,9 @@ export const useNotesStore = create<NotesState>()(
       });
       if (typeof window !== "undefined" && isTauri) {
         localStorage.removeItem("webnotes_user");
+        TauriDB.signOutAccount().catch((e) =>
+          console.error("Failed to sign out of account:", e)
+        );
       }
     },
 
@@ -283,6 +302,8 @@ export const useNotesStore = create<NotesState>()(
       } finally {
         set({ _loadingPromise: null });
       }
+
+      await get().refreshSmartFolders();
     },
 
     // =========================================================================
@@ -652,6 +673,22 @@ export const useNotesStore = create<NotesState>()(
       }
     },
 
+    // =========================================================================
+    // SMART FOLDER OPERATIONS
+    // =========================================================================
+    refreshSmartFolders: async () => {
+      try {
+        const smartFolders = await storage.getSmartFolders();
+        const smartFolderCounts = await storage.getSmartFolderCounts(
+          smartFolders,
+          get().notes
+        );
+        set({ smartFolders, smartFolderCounts });
+      } catch (error) {
+        console.error("Failed to load smart folders:", error);
+      }
+    },
+
     // =========================================================================
     // SETTINGS
     // =========================================================================